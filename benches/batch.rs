@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pikt::{render_all, render_all_sequential, Options};
+
+const DIAGRAM: &str = r#"
+box "start" rad 10px
+arrow
+box "parse"
+arrow
+box "layout"
+arrow
+oval "end"
+circle "a" at 1cm below first box
+arrow from last circle.e right 1cm
+circle "b"
+"#;
+
+/// The inputs are leaked so they can be borrowed for the whole benchmark.
+fn inputs() -> Vec<(&'static str, Options)> {
+    (0..50)
+        .map(|n| {
+            let input: &'static str =
+                Box::leak(format!("{}\ntext \"{}\"", DIAGRAM, n).into_boxed_str());
+
+            (input, Options::default())
+        })
+        .collect()
+}
+
+fn bench_batch(c: &mut Criterion) {
+    let pairs = inputs();
+
+    // The thread pool must produce the same output as the sequential renders.
+    assert_eq!(render_all(&pairs), render_all_sequential(&pairs));
+
+    let mut group = c.benchmark_group("batch_50");
+    group.bench_function("sequential", |b| {
+        b.iter(|| render_all_sequential(black_box(&pairs)))
+    });
+    group.bench_function("parallel", |b| b.iter(|| render_all(black_box(&pairs))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_batch);
+criterion_main!(benches);
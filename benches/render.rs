@@ -0,0 +1,124 @@
+use criterion::BatchSize;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pikt::{render, render_owned, render_with, Flags, Options, OptionsBuilder};
+use std::time::Duration;
+
+const COMPLEX: &str = r#"
+box "start" rad 10px
+arrow
+box "parse"
+arrow
+box "layout"
+arrow
+box "render"
+arrow
+oval "end"
+circle "a" at 1cm below first box
+arrow from last circle.e right 1cm
+circle "b"
+arrow
+circle "c"
+arrow from first box.s to first circle.n
+"#;
+
+fn bench_simple_render(c: &mut Criterion) {
+    c.bench_function("simple_render", |b| b.iter(|| render(black_box("box"))));
+}
+
+fn bench_complex_render(c: &mut Criterion) {
+    c.bench_function("complex_render", |b| b.iter(|| render(black_box(COMPLEX))));
+}
+
+fn bench_batch_render(c: &mut Criterion) {
+    c.bench_function("batch_render", |b| {
+        b.iter(|| {
+            for _ in 0..100 {
+                let _ = render(black_box("box \"pikchr\""));
+            }
+        })
+    });
+}
+
+fn bench_error_path(c: &mut Criterion) {
+    c.bench_function("error_path", |b| {
+        b.iter(|| render(black_box("box 'pikchr'")))
+    });
+}
+
+fn bench_dark_mode(c: &mut Criterion) {
+    let mut builder = OptionsBuilder::default();
+    builder.flags(Flags::DARK_MODE);
+    let options = builder.build();
+
+    c.bench_function("dark_mode", |b| {
+        b.iter(|| render_with(black_box(COMPLEX), options.clone()))
+    });
+}
+
+fn bench_options_accessors(c: &mut Criterion) {
+    let mut builder = OptionsBuilder::default();
+    builder.width(300);
+    builder.height(150);
+    let options = builder.build();
+
+    c.bench_function("options_accessors", |b| {
+        b.iter(|| {
+            let options = black_box(&options);
+            (
+                options.width(),
+                options.height(),
+                options.flags(),
+                options.class().len(),
+            )
+        })
+    });
+}
+
+/// Repeats a line of markup up to roughly `size` bytes.
+fn input_of_size(size: usize) -> String {
+    let line = "box \"pikchr\" fit\n";
+
+    line.repeat(size / line.len())
+}
+
+fn bench_owned_input(c: &mut Criterion) {
+    for (name, size) in [("1KB", 1024), ("100KB", 100 * 1024)] {
+        let input = input_of_size(size);
+
+        c.bench_function(&format!("borrowed_input_{}", name), |b| {
+            b.iter(|| render(black_box(&input)))
+        });
+        c.bench_function(&format!("owned_input_{}", name), |b| {
+            b.iter_batched(
+                // The spare byte lets `render_owned` append the nul terminator in place.
+                || {
+                    let mut owned = String::with_capacity(input.len() + 1);
+                    owned.push_str(&input);
+                    owned
+                },
+                |input| render_owned(black_box(input), Options::default()),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+fn config() -> Criterion {
+    Criterion::default()
+        .measurement_time(Duration::from_secs(10))
+        .sample_size(100)
+}
+
+criterion_group! {
+    name = benches;
+    config = config();
+    targets =
+        bench_simple_render,
+        bench_complex_render,
+        bench_batch_render,
+        bench_error_path,
+        bench_dark_mode,
+        bench_options_accessors,
+        bench_owned_input
+}
+criterion_main!(benches);
@@ -0,0 +1,24 @@
+//! Renders several diagrams, printing each one followed by a separator.
+//!
+//! ```sh
+//! cargo run --example batch_render
+//! ```
+
+fn main() {
+    let diagrams = [
+        r#"box "one""#,
+        r#"circle "two""#,
+        r#"oval "three"; arrow; box"#,
+        r#"cylinder "four""#,
+        r#"file "five" fill lightblue"#,
+    ];
+
+    for (index, markup) in diagrams.iter().enumerate() {
+        match pikt::render(markup) {
+            Ok(output) => print!("{}", output),
+            Err(err) => eprintln!("diagram {}: {}", index + 1, err),
+        }
+
+        println!("<!-- end of diagram {} -->", index + 1);
+    }
+}
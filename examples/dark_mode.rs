@@ -0,0 +1,30 @@
+//! Renders the same diagram in light and dark mode and shows how they differ.
+//!
+//! ```sh
+//! cargo run --example dark_mode
+//! ```
+
+use pikt::{render, render_with, Flags, OptionsBuilder};
+
+fn main() -> pikt::Result<()> {
+    let markup = r#"box "pikchr"; arrow; circle "dark""#;
+
+    let mut builder = OptionsBuilder::default();
+    builder.flags(Flags::DARK_MODE);
+
+    let light = render(markup)?;
+    let dark = render_with(markup, builder.build())?;
+
+    assert_ne!(light.svg, dark.svg);
+
+    println!("Light mode:\n{}", light);
+    println!("Dark mode:\n{}", dark);
+
+    for (light_line, dark_line) in light.svg.lines().zip(dark.svg.lines()) {
+        if light_line != dark_line {
+            println!("- {}\n+ {}", light_line, dark_line);
+        }
+    }
+
+    Ok(())
+}
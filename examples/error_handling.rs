@@ -0,0 +1,103 @@
+//! Renders invalid diagrams and handles each kind of error.
+//!
+//! ```sh
+//! cargo run --example error_handling
+//! ```
+
+use pikt::{render, PiktError, PiktErrorReason};
+
+fn explain(err: &PiktError) -> String {
+    use PiktErrorReason::*;
+
+    match err.reason() {
+        IncompatibleInput(nul) => format!("the input has a nul byte at {}", nul.nul_position()),
+        SyntaxError | TokenUnknown | TokenTooLong => {
+            format!("the markup is malformed, check line {}", err.line())
+        }
+        DivisionByZero | NegativeSqrt | ArcGeometryError | HeadingOutOfBounds => {
+            "an expression can't be computed".to_string()
+        }
+        UnknownObject
+        | UnknownObjectType
+        | UnknownColorName
+        | UnknownVariable
+        | VertexUnknown
+        | OrdinalOutOfBounds
+        | MissingPriorObjectType
+        | NotALine => {
+            format!(
+                "something referenced at {}:{} doesn't exist",
+                err.line(),
+                err.column()
+            )
+        }
+        ValueAlreadySet
+        | ValueAlreadyFixed
+        | StartLineAlreadyFixed
+        | PositionAlreadyFixedByAt
+        | OnlyWithLineOrientedObject
+        | NoPriorPathPoints
+        | MissingAt
+        | MissingFromTo
+        | ClosedPolygon
+        | TooFewVertexes
+        | AttributeTooManyTerms
+        | AttributeMissingText => "an attribute is used incorrectly".to_string(),
+        MacroTooManyArguments
+        | MacroUnterminatedArgumentList
+        | MacroTooDeep
+        | MacroRecursive
+        | DuplicateMacro(_)
+        | MacroArgumentMismatch { .. } => "a macro is defined or used incorrectly".to_string(),
+        ParserStackOverflow | OutOfMemory => "the diagram is too complex".to_string(),
+        InvalidFlags(_)
+        | InvalidScale(_)
+        | InvalidViewBox(_)
+        | InvalidAttributeName(_)
+        | InvalidCssVariable(_)
+        | InvalidClassPrefix(_)
+        | InvalidNamespace(_)
+        | InvalidColor(_) => "the options are invalid".to_string(),
+        InputTooLarge { actual, limit } => {
+            format!("the input has {} bytes, more than {}", actual, limit)
+        }
+        TooComplex { actual, limit } => {
+            format!("the input is {}, more complex than {}", actual, limit)
+        }
+        OutputTooLarge { actual, limit } => {
+            format!("the SVG has {} bytes, more than {}", actual, limit)
+        }
+        UnknownTemplateVariable(name) => format!("the template variable {} has no value", name),
+        UnsupportedOutputFormat(format) => format!("{} output is not supported", format),
+        Timeout => "the render took too long".to_string(),
+        EmptyInput => "there is no markup to render".to_string(),
+        Io(io) => format!("the input can't be read: {}", io),
+        InvalidUtf8(_) => "the input is not valid UTF-8".to_string(),
+        DiagramFailed { id, reason } => format!("the diagram {} failed: {}", id, reason),
+        Other(message) => format!("pikchr failed: {}", message),
+    }
+}
+
+fn main() {
+    let inputs = [
+        "box \"pikchr\"\0",
+        "circ \"1\"",
+        "box 'pikchr'",
+        "box\narrow from first box to (0/0, 0)",
+        "arrow from first oval",
+        "define m { m }\nm",
+    ];
+
+    for input in inputs.iter() {
+        match render(input) {
+            Ok(_) => println!("{:?} rendered", input),
+            Err(err) => println!(
+                "{:?} failed with error {}: {}\n  {}",
+                input,
+                err.reason().error_code(),
+                err,
+                explain(&err)
+            ),
+        }
+    }
+}
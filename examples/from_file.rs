@@ -0,0 +1,51 @@
+//! Renders the pikchr file given as the first argument.
+//!
+//! The SVG is written to stdout or to the file given with `-o`.
+//!
+//! ```sh
+//! cargo run --example from_file -- diagram.pikchr -o diagram.svg
+//! ```
+
+use std::error::Error;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "usage: from_file INPUT [-o OUTPUT]";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = std::env::args().skip(1);
+    let mut input = None;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => output = args.next(),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if input.is_none() => input = Some(arg),
+            _ => {
+                eprintln!("unexpected argument {}\n{}", arg, USAGE);
+                process::exit(2);
+            }
+        }
+    }
+
+    let input = input.unwrap_or_else(|| {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+
+    let markup = fs::read_to_string(&input)?;
+    let svg = pikt::render(&markup)
+        .map_err(|err| format!("{}:{}", input, err))?
+        .svg;
+
+    match output {
+        Some(path) => fs::write(path, svg)?,
+        None => print!("{}", svg),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,19 @@
+//! Renders a hardcoded diagram and prints the SVG to stdout.
+//!
+//! ```sh
+//! cargo run --example simple
+//! ```
+
+fn main() -> Result<(), pikt::PiktError> {
+    let markup = r#"
+    circle "1"
+    move
+    circle "2"
+    arrow from first circle.end to last circle.start
+    "#;
+
+    let output = pikt::render(markup)?;
+    print!("{}", output);
+
+    Ok(())
+}
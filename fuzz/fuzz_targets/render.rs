@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Any input must either render or fail with a `PiktError`, never panic.
+    let _ = pikt::render_from_bytes(data);
+});
@@ -1,6 +1,9 @@
-use bindgen::Builder;
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Set to anything but `0` to compile pikchr even if the sources didn't change.
+const FORCE_REBUILD_VAR: &str = "PIKCHR_FORCE_REBUILD";
 
 fn main() {
     let lib_name = "pikchr";
@@ -9,9 +12,100 @@ fn main() {
 
     println!("cargo:rerun-if-changed={}", lib_path);
     println!("cargo:rerun-if-changed={}", header_path);
+    println!("cargo:rerun-if-env-changed={}", FORCE_REBUILD_VAR);
+    rerun_if_compiler_env_changed();
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let hash_path = out_dir.join("pikchr_hash.txt");
+    let mut build = cc::Build::new();
+    build.file(lib_path);
+    let hash = build_hash(&build, &[lib_path, header_path]);
+
+    // Touching the sources, e.g. on checkout, reruns this script but only a change of content
+    // or of the compiler and its flags needs a new compilation.
+    if is_up_to_date(&out_dir, lib_name, &hash_path, &hash) {
+        println!("cargo:rustc-link-search=native={}", out_dir.display());
+        println!("cargo:rustc-link-lib=static={}", lib_name);
+    } else {
+        build.compile(lib_name);
+        fs::write(&hash_path, &hash).expect("Couldn't write the pikchr sources hash");
+    }
+
+    generate_bindings(header_path);
+}
+
+/// The CRC32 of the compiler, its flags and the contents of the given files, as hex.
+fn build_hash(build: &cc::Build, paths: &[&str]) -> String {
+    let mut hasher = crc32fast::Hasher::new();
+    let compiler = build.get_compiler();
+
+    hasher.update(compiler.path().as_os_str().to_string_lossy().as_bytes());
+    for arg in compiler.args() {
+        hasher.update(arg.to_string_lossy().as_bytes());
+    }
+
+    for path in paths {
+        let contents =
+            fs::read(path).unwrap_or_else(|err| panic!("Couldn't read {}: {}", path, err));
+        hasher.update(&contents);
+    }
+
+    format!("{:08x}", hasher.finalize())
+}
+
+/// `cc` only watches the variables that pick the compiler and its flags when it compiles, so they
+/// are watched here for the runs that skip the compilation.
+fn rerun_if_compiler_env_changed() {
+    let target = env::var("TARGET").unwrap();
+    let kind = if env::var("HOST").as_deref() == Ok(target.as_str()) {
+        "HOST"
+    } else {
+        "TARGET"
+    };
+
+    for var in &["CC", "CFLAGS", "AR", "ARFLAGS", "CRATE_CC_NO_DEFAULTS"] {
+        println!("cargo:rerun-if-env-changed={}", var);
+        println!("cargo:rerun-if-env-changed={}_{}", kind, var);
+        println!("cargo:rerun-if-env-changed={}_{}", var, target);
+        println!(
+            "cargo:rerun-if-env-changed={}_{}",
+            var,
+            target.replace('-', "_")
+        );
+    }
+}
+
+/// Whether the library compiled by a previous run is still there and was compiled from sources
+/// with the same hash.
+fn is_up_to_date(out_dir: &Path, lib_name: &str, hash_path: &Path, hash: &str) -> bool {
+    if env::var(FORCE_REBUILD_VAR).is_ok_and(|value| value != "0") {
+        return false;
+    }
+
+    let archive = if env::var("CARGO_CFG_TARGET_ENV").as_deref() == Ok("msvc") {
+        format!("{}.lib", lib_name)
+    } else {
+        format!("lib{}.a", lib_name)
+    };
+
+    out_dir.join(archive).exists() && fs::read_to_string(hash_path).ok().as_deref() == Some(hash)
+}
+
+/// With the `pregenerated-bindings` feature the bindings are taken from
+/// `pregenerated/bindings.rs` so there is nothing to generate.
+#[cfg(feature = "pregenerated-bindings")]
+fn generate_bindings(_header_path: &str) {
+    println!("cargo:rerun-if-changed=pregenerated/bindings.rs");
+}
+
+#[cfg(not(any(feature = "bindgen", feature = "pregenerated-bindings")))]
+compile_error!("pikchr-sys needs either the `bindgen` or the `pregenerated-bindings` feature");
 
-    cc::Build::new().file(lib_path).compile(lib_name);
-    println!("cargo:rustc-link-lib={}", lib_name);
+#[cfg(all(feature = "bindgen", not(feature = "pregenerated-bindings")))]
+fn generate_bindings(header_path: &str) {
+    use bindgen::Builder;
+    use std::env;
+    use std::path::PathBuf;
 
     let bindings = Builder::default()
         .header(header_path)
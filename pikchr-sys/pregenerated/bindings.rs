@@ -0,0 +1,13 @@
+/* automatically generated by rust-bindgen */
+
+pub const PIKCHR_PLAINTEXT_ERRORS: u32 = 1;
+pub const PIKCHR_DARK_MODE: u32 = 2;
+extern "C" {
+    pub fn pikchr(
+        zText: *const ::std::os::raw::c_char,
+        zClass: *const ::std::os::raw::c_char,
+        mFlags: ::std::os::raw::c_uint,
+        pnWidth: *mut ::std::os::raw::c_int,
+        pnHeight: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_char;
+}
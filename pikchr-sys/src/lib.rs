@@ -1,6 +1,7 @@
 //! The raw bindings for `pikchr.c`.
 //!
-//! Using [`pikchr`] will require manually freeing the buffer. Quoting the C source code:
+//! Using [`pikchr`] will require freeing the buffer, e.g. by wrapping it in a [`PikchrBuffer`].
+//! Quoting the C source code:
 //!
 //! > This file implements a C-language subroutine that accepts a string
 //! > of PIKCHR language text and generates a second string of SVG output that
@@ -24,32 +25,79 @@
 //! let mut height: c_int = 0;
 //! let input = CString::new(input)?;
 //!
-//! let res: *mut c_char = unsafe {
-//!     pikchr(
+//! let buffer = unsafe {
+//!     PikchrBuffer::from_raw(pikchr(
 //!         input.as_ptr() as *const c_char,
 //!         std::ptr::null(),
 //!         PIKCHR_PLAINTEXT_ERRORS,
 //!         &mut width as *mut c_int,
 //!         &mut height as *mut c_int,
-//!     )
-//! };
+//!     ))
+//! }
+//! .expect("out of memory");
 //!
-//! let cstr = unsafe { CStr::from_ptr(res) };
-//! let output = String::from_utf8_lossy(cstr.to_bytes()).into_owned();
-//!
-//! unsafe { free(res as *mut c_void) };
+//! let output = buffer.to_string_lossy().into_owned();
 //! ```
 //!
 //! ## Errors
 //!
 //! If an error occurs, the _width_ will be `-1` and the buffer will contain the error message.
+//!
+//! ## Features
+//!
+//! - `bindgen` (default): Generate the bindings with bindgen at build time, which needs
+//!   `libclang`.
+//! - `pregenerated-bindings`: Use the bindings checked in at `pregenerated/bindings.rs` instead
+//!   of generating them. With the default features disabled this removes the need for bindgen
+//!   and `libclang` at build time.
+#[cfg(not(feature = "pregenerated-bindings"))]
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+#[cfg(feature = "pregenerated-bindings")]
+include!("../pregenerated/bindings.rs");
+
+/// An owned buffer returned by [`pikchr`], freed when dropped.
+#[derive(Debug)]
+pub struct PikchrBuffer(*mut std::os::raw::c_char);
+
+impl PikchrBuffer {
+    /// Takes ownership of the given buffer. Returns `None` if it is null, which [`pikchr`]
+    /// returns when it runs out of memory.
+    ///
+    /// ## Safety
+    ///
+    /// The pointer must be a nul-terminated buffer allocated with `malloc`, such as the result
+    /// of [`pikchr`], and it must not be used or freed elsewhere.
+    pub unsafe fn from_raw(ptr: *mut std::os::raw::c_char) -> Option<Self> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Self(ptr))
+        }
+    }
+}
+
+impl std::ops::Deref for PikchrBuffer {
+    type Target = std::ffi::CStr;
+
+    fn deref(&self) -> &Self::Target {
+        // The pointer is non-null and nul-terminated as required by `from_raw`.
+        unsafe { std::ffi::CStr::from_ptr(self.0) }
+    }
+}
+
+impl Drop for PikchrBuffer {
+    fn drop(&mut self) {
+        unsafe { libc::free(self.0 as *mut libc::c_void) }
+    }
+}
+
+// SAFETY: The buffer is uniquely owned and `free` can be called from any thread.
+unsafe impl Send for PikchrBuffer {}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use libc::free;
-    use std::ffi::{CStr, CString};
+    use std::ffi::CString;
     use std::os::raw::*;
 
     #[test]
@@ -62,20 +110,30 @@ mod tests {
         let mut width: c_int = 0;
         let mut height: c_int = 0;
 
-        let res: *mut c_char = unsafe {
-            pikchr(
+        let buffer = unsafe {
+            PikchrBuffer::from_raw(pikchr(
                 source.as_ptr() as *const c_char,
                 std::ptr::null(),
                 flags,
                 &mut width as *mut c_int,
                 &mut height as *mut c_int,
-            )
-        };
-        let cstr = unsafe { CStr::from_ptr(res) };
-        let actual = String::from_utf8_lossy(cstr.to_bytes()).into_owned();
+            ))
+        }
+        .unwrap();
+        let actual = buffer.to_string_lossy();
+
+        assert_eq!(actual, expected);
+    }
 
-        unsafe { free(res as *mut c_void) };
+    #[test]
+    fn null_buffer() {
+        assert!(unsafe { PikchrBuffer::from_raw(std::ptr::null_mut()) }.is_none());
+    }
+
+    #[test]
+    fn buffer_is_send() {
+        fn assert_send<T: Send>() {}
 
-        assert_eq!(&actual, expected);
+        assert_send::<PikchrBuffer>();
     }
 }
@@ -0,0 +1,75 @@
+//! Compile-time rendering of [Pikchr] diagrams.
+//!
+//! [Pikchr]: https://pikchr.org/
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote_spanned;
+use syn::{parse_macro_input, LitStr};
+
+/// Renders the given pikchr markup at compile time expanding to the SVG as a `&'static str`.
+///
+/// Invalid markup is reported as a compile error.
+///
+/// ## Example
+///
+/// ```
+/// use pikt_macros::pikchr;
+///
+/// const SVG: &str = pikchr!("box \"pikchr\"");
+///
+/// assert!(SVG.starts_with("<svg"));
+/// ```
+#[proc_macro]
+pub fn pikchr(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let span = literal.span();
+
+    let expanded = match pikt::render(&literal.value()) {
+        Ok(output) => {
+            let svg = output.svg;
+            quote_spanned!(span=> #svg)
+        }
+        Err(err) => compile_error(&err, span),
+    };
+
+    expanded.into()
+}
+
+/// Validates the given pikchr markup at compile time expanding to the markup as a
+/// `&'static str`.
+///
+/// Invalid markup is reported as a compile error. Use it to check markup rendered at runtime,
+/// for example with custom options.
+///
+/// ## Example
+///
+/// ```
+/// use pikt_macros::validate_pikchr;
+///
+/// const MARKUP: &str = validate_pikchr!("box \"pikchr\"");
+///
+/// assert!(pikt::render(MARKUP).is_ok());
+/// ```
+#[proc_macro]
+pub fn validate_pikchr(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let span = literal.span();
+
+    let expanded = match pikt::validate(&literal.value()) {
+        Ok(()) => quote_spanned!(span=> #literal),
+        Err(err) => compile_error(&err, span),
+    };
+
+    expanded.into()
+}
+
+fn compile_error(err: &pikt::PiktError, span: Span) -> TokenStream2 {
+    let message = format!(
+        "line {}, col {}: {}",
+        err.line(),
+        err.column(),
+        err.reason()
+    );
+
+    quote_spanned!(span=> compile_error!(#message))
+}
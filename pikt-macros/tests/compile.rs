@@ -0,0 +1,8 @@
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pikchr_valid.rs");
+    t.compile_fail("tests/ui/pikchr_invalid.rs");
+    t.pass("tests/ui/validate_valid.rs");
+    t.compile_fail("tests/ui/validate_invalid.rs");
+}
@@ -0,0 +1,7 @@
+use pikt_macros::pikchr;
+
+const SVG: &str = pikchr!("box 'pikchr'");
+
+fn main() {
+    println!("{}", SVG);
+}
@@ -0,0 +1,8 @@
+use pikt_macros::pikchr;
+
+const SVG: &str = pikchr!("box \"pikchr\"");
+
+fn main() {
+    assert!(!SVG.is_empty());
+    assert!(SVG.starts_with("<svg"));
+}
@@ -0,0 +1,7 @@
+use pikt_macros::validate_pikchr;
+
+const MARKUP: &str = validate_pikchr!("circ \"1\"");
+
+fn main() {
+    println!("{}", MARKUP);
+}
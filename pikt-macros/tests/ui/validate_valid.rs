@@ -0,0 +1,7 @@
+use pikt_macros::validate_pikchr;
+
+const MARKUP: &str = validate_pikchr!("box \"pikchr\"");
+
+fn main() {
+    assert_eq!(MARKUP, "box \"pikchr\"");
+}
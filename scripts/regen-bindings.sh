@@ -0,0 +1,29 @@
+#!/usr/bin/env bash
+#
+# Regenerates `pikchr-sys/pregenerated/bindings.rs` from `pikchr-sys/pikchr/pikchr.h`.
+#
+# Requires libclang. Run it from anywhere in the repository after updating the bundled pikchr
+# sources and commit the resulting file.
+
+set -euo pipefail
+
+root="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
+target="$root/pikchr-sys/pregenerated/bindings.rs"
+
+cd "$root"
+
+out_dir="$(
+  cargo build --package pikchr-sys --message-format=json \
+    | grep '"reason":"build-script-executed"' \
+    | grep '"package_id":"[^"]*pikchr-sys' \
+    | sed -e 's/.*"out_dir":"\([^"]*\)".*/\1/' \
+    | tail -n 1
+)"
+
+if [ -z "$out_dir" ] || [ ! -f "$out_dir/bindings.rs" ]; then
+  echo "error: could not find the generated bindings.rs" >&2
+  exit 1
+fi
+
+cp "$out_dir/bindings.rs" "$target"
+echo "Updated $target"
@@ -0,0 +1,181 @@
+//! Alternative text derived from the pikchr source, for renders without a hand-written
+//! description.
+
+use crate::complexity::SHAPES;
+
+/// Describes the diagram from its shapes and their labels, e.g.
+/// `Diagram containing: box labeled 'A', arrow`.
+///
+/// Each statement, up to a newline or `;`, is described by its first shape keyword and the
+/// strings that follow it. Statements without a shape are skipped, and a diagram without any
+/// shape is described as `Diagram`. This reads the source rather than the layout, so shapes
+/// created by macros or loops are not listed.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::generate_alt_text;
+///
+/// assert_eq!(
+///     generate_alt_text("box \"A\"; arrow\ncircle \"B\""),
+///     "Diagram containing: box labeled 'A', arrow, circle labeled 'B'"
+/// );
+/// assert_eq!(generate_alt_text(""), "Empty diagram");
+/// ```
+pub fn generate_alt_text(input: &str) -> String {
+    let mut elements = Vec::new();
+    let mut shape = None;
+    let mut labels = Vec::new();
+    let mut empty = true;
+
+    for token in tokenize(input) {
+        match token {
+            Token::Word(word) => {
+                empty = false;
+
+                if shape.is_none() && SHAPES.contains(&word) {
+                    shape = Some(word);
+                }
+            }
+            Token::String(text) => {
+                empty = false;
+
+                if shape.is_some() {
+                    labels.push(text);
+                }
+            }
+            Token::End => {
+                if let Some(shape) = shape.take() {
+                    elements.push(describe(shape, &labels));
+                }
+                labels.clear();
+            }
+        }
+    }
+
+    if let Some(shape) = shape {
+        elements.push(describe(shape, &labels));
+    }
+
+    match (empty, elements.is_empty()) {
+        (true, _) => "Empty diagram".to_string(),
+        (false, true) => "Diagram".to_string(),
+        (false, false) => format!("Diagram containing: {}", elements.join(", ")),
+    }
+}
+
+fn describe(shape: &str, labels: &[String]) -> String {
+    if labels.is_empty() {
+        return shape.to_string();
+    }
+
+    let labels: Vec<_> = labels.iter().map(|label| format!("'{}'", label)).collect();
+
+    format!("{} labeled {}", shape, labels.join(" and "))
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    /// The content of a string, unescaped.
+    String(String),
+    /// The end of a statement.
+    End,
+}
+
+/// Splits the source into words, strings and statement ends, skipping comments.
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '\n' | ';' => tokens.push(Token::End),
+            '"' => {
+                let mut text = String::new();
+
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => text.extend(chars.next().map(|(_, c)| c)),
+                        '"' => break,
+                        c => text.push(c),
+                    }
+                }
+
+                tokens.push(Token::String(text));
+            }
+            '#' => while chars.next_if(|&(_, c)| c != '\n').is_some() {},
+            '/' if chars.peek().map(|&(_, c)| c) == Some('/') => {
+                while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut end = start + c.len_utf8();
+
+                while let Some((index, c)) =
+                    chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    end = index + c.len_utf8();
+                }
+
+                tokens.push(Token::Word(&input[start..end]));
+            }
+            _ => {}
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labeled_box() {
+        assert_eq!(
+            generate_alt_text("box \"A\" fit"),
+            "Diagram containing: box labeled 'A'"
+        );
+    }
+
+    #[test]
+    fn without_labels() {
+        assert_eq!(
+            generate_alt_text("box\narrow right\ncircle"),
+            "Diagram containing: box, arrow, circle"
+        );
+    }
+
+    #[test]
+    fn many_elements() {
+        let input = r#"# The flow
+box "Start" "here"; arrow
+cylinder "DB \"main\"" // the store
+line dashed
+"#;
+
+        assert_eq!(
+            generate_alt_text(input),
+            "Diagram containing: box labeled 'Start' and 'here', arrow, cylinder labeled 'DB \"main\"', line"
+        );
+    }
+
+    #[test]
+    fn keywords_in_strings_are_labels() {
+        assert_eq!(
+            generate_alt_text("box \"a circle\"; boxwidth = 1"),
+            "Diagram containing: box labeled 'a circle'"
+        );
+    }
+
+    #[test]
+    fn without_shapes() {
+        assert_eq!(generate_alt_text("down; $x = 1"), "Diagram");
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(generate_alt_text(""), "Empty diagram");
+        assert_eq!(generate_alt_text("\n  # only a comment\n"), "Empty diagram");
+    }
+}
@@ -0,0 +1,227 @@
+//! Support for pikchr diagrams embedded in AsciiDoc.
+//!
+//! Diagrams are listing blocks with either `[pikchr]` or `[source,pikchr]` as the attribute
+//! list:
+//!
+//! ```asciidoc
+//! [pikchr]
+//! ----
+//! box "pikchr"
+//! ----
+//! ```
+//!
+//! Attribute entries such as `:class: foo` are allowed between the attribute list and the
+//! opening delimiter.
+
+use crate::{replace_blocks, Options, PikchrBlock, Result};
+use std::borrow::Cow;
+
+/// Finds the pikchr blocks in the given AsciiDoc text.
+///
+/// The block source has its leading and trailing whitespace stripped. Blocks without a closing
+/// delimiter are ignored.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::asciidoc::find_pikchr_blocks;
+///
+/// let text = "= Title\n\n[pikchr]\n----\nbox\n----\n";
+/// let blocks: Vec<_> = find_pikchr_blocks(text).collect();
+///
+/// assert_eq!(blocks.len(), 1);
+/// assert_eq!(blocks[0].source, "box");
+/// ```
+pub fn find_pikchr_blocks(text: &str) -> impl Iterator<Item = PikchrBlock<'_>> {
+    Blocks { text, offset: 0 }
+}
+
+/// Replaces every pikchr block in the given AsciiDoc text with its rendered SVG wrapped in a
+/// passthrough block.
+///
+/// A block that fails to render is replaced with a `<pre class="pikchr-error">` element
+/// containing the error message so the rest of the blocks are still rendered.
+pub fn replace_pikchr_blocks(text: &str, options: Options) -> Result<String> {
+    Ok(replace_blocks(
+        text,
+        find_pikchr_blocks(text),
+        &options,
+        |html| format!("++++\n{}\n++++", html),
+    ))
+}
+
+struct Blocks<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = PikchrBlock<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.offset < self.text.len() {
+            let start = self.offset;
+            let line = self.next_line();
+
+            if !is_pikchr_attribute_list(line) {
+                continue;
+            }
+
+            // Attribute entries can sit between the attribute list and the delimiter.
+            let mut delimiter_start = self.offset;
+            let mut delimiter = self.next_line();
+            while is_attribute_entry(delimiter) {
+                delimiter_start = self.offset;
+                delimiter = self.next_line();
+            }
+
+            let delimiter = delimiter.trim_end();
+            if !is_delimiter(delimiter) {
+                // Not a block, the line is scanned again as part of the surrounding text.
+                self.offset = delimiter_start;
+                continue;
+            }
+
+            let content_start = self.offset;
+
+            while self.offset < self.text.len() {
+                let line_start = self.offset;
+                let line = self.next_line();
+
+                if line.trim_end() == delimiter {
+                    let end = line_start + line.trim_end().len();
+
+                    return Some(PikchrBlock {
+                        source: Cow::Borrowed(self.text[content_start..line_start].trim()),
+                        byte_range: start..end,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a> Blocks<'a> {
+    /// Consumes the next line, line ending included.
+    fn next_line(&mut self) -> &'a str {
+        let rest = &self.text[self.offset..];
+        let line = match rest.find('\n') {
+            Some(index) => &rest[..=index],
+            None => rest,
+        };
+        self.offset += line.len();
+
+        line
+    }
+}
+
+fn is_pikchr_attribute_list(line: &str) -> bool {
+    let line = line.trim();
+    let attributes = match line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+        Some(attributes) => attributes,
+        None => return false,
+    };
+    let mut positional = attributes.split(',').map(str::trim);
+
+    match positional.next() {
+        Some("pikchr") => true,
+        Some("source") => positional.next() == Some("pikchr"),
+        _ => false,
+    }
+}
+
+fn is_attribute_entry(line: &str) -> bool {
+    let line = line.trim();
+
+    match line.strip_prefix(':') {
+        Some(rest) => rest
+            .find(':')
+            .map(|index| index > 0 && !rest[..index].contains(char::is_whitespace))
+            .unwrap_or(false),
+        None => false,
+    }
+}
+
+fn is_delimiter(line: &str) -> bool {
+    line.len() >= 4 && line.chars().all(|c| c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_block() {
+        let text = "[pikchr]\n----\n\nbox \"a\"\n\n----\n";
+        let blocks: Vec<_> = find_pikchr_blocks(text).collect();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].source, "box \"a\"");
+        assert_eq!(&text[blocks[0].byte_range.clone()], text.trim_end());
+    }
+
+    #[test]
+    fn source_block() {
+        let text = "[source,pikchr]\n----\nbox\n----\n\n[source,rust]\n----\nfn main() {}\n----\n";
+        let blocks: Vec<_> = find_pikchr_blocks(text).collect();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].source, "box");
+    }
+
+    #[test]
+    fn block_with_attribute_entries() {
+        let text = "[pikchr]\n:class: foo\n:width: 300\n----\nbox\n----\n";
+        let blocks: Vec<_> = find_pikchr_blocks(text).collect();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].source, "box");
+        assert_eq!(blocks[0].byte_range, 0..text.len() - 1);
+    }
+
+    #[test]
+    fn multiple_blocks() {
+        let text = "[pikchr]\n----\nbox\n----\n\ntext\n\n[pikchr]\n------\ncircle\n----\n------\n";
+        let blocks: Vec<_> = find_pikchr_blocks(text).collect();
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].source, "box");
+        assert_eq!(blocks[1].source, "circle\n----");
+    }
+
+    #[test]
+    fn line_after_attribute_list_is_scanned() {
+        let text = "[pikchr]\n[pikchr]\n----\nbox\n----\n";
+
+        let blocks: Vec<_> = find_pikchr_blocks(text).collect();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].source, "box");
+        assert_eq!(blocks[0].byte_range, 9..text.len() - 1);
+    }
+
+    #[test]
+    fn unclosed_block_is_ignored() {
+        let text = "[pikchr]\n----\nbox\n";
+
+        assert_eq!(find_pikchr_blocks(text).count(), 0);
+    }
+
+    #[test]
+    fn replace_with_error_recovery() -> Result<()> {
+        let text = "[pikchr]\n----\nbox 'x'\n----\n\n[pikchr]\n----\nbox\n----\n";
+        let options = Options::default();
+
+        let actual = replace_pikchr_blocks(text, options)?;
+
+        assert!(actual.starts_with(
+            "++++\n<pre class=\"pikchr-error\">line 1, column 5: unknown token</pre>\n++++\n"
+        ));
+        assert!(actual.contains("\n\n++++\n<svg"));
+        assert!(actual.ends_with("</svg>\n++++\n"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+//! A log of render operations, e.g. to audit what a service renders.
+
+use crate::{render_with, Flags, Options, PikchrOutput, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Instant, SystemTime};
+
+/// A record of a single render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// When the render started.
+    pub timestamp: SystemTime,
+    /// A hash of the input markup. It is only meant to tell inputs apart within a process.
+    pub input_hash: u64,
+    pub flags: Flags,
+    pub success: bool,
+    /// The error reason if the render failed.
+    pub error_reason: Option<String>,
+    /// The time spent rendering, in microseconds.
+    pub duration_us: u64,
+    /// The id given to [`AuditLogger::render_with_id`], e.g. to match the entry with the
+    /// request that caused the render.
+    pub correlation_id: Option<String>,
+}
+
+/// Renders diagrams recording an [`AuditEntry`] for each of them.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{AuditLogger, Options};
+///
+/// let mut logger = AuditLogger::new();
+/// let _ = logger.render("box", Options::default());
+/// let _ = logger.render("box 'x'", Options::default());
+///
+/// assert_eq!(logger.success_count(), 1);
+/// assert_eq!(logger.error_count(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AuditLogger {
+    log: Vec<AuditEntry>,
+}
+
+impl AuditLogger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders the given markup with [`render_with`] and records the outcome.
+    pub fn render(&mut self, input: &str, options: Options) -> Result<PikchrOutput> {
+        self.record(input, options, None)
+    }
+
+    /// Renders the given markup like [`AuditLogger::render`], recording the correlation id in
+    /// the entry. The output is not modified.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{AuditLogger, Options};
+    ///
+    /// let mut logger = AuditLogger::new();
+    /// let _ = logger.render_with_id("box", Options::default(), "req-42");
+    ///
+    /// assert_eq!(logger.entries()[0].correlation_id.as_deref(), Some("req-42"));
+    /// ```
+    pub fn render_with_id(
+        &mut self,
+        input: &str,
+        options: Options,
+        correlation_id: &str,
+    ) -> Result<PikchrOutput> {
+        self.record(input, options, Some(correlation_id.to_string()))
+    }
+
+    fn record(
+        &mut self,
+        input: &str,
+        options: Options,
+        correlation_id: Option<String>,
+    ) -> Result<PikchrOutput> {
+        let mut hasher = DefaultHasher::new();
+        input.hash(&mut hasher);
+        let flags = options.flags();
+        let timestamp = SystemTime::now();
+        let start = Instant::now();
+
+        let result = render_with(input, options);
+
+        self.log.push(AuditEntry {
+            timestamp,
+            input_hash: hasher.finish(),
+            flags,
+            success: result.is_ok(),
+            error_reason: result.as_ref().err().map(|err| err.reason().to_string()),
+            duration_us: start.elapsed().as_micros() as u64,
+            correlation_id,
+        });
+
+        result
+    }
+
+    /// The recorded entries, oldest first.
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.log
+    }
+
+    /// Removes all the recorded entries.
+    pub fn clear(&mut self) {
+        self.log.clear();
+    }
+
+    /// The number of successful renders recorded.
+    pub fn success_count(&self) -> usize {
+        self.log.iter().filter(|entry| entry.success).count()
+    }
+
+    /// The number of failed renders recorded.
+    pub fn error_count(&self) -> usize {
+        self.log.len() - self.success_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OptionsBuilder;
+
+    #[test]
+    fn records_success() {
+        let mut logger = AuditLogger::new();
+        let before = SystemTime::now();
+
+        assert!(logger.render("box", Options::default()).is_ok());
+
+        let entry = &logger.entries()[0];
+        assert!(entry.success);
+        assert_eq!(entry.error_reason, None);
+        assert_eq!(entry.flags, Flags::empty());
+        assert_eq!(entry.correlation_id, None);
+        assert!(entry.timestamp >= before);
+    }
+
+    #[test]
+    fn records_correlation_ids() -> Result<()> {
+        let mut logger = AuditLogger::new();
+
+        let output = logger.render_with_id("box", Options::default(), "a")?;
+        assert!(logger
+            .render_with_id("box 'x'", Options::default(), "b")
+            .is_err());
+
+        assert_eq!(output, crate::render("box")?);
+
+        let ids: Vec<_> = logger
+            .entries()
+            .iter()
+            .map(|entry| entry.correlation_id.as_deref())
+            .collect();
+        assert_eq!(ids, [Some("a"), Some("b")]);
+        assert!(!logger.entries()[1].success);
+
+        Ok(())
+    }
+
+    #[test]
+    fn records_failure() {
+        let mut logger = AuditLogger::new();
+        let mut builder = OptionsBuilder::default();
+        builder.dark_mode();
+
+        assert!(logger.render("circ \"1\"", builder.build()).is_err());
+
+        let entry = &logger.entries()[0];
+        assert!(!entry.success);
+        assert_eq!(entry.error_reason.as_deref(), Some("syntax error"));
+        assert_eq!(entry.flags, Flags::DARK_MODE);
+    }
+
+    #[test]
+    fn counts_and_clear() {
+        let mut logger = AuditLogger::new();
+
+        for input in ["box", "box", "box 'x'"] {
+            let _ = logger.render(input, Options::default());
+        }
+
+        assert_eq!(logger.entries().len(), 3);
+        assert_eq!(
+            logger.entries()[0].input_hash,
+            logger.entries()[1].input_hash
+        );
+        assert_ne!(
+            logger.entries()[0].input_hash,
+            logger.entries()[2].input_hash
+        );
+        assert_eq!(logger.success_count(), 2);
+        assert_eq!(logger.error_count(), 1);
+
+        logger.clear();
+
+        assert!(logger.entries().is_empty());
+        assert_eq!(logger.error_count(), 0);
+    }
+}
@@ -0,0 +1,331 @@
+//! Rendering of many diagrams at once, reporting every failure.
+
+use crate::{render_with, Options, PikchrOutput, PiktError};
+use std::fmt;
+use std::path::Path;
+
+/// An error tagged with the input it comes from, e.g. a file path.
+#[derive(Debug, PartialEq)]
+pub struct LabeledError {
+    pub label: String,
+    pub error: PiktError,
+}
+
+impl fmt::Display for LabeledError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.label, self.error)
+    }
+}
+
+impl std::error::Error for LabeledError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The error of [`render_with_label`], tagged with the label it was given.
+#[derive(Debug, PartialEq)]
+pub struct LabeledPiktError {
+    pub label: String,
+    pub error: PiktError,
+}
+
+impl LabeledPiktError {
+    /// Drops the label.
+    pub fn into_pikt_error(self) -> PiktError {
+        self.error
+    }
+}
+
+/// The label is quoted so that labels with spaces or colons stay readable, e.g.
+/// `"a.pikchr": line 1, column 1: syntax error`.
+impl fmt::Display for LabeledPiktError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.label, self.error)
+    }
+}
+
+impl std::error::Error for LabeledPiktError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The errors of every input that failed to render.
+#[derive(Debug, PartialEq)]
+pub struct MultiRenderError {
+    pub errors: Vec<LabeledError>,
+}
+
+/// Lists each error on its own line.
+impl fmt::Display for MultiRenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} diagram(s) failed to render", self.errors.len())?;
+
+        for error in &self.errors {
+            write!(f, "\n{}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for MultiRenderError {}
+
+/// Renders the given pikchr markup, tagging the error with the label, e.g. a file name or a key.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_with_label, Options};
+///
+/// let err = render_with_label("box 'a'", "intro", Options::default()).unwrap_err();
+///
+/// assert_eq!(err.label, "intro");
+/// assert!(err.to_string().starts_with("\"intro\": "));
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`].
+pub fn render_with_label(
+    input: &str,
+    label: impl Into<String>,
+    options: Options,
+) -> Result<PikchrOutput, LabeledPiktError> {
+    render_with(input, options).map_err(|error| LabeledPiktError {
+        label: label.into(),
+        error,
+    })
+}
+
+/// Renders each `(input, label)` pair as one line of [JSON Lines](https://jsonlines.org/).
+///
+/// A successful render is written as `{"label":"...","svg":"...","width":N,"height":N}` and a
+/// failure as `{"label":"...","error":{"line":N,"col":M,"reason":"..."}}`. Every line, the last
+/// one included, ends with a newline.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_many_to_jsonl, Options};
+///
+/// let jsonl = render_many_to_jsonl([("box", "a"), ("box 'x'", "b")], &Options::default());
+///
+/// assert_eq!(jsonl.lines().count(), 2);
+/// ```
+#[cfg(feature = "json")]
+pub fn render_many_to_jsonl<'a, I>(inputs: I, options: &Options) -> String
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut output = String::new();
+
+    for (input, label) in inputs {
+        let line = match render_with(input, options.clone()) {
+            Ok(rendered) => serde_json::json!({
+                "label": label,
+                "svg": rendered.svg,
+                "width": rendered.width,
+                "height": rendered.height,
+            }),
+            Err(err) => serde_json::json!({
+                "label": label,
+                "error": {
+                    "line": err.line(),
+                    "col": err.column(),
+                    "reason": err.reason().to_string(),
+                },
+            }),
+        };
+
+        output.push_str(&line.to_string());
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Renders each of the given files with the same configuration.
+///
+/// Every file is rendered even if some fail. Errors are labeled with the file path.
+///
+/// ## Example
+///
+/// ```no_run
+/// use pikt::{render_files, Options};
+///
+/// match render_files(&["a.pikchr", "b.pikchr"], &Options::default()) {
+///     Ok(outputs) => println!("rendered {} diagrams", outputs.len()),
+///     Err(err) => eprintln!("{}", err),
+/// }
+/// ```
+///
+/// ## Errors
+///
+/// Fails with all the errors, including files that cannot be read, if any file fails.
+pub fn render_files<P: AsRef<Path>>(
+    paths: &[P],
+    options: &Options,
+) -> Result<Vec<PikchrOutput>, MultiRenderError> {
+    let mut outputs = Vec::with_capacity(paths.len());
+    let mut errors = Vec::new();
+
+    for path in paths {
+        let path = path.as_ref();
+        let result = std::fs::read_to_string(path)
+            .map_err(PiktError::from)
+            .and_then(|input| render_with(&input, options.clone()));
+
+        match result {
+            Ok(output) => outputs.push(output),
+            Err(error) => errors.push(LabeledError {
+                label: path.display().to_string(),
+                error,
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(outputs)
+    } else {
+        Err(MultiRenderError { errors })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PiktErrorReason;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/regression")
+            .join(name)
+    }
+
+    #[test]
+    fn all_succeed() -> Result<(), MultiRenderError> {
+        let paths = [fixture("valid/box.pikchr"), fixture("valid/arrow.pikchr")];
+
+        let actual = render_files(&paths, &Options::default())?;
+
+        assert_eq!(actual.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn all_fail() {
+        let paths = [
+            fixture("invalid/syntax.pikchr"),
+            fixture("invalid/missing.pikchr"),
+        ];
+
+        let actual = render_files(&paths, &Options::default()).unwrap_err();
+
+        assert_eq!(actual.errors.len(), 2);
+        assert_eq!(actual.errors[0].label, paths[0].display().to_string());
+        assert_eq!(
+            actual.errors[0].error.reason(),
+            &PiktErrorReason::SyntaxError
+        );
+        assert!(matches!(
+            actual.errors[1].error.reason(),
+            PiktErrorReason::Io(_)
+        ));
+    }
+
+    #[test]
+    fn mixed() {
+        let paths = [
+            fixture("valid/box.pikchr"),
+            fixture("invalid/unknown_token.pikchr"),
+            fixture("valid/arrow.pikchr"),
+        ];
+
+        let actual = render_files(&paths, &Options::default()).unwrap_err();
+
+        assert_eq!(actual.errors.len(), 1);
+        assert!(actual.errors[0].label.ends_with("unknown_token.pikchr"));
+
+        let message = actual.to_string();
+        assert!(message.starts_with("1 diagram(s) failed to render\n"));
+        assert!(message.contains(&format!("\n{}: ", actual.errors[0].label)));
+        assert!(message.ends_with("unknown token"));
+    }
+
+    #[test]
+    fn label_ignored_on_success() -> Result<(), LabeledPiktError> {
+        let actual = render_with_label("box", "a", Options::default())?;
+
+        assert_eq!(Ok(actual), crate::render("box"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn labeled_error() {
+        let expected = crate::render("box 'a'").unwrap_err();
+        let actual = render_with_label("box 'a'", "a.pikchr", Options::default()).unwrap_err();
+
+        assert_eq!(actual.label, "a.pikchr");
+        assert_eq!(actual.to_string(), format!("\"a.pikchr\": {}", expected));
+        assert!(std::error::Error::source(&actual).is_some());
+        assert_eq!(actual.into_pikt_error(), expected);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn jsonl_lines() {
+        let expected = crate::render("box").unwrap();
+        let actual = render_many_to_jsonl(
+            [
+                ("box", "ok"),
+                ("box 'x'", "broken \"one\""),
+                ("box", "again"),
+            ],
+            &Options::default(),
+        );
+
+        assert!(actual.ends_with("}\n"));
+
+        let lines: Vec<serde_json::Value> = actual
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            serde_json::json!({
+                "label": "ok",
+                "svg": expected.svg,
+                "width": expected.width,
+                "height": expected.height,
+            })
+        );
+        assert_eq!(
+            lines[1],
+            serde_json::json!({
+                "label": "broken \"one\"",
+                "error": {"line": 1, "col": 5, "reason": "unknown token"},
+            })
+        );
+        assert_eq!(lines[2]["label"], "again");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn jsonl_empty() {
+        assert_eq!(render_many_to_jsonl([], &Options::default()), "");
+    }
+
+    #[test]
+    fn empty() {
+        let paths: [&str; 0] = [];
+
+        assert_eq!(render_files(&paths, &Options::default()), Ok(vec![]));
+    }
+}
@@ -0,0 +1,358 @@
+//! An in-memory LRU cache of rendered diagrams.
+//!
+//! Entries are keyed by the input markup and the [`Options`], so the same markup rendered with
+//! different options is cached once for each.
+
+use crate::{render_with, Options, PikchrOutput, Result};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+type CacheKey = (String, Options);
+
+/// A least-recently-used cache of render results.
+///
+/// Only successful renders are cached.
+#[derive(Debug)]
+pub struct RenderCache {
+    entries: LruCache<CacheKey, PikchrOutput>,
+    // Atomic so that hits can be counted behind the read lock of a `SharedRenderCache`.
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl RenderCache {
+    /// Creates a cache holding at most `capacity` renders.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).expect("cache capacity must be greater than 0");
+
+        Self {
+            entries: LruCache::new(capacity),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the cached output for the given input or renders it and caches the result.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{Options, RenderCache};
+    ///
+    /// let mut cache = RenderCache::new(10);
+    /// let options = Options::default();
+    /// let first = cache.get_or_render("box", &options).unwrap();
+    /// let second = cache.get_or_render("box", &options).unwrap();
+    ///
+    /// assert_eq!(first, second);
+    /// assert_eq!(cache.hit_ratio(), 0.5);
+    /// ```
+    pub fn get_or_render(&mut self, input: &str, options: &Options) -> Result<PikchrOutput> {
+        let key = (input.to_string(), options.clone());
+
+        if let Some(output) = self.entries.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(output.clone());
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let output = render_with(input, options.clone())?;
+        self.entries.put(key, output.clone());
+
+        Ok(output)
+    }
+
+    /// Returns the cached output without rendering or updating the recency of the entry.
+    fn peek(&self, input: &str, options: &Options) -> Option<PikchrOutput> {
+        let output = self
+            .entries
+            .peek(&(input.to_string(), options.clone()))?
+            .clone();
+        self.hits.fetch_add(1, Ordering::Relaxed);
+
+        Some(output)
+    }
+
+    /// Removes all entries from the cache. Statistics are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// The number of cached entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The ratio of lookups served from the cache. It is `0.0` if there were no lookups.
+    pub fn hit_ratio(&self) -> f64 {
+        let (hits, misses) = (self.hits(), self.misses());
+        let total = hits + misses;
+
+        if total == 0 {
+            return 0.0;
+        }
+
+        hits as f64 / total as f64
+    }
+
+    fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+/// A [`RenderCache`] that can be shared across threads. Cloning it is cheap and shares the
+/// entries.
+///
+/// Lookups only take a read lock so concurrent hits don't block each other. A miss takes the
+/// write lock while rendering, so each diagram is rendered once even when requested by many
+/// threads at the same time. Unlike with [`RenderCache`], hits don't refresh the entries so
+/// the eviction order is the insertion order.
+#[derive(Debug, Clone)]
+pub struct SharedRenderCache(Arc<RwLock<RenderCache>>);
+
+impl SharedRenderCache {
+    /// Creates a shared cache holding at most `capacity` renders.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        Self(Arc::new(RwLock::new(RenderCache::new(capacity))))
+    }
+
+    /// See [`RenderCache::get_or_render`].
+    pub fn get_or_render(&self, input: &str, options: &Options) -> Result<PikchrOutput> {
+        if let Some(output) = self.read().peek(input, options) {
+            return Ok(output);
+        }
+
+        // Another thread may have rendered the same input while waiting for the lock.
+        self.write().get_or_render(input, options)
+    }
+
+    /// Same as [`SharedRenderCache::get_or_render`].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{Options, SharedRenderCache};
+    ///
+    /// let cache = SharedRenderCache::new(10);
+    /// let options = Options::default();
+    /// let handle = {
+    ///     let cache = cache.clone();
+    ///     let options = options.clone();
+    ///     std::thread::spawn(move || cache.render("box", &options))
+    /// };
+    ///
+    /// assert_eq!(handle.join().unwrap().unwrap(), cache.render("box", &options).unwrap());
+    /// assert_eq!(cache.hit_ratio(), 0.5);
+    /// ```
+    pub fn render(&self, input: &str, options: &Options) -> Result<PikchrOutput> {
+        self.get_or_render(input, options)
+    }
+
+    /// See [`RenderCache::clear`].
+    pub fn clear(&self) {
+        self.write().clear()
+    }
+
+    /// See [`RenderCache::len`].
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// See [`RenderCache::hit_ratio`].
+    pub fn hit_ratio(&self) -> f64 {
+        self.read().hit_ratio()
+    }
+
+    // A panic while holding the lock can only come from a render which leaves the cache
+    // consistent.
+    fn read(&self) -> RwLockReadGuard<'_, RenderCache> {
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, RenderCache> {
+        self.0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Flags, OptionsBuilder};
+
+    #[test]
+    fn hit_avoids_render() -> Result<()> {
+        let options = Options::default();
+        let mut cache = RenderCache::new(2);
+
+        let first = cache.get_or_render("box", &options)?;
+        let second = cache.get_or_render("box", &options)?;
+
+        assert_eq!(first, second);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.misses(), 1);
+        assert_eq!(cache.hit_ratio(), 0.5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn flags_are_part_of_the_key() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.flags(Flags::DARK_MODE);
+        let dark = builder.build();
+        let light = Options::default();
+        let mut cache = RenderCache::new(2);
+
+        let a = cache.get_or_render("box", &light)?;
+        let b = cache.get_or_render("box", &dark)?;
+
+        assert_ne!(a, b);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.hits(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn options_are_part_of_the_key() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.class("diagram").view_box(0.0, 0.0, 10.0, 10.0);
+        let custom = builder.build();
+        let mut cache = RenderCache::new(2);
+
+        let a = cache.get_or_render("box", &Options::default())?;
+        let b = cache.get_or_render("box", &custom)?;
+
+        assert_ne!(a, b);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get_or_render("box", &custom)?, b);
+        assert_eq!(cache.hits(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn evicts_least_recently_used() -> Result<()> {
+        let options = Options::default();
+        let mut cache = RenderCache::new(2);
+
+        cache.get_or_render("box", &options)?;
+        cache.get_or_render("circle", &options)?;
+        cache.get_or_render("box", &options)?;
+        cache.get_or_render("oval", &options)?;
+
+        assert_eq!(cache.len(), 2);
+
+        // `circle` was the least recently used entry.
+        cache.get_or_render("circle", &options)?;
+
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.misses(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn miss_after_clear() -> Result<()> {
+        let options = Options::default();
+        let mut cache = RenderCache::new(2);
+
+        cache.get_or_render("box", &options)?;
+        cache.clear();
+
+        assert!(cache.is_empty());
+
+        cache.get_or_render("box", &options)?;
+
+        assert_eq!(cache.hits(), 0);
+        assert_eq!(cache.misses(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let options = Options::default();
+        let mut cache = RenderCache::new(2);
+
+        assert!(cache.get_or_render("circ \"1\"", &options).is_err());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn shared_across_threads() -> Result<()> {
+        let cache = SharedRenderCache::new(2);
+        let options = Options::default();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let cache = cache.clone();
+                let options = options.clone();
+                std::thread::spawn(move || cache.get_or_render("box", &options))
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.hit_ratio(), 0.75);
+
+        Ok(())
+    }
+
+    #[test]
+    fn shared_hit_after_render_in_other_thread() -> Result<()> {
+        let cache = SharedRenderCache::new(2);
+        let options = Options::default();
+
+        let render_in_thread = || {
+            let cache = cache.clone();
+            let options = options.clone();
+            std::thread::spawn(move || cache.render("box", &options))
+                .join()
+                .unwrap()
+        };
+
+        let a = render_in_thread()?;
+
+        assert_eq!(cache.read().misses(), 1);
+
+        let b = render_in_thread()?;
+
+        assert_eq!(a, b);
+        assert_eq!(a, crate::render("box")?);
+        assert_eq!(cache.read().hits(), 1);
+        assert_eq!(cache.read().misses(), 1);
+
+        Ok(())
+    }
+}
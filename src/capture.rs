@@ -0,0 +1,177 @@
+//! Capture of anything the native library writes to stderr while rendering.
+
+use crate::{render_with, Options, PikchrOutput, Result};
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::io::FromRawFd;
+use std::sync::Mutex;
+
+/// Serialises the redirections as stderr is shared by the whole process.
+static STDERR_LOCK: Mutex<()> = Mutex::new(());
+
+/// Renders the given pikchr markup capturing what pikchr writes to stderr.
+///
+/// Returns the output with the captured text, empty in normal operation. Stderr is redirected to
+/// a pipe for the duration of the render so anything else the process writes to it in the
+/// meantime is captured as well. The pipe is drained by a thread while rendering so writes of
+/// any size complete.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_capturing_stderr, Options};
+///
+/// let (output, stderr) = render_capturing_stderr("box", Options::default()).unwrap();
+///
+/// assert!(output.svg.starts_with("<svg"));
+/// assert!(stderr.is_empty());
+/// ```
+///
+/// ## Errors
+///
+/// Fails with [`crate::PiktErrorReason::Io`] if stderr cannot be redirected, or with any of the
+/// errors of [`render_with`]. The captured text is discarded on failure.
+pub fn render_capturing_stderr(input: &str, options: Options) -> Result<(PikchrOutput, String)> {
+    let (result, captured) = capture_stderr(|| render_with(input, options))?;
+
+    result.map(|output| (output, String::from_utf8_lossy(&captured).into_owned()))
+}
+
+/// Runs `f` with stderr redirected to a pipe, returning its result and what was written.
+fn capture_stderr<T>(f: impl FnOnce() -> T) -> io::Result<(T, Vec<u8>)> {
+    let _guard = STDERR_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let [read_fd, write_fd] = fds;
+    // Owning the read end closes it on every path.
+    let mut reader = unsafe { File::from_raw_fd(read_fd) };
+
+    // Reads until every write end is closed, which at the latest is when stderr is restored.
+    let drain = std::thread::Builder::new()
+        .name("pikt-stderr".to_string())
+        .spawn(move || {
+            let mut captured = Vec::new();
+            reader.read_to_end(&mut captured).map(|_| captured)
+        });
+    let drain = match drain {
+        Ok(drain) => drain,
+        Err(err) => {
+            unsafe { libc::close(write_fd) };
+            return Err(err);
+        }
+    };
+
+    let saved = unsafe { libc::dup(libc::STDERR_FILENO) };
+    if saved == -1 {
+        let err = io::Error::last_os_error();
+        unsafe { libc::close(write_fd) };
+        return Err(err);
+    }
+
+    if unsafe { libc::dup2(write_fd, libc::STDERR_FILENO) } == -1 {
+        let err = io::Error::last_os_error();
+        unsafe {
+            libc::close(write_fd);
+            libc::close(saved);
+        }
+        return Err(err);
+    }
+    unsafe { libc::close(write_fd) };
+
+    let result = f();
+
+    // Restoring stderr closes the last write end of the pipe so the drain stops at the end.
+    let restored = unsafe { libc::dup2(saved, libc::STDERR_FILENO) };
+    unsafe { libc::close(saved) };
+    if restored == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let captured = drain
+        .join()
+        .map_err(|_| io::Error::other("the stderr reader panicked"))??;
+
+    Ok((result, captured))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PiktErrorReason;
+
+    fn stderr_identity() -> (libc::dev_t, libc::ino_t) {
+        let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+        assert_eq!(unsafe { libc::fstat(libc::STDERR_FILENO, &mut stat) }, 0);
+
+        (stat.st_dev, stat.st_ino)
+    }
+
+    #[test]
+    fn success_without_output() -> Result<()> {
+        let (output, stderr) = render_capturing_stderr("box \"pikchr\"", Options::default())?;
+
+        assert!(output.svg.starts_with("<svg"));
+        assert_eq!(stderr, "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_path() {
+        let actual = render_capturing_stderr("box 'pikchr'", Options::default()).unwrap_err();
+
+        assert_eq!(actual.reason(), &PiktErrorReason::TokenUnknown);
+    }
+
+    #[test]
+    fn stderr_is_restored() {
+        let before = stderr_identity();
+
+        let _ = render_capturing_stderr("box", Options::default());
+        let _ = render_capturing_stderr("box 'pikchr'", Options::default());
+
+        assert_eq!(stderr_identity(), before);
+
+        // An empty write checks the descriptor is usable without polluting the test output.
+        let message = b"";
+        let written = unsafe {
+            libc::write(
+                libc::STDERR_FILENO,
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+            )
+        };
+        assert_eq!(written, 0);
+    }
+
+    #[test]
+    fn captures_beyond_pipe_capacity() -> io::Result<()> {
+        let message = vec![b'x'; 256 * 1024];
+
+        let (written, captured) = capture_stderr(|| {
+            let mut written = 0;
+            while written < message.len() {
+                let n = unsafe {
+                    libc::write(
+                        libc::STDERR_FILENO,
+                        message[written..].as_ptr() as *const libc::c_void,
+                        message.len() - written,
+                    )
+                };
+                assert!(n > 0);
+                written += n as usize;
+            }
+            written
+        })?;
+
+        assert_eq!(written, message.len());
+        assert_eq!(captured, message);
+
+        Ok(())
+    }
+}
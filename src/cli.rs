@@ -0,0 +1,168 @@
+//! Conversion of options to and from command-line arguments, for tools calling a pikchr CLI as
+//! a subprocess.
+//!
+//! The arguments are `--class <name>`, `--dark-mode`, `--width <n>` and `--height <n>`. Other
+//! options have no command-line equivalent and are not converted.
+
+use crate::{Flags, Options, OptionsBuilder};
+use thiserror::Error;
+
+/// The error returned by [`Options::from_cli_args`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CliArgsError {
+    #[error("unknown argument: {0}")]
+    UnknownFlag(String),
+    #[error("missing value for {0}")]
+    MissingValue(String),
+    #[error("invalid value for {flag}: {value}")]
+    InvalidValue { flag: String, value: String },
+}
+
+impl Options {
+    /// Converts the options to command-line arguments. See [`Options::from_cli_args`].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::OptionsBuilder;
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.dark_mode();
+    ///
+    /// assert_eq!(builder.build().to_cli_args(), ["--class", "pikchr", "--dark-mode"]);
+    /// ```
+    pub fn to_cli_args(&self) -> Vec<String> {
+        let mut args = vec!["--class".to_string(), self.class().to_string()];
+
+        if self.flags().contains(Flags::DARK_MODE) {
+            args.push("--dark-mode".to_string());
+        }
+
+        if self.width() != 0 {
+            args.push("--width".to_string());
+            args.push(self.width().to_string());
+        }
+
+        if self.height() != 0 {
+            args.push("--height".to_string());
+            args.push(self.height().to_string());
+        }
+
+        args
+    }
+
+    /// Parses the options from command-line arguments as produced by [`Options::to_cli_args`].
+    ///
+    /// ## Errors
+    ///
+    /// - [`CliArgsError::UnknownFlag`] for any other argument.
+    /// - [`CliArgsError::MissingValue`] if `--class`, `--width` or `--height` is the last
+    ///   argument.
+    /// - [`CliArgsError::InvalidValue`] if the class contains a nul byte or a dimension is not a
+    ///   number.
+    pub fn from_cli_args(args: &[&str]) -> Result<Options, CliArgsError> {
+        let mut builder = OptionsBuilder::default();
+        let mut args = args.iter();
+
+        while let Some(&flag) = args.next() {
+            match flag {
+                "--dark-mode" => {
+                    builder.dark_mode();
+                }
+                "--class" | "--width" | "--height" => {
+                    let value = *args
+                        .next()
+                        .ok_or_else(|| CliArgsError::MissingValue(flag.to_string()))?;
+                    let invalid = || CliArgsError::InvalidValue {
+                        flag: flag.to_string(),
+                        value: value.to_string(),
+                    };
+
+                    match flag {
+                        "--class" => {
+                            builder.try_class(value).map_err(|_| invalid())?;
+                        }
+                        "--width" => {
+                            builder.width(value.parse().map_err(|_| invalid())?);
+                        }
+                        _ => {
+                            builder.height(value.parse().map_err(|_| invalid())?);
+                        }
+                    }
+                }
+                _ => return Err(CliArgsError::UnknownFlag(flag.to_string())),
+            }
+        }
+
+        Ok(builder.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(options: &Options) -> Options {
+        let args = options.to_cli_args();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        Options::from_cli_args(&args).unwrap()
+    }
+
+    #[test]
+    fn light_mode_args() {
+        let mut builder = OptionsBuilder::default();
+        builder.class("diagram");
+
+        assert_eq!(builder.build().to_cli_args(), ["--class", "diagram"]);
+    }
+
+    #[test]
+    fn round_trips() {
+        for dark in [false, true] {
+            for class in ["pikchr", "diagram wide", ""] {
+                let mut builder = OptionsBuilder::default();
+                builder.class(class);
+                builder.width(if dark { 300 } else { 0 });
+                builder.height(150);
+                if dark {
+                    builder.dark_mode();
+                }
+                let options = builder.build();
+
+                assert_eq!(round_trip(&options), options);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_flag() {
+        assert_eq!(
+            Options::from_cli_args(&["--minify"]),
+            Err(CliArgsError::UnknownFlag("--minify".to_string()))
+        );
+    }
+
+    #[test]
+    fn missing_value() {
+        assert_eq!(
+            Options::from_cli_args(&["--dark-mode", "--class"]),
+            Err(CliArgsError::MissingValue("--class".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_value() {
+        assert_eq!(
+            Options::from_cli_args(&["--width", "wide"]),
+            Err(CliArgsError::InvalidValue {
+                flag: "--width".to_string(),
+                value: "wide".to_string()
+            })
+        );
+        assert!(matches!(
+            Options::from_cli_args(&["--class", "a\0b"]),
+            Err(CliArgsError::InvalidValue { .. })
+        ));
+    }
+}
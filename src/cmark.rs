@@ -0,0 +1,120 @@
+//! Rendering of the pikchr code blocks of a [`pulldown_cmark`] event stream.
+//!
+//! ```
+//! use pikt::cmark::{render_events, OnError};
+//! use pikt::Options;
+//! use pulldown_cmark::{Event, Parser};
+//!
+//! let parser = Parser::new("```pikchr\nbox\n```\n");
+//! let events: Vec<_> = render_events(parser, Options::default(), OnError::Fail).collect();
+//!
+//! assert!(matches!(&events[0], Event::Html(svg) if svg.starts_with("<svg")));
+//! ```
+
+use crate::markdown::is_pikchr_info;
+use crate::{render_with, Options, PiktError};
+use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
+use std::collections::VecDeque;
+
+/// What [`render_events`] does with a block that fails to render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    /// Emits the events of the code block unchanged.
+    Passthrough,
+    /// Ends the stream before the code block.
+    Fail,
+}
+
+/// Replaces each fenced code block with `pikchr` as its language with a single
+/// [`Event::Html`] holding the rendered SVG. The other events are passed through unchanged.
+///
+/// The errors of the blocks that failed to render are kept in [`RenderEvents::errors`], so
+/// iterate with [`Iterator::by_ref`] to read them afterwards.
+pub fn render_events<'a, I>(events: I, options: Options, on_error: OnError) -> RenderEvents<'a, I>
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    RenderEvents {
+        events,
+        options,
+        on_error,
+        pending: VecDeque::new(),
+        errors: Vec::new(),
+        failed: false,
+    }
+}
+
+/// The iterator returned by [`render_events`].
+#[derive(Debug)]
+pub struct RenderEvents<'a, I> {
+    events: I,
+    options: Options,
+    on_error: OnError,
+    /// The events of a block that failed to render with [`OnError::Passthrough`].
+    pending: VecDeque<Event<'a>>,
+    errors: Vec<PiktError>,
+    failed: bool,
+}
+
+impl<'a, I> RenderEvents<'a, I> {
+    /// The errors of the blocks that failed to render so far, in document order. With
+    /// [`OnError::Fail`] there is at most one.
+    pub fn errors(&self) -> &[PiktError] {
+        &self.errors
+    }
+}
+
+impl<'a, I> Iterator for RenderEvents<'a, I>
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+
+            if self.failed {
+                return None;
+            }
+
+            let event = self.events.next()?;
+
+            match &event {
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))
+                    if is_pikchr_info(info) => {}
+                _ => return Some(event),
+            }
+
+            let mut block = vec![event];
+            let mut source = String::new();
+
+            for event in self.events.by_ref() {
+                let is_end = matches!(event, Event::End(TagEnd::CodeBlock));
+
+                if let Event::Text(text) = &event {
+                    source.push_str(text);
+                }
+                block.push(event);
+
+                if is_end {
+                    break;
+                }
+            }
+
+            match render_with(&source, self.options.clone()) {
+                Ok(output) => return Some(Event::Html(output.svg.into())),
+                Err(err) => {
+                    self.errors.push(err);
+
+                    match self.on_error {
+                        OnError::Passthrough => self.pending.extend(block),
+                        OnError::Fail => self.failed = true,
+                    }
+                }
+            }
+        }
+    }
+}
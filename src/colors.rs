@@ -0,0 +1,247 @@
+//! The colour names known to pikchr, e.g. for validation and autocompletion.
+//!
+//! These are the CSS named colours plus `None` and `Off`, which pikchr uses for no colour.
+
+/// A colour as its red, green and blue components.
+pub type Rgb = (u8, u8, u8);
+
+/// The colour names as written in the pikchr source, in case-insensitive alphabetical order, with
+/// their RGB value. The value is `None` for the names meaning no colour.
+pub const NAMED_COLORS: &[(&str, Option<Rgb>)] = &[
+    ("AliceBlue", Some((240, 248, 255))),
+    ("AntiqueWhite", Some((250, 235, 215))),
+    ("Aqua", Some((0, 255, 255))),
+    ("Aquamarine", Some((127, 255, 212))),
+    ("Azure", Some((240, 255, 255))),
+    ("Beige", Some((245, 245, 220))),
+    ("Bisque", Some((255, 228, 196))),
+    ("Black", Some((0, 0, 0))),
+    ("BlanchedAlmond", Some((255, 235, 205))),
+    ("Blue", Some((0, 0, 255))),
+    ("BlueViolet", Some((138, 43, 226))),
+    ("Brown", Some((165, 42, 42))),
+    ("BurlyWood", Some((222, 184, 135))),
+    ("CadetBlue", Some((95, 158, 160))),
+    ("Chartreuse", Some((127, 255, 0))),
+    ("Chocolate", Some((210, 105, 30))),
+    ("Coral", Some((255, 127, 80))),
+    ("CornflowerBlue", Some((100, 149, 237))),
+    ("Cornsilk", Some((255, 248, 220))),
+    ("Crimson", Some((220, 20, 60))),
+    ("Cyan", Some((0, 255, 255))),
+    ("DarkBlue", Some((0, 0, 139))),
+    ("DarkCyan", Some((0, 139, 139))),
+    ("DarkGoldenrod", Some((184, 134, 11))),
+    ("DarkGray", Some((169, 169, 169))),
+    ("DarkGreen", Some((0, 100, 0))),
+    ("DarkGrey", Some((169, 169, 169))),
+    ("DarkKhaki", Some((189, 183, 107))),
+    ("DarkMagenta", Some((139, 0, 139))),
+    ("DarkOliveGreen", Some((85, 107, 47))),
+    ("DarkOrange", Some((255, 140, 0))),
+    ("DarkOrchid", Some((153, 50, 204))),
+    ("DarkRed", Some((139, 0, 0))),
+    ("DarkSalmon", Some((233, 150, 122))),
+    ("DarkSeaGreen", Some((143, 188, 143))),
+    ("DarkSlateBlue", Some((72, 61, 139))),
+    ("DarkSlateGray", Some((47, 79, 79))),
+    ("DarkSlateGrey", Some((47, 79, 79))),
+    ("DarkTurquoise", Some((0, 206, 209))),
+    ("DarkViolet", Some((148, 0, 211))),
+    ("DeepPink", Some((255, 20, 147))),
+    ("DeepSkyBlue", Some((0, 191, 255))),
+    ("DimGray", Some((105, 105, 105))),
+    ("DimGrey", Some((105, 105, 105))),
+    ("DodgerBlue", Some((30, 144, 255))),
+    ("Firebrick", Some((178, 34, 34))),
+    ("FloralWhite", Some((255, 250, 240))),
+    ("ForestGreen", Some((34, 139, 34))),
+    ("Fuchsia", Some((255, 0, 255))),
+    ("Gainsboro", Some((220, 220, 220))),
+    ("GhostWhite", Some((248, 248, 255))),
+    ("Gold", Some((255, 215, 0))),
+    ("Goldenrod", Some((218, 165, 32))),
+    ("Gray", Some((128, 128, 128))),
+    ("Green", Some((0, 128, 0))),
+    ("GreenYellow", Some((173, 255, 47))),
+    ("Grey", Some((128, 128, 128))),
+    ("Honeydew", Some((240, 255, 240))),
+    ("HotPink", Some((255, 105, 180))),
+    ("IndianRed", Some((205, 92, 92))),
+    ("Indigo", Some((75, 0, 130))),
+    ("Ivory", Some((255, 255, 240))),
+    ("Khaki", Some((240, 230, 140))),
+    ("Lavender", Some((230, 230, 250))),
+    ("LavenderBlush", Some((255, 240, 245))),
+    ("LawnGreen", Some((124, 252, 0))),
+    ("LemonChiffon", Some((255, 250, 205))),
+    ("LightBlue", Some((173, 216, 230))),
+    ("LightCoral", Some((240, 128, 128))),
+    ("LightCyan", Some((224, 255, 255))),
+    ("LightGoldenrodYellow", Some((250, 250, 210))),
+    ("LightGray", Some((211, 211, 211))),
+    ("LightGreen", Some((144, 238, 144))),
+    ("LightGrey", Some((211, 211, 211))),
+    ("LightPink", Some((255, 182, 193))),
+    ("LightSalmon", Some((255, 160, 122))),
+    ("LightSeaGreen", Some((32, 178, 170))),
+    ("LightSkyBlue", Some((135, 206, 250))),
+    ("LightSlateGray", Some((119, 136, 153))),
+    ("LightSlateGrey", Some((119, 136, 153))),
+    ("LightSteelBlue", Some((176, 196, 222))),
+    ("LightYellow", Some((255, 255, 224))),
+    ("Lime", Some((0, 255, 0))),
+    ("LimeGreen", Some((50, 205, 50))),
+    ("Linen", Some((250, 240, 230))),
+    ("Magenta", Some((255, 0, 255))),
+    ("Maroon", Some((128, 0, 0))),
+    ("MediumAquamarine", Some((102, 205, 170))),
+    ("MediumBlue", Some((0, 0, 205))),
+    ("MediumOrchid", Some((186, 85, 211))),
+    ("MediumPurple", Some((147, 112, 219))),
+    ("MediumSeaGreen", Some((60, 179, 113))),
+    ("MediumSlateBlue", Some((123, 104, 238))),
+    ("MediumSpringGreen", Some((0, 250, 154))),
+    ("MediumTurquoise", Some((72, 209, 204))),
+    ("MediumVioletRed", Some((199, 21, 133))),
+    ("MidnightBlue", Some((25, 25, 112))),
+    ("MintCream", Some((245, 255, 250))),
+    ("MistyRose", Some((255, 228, 225))),
+    ("Moccasin", Some((255, 228, 181))),
+    ("NavajoWhite", Some((255, 222, 173))),
+    ("Navy", Some((0, 0, 128))),
+    ("None", None),
+    ("Off", None),
+    ("OldLace", Some((253, 245, 230))),
+    ("Olive", Some((128, 128, 0))),
+    ("OliveDrab", Some((107, 142, 35))),
+    ("Orange", Some((255, 165, 0))),
+    ("OrangeRed", Some((255, 69, 0))),
+    ("Orchid", Some((218, 112, 214))),
+    ("PaleGoldenrod", Some((238, 232, 170))),
+    ("PaleGreen", Some((152, 251, 152))),
+    ("PaleTurquoise", Some((175, 238, 238))),
+    ("PaleVioletRed", Some((219, 112, 147))),
+    ("PapayaWhip", Some((255, 239, 213))),
+    ("PeachPuff", Some((255, 218, 185))),
+    ("Peru", Some((205, 133, 63))),
+    ("Pink", Some((255, 192, 203))),
+    ("Plum", Some((221, 160, 221))),
+    ("PowderBlue", Some((176, 224, 230))),
+    ("Purple", Some((128, 0, 128))),
+    ("RebeccaPurple", Some((102, 51, 153))),
+    ("Red", Some((255, 0, 0))),
+    ("RosyBrown", Some((188, 143, 143))),
+    ("RoyalBlue", Some((65, 105, 225))),
+    ("SaddleBrown", Some((139, 69, 19))),
+    ("Salmon", Some((250, 128, 114))),
+    ("SandyBrown", Some((244, 164, 96))),
+    ("SeaGreen", Some((46, 139, 87))),
+    ("Seashell", Some((255, 245, 238))),
+    ("Sienna", Some((160, 82, 45))),
+    ("Silver", Some((192, 192, 192))),
+    ("SkyBlue", Some((135, 206, 235))),
+    ("SlateBlue", Some((106, 90, 205))),
+    ("SlateGray", Some((112, 128, 144))),
+    ("SlateGrey", Some((112, 128, 144))),
+    ("Snow", Some((255, 250, 250))),
+    ("SpringGreen", Some((0, 255, 127))),
+    ("SteelBlue", Some((70, 130, 180))),
+    ("Tan", Some((210, 180, 140))),
+    ("Teal", Some((0, 128, 128))),
+    ("Thistle", Some((216, 191, 216))),
+    ("Tomato", Some((255, 99, 71))),
+    ("Turquoise", Some((64, 224, 208))),
+    ("Violet", Some((238, 130, 238))),
+    ("Wheat", Some((245, 222, 179))),
+    ("White", Some((255, 255, 255))),
+    ("WhiteSmoke", Some((245, 245, 245))),
+    ("Yellow", Some((255, 255, 0))),
+    ("YellowGreen", Some((154, 205, 50))),
+];
+
+/// Whether pikchr knows the colour name. Like pikchr, the lookup is case-insensitive.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::colors::is_valid_color_name;
+///
+/// assert!(is_valid_color_name("SteelBlue"));
+/// assert!(is_valid_color_name("steelblue"));
+/// assert!(!is_valid_color_name("steel"));
+/// ```
+pub fn is_valid_color_name(name: &str) -> bool {
+    lookup(name).is_some()
+}
+
+/// The RGB value of the colour name, ignoring case.
+///
+/// Returns `None` for unknown names and for `None` and `Off`, which have no colour.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::colors::color_name_to_rgb;
+///
+/// assert_eq!(color_name_to_rgb("red"), Some((255, 0, 0)));
+/// ```
+pub fn color_name_to_rgb(name: &str) -> Option<Rgb> {
+    lookup(name)?
+}
+
+/// The RGB value of the colour name if pikchr knows it.
+fn lookup(name: &str) -> Option<Option<Rgb>> {
+    NAMED_COLORS
+        .iter()
+        .find(|(color, _)| color.eq_ignore_ascii_case(name))
+        .map(|(_, rgb)| *rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    #[test]
+    fn table_is_complete() {
+        assert_eq!(NAMED_COLORS.len(), 150);
+        assert!(NAMED_COLORS
+            .windows(2)
+            .all(|pair| pair[0].0.to_ascii_lowercase() < pair[1].0.to_ascii_lowercase()));
+    }
+
+    #[test]
+    fn known_colors() {
+        assert_eq!(color_name_to_rgb("red"), Some((255, 0, 0)));
+        assert_eq!(color_name_to_rgb("blue"), Some((0, 0, 255)));
+        assert_eq!(color_name_to_rgb("green"), Some((0, 128, 0)));
+        assert_eq!(color_name_to_rgb("RebeccaPurple"), Some((102, 51, 153)));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        assert_eq!(color_name_to_rgb("RED"), color_name_to_rgb("Red"));
+        assert!(is_valid_color_name("aliceblue"));
+    }
+
+    #[test]
+    fn unknown_colors() {
+        assert_eq!(color_name_to_rgb("reddish"), None);
+        assert!(!is_valid_color_name(""));
+    }
+
+    #[test]
+    fn no_color() {
+        assert!(is_valid_color_name("none"));
+        assert_eq!(color_name_to_rgb("None"), None);
+        assert_eq!(color_name_to_rgb("off"), None);
+    }
+
+    #[test]
+    fn names_are_accepted_by_pikchr() {
+        for (name, _) in NAMED_COLORS {
+            assert!(render(&format!("box color {}", name)).is_ok(), "{}", name);
+        }
+    }
+}
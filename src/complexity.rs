@@ -0,0 +1,226 @@
+//! A rough estimate of the size of a diagram from its source, e.g. to reject or queue large
+//! diagrams before rendering them.
+
+/// The size of a diagram as estimated by [`estimate_complexity`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiagramComplexity {
+    pub line_count: usize,
+    /// The number of whitespace separated words.
+    pub approx_token_count: usize,
+    /// The number of shape keywords outside of strings.
+    pub approx_element_count: usize,
+}
+
+/// A coarse size class of a diagram, ordered from the smallest, as computed by
+/// [`classify_complexity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ComplexityClass {
+    Simple,
+    Moderate,
+    Complex,
+    /// Likely too large or too deeply nested to render in reasonable time and memory.
+    Excessive,
+}
+
+impl std::fmt::Display for ComplexityClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Simple => "simple",
+            Self::Moderate => "moderate",
+            Self::Complex => "complex",
+            Self::Excessive => "excessive",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// The keywords of the pikchr objects.
+pub(crate) const SHAPES: [&str; 10] = [
+    "box", "circle", "arrow", "line", "cylinder", "file", "oval", "arc", "spline", "dot",
+];
+
+/// Estimates the size of a diagram without parsing it.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::estimate_complexity;
+///
+/// let actual = estimate_complexity("box \"a box\"\narrow");
+///
+/// assert_eq!(actual.line_count, 2);
+/// assert_eq!(actual.approx_token_count, 4);
+/// assert_eq!(actual.approx_element_count, 2);
+/// ```
+pub fn estimate_complexity(input: &str) -> DiagramComplexity {
+    DiagramComplexity {
+        line_count: input.lines().count(),
+        approx_token_count: input.split_ascii_whitespace().count(),
+        approx_element_count: strip_strings(input)
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| SHAPES.contains(word))
+            .count(),
+    }
+}
+
+/// Classifies a diagram from its [`estimate_complexity`] and the deepest nesting of braces, as
+/// used by macro bodies and sublists.
+///
+/// A class is reached when any of the measures reaches its threshold:
+///
+/// | Class       | Lines  | Tokens  | Elements | Nesting |
+/// |-------------|--------|---------|----------|---------|
+/// | `Moderate`  | 50     | 200     | 20       | 2       |
+/// | `Complex`   | 500    | 2,000   | 100      | 4       |
+/// | `Excessive` | 10,000 | 100,000 | 10,000   | 16      |
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{classify_complexity, ComplexityClass};
+///
+/// assert_eq!(classify_complexity("box; arrow; circle"), ComplexityClass::Simple);
+/// assert_eq!(classify_complexity(&"box\n".repeat(200)), ComplexityClass::Complex);
+/// ```
+pub fn classify_complexity(input: &str) -> ComplexityClass {
+    let estimate = estimate_complexity(input);
+    let measures = [
+        estimate.line_count,
+        estimate.approx_token_count,
+        estimate.approx_element_count,
+        max_nesting_depth(input),
+    ];
+    let reaches = |thresholds: [usize; 4]| {
+        measures
+            .iter()
+            .zip(thresholds)
+            .any(|(measure, threshold)| *measure >= threshold)
+    };
+
+    if reaches([10_000, 100_000, 10_000, 16]) {
+        ComplexityClass::Excessive
+    } else if reaches([500, 2_000, 100, 4]) {
+        ComplexityClass::Complex
+    } else if reaches([50, 200, 20, 2]) {
+        ComplexityClass::Moderate
+    } else {
+        ComplexityClass::Simple
+    }
+}
+
+/// The deepest nesting of braces outside of strings. Unbalanced closing braces are ignored.
+fn max_nesting_depth(input: &str) -> usize {
+    let mut depth: usize = 0;
+    let mut max = 0;
+
+    for c in strip_strings(input).chars() {
+        match c {
+            '{' => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    max
+}
+
+/// Removes the double-quoted strings so that labels aren't counted as keywords.
+fn strip_strings(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in input.chars() {
+        match (in_string, c) {
+            (true, _) if escaped => escaped = false,
+            (true, '\\') => escaped = true,
+            (_, '"') => {
+                in_string = !in_string;
+                output.push(' ');
+            }
+            (true, _) => {}
+            (false, c) => output.push(c),
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_element() {
+        assert_eq!(estimate_complexity("box").approx_element_count, 1);
+    }
+
+    #[test]
+    fn complex_diagram() {
+        let simple = estimate_complexity("box");
+        let actual = estimate_complexity(
+            "box \"start\"; arrow\ncircle \"a box\" fit\nline dashed; dot\nspline -> right then down\n",
+        );
+
+        assert_eq!(actual.line_count, 4);
+        assert_eq!(actual.approx_element_count, 6);
+        assert!(actual.approx_token_count > simple.approx_token_count);
+    }
+
+    #[test]
+    fn keywords_need_whole_words() {
+        assert_eq!(
+            estimate_complexity("box linewidth 2 \"a \\\" box\"").approx_element_count,
+            1
+        );
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(estimate_complexity(""), DiagramComplexity::default());
+    }
+
+    #[test]
+    fn simple_inputs() {
+        assert_eq!(classify_complexity(""), ComplexityClass::Simple);
+        assert_eq!(
+            classify_complexity("box \"a\"; arrow; circle \"{b}\""),
+            ComplexityClass::Simple
+        );
+        assert_eq!(
+            classify_complexity("define a { box $1 }\na(\"x\")"),
+            ComplexityClass::Simple
+        );
+    }
+
+    #[test]
+    fn many_elements() {
+        let input = "box; arrow\n".repeat(100);
+
+        assert_eq!(classify_complexity(&input), ComplexityClass::Complex);
+        assert_eq!(
+            classify_complexity(&"box\n".repeat(30)),
+            ComplexityClass::Moderate
+        );
+    }
+
+    #[test]
+    fn deeply_nested() {
+        let input = format!("{}box{}", "define m { ".repeat(20), " }".repeat(20));
+
+        assert_eq!(max_nesting_depth(&input), 20);
+        assert_eq!(classify_complexity(&input), ComplexityClass::Excessive);
+    }
+
+    #[test]
+    fn classes_are_ordered() {
+        assert!(ComplexityClass::Simple < ComplexityClass::Moderate);
+        assert!(ComplexityClass::Complex < ComplexityClass::Excessive);
+        assert_eq!(ComplexityClass::Excessive.to_string(), "excessive");
+    }
+}
@@ -0,0 +1,374 @@
+//! Options as TOML or JSON configuration.
+
+use crate::{ComplexityClass, Flags, Options, OptionsBuilder, OutputFormat, ViewBox};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// The serialized representation of [`Options`]. Missing keys take the default value.
+///
+/// `flags` lists flags by name, e.g. `["DARK_MODE"]`, and is combined with `dark_mode`. Unset
+/// and empty options are not written. `view_box` comes last as it is a table in TOML.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigOptions {
+    dark_mode: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    flags: Vec<String>,
+    class: String,
+    width: u32,
+    height: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    svg_attributes: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    data_attributes: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aria_label: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    css_variables: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    css: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background_color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+    minify: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pretty_print: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_bytes: Option<usize>,
+    max_input_bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_complexity: Option<ComplexityClass>,
+    inject_dimensions: bool,
+    annotate_elements: bool,
+    output_format: OutputFormat,
+    standalone: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    view_box: Option<ViewBox>,
+}
+
+impl Default for ConfigOptions {
+    fn default() -> Self {
+        (&Options::default()).into()
+    }
+}
+
+impl From<&Options> for ConfigOptions {
+    fn from(options: &Options) -> Self {
+        Self {
+            dark_mode: options.flags.contains(Flags::DARK_MODE),
+            flags: Vec::new(),
+            class: options.class.clone(),
+            width: options.width,
+            height: options.height,
+            svg_attributes: options.svg_attributes.clone(),
+            data_attributes: options.data_attributes.clone(),
+            title: options.title.clone(),
+            description: options.description.clone(),
+            aria_label: options.aria_label.clone(),
+            css_variables: options.css_variables.clone(),
+            css: options.css.clone(),
+            background_color: options.background_color.clone(),
+            id_prefix: options.id_prefix.clone(),
+            namespace: options.namespace.clone(),
+            minify: options.minify,
+            pretty_print: options.pretty_print,
+            max_output_bytes: options.max_output_bytes,
+            max_input_bytes: options.max_input_bytes,
+            max_complexity: options.max_complexity,
+            inject_dimensions: options.inject_dimensions,
+            annotate_elements: options.annotate_elements,
+            output_format: options.output_format,
+            standalone: options.standalone,
+            view_box: options.view_box,
+        }
+    }
+}
+
+impl ConfigOptions {
+    /// Builds the options with [`OptionsBuilder::try_build`], so they are validated the same way.
+    fn into_options(self) -> Result<Options, String> {
+        let mut flags = Flags::empty();
+
+        if self.dark_mode {
+            flags.insert(Flags::DARK_MODE);
+        }
+
+        for name in &self.flags {
+            match name.as_str() {
+                "DARK_MODE" => flags.insert(Flags::DARK_MODE),
+                _ => return Err(format!("unknown flag `{}`", name)),
+            };
+        }
+
+        let builder = OptionsBuilder {
+            flags,
+            width: self.width,
+            height: self.height,
+            class: self.class,
+            svg_attributes: self.svg_attributes,
+            data_attributes: self.data_attributes,
+            title: self.title,
+            description: self.description,
+            aria_label: self.aria_label,
+            css_variables: self.css_variables,
+            css: self.css,
+            background_color: self.background_color,
+            id_prefix: self.id_prefix,
+            namespace: self.namespace,
+            view_box: self.view_box,
+            minify: self.minify,
+            pretty_print: self.pretty_print,
+            max_output_bytes: self.max_output_bytes,
+            max_input_bytes: self.max_input_bytes,
+            max_complexity: self.max_complexity,
+            inject_dimensions: self.inject_dimensions,
+            annotate_elements: self.annotate_elements,
+            output_format: self.output_format,
+            standalone: self.standalone,
+            ..OptionsBuilder::default()
+        };
+
+        Options::try_from(builder).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(feature = "toml")]
+impl Options {
+    /// Reads the options from TOML. Unknown keys are rejected.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{Flags, Options};
+    ///
+    /// let options = Options::from_toml("dark_mode = true\nclass = \"diagram\"").unwrap();
+    ///
+    /// assert_eq!(options.flags(), Flags::DARK_MODE);
+    /// assert_eq!(options.class(), "diagram");
+    /// assert_eq!(options.width(), 0);
+    /// ```
+    pub fn from_toml(s: &str) -> Result<Options, toml::de::Error> {
+        use serde::de::Error;
+
+        let config: ConfigOptions = toml::from_str(s)?;
+
+        config.into_options().map_err(toml::de::Error::custom)
+    }
+
+    /// Writes the options as TOML. [`Options::from_toml`] reads them back unchanged.
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(&ConfigOptions::from(self))
+    }
+}
+
+#[cfg(feature = "json")]
+impl Options {
+    /// Reads the options from JSON. Unknown keys are rejected.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{Flags, Options};
+    ///
+    /// let options = Options::from_json(r#"{"flags": ["DARK_MODE"], "width": 300}"#).unwrap();
+    ///
+    /// assert_eq!(options.flags(), Flags::DARK_MODE);
+    /// assert_eq!(options.width(), 300);
+    /// ```
+    pub fn from_json(s: &str) -> Result<Options, serde_json::Error> {
+        use serde::de::Error;
+
+        let config: ConfigOptions = serde_json::from_str(s)?;
+
+        config.into_options().map_err(serde_json::Error::custom)
+    }
+
+    /// Writes the options as JSON. [`Options::from_json`] reads them back unchanged.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&ConfigOptions::from(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom_options() -> Options {
+        let mut builder = OptionsBuilder::default();
+        builder.dark_mode().class("diagram");
+        builder.width(300);
+        builder.height(150);
+        builder.build()
+    }
+
+    fn full_options() -> Options {
+        let mut builder = OptionsBuilder::default();
+        builder
+            .dark_mode()
+            .class("diagram")
+            .svg_attribute("role", "img")
+            .data_attribute("data-id", "1")
+            .title("Title")
+            .description("Description")
+            .aria_label("Label")
+            .css_variable("--fill", "red")
+            .inject_css("svg { margin: 0 }")
+            .background_color("#fff")
+            .id_prefix("d1")
+            .namespace("ns")
+            .view_box(0.0, 0.0, 100.0, 50.0)
+            .minify()
+            .max_output_bytes(4096)
+            .max_input_bytes(1024)
+            .max_complexity(ComplexityClass::Simple)
+            .inject_dimensions()
+            .annotate_elements()
+            .standalone(true);
+        builder.width(300).height(150);
+        builder.build()
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_round_trip() {
+        let options = Options::default();
+
+        let toml = options.to_toml().unwrap();
+
+        assert_eq!(
+            toml,
+            "dark_mode = false\n\
+             class = \"pikchr\"\n\
+             width = 0\n\
+             height = 0\n\
+             minify = false\n\
+             max_input_bytes = 1048576\n\
+             inject_dimensions = false\n\
+             annotate_elements = false\n\
+             output_format = \"Svg\"\n\
+             standalone = true\n"
+        );
+        assert_eq!(Options::from_toml(&toml).unwrap(), options);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_round_trip_custom() {
+        let options = custom_options();
+
+        let actual = Options::from_toml(&options.to_toml().unwrap()).unwrap();
+
+        assert_eq!(actual, options);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_round_trip_full() {
+        let options = full_options();
+
+        let actual = Options::from_toml(&options.to_toml().unwrap()).unwrap();
+
+        assert_eq!(actual, options);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_missing_keys_use_defaults() {
+        let actual = Options::from_toml("width = 100").unwrap();
+
+        assert_eq!(actual.width(), 100);
+        assert_eq!(actual.height(), 0);
+        assert_eq!(actual.class(), "pikchr");
+        assert_eq!(actual.flags(), Flags::empty());
+        assert_eq!(Options::from_toml("").unwrap(), Options::default());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_invalid_type() {
+        assert!(Options::from_toml("width = \"wide\"").is_err());
+        assert!(Options::from_toml("dark_mode = 1").is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_unknown_key() {
+        let err = Options::from_toml("dark_mod = true").unwrap_err();
+
+        assert!(err.to_string().contains("unknown field `dark_mod`"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_nul_in_class() {
+        assert!(Options::from_toml("class = \"a\\u0000b\"").is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        let options = Options::default();
+
+        let json = options.to_json().unwrap();
+
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"dark_mode":false,"class":"pikchr","width":0,"height":0,"minify":false,"#,
+                r#""max_input_bytes":1048576,"inject_dimensions":false,"annotate_elements":false,"#,
+                r#""output_format":"Svg","standalone":true}"#
+            )
+        );
+        assert_eq!(Options::from_json(&json).unwrap(), options);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip_custom() {
+        let options = custom_options();
+
+        let actual = Options::from_json(&options.to_json().unwrap()).unwrap();
+
+        assert_eq!(actual, options);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip_full() {
+        let options = full_options();
+
+        let actual = Options::from_json(&options.to_json().unwrap()).unwrap();
+
+        assert_eq!(actual, options);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_empty_object_uses_defaults() {
+        assert_eq!(Options::from_json("{}").unwrap(), Options::default());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_unknown_key() {
+        let err = Options::from_json(r#"{"dark_mod": true}"#).unwrap_err();
+
+        assert!(err.to_string().contains("unknown field `dark_mod`"));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_flags() {
+        let actual = Options::from_json(r#"{"flags": ["DARK_MODE"]}"#).unwrap();
+
+        assert_eq!(actual.flags(), Flags::DARK_MODE);
+        assert!(Options::from_json(r#"{"flags": ["LIGHT_MODE"]}"#).is_err());
+    }
+}
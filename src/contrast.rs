@@ -0,0 +1,131 @@
+//! Contrast checks of the rendered colours against the page background, following WCAG 2.
+
+use crate::PikchrOutput;
+
+/// The contrast of a diagram against a background, see [`check_contrast`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContrastResult {
+    pub foreground_rgb: (u8, u8, u8),
+    /// From `1.0`, for identical colours, to `21.0`, for black and white.
+    pub ratio: f64,
+    /// Whether the ratio is at least 4.5:1, the WCAG AA level for text.
+    pub passes_wcag_aa: bool,
+    /// Whether the ratio is at least 7:1, the WCAG AAA level for text.
+    pub passes_wcag_aaa: bool,
+}
+
+/// Checks the contrast of the diagram against the given background.
+///
+/// The foreground is the first `stroke:rgb(...)` of the SVG, or black if there is none, as
+/// pikchr uses a single foreground colour unless the source sets others.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{check_contrast, render};
+///
+/// let actual = check_contrast(&render("box").unwrap(), (255, 255, 255));
+///
+/// assert_eq!(actual.foreground_rgb, (0, 0, 0));
+/// assert!(actual.passes_wcag_aaa);
+/// ```
+pub fn check_contrast(output: &PikchrOutput, background_rgb: (u8, u8, u8)) -> ContrastResult {
+    let foreground_rgb = first_stroke(&output.svg).unwrap_or((0, 0, 0));
+    let ratio = contrast_ratio(foreground_rgb, background_rgb);
+
+    ContrastResult {
+        foreground_rgb,
+        ratio,
+        passes_wcag_aa: ratio >= 4.5,
+        passes_wcag_aaa: ratio >= 7.0,
+    }
+}
+
+fn first_stroke(svg: &str) -> Option<(u8, u8, u8)> {
+    svg.match_indices("stroke:rgb(")
+        .find_map(|(start, pattern)| {
+            let rest = &svg[start + pattern.len()..];
+            let mut channels = rest[..rest.find(')')?]
+                .split(',')
+                .map(|channel| channel.trim().parse::<u8>());
+
+            let rgb = (
+                channels.next()?.ok()?,
+                channels.next()?.ok()?,
+                channels.next()?.ok()?,
+            );
+
+            channels.next().is_none().then_some(rgb)
+        })
+}
+
+/// The WCAG relative luminance of an sRGB colour.
+fn relative_luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    let linear = |channel: u8| {
+        let value = channel as f64 / 255.0;
+
+        if value <= 0.03928 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    let (lighter, darker) = if a > b { (a, b) } else { (b, a) };
+
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render, render_dark, Result};
+
+    #[test]
+    fn black_on_white() -> Result<()> {
+        let actual = check_contrast(&render("box")?, (255, 255, 255));
+
+        assert_eq!(actual.foreground_rgb, (0, 0, 0));
+        assert!((actual.ratio - 21.0).abs() < 1e-9);
+        assert!(actual.passes_wcag_aa);
+        assert!(actual.passes_wcag_aaa);
+
+        Ok(())
+    }
+
+    #[test]
+    fn white_on_black() -> Result<()> {
+        let actual = check_contrast(&render_dark("box")?, (0, 0, 0));
+
+        assert_eq!(actual.foreground_rgb, (255, 255, 255));
+        assert!((actual.ratio - 21.0).abs() < 1e-9);
+        assert!(actual.passes_wcag_aa);
+        assert!(actual.passes_wcag_aaa);
+
+        Ok(())
+    }
+
+    #[test]
+    fn low_contrast() -> Result<()> {
+        let actual = check_contrast(&render("box color 0xaaaaaa")?, (255, 255, 255));
+
+        assert_eq!(actual.foreground_rgb, (170, 170, 170));
+        assert!(actual.ratio < 4.5);
+        assert!(!actual.passes_wcag_aa);
+        assert!(!actual.passes_wcag_aaa);
+
+        Ok(())
+    }
+
+    #[test]
+    fn without_stroke() {
+        let output = PikchrOutput::from_parts("<svg></svg>".into(), 0, 0);
+
+        assert_eq!(check_contrast(&output, (0, 0, 0)).foreground_rgb, (0, 0, 0));
+    }
+}
@@ -0,0 +1,273 @@
+//! Human-friendly error reports.
+
+use crate::{render_with, Options, PikchrOutput, PiktError};
+use std::fmt;
+
+/// Where a pikchr source comes from, e.g. a block starting after line 10 of a document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub file: String,
+    /// The number of lines before the source in `file`.
+    pub source_line_offset: usize,
+}
+
+/// A [`PiktError`] located in the file of its [`ErrorContext`].
+///
+/// It displays as `file:line:column: error: reason`, in the style of compilers.
+#[derive(Debug, PartialEq)]
+pub struct ContextualPiktError {
+    /// The error with its line relative to the file. Errors without a location keep line `0`.
+    pub error: PiktError,
+    pub context: ErrorContext,
+}
+
+impl ContextualPiktError {
+    /// Drops the context. The line stays relative to the file.
+    pub fn into_pikt_error(self) -> PiktError {
+        self.error
+    }
+}
+
+impl fmt::Display for ContextualPiktError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: error: {}",
+            self.context.file,
+            self.error.line(),
+            self.error.column(),
+            self.error.reason()
+        )
+    }
+}
+
+impl std::error::Error for ContextualPiktError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Renders the given pikchr markup as SVG reporting errors at their location in the file of
+/// `ctx`.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_with_context_info, ErrorContext, Options};
+///
+/// let ctx = ErrorContext {
+///     file: "README.md".to_string(),
+///     source_line_offset: 10,
+/// };
+/// let err = render_with_context_info("circ \"1\"", Options::default(), &ctx).unwrap_err();
+///
+/// assert_eq!(err.to_string(), "README.md:11:8: error: syntax error");
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`].
+pub fn render_with_context_info(
+    input: &str,
+    options: Options,
+    ctx: &ErrorContext,
+) -> Result<PikchrOutput, ContextualPiktError> {
+    render_with(input, options).map_err(|error| ContextualPiktError {
+        error: error.with_offset(ctx.source_line_offset, 0),
+        context: ctx.clone(),
+    })
+}
+
+/// Formats the error in the style of the Rust compiler, quoting the offending line of `source`
+/// and pointing at the error column with a caret.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{format_diagnostic_rust_style, render};
+///
+/// let source = "circ \"1\"";
+/// let err = render(source).unwrap_err();
+///
+/// assert_eq!(
+///     format_diagnostic_rust_style(&err, source, "diagram.pikchr"),
+///     "error: syntax error\n --> diagram.pikchr:1:8\n  |\n1 | circ \"1\"\n  |        ^\n"
+/// );
+/// ```
+pub fn format_diagnostic_rust_style(error: &PiktError, source: &str, source_label: &str) -> String {
+    let line_number = error.line().to_string();
+    let padding = " ".repeat(line_number.len());
+    let mut output = format!("error: {}\n", error.reason());
+
+    output.push_str(&format!(
+        "{}--> {}:{}:{}\n",
+        padding,
+        source_label,
+        error.line(),
+        error.column()
+    ));
+
+    let source_line = match error.line().checked_sub(1) {
+        Some(index) => source.lines().nth(index),
+        None => None,
+    };
+
+    if let Some(source_line) = source_line {
+        // Keep tabs so the caret lines up with the quoted line.
+        let caret_padding: String = source_line
+            .chars()
+            .take(error.column().saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        output.push_str(&format!("{} |\n", padding));
+        output.push_str(&format!("{} | {}\n", line_number, source_line));
+        output.push_str(&format!("{} | {}^\n", padding, caret_padding));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    fn caret_column(report: &str) -> usize {
+        let caret_line = report.lines().last().unwrap();
+        let (_, rest) = caret_line.split_once(" | ").unwrap();
+
+        rest.find('^').unwrap() + 1
+    }
+
+    #[test]
+    fn unknown_token() {
+        let source = "box 'pikchr'";
+        let err = render(source).unwrap_err();
+
+        let actual = format_diagnostic_rust_style(&err, source, "a.pikchr");
+
+        assert!(actual.starts_with("error: unknown token\n"));
+        assert!(actual.contains(" --> a.pikchr:1:5\n"));
+        assert!(actual.contains("1 | box 'pikchr'\n"));
+        assert_eq!(caret_column(&actual), 5);
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let source = r#"box "pikchr"
+        arrow from first box to (0/0, 0)
+        "#;
+        let err = render(source).unwrap_err();
+
+        let actual = format_diagnostic_rust_style(&err, source, "a.pikchr");
+
+        assert!(actual.starts_with("error: division by zero\n"));
+        assert!(actual.contains("2 |         arrow from first box to (0/0, 0)\n"));
+        assert_eq!(caret_column(&actual), 36);
+    }
+
+    #[test]
+    fn syntax_error() {
+        let source = r#"circ "1""#;
+        let err = render(source).unwrap_err();
+
+        let actual = format_diagnostic_rust_style(&err, source, "a.pikchr");
+
+        assert!(actual.contains("error: syntax error"));
+        assert!(actual.contains(r#"1 | circ "1""#));
+        assert_eq!(caret_column(&actual), 8);
+    }
+
+    #[test]
+    fn unknown_object() {
+        let source = r#"arrow from A to B"#;
+        let err = render(source).unwrap_err();
+
+        let actual = format_diagnostic_rust_style(&err, source, "a.pikchr");
+
+        assert!(actual.contains("error: unknown object"));
+        assert!(actual.contains("1 | arrow from A to B"));
+        assert_eq!(caret_column(&actual), 12);
+    }
+
+    #[test]
+    fn wide_gutter() {
+        let source = format!("{}box 'x'", "box\n".repeat(9));
+        let err = PiktError {
+            line: 10,
+            column: 5,
+            reason: crate::PiktErrorReason::TokenUnknown,
+            raw_message: String::new(),
+        };
+
+        let actual = format_diagnostic_rust_style(&err, &source, "a.pikchr");
+
+        assert_eq!(
+            actual,
+            "error: unknown token\n  --> a.pikchr:10:5\n   |\n10 | box 'x'\n   |     ^\n"
+        );
+    }
+
+    #[test]
+    fn without_location() {
+        let source = "box\0";
+        let err = render(source).unwrap_err();
+
+        let actual = format_diagnostic_rust_style(&err, source, "a.pikchr");
+
+        assert_eq!(
+            actual,
+            "error: incompatible input. Nul bytes are not allowed.\n --> a.pikchr:0:0\n"
+        );
+    }
+
+    fn context(offset: usize) -> ErrorContext {
+        ErrorContext {
+            file: "docs/guide.md".to_string(),
+            source_line_offset: offset,
+        }
+    }
+
+    #[test]
+    fn context_shifts_line() {
+        let source = "box\nbox\ncirc \"1\"";
+        let expected = render(source).unwrap_err();
+
+        let actual =
+            render_with_context_info(source, Options::default(), &context(10)).unwrap_err();
+
+        assert_eq!(expected.line(), 3);
+        assert_eq!(actual.error.line(), 13);
+        assert_eq!(actual.error.column(), expected.column());
+        assert_eq!(actual.error.reason(), expected.reason());
+        assert_eq!(actual.context, context(10));
+        assert_eq!(
+            actual.to_string(),
+            format!(
+                "docs/guide.md:13:{}: error: syntax error",
+                expected.column()
+            )
+        );
+        assert_eq!(actual.into_pikt_error().line(), 13);
+    }
+
+    #[test]
+    fn context_without_location() {
+        let actual =
+            render_with_context_info("box\0", Options::default(), &context(10)).unwrap_err();
+
+        assert_eq!(actual.error.line(), 0);
+        assert!(actual
+            .to_string()
+            .starts_with("docs/guide.md:0:0: error: incompatible input"));
+    }
+
+    #[test]
+    fn context_on_success() {
+        assert_eq!(
+            render_with_context_info("box", Options::default(), &context(10)),
+            Ok(render("box").unwrap())
+        );
+    }
+}
@@ -0,0 +1,675 @@
+//! A struct-centric entry point for rendering.
+
+use crate::{render_with, validate, Options, PikchrOutput, PiktError, PiktErrorReason, Result};
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::convert::{Infallible, TryFrom};
+use std::fmt;
+use std::str::FromStr;
+
+/// A pikchr diagram.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::Diagram;
+///
+/// let diagram: Diagram = "box \"pikchr\"".parse().unwrap();
+/// let output = diagram.render().unwrap();
+///
+/// assert!(output.svg.starts_with("<svg"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagram {
+    source: String,
+}
+
+impl Diagram {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+        }
+    }
+
+    /// Renders the diagram with the default options.
+    pub fn render(&self) -> Result<PikchrOutput> {
+        self.render_with(Options::default())
+    }
+
+    /// Renders the diagram with the given options.
+    pub fn render_with(&self, options: Options) -> Result<PikchrOutput> {
+        render_with(&self.source, options)
+    }
+
+    /// The pikchr markup.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+impl FromStr for Diagram {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+/// Outputs the pikchr markup.
+impl fmt::Display for Diagram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// A pikchr diagram checked to render when it is constructed, e.g. markup loaded once from a
+/// configuration file.
+///
+/// Parsing does a throw-away render with the default options and stores the markup. The check
+/// is about the markup only: it catches syntax and layout errors, not the ones caused by the
+/// options given to [`ValidatedDiagram::render_with`], e.g. a
+/// [`max_output_bytes`](crate::OptionsBuilder::max_output_bytes) limit. Flags and classes such
+/// as [`Flags::DARK_MODE`](crate::Flags::DARK_MODE) don't change whether a diagram renders.
+///
+/// With the `serde` feature it can be deserialized from a string, which fails with the
+/// [`PiktError`] message.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::ValidatedDiagram;
+///
+/// let diagram: ValidatedDiagram = "box \"pikchr\"".parse().unwrap();
+///
+/// assert!(diagram.render().starts_with("<svg"));
+/// assert!("box 'pikchr'".parse::<ValidatedDiagram>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatedDiagram(String);
+
+impl ValidatedDiagram {
+    /// Renders the diagram with the default options, returning the SVG.
+    pub fn render(&self) -> String {
+        self.render_with(Options::default())
+    }
+
+    /// Renders the diagram with the given options, returning the SVG.
+    ///
+    /// ## Panics
+    ///
+    /// If the options make the render fail, e.g. with a limit the output exceeds or an
+    /// unsupported [`OutputFormat`](crate::OutputFormat).
+    pub fn render_with(&self, options: Options) -> String {
+        match render_with(&self.0, options) {
+            Ok(output) => output.svg,
+            Err(err) => panic!("validated diagram failed to render: {}", err),
+        }
+    }
+
+    /// The pikchr markup.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for ValidatedDiagram {
+    type Err = PiktError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        validate(s)?;
+
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl TryFrom<Diagram> for ValidatedDiagram {
+    type Error = PiktError;
+
+    fn try_from(diagram: Diagram) -> Result<Self, Self::Error> {
+        validate(diagram.source())?;
+
+        Ok(Self(diagram.source))
+    }
+}
+
+/// Outputs the pikchr markup.
+impl fmt::Display for ValidatedDiagram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ValidatedDiagram {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+
+        source.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A pikchr diagram built incrementally with [`write!`] and [`writeln!`].
+///
+/// ## Example
+///
+/// ```
+/// use pikt::DiagramBuilder;
+/// use std::fmt::Write;
+///
+/// let mut builder = DiagramBuilder::new();
+/// writeln!(builder, "box \"hello\"").unwrap();
+/// writeln!(builder, "arrow").unwrap();
+/// let output = builder.render().unwrap();
+///
+/// assert!(output.svg.contains(">hello</text>"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiagramBuilder {
+    source: String,
+}
+
+impl DiagramBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders the markup written so far with the default options.
+    pub fn render(&self) -> Result<PikchrOutput> {
+        self.render_with(Options::default())
+    }
+
+    /// Renders the markup written so far with the given options.
+    ///
+    /// ## Errors
+    ///
+    /// [`PiktErrorReason::EmptyInput`] if nothing but whitespace was written.
+    pub fn render_with(&self, options: Options) -> Result<PikchrOutput> {
+        if self.source.trim().is_empty() {
+            return Err(PiktError::new(PiktErrorReason::EmptyInput));
+        }
+
+        render_with(&self.source, options)
+    }
+
+    /// The pikchr markup written so far.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Discards the markup written so far.
+    pub fn clear(&mut self) {
+        self.source.clear();
+    }
+
+    /// Appends a box with the given label, e.g. `box "Start"`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::DiagramBuilder;
+    ///
+    /// let builder = DiagramBuilder::new().add_box("a").add_arrow().add_circle("b");
+    ///
+    /// assert_eq!(builder.source(), "box \"a\"\narrow\ncircle \"b\"\n");
+    /// ```
+    pub fn add_box(self, label: &str) -> Self {
+        self.add_labeled("box", label)
+    }
+
+    /// Appends a circle with the given label.
+    pub fn add_circle(self, label: &str) -> Self {
+        self.add_labeled("circle", label)
+    }
+
+    /// Appends an arrow in the current direction.
+    pub fn add_arrow(self) -> Self {
+        self.add_line_of("arrow")
+    }
+
+    /// Appends a line in the current direction.
+    pub fn add_line(self) -> Self {
+        self.add_line_of("line")
+    }
+
+    /// Leaves a gap to the right, which also makes right the current direction.
+    pub fn move_right(self) -> Self {
+        self.add_line_of("move right")
+    }
+
+    /// Leaves a gap below, which also makes down the current direction.
+    pub fn move_down(self) -> Self {
+        self.add_line_of("move down")
+    }
+
+    fn add_labeled(self, shape: &str, label: &str) -> Self {
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+
+        self.add_line_of(&format!("{} \"{}\"", shape, label))
+    }
+
+    fn add_line_of(mut self, markup: &str) -> Self {
+        self.source.push_str(markup);
+        self.source.push('\n');
+        self
+    }
+}
+
+impl fmt::Write for DiagramBuilder {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.source.push_str(s);
+        Ok(())
+    }
+}
+
+/// A set of named diagrams, e.g. the diagrams of a documentation site.
+///
+/// Diagrams are kept in insertion order.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{Diagram, DiagramCollection, Options};
+///
+/// let mut collection = DiagramCollection::new();
+/// collection.insert("intro", Diagram::new("box"));
+/// collection.insert("flow", Diagram::new("arrow"));
+/// let outputs = collection.render_all_or_fail(&Options::default()).unwrap();
+///
+/// assert_eq!(outputs.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiagramCollection {
+    items: IndexMap<String, Diagram>,
+}
+
+impl DiagramCollection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a diagram, returning the one it replaces if the name was taken. A replaced diagram
+    /// keeps its position.
+    pub fn insert(&mut self, name: impl Into<String>, diagram: Diagram) -> Option<Diagram> {
+        self.items.insert(name.into(), diagram)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Diagram> {
+        self.items.get(name)
+    }
+
+    /// The diagram names, in insertion order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.items.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Renders every diagram with the given options.
+    pub fn render_all(&self, options: &Options) -> HashMap<String, Result<PikchrOutput>> {
+        self.items
+            .iter()
+            .map(|(name, diagram)| (name.clone(), diagram.render_with(options.clone())))
+            .collect()
+    }
+
+    /// Renders every diagram with the given options, stopping at the first error.
+    ///
+    /// ## Errors
+    ///
+    /// The name of the first diagram, in insertion order, that fails to render along with its
+    /// error.
+    pub fn render_all_or_fail(
+        &self,
+        options: &Options,
+    ) -> Result<HashMap<String, PikchrOutput>, (String, PiktError)> {
+        self.items
+            .iter()
+            .map(|(name, diagram)| {
+                diagram
+                    .render_with(options.clone())
+                    .map(|output| (name.clone(), output))
+                    .map_err(|err| (name.clone(), err))
+            })
+            .collect()
+    }
+}
+
+/// A diagram built line by line, where staged lines are only kept if the whole diagram is still
+/// valid, e.g. for an interactive editor.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::IncrementalDiagram;
+///
+/// let mut diagram = IncrementalDiagram::new();
+/// diagram.stage("box \"a\"");
+/// diagram.commit().unwrap();
+///
+/// diagram.stage("arrow from nowhere");
+/// assert!(diagram.commit().is_err());
+///
+/// assert_eq!(diagram.committed(), "box \"a\"\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IncrementalDiagram {
+    committed: String,
+    pending: String,
+}
+
+impl IncrementalDiagram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a line to the pending lines.
+    pub fn stage(&mut self, line: &str) {
+        self.pending.push_str(line);
+        self.pending.push('\n');
+    }
+
+    /// Keeps the pending lines if the diagram with them is valid. The pending lines are
+    /// discarded either way.
+    ///
+    /// ## Errors
+    ///
+    /// See [`validate`].
+    pub fn commit(&mut self) -> Result<()> {
+        let pending = std::mem::take(&mut self.pending);
+        let candidate = format!("{}{}", self.committed, pending);
+
+        validate(&candidate)?;
+        self.committed = candidate;
+
+        Ok(())
+    }
+
+    /// Discards the pending lines.
+    pub fn rollback(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Renders the committed lines with the default options.
+    pub fn render(&self) -> Result<PikchrOutput> {
+        render_with(&self.committed, Options::default())
+    }
+
+    /// The committed lines.
+    pub fn committed(&self) -> &str {
+        &self.committed
+    }
+
+    /// The lines staged since the last commit or rollback.
+    pub fn pending(&self) -> &str {
+        &self.pending
+    }
+}
+
+impl TryFrom<Diagram> for PikchrOutput {
+    type Error = PiktError;
+
+    fn try_from(diagram: Diagram) -> Result<Self, Self::Error> {
+        diagram.render()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render, Flags, OptionsBuilder};
+
+    #[test]
+    fn render_from_str() -> Result<(), Box<dyn std::error::Error>> {
+        let output = "box".parse::<Diagram>()?.render()?;
+
+        assert_eq!(output, render("box")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_with_options() -> Result<()> {
+        let diagram = Diagram::new("box");
+        let mut builder = OptionsBuilder::default();
+        builder.flags(Flags::DARK_MODE);
+
+        let output = diagram.render_with(builder.build())?;
+
+        assert!(output.svg.contains("rgb(255,255,255)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_markup() {
+        let diagram = Diagram::new("box 'x'");
+
+        assert!(diagram.render().is_err());
+    }
+
+    #[test]
+    fn source_and_display() {
+        let diagram = Diagram::new(String::from("box \"a\""));
+
+        assert_eq!(diagram.source(), "box \"a\"");
+        assert_eq!(diagram.to_string(), "box \"a\"");
+    }
+
+    #[test]
+    fn try_into_output() -> Result<()> {
+        let output = PikchrOutput::try_from(Diagram::new("circle"))?;
+
+        assert!(output.svg.contains("<circle"));
+        assert!(PikchrOutput::try_from(Diagram::new("circ \"1\"")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn builder_writes() -> Result<(), Box<dyn std::error::Error>> {
+        use std::fmt::Write;
+
+        let mut builder = DiagramBuilder::new();
+        writeln!(builder, "box \"one\"")?;
+        writeln!(builder, "circle \"{}\"", 2)?;
+        let output = builder.render()?;
+
+        assert_eq!(builder.source(), "box \"one\"\ncircle \"2\"\n");
+        assert!(output.svg.contains(">one</text>"));
+        assert!(output.svg.contains("<circle"));
+        assert!(output.svg.contains(">2</text>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn builder_clear() -> Result<(), Box<dyn std::error::Error>> {
+        use std::fmt::Write;
+
+        let mut builder = DiagramBuilder::new();
+        writeln!(builder, "box")?;
+        builder.clear();
+        let err = builder.render().unwrap_err();
+
+        assert_eq!(builder.source(), "");
+        assert_eq!(err.reason, PiktErrorReason::EmptyInput);
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_collection() {
+        let collection = DiagramCollection::new();
+
+        assert!(collection.is_empty());
+        assert!(collection.render_all(&Options::default()).is_empty());
+        assert_eq!(
+            collection.render_all_or_fail(&Options::default()),
+            Ok(HashMap::new())
+        );
+    }
+
+    #[test]
+    fn collection_mixed_results() {
+        let mut collection = DiagramCollection::new();
+        collection.insert("box", Diagram::new("box"));
+        collection.insert("broken", Diagram::new("box 'x'"));
+        let actual = collection.render_all(&Options::default());
+
+        assert_eq!(actual.len(), 2);
+        assert!(actual["box"].is_ok());
+        assert!(actual["broken"].is_err());
+    }
+
+    #[test]
+    fn collection_order() {
+        let mut collection = DiagramCollection::new();
+        collection.insert("c", Diagram::new("circle"));
+        collection.insert("b", Diagram::new("circ 'x'"));
+        collection.insert("a", Diagram::new("box 'x'"));
+
+        let replaced = collection.insert("c", Diagram::new("box"));
+
+        assert_eq!(replaced, Some(Diagram::new("circle")));
+        assert_eq!(collection.names().collect::<Vec<_>>(), ["c", "b", "a"]);
+
+        let (name, _) = collection
+            .render_all_or_fail(&Options::default())
+            .unwrap_err();
+
+        assert_eq!(name, "b");
+    }
+
+    #[test]
+    fn incremental_commits_valid_lines() -> Result<()> {
+        let mut diagram = IncrementalDiagram::new();
+        diagram.stage("box");
+        diagram.stage("arrow");
+        diagram.commit()?;
+
+        assert_eq!(diagram.committed(), "box\narrow\n");
+        assert_eq!(diagram.pending(), "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn incremental_discards_invalid_lines() -> Result<()> {
+        let mut diagram = IncrementalDiagram::new();
+        diagram.stage("box");
+        diagram.commit()?;
+        diagram.stage("circ 'x'");
+
+        assert!(diagram.commit().is_err());
+        assert_eq!(diagram.committed(), "box\n");
+        assert_eq!(diagram.pending(), "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn incremental_rollback() -> Result<()> {
+        let mut diagram = IncrementalDiagram::new();
+        diagram.stage("box");
+        diagram.commit()?;
+        diagram.stage("circle");
+        diagram.rollback();
+
+        assert_eq!(diagram.pending(), "");
+        assert_eq!(diagram.render()?, render("box\n")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn builder_shape_helpers() -> Result<()> {
+        let builder = DiagramBuilder::new()
+            .add_box("Start")
+            .add_arrow()
+            .add_circle("Check \"a\"")
+            .move_down()
+            .add_line()
+            .move_right()
+            .add_box("End");
+        let output = builder.render()?;
+
+        assert_eq!(
+            builder.source(),
+            "box \"Start\"\narrow\ncircle \"Check \\\"a\\\"\"\nmove down\nline\nmove right\nbox \"End\"\n"
+        );
+        assert_eq!(
+            crate::svg::text_contents(&output.svg),
+            // pikchr writes spaces as non-breaking spaces.
+            ["Start", "Check\u{a0}\"a\"", "End"]
+        );
+        // The boxes, the arrow and the line. Circles are `<circle>` elements.
+        assert_eq!(crate::svg::path_count(&output.svg), 4);
+        assert_eq!(output.svg.matches("<circle").count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn validated_diagram() -> Result<()> {
+        let diagram: ValidatedDiagram = "box \"a\"".parse()?;
+
+        assert_eq!(diagram.as_str(), "box \"a\"");
+        assert_eq!(diagram.to_string(), "box \"a\"");
+        assert_eq!(diagram.render(), render("box \"a\"")?.svg);
+
+        let mut builder = OptionsBuilder::default();
+        builder.flags(Flags::DARK_MODE);
+        assert!(diagram
+            .render_with(builder.build())
+            .contains("rgb(255,255,255)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn validated_diagram_rejects_invalid_markup() {
+        let err = "circ \"1\"".parse::<ValidatedDiagram>().unwrap_err();
+
+        assert_eq!(err, render("circ \"1\"").unwrap_err());
+        assert!(ValidatedDiagram::try_from(Diagram::new("box 'x'")).is_err());
+        assert!(ValidatedDiagram::try_from(Diagram::new("box")).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "validated diagram failed to render")]
+    fn validated_diagram_panics_on_options_error() {
+        let mut builder = OptionsBuilder::default();
+        builder.max_output_bytes(1);
+
+        "box"
+            .parse::<ValidatedDiagram>()
+            .unwrap()
+            .render_with(builder.build());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_validated_diagram() {
+        use serde::de::value::{Error, StrDeserializer};
+        use serde::de::IntoDeserializer;
+        use serde::Deserialize;
+
+        let deserializer: StrDeserializer<'_, Error> = "box".into_deserializer();
+        assert_eq!(
+            ValidatedDiagram::deserialize(deserializer)
+                .unwrap()
+                .as_str(),
+            "box"
+        );
+
+        let deserializer: StrDeserializer<'_, Error> = "box 'x'".into_deserializer();
+        let err = ValidatedDiagram::deserialize(deserializer).unwrap_err();
+        assert_eq!(err.to_string(), render("box 'x'").unwrap_err().to_string());
+    }
+}
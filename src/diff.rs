@@ -0,0 +1,74 @@
+//! Line diffs of pikchr markup, e.g. to review a change to a diagram.
+
+use similar::TextDiff;
+
+/// Builds a unified diff from `old` to `new_src`, with `old` and `new` as the file names.
+///
+/// Returns an empty string when the sources are identical.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::diff_sources;
+///
+/// let diff = diff_sources("box\narrow\n", "box\narrow\ncircle\n");
+///
+/// assert!(diff.contains("\n+circle\n"));
+/// ```
+pub fn diff_sources(old: &str, new_src: &str) -> String {
+    if !has_changed(old, new_src) {
+        return String::new();
+    }
+
+    TextDiff::from_lines(old, new_src)
+        .unified_diff()
+        .header("old", "new")
+        .to_string()
+}
+
+/// Whether the sources differ, without computing the diff.
+pub fn has_changed(old: &str, new_src: &str) -> bool {
+    old != new_src
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical() {
+        assert_eq!(diff_sources("box\n", "box\n"), "");
+        assert!(!has_changed("box\n", "box\n"));
+    }
+
+    #[test]
+    fn addition() {
+        let actual = diff_sources("box\narrow\n", "box\narrow\ncircle\n");
+
+        let hunk = actual.strip_prefix("--- old\n+++ new\n").unwrap();
+
+        assert!(hunk.contains("\n+circle\n"));
+        assert!(!hunk.contains("\n-"));
+    }
+
+    #[test]
+    fn deletion() {
+        let actual = diff_sources("box\narrow\ncircle\n", "box\ncircle\n");
+
+        let hunk = actual.strip_prefix("--- old\n+++ new\n").unwrap();
+
+        assert!(hunk.contains("\n-arrow\n"));
+        assert!(!hunk.contains("\n+"));
+    }
+
+    #[test]
+    fn replacement() {
+        let actual = diff_sources("box\narrow\n", "circle\nline\n");
+
+        assert_eq!(
+            actual,
+            "--- old\n+++ new\n@@ -1,2 +1,2 @@\n-box\n-arrow\n+circle\n+line\n"
+        );
+        assert!(has_changed("box\narrow\n", "circle\nline\n"));
+    }
+}
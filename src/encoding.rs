@@ -0,0 +1,183 @@
+//! Base64 encoding of rendered diagrams.
+
+use crate::html::escape_html;
+use crate::postprocess::is_valid_attribute_name;
+use crate::{render_with, Options, PikchrOutput, Result};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+
+/// Renders the given pikchr markup as SVG encoded in base64 with the standard alphabet.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_to_base64, Options};
+///
+/// let encoded = render_to_base64("box", Options::default()).unwrap();
+///
+/// assert!(encoded.starts_with("PHN2Zy"));
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`].
+pub fn render_to_base64(input: &str, options: Options) -> Result<String> {
+    let output = render_with(input, options)?;
+
+    Ok(STANDARD.encode(output.svg.as_bytes()))
+}
+
+/// Renders the given pikchr markup as SVG encoded in base64 with the URL-safe alphabet and no
+/// padding.
+///
+/// ## Errors
+///
+/// See [`render_with`].
+pub fn render_to_base64_url(input: &str, options: Options) -> Result<String> {
+    let output = render_with(input, options)?;
+
+    Ok(URL_SAFE_NO_PAD.encode(output.svg.as_bytes()))
+}
+
+impl PikchrOutput {
+    /// Builds an `<img>` element with the SVG as a base64 data URI.
+    ///
+    /// The alt text and the extra attributes are escaped. Extra attributes whose name is empty or
+    /// contains whitespace, quotes, `<`, `>`, `/` or `=` are skipped, as they would break the
+    /// markup. The `width` and `height` attributes are omitted when zero, as for an empty
+    /// diagram.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// let output = pikt::render("box").unwrap();
+    /// let img = output.to_img_tag("A box", &[("loading", "lazy")]);
+    ///
+    /// assert!(img.starts_with("<img src=\"data:image/svg+xml;base64,"));
+    /// assert!(img.contains(" alt=\"A box\""));
+    /// assert!(img.ends_with(" loading=\"lazy\">"));
+    /// ```
+    pub fn to_img_tag(&self, alt: &str, attrs: &[(&str, &str)]) -> String {
+        let mut tag = format!(
+            "<img src=\"data:image/svg+xml;base64,{}\" alt=\"{}\"",
+            STANDARD.encode(self.svg.as_bytes()),
+            escape_html(alt)
+        );
+
+        if self.width != 0 {
+            tag.push_str(&format!(" width=\"{}\"", self.width));
+        }
+
+        if self.height != 0 {
+            tag.push_str(&format!(" height=\"{}\"", self.height));
+        }
+
+        for (name, value) in attrs
+            .iter()
+            .filter(|(name, _)| is_valid_attribute_name(name))
+        {
+            tag.push_str(&format!(
+                " {}=\"{}\"",
+                escape_html(name),
+                escape_html(value)
+            ));
+        }
+
+        tag.push('>');
+        tag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    const INPUT: &str = "box \"a?b>c\"; arrow; circle \"~~~\"";
+
+    #[test]
+    fn standard_round_trip() -> Result<()> {
+        let encoded = render_to_base64(INPUT, Options::default())?;
+
+        assert_eq!(
+            STANDARD.decode(encoded).unwrap(),
+            render(INPUT)?.svg.as_bytes()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn url_safe_round_trip() -> Result<()> {
+        let encoded = render_to_base64_url(INPUT, Options::default())?;
+
+        assert!(!encoded.contains(['+', '/', '=']));
+        assert_eq!(
+            URL_SAFE_NO_PAD.decode(encoded).unwrap(),
+            render(INPUT)?.svg.as_bytes()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            render_to_base64("box 'x'", Options::default()).unwrap_err(),
+            render("box 'x'").unwrap_err()
+        );
+        assert!(render_to_base64_url("box 'x'", Options::default()).is_err());
+    }
+
+    #[test]
+    fn img_tag() -> Result<()> {
+        let output = render("box")?;
+        let actual = output.to_img_tag("A box", &[]);
+
+        assert_eq!(
+            actual,
+            format!(
+                "<img src=\"data:image/svg+xml;base64,{}\" alt=\"A box\" width=\"{}\" height=\"{}\">",
+                STANDARD.encode(&output.svg),
+                output.width,
+                output.height
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn img_tag_escapes() -> Result<()> {
+        let actual = render("box")?.to_img_tag("\"A\" box", &[("class", "a&b"), ("id", "d1")]);
+
+        assert!(actual.contains(" alt=\"&quot;A&quot; box\""));
+        assert!(actual.ends_with(" class=\"a&amp;b\" id=\"d1\">"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn img_tag_skips_invalid_attribute_names() -> Result<()> {
+        let actual = render("box")?.to_img_tag(
+            "",
+            &[("x onerror", "alert(1)"), ("a=b", "c"), ("loading", "lazy")],
+        );
+
+        assert!(!actual.contains("onerror"));
+        assert!(!actual.contains("a=b"));
+        assert!(actual.ends_with(" loading=\"lazy\">"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn img_tag_without_dimensions() -> Result<()> {
+        let actual = render("")?.to_img_tag("", &[]);
+
+        assert!(!actual.contains("width="));
+        assert!(!actual.contains("height="));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Options read from `PIKCHR_*` environment variables.
+
+use crate::OptionsBuilder;
+use std::ffi::OsString;
+use thiserror::Error;
+
+/// The error returned by [`OptionsBuilder::from_env_strict`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EnvConfigError {
+    #[error("invalid value for {name}: {value:?}")]
+    InvalidValue { name: String, value: String },
+}
+
+impl OptionsBuilder {
+    /// Creates a builder configured from the environment, ignoring invalid values.
+    ///
+    /// - `PIKCHR_DARK_MODE`: Enables dark mode when `1` or `true`. `0` and `false` are also
+    ///   valid.
+    /// - `PIKCHR_CLASS`: The class of the SVG element.
+    /// - `PIKCHR_WIDTH` and `PIKCHR_HEIGHT`: The width and height, as non-negative integers.
+    ///
+    /// See [`OptionsBuilder::from_env_strict`] to fail on invalid values instead.
+    pub fn from_env() -> OptionsBuilder {
+        from_lookup(|name| std::env::var_os(name), false).expect("invalid values are ignored")
+    }
+
+    /// Creates a builder configured from the environment. See [`OptionsBuilder::from_env`].
+    ///
+    /// ## Errors
+    ///
+    /// [`EnvConfigError::InvalidValue`] if a variable has a value not valid for it, including
+    /// values that are not valid Unicode.
+    pub fn from_env_strict() -> Result<OptionsBuilder, EnvConfigError> {
+        from_lookup(|name| std::env::var_os(name), true)
+    }
+}
+
+/// Builds the options from the variables returned by `lookup`.
+fn from_lookup<F>(lookup: F, strict: bool) -> Result<OptionsBuilder, EnvConfigError>
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    let boolean = |value: &str| match value {
+        "1" | "true" => Some(true),
+        "0" | "false" => Some(false),
+        _ => None,
+    };
+    let number = |value: &str| value.parse::<u32>().ok();
+
+    let mut builder = OptionsBuilder::default();
+
+    if read(&lookup, strict, "PIKCHR_DARK_MODE", boolean)? == Some(true) {
+        builder.dark_mode();
+    }
+
+    if let Some(class) = read(&lookup, strict, "PIKCHR_CLASS", |value| {
+        Some(value.to_string())
+    })? {
+        builder.class(&class);
+    }
+
+    if let Some(width) = read(&lookup, strict, "PIKCHR_WIDTH", number)? {
+        builder.width(width);
+    }
+
+    if let Some(height) = read(&lookup, strict, "PIKCHR_HEIGHT", number)? {
+        builder.height(height);
+    }
+
+    Ok(builder)
+}
+
+/// Reads the variable with `lookup` and parses it. Invalid values fail when `strict` and are
+/// ignored otherwise.
+fn read<T>(
+    lookup: &dyn Fn(&str) -> Option<OsString>,
+    strict: bool,
+    name: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Option<T>, EnvConfigError> {
+    let value = match lookup(name) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    match value.to_str().and_then(parse) {
+        Some(value) => Ok(Some(value)),
+        None if strict => Err(EnvConfigError::InvalidValue {
+            name: name.to_string(),
+            value: value.to_string_lossy().into_owned(),
+        }),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Flags;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Serialises the tests changing the process environment.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<OsString> {
+        let vars: HashMap<String, OsString> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.into()))
+            .collect();
+
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn all_variables() {
+        let vars = [
+            ("PIKCHR_DARK_MODE", "true"),
+            ("PIKCHR_CLASS", "diagram"),
+            ("PIKCHR_WIDTH", "300"),
+            ("PIKCHR_HEIGHT", "200"),
+        ];
+        let options = from_lookup(lookup(&vars), true).unwrap().build();
+
+        assert_eq!(options.flags(), Flags::DARK_MODE);
+        assert_eq!(options.class(), "diagram");
+        assert_eq!((options.width(), options.height()), (300, 200));
+    }
+
+    #[test]
+    fn dark_mode_values() {
+        for (value, dark) in [("1", true), ("true", true), ("0", false), ("false", false)] {
+            let options = from_lookup(lookup(&[("PIKCHR_DARK_MODE", value)]), true)
+                .unwrap()
+                .build();
+
+            assert_eq!(options.flags().contains(Flags::DARK_MODE), dark);
+        }
+    }
+
+    #[test]
+    fn invalid_values() {
+        for (name, value) in [
+            ("PIKCHR_DARK_MODE", "yes"),
+            ("PIKCHR_WIDTH", "wide"),
+            ("PIKCHR_HEIGHT", "-1"),
+        ] {
+            let vars = [(name, value)];
+
+            assert_eq!(
+                from_lookup(lookup(&vars), false).unwrap().build(),
+                OptionsBuilder::default().build()
+            );
+            assert_eq!(
+                from_lookup(lookup(&vars), true).unwrap_err(),
+                EnvConfigError::InvalidValue {
+                    name: name.to_string(),
+                    value: value.to_string()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn reads_the_process_environment() {
+        let _lock = ENV_LOCK.lock().unwrap();
+        std::env::set_var("PIKCHR_CLASS", "from-env");
+        std::env::set_var("PIKCHR_WIDTH", "wide");
+
+        let lenient = OptionsBuilder::from_env().build();
+        let strict = OptionsBuilder::from_env_strict();
+
+        std::env::remove_var("PIKCHR_CLASS");
+        std::env::remove_var("PIKCHR_WIDTH");
+
+        assert_eq!(lenient.class(), "from-env");
+        assert_eq!(lenient.width(), 0);
+        assert!(strict.is_err());
+    }
+}
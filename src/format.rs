@@ -0,0 +1,246 @@
+//! Normalisation of pikchr source text.
+
+use crate::{render, validate, Result};
+use std::borrow::Cow;
+
+/// Formats the given pikchr source.
+///
+/// The source is validated first, returning any error unchanged. Then:
+///
+/// - Line endings are normalised to LF.
+/// - Trailing whitespace is stripped from each line.
+/// - Three or more consecutive blank lines are collapsed to two.
+/// - The result ends with exactly one newline.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::format_source;
+///
+/// let formatted = format_source("box  \r\n\r\n\r\n\r\ncircle\r\n\r\n").unwrap();
+///
+/// assert_eq!(formatted, "box\n\n\ncircle\n");
+/// ```
+pub fn format_source(input: &str) -> Result<String> {
+    validate(input)?;
+
+    let normalized = strip_trailing_whitespace(&normalize_line_endings(input));
+    let mut output = String::with_capacity(normalized.len());
+    let mut blank_lines = 0;
+
+    for line in normalized.lines() {
+        if line.is_empty() {
+            blank_lines += 1;
+            if blank_lines > 2 {
+                continue;
+            }
+        } else {
+            blank_lines = 0;
+        }
+
+        output.push_str(line);
+        output.push('\n');
+    }
+
+    let content_len = output.trim_end_matches('\n').len();
+    output.truncate(content_len);
+    output.push('\n');
+
+    Ok(output)
+}
+
+/// Checks that formatting the source with [`format_source`] doesn't change its rendering.
+///
+/// Returns `Ok(false)` if the SVG of the formatted source differs from the original one, which
+/// means the formatter changed the meaning of the source.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::validate_format_stable;
+///
+/// assert!(validate_format_stable("box \"a\"  \r\n\r\n\r\n\r\narrow").unwrap());
+/// ```
+///
+/// ## Errors
+///
+/// Fails if the source is invalid.
+pub fn validate_format_stable(input: &str) -> Result<bool> {
+    let formatted = format_source(input)?;
+
+    renders_identically(input, &formatted)
+}
+
+fn renders_identically(original: &str, formatted: &str) -> Result<bool> {
+    Ok(render(original)?.svg == render(formatted)?.svg)
+}
+
+/// Converts CRLF and lone CR line endings to LF. The input is borrowed unchanged if it has no CR.
+pub fn normalize_line_endings(input: &str) -> Cow<'_, str> {
+    if input.contains('\r') {
+        Cow::Owned(input.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(input)
+    }
+}
+
+/// Strips the UTF-8 byte order mark from the start of the input, if any.
+///
+/// pikchr doesn't recognise it and fails with [`crate::PiktErrorReason::TokenUnknown`].
+pub fn strip_bom(input: &str) -> &str {
+    input.strip_prefix('\u{FEFF}').unwrap_or(input)
+}
+
+/// The byte counterpart of [`strip_bom`] followed by [`normalize_line_endings`], for input that
+/// may not be valid UTF-8.
+pub(crate) fn normalize_bytes(input: &[u8]) -> Cow<'_, [u8]> {
+    let input = input.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(input);
+
+    if !input.contains(&b'\r') {
+        return Cow::Borrowed(input);
+    }
+
+    let mut output = Vec::with_capacity(input.len());
+    let mut bytes = input.iter().peekable();
+
+    while let Some(&byte) = bytes.next() {
+        if byte == b'\r' {
+            bytes.next_if_eq(&&b'\n');
+            output.push(b'\n');
+        } else {
+            output.push(byte);
+        }
+    }
+
+    Cow::Owned(output)
+}
+
+/// Strips trailing whitespace from each line keeping the line endings.
+pub fn strip_trailing_whitespace(input: &str) -> String {
+    input
+        .split_inclusive('\n')
+        .map(|line| {
+            let (content, ending) = match line.strip_suffix("\r\n") {
+                Some(content) => (content, "\r\n"),
+                None => match line.strip_suffix('\n') {
+                    Some(content) => (content, "\n"),
+                    None => (line, ""),
+                },
+            };
+
+            format!("{}{}", content.trim_end(), ending)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PiktErrorReason;
+
+    #[test]
+    fn clean_input_is_unchanged() -> Result<()> {
+        let source = "box \"a\"\n\narrow\ncircle \"b\"\n";
+
+        assert_eq!(format_source(source)?, source);
+
+        Ok(())
+    }
+
+    #[test]
+    fn dirty_input_is_normalized() -> Result<()> {
+        let source = "box \"a\"  \r\n\r\n\r\n\r\n\r\narrow\t\ncircle \"b\"\n\n\n";
+
+        assert_eq!(
+            format_source(source)?,
+            "box \"a\"\n\n\narrow\ncircle \"b\"\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_trailing_newline_is_added() -> Result<()> {
+        assert_eq!(format_source("box")?, "box\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_input_fails_before_formatting() {
+        let actual = format_source("circ \"1\"  \r\n");
+
+        assert_eq!(
+            actual.expect_err("expected syntax error").reason,
+            PiktErrorReason::SyntaxError
+        );
+    }
+
+    #[test]
+    fn line_endings() {
+        assert_eq!(normalize_line_endings("a\r\nb\rc\n"), "a\nb\nc\n");
+        assert!(matches!(normalize_line_endings("a\nb\n"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn bom() {
+        assert_eq!(strip_bom("\u{FEFF}box"), "box");
+        assert_eq!(strip_bom("box\u{FEFF}"), "box\u{FEFF}");
+        assert_eq!(strip_bom("\u{FEFF}"), "");
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(normalize_bytes(b"\xEF\xBB\xBFbox"), &b"box"[..]);
+        assert_eq!(normalize_bytes(b"a\r\nb\rc\n\xff"), &b"a\nb\nc\n\xff"[..]);
+        assert!(matches!(
+            normalize_bytes(b"box\xEF\xBB\xBF"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn bom_bytes_input() -> Result<()> {
+        assert_eq!(
+            crate::render_from_bytes(b"\xEF\xBB\xBFbox\r\ncircle")?,
+            render("box\ncircle")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn trailing_whitespace() {
+        assert_eq!(strip_trailing_whitespace("a  \r\nb\t\nc "), "a\r\nb\nc");
+    }
+
+    #[test]
+    fn formatting_is_stable() -> Result<()> {
+        for source in [
+            "box \"a\"\narrow\ncircle \"b\"\n",
+            "box \"a\"  \r\n\r\n\r\n\r\narrow\t\ncircle \"b\"\n\n\n",
+            "A: box\rB: box at A + (1, 1)\r\rarrow from A to B",
+            "\n\n\nbox; circle   \n\n",
+        ] {
+            assert!(validate_format_stable(source)?, "{:?}", source);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn changed_rendering_is_detected() -> Result<()> {
+        assert!(!renders_identically("box \"a\"", "box \"a \"")?);
+        assert!(!renders_identically("box\ncircle", "box\n")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stability_of_invalid_input_fails() {
+        assert_eq!(
+            validate_format_stable("circ \"1\"").unwrap_err().reason,
+            PiktErrorReason::SyntaxError
+        );
+    }
+}
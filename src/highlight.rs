@@ -0,0 +1,133 @@
+//! Syntax highlighting of pikchr markup as HTML, e.g. to show the source next to a diagram.
+
+use std::sync::OnceLock;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::{SyntaxDefinition, SyntaxSet};
+use thiserror::Error;
+
+/// The grammar of the pikchr markup.
+const SYNTAX: &str = include_str!("pikchr.sublime-syntax");
+
+#[derive(Error, Debug)]
+pub enum HighlightError {
+    /// Raised when the theme is not one of the default syntect themes.
+    #[error("unknown theme: {0:?}")]
+    UnknownTheme(String),
+
+    #[error("highlighting failed: {0}")]
+    Highlight(#[from] syntect::Error),
+}
+
+/// Highlights the given pikchr markup as HTML with inline styles, in a `<pre>` with the theme
+/// background.
+///
+/// The theme is one of the syntect defaults, such as `InspiredGitHub`, `Solarized (dark)` or
+/// `base16-ocean.dark`.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::highlight_pikchr;
+///
+/// let html = highlight_pikchr("box \"pikchr\"", "InspiredGitHub").unwrap();
+///
+/// assert!(html.starts_with("<pre style="));
+/// ```
+///
+/// ## Errors
+///
+/// Fails if the theme is unknown.
+pub fn highlight_pikchr(input: &str, theme_name: &str) -> Result<String, HighlightError> {
+    let syntaxes = syntaxes();
+    let theme = themes()
+        .themes
+        .get(theme_name)
+        .ok_or_else(|| HighlightError::UnknownTheme(theme_name.to_string()))?;
+    let syntax = syntaxes
+        .find_syntax_by_scope(
+            syntect::parsing::Scope::new("source.pikchr").expect("the scope is valid"),
+        )
+        .expect("the pikchr syntax is loaded");
+
+    Ok(syntect::html::highlighted_html_for_string(
+        input, syntaxes, syntax, theme,
+    )?)
+}
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+
+    SYNTAXES.get_or_init(|| {
+        let syntax = SyntaxDefinition::load_from_str(SYNTAX, true, Some("pikchr"))
+            .expect("the bundled pikchr syntax is valid");
+        let mut builder = SyntaxSet::new().into_builder();
+        builder.add(syntax);
+        builder.build()
+    })
+}
+
+fn themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THEME: &str = "InspiredGitHub";
+
+    /// The style of the first span starting with the given text. syntect merges the whitespace
+    /// after a token into its span.
+    fn style_of<'a>(html: &'a str, text: &str) -> &'a str {
+        html.split("<span style=\"")
+            .skip(1)
+            .find_map(|span| {
+                let (style, content) = span.split_once("\">")?;
+                content.starts_with(text).then_some(style)
+            })
+            .unwrap_or_else(|| panic!("{:?} is not in a span", text))
+    }
+
+    #[test]
+    fn highlights_tokens() -> Result<(), HighlightError> {
+        let html = highlight_pikchr("box \"hello\" fit\nfoo: circle # note\n", THEME)?;
+
+        assert!(html.contains("<span"));
+        assert_ne!(style_of(&html, "box"), style_of(&html, "foo"));
+        assert_eq!(style_of(&html, "box"), style_of(&html, "circle"));
+        assert_ne!(style_of(&html, "fit"), style_of(&html, "foo"));
+        assert_ne!(style_of(&html, "#"), style_of(&html, "foo"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn highlights_strings() -> Result<(), HighlightError> {
+        let html = highlight_pikchr("box \"hello\" hello", THEME)?;
+
+        assert_ne!(
+            style_of(&html, "&quot;hello&quot;"),
+            style_of(&html, "hello")
+        );
+        assert_ne!(style_of(&html, "&quot;hello&quot;"), style_of(&html, "box"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_input() -> Result<(), HighlightError> {
+        assert!(highlight_pikchr("", THEME)?.starts_with("<pre"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_theme() {
+        assert!(matches!(
+            highlight_pikchr("box", "nope"),
+            Err(HighlightError::UnknownTheme(name)) if name == "nope"
+        ));
+    }
+}
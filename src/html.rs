@@ -0,0 +1,157 @@
+//! HTML helpers shared by the post-processing and embedding functions, and the functions
+//! wrapping a rendered diagram in HTML.
+
+use crate::{render_with, Options, Result};
+
+/// Escapes the characters with special meaning in HTML text and attribute values.
+pub(crate) fn escape_html(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            c => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// Renders the given pikchr markup wrapped in a `<div>` with the given class and id.
+///
+/// The class and id are escaped. The `id` attribute is omitted when `div_id` is `None`.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_to_html_fragment, Options};
+///
+/// let html = render_to_html_fragment("box", Options::default(), "diagram", Some("d1")).unwrap();
+///
+/// assert!(html.starts_with("<div class=\"diagram\" id=\"d1\"><svg"));
+/// assert!(html.ends_with("</svg>\n</div>"));
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`].
+pub fn render_to_html_fragment(
+    input: &str,
+    options: Options,
+    div_class: &str,
+    div_id: Option<&str>,
+) -> Result<String> {
+    let output = render_with(input, options)?;
+    let id = div_id
+        .map(|id| format!(" id=\"{}\"", escape_html(id)))
+        .unwrap_or_default();
+
+    Ok(format!(
+        "<div class=\"{}\"{}>{}</div>",
+        escape_html(div_class),
+        id,
+        output.svg
+    ))
+}
+
+/// Renders the given pikchr markup wrapped in a `<figure>`, with the caption as a
+/// `<figcaption>` after the diagram.
+///
+/// The caption is escaped.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_to_figure, Options};
+///
+/// let html = render_to_figure("box", Options::default(), Some("A box")).unwrap();
+///
+/// assert!(html.starts_with("<figure><svg"));
+/// assert!(html.ends_with("</svg>\n<figcaption>A box</figcaption></figure>"));
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`].
+pub fn render_to_figure(input: &str, options: Options, caption: Option<&str>) -> Result<String> {
+    let output = render_with(input, options)?;
+    let caption = caption
+        .map(|caption| format!("<figcaption>{}</figcaption>", escape_html(caption)))
+        .unwrap_or_default();
+
+    Ok(format!("<figure>{}{}</figure>", output.svg, caption))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(
+            escape_html(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn leaves_plain_text_unchanged() {
+        assert_eq!(escape_html("plain text"), "plain text");
+    }
+
+    #[test]
+    fn fragment_wraps_the_svg() -> Result<()> {
+        let svg = render("box")?.svg;
+        let actual = render_to_html_fragment("box", Options::default(), "diagram", None)?;
+
+        assert_eq!(actual, format!("<div class=\"diagram\">{}</div>", svg));
+
+        Ok(())
+    }
+
+    #[test]
+    fn fragment_with_id() -> Result<()> {
+        let actual = render_to_html_fragment("box", Options::default(), "diagram", Some("d1"))?;
+
+        assert!(actual.starts_with("<div class=\"diagram\" id=\"d1\"><svg"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn fragment_escapes_attributes() -> Result<()> {
+        let actual = render_to_html_fragment("box", Options::default(), "<a&b>", Some("\"x\""))?;
+
+        assert!(actual.starts_with("<div class=\"&lt;a&amp;b&gt;\" id=\"&quot;x&quot;\"><svg"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn fragment_error() {
+        let actual = render_to_html_fragment("box 'x'", Options::default(), "diagram", None);
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn figure_with_and_without_caption() -> Result<()> {
+        let svg = render("box")?.svg;
+
+        assert_eq!(
+            render_to_figure("box", Options::default(), None)?,
+            format!("<figure>{}</figure>", svg)
+        );
+        assert_eq!(
+            render_to_figure("box", Options::default(), Some("1 < 2"))?,
+            format!("<figure>{}<figcaption>1 &lt; 2</figcaption></figure>", svg)
+        );
+
+        Ok(())
+    }
+}
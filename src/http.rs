@@ -0,0 +1,165 @@
+//! An HTTP handler for server-side rendering.
+//!
+//! The [`pikchr_render_handler`] router accepts `POST /render` requests with pikchr markup as
+//! the body (`Content-Type: text/x-pikchr`) and responds with the SVG
+//! (`Content-Type: image/svg+xml; charset=utf-8`) or with a JSON error and status `422`.
+//!
+//! The following request headers customise the render:
+//!
+//! - `X-Pikchr-Dark-Mode: 1`: Renders in dark mode.
+//! - `X-Pikchr-Class: <name>`: Replaces the class of the SVG element.
+
+use crate::{render_with, Flags, OptionsBuilder, PiktError};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+
+/// The content type expected for the request body.
+pub const PIKCHR_CONTENT_TYPE: &str = "text/x-pikchr";
+
+/// The content type of a successful response.
+pub const SVG_CONTENT_TYPE: &str = "image/svg+xml; charset=utf-8";
+
+/// Builds a router serving `POST /render`.
+///
+/// ## Example
+///
+/// ```no_run
+/// # async fn run() {
+/// let app = pikt::http::pikchr_render_handler();
+/// // Serve `app` with your server of choice.
+/// # }
+/// ```
+pub fn pikchr_render_handler() -> Router {
+    Router::new().route("/render", post(render_handler))
+}
+
+async fn render_handler(headers: HeaderMap, body: String) -> Response {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(str::trim);
+
+    if content_type != Some(PIKCHR_CONTENT_TYPE) {
+        return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+    }
+
+    let mut builder = OptionsBuilder::default();
+
+    if header_value(&headers, "x-pikchr-dark-mode") == Some("1") {
+        builder.flags(Flags::DARK_MODE);
+    }
+
+    if let Some(class) = header_value(&headers, "x-pikchr-class") {
+        builder.class(class);
+    }
+
+    match render_with(&body, builder.build()) {
+        Ok(output) => ([(header::CONTENT_TYPE, SVG_CONTENT_TYPE)], output.svg).into_response(),
+        Err(err) => (StatusCode::UNPROCESSABLE_ENTITY, Json(error_body(&err))).into_response(),
+    }
+}
+
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn error_body(err: &PiktError) -> serde_json::Value {
+    serde_json::json!({
+        "line": err.line,
+        "column": err.column,
+        "reason": err.reason.to_string(),
+        "message": err.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn request() -> axum::http::request::Builder {
+        Request::builder()
+            .method("POST")
+            .uri("/render")
+            .header(header::CONTENT_TYPE, PIKCHR_CONTENT_TYPE)
+    }
+
+    async fn send(request: Request<Body>) -> (StatusCode, HeaderMap, String) {
+        let response = pikchr_render_handler().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, headers, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn valid_markup() {
+        let req = request().body(Body::from("box \"pikchr\"")).unwrap();
+
+        let (status, headers, body) = send(req).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], SVG_CONTENT_TYPE);
+        assert!(body.starts_with("<svg"));
+        assert!(body.contains("class=\"pikchr\""));
+    }
+
+    #[tokio::test]
+    async fn invalid_markup() {
+        let req = request().body(Body::from("box 'pikchr'")).unwrap();
+
+        let (status, headers, body) = send(req).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+        assert_eq!(json["line"], 1);
+        assert_eq!(json["column"], 5);
+        assert_eq!(json["reason"], "unknown token");
+    }
+
+    #[tokio::test]
+    async fn dark_mode_header() {
+        let req = request()
+            .header("X-Pikchr-Dark-Mode", "1")
+            .body(Body::from("box"))
+            .unwrap();
+
+        let (status, _, body) = send(req).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("stroke:rgb(255,255,255)"));
+    }
+
+    #[tokio::test]
+    async fn class_header() {
+        let req = request()
+            .header("X-Pikchr-Class", "diagram")
+            .body(Body::from("box"))
+            .unwrap();
+
+        let (_, _, body) = send(req).await;
+
+        assert!(body.contains("class=\"diagram\""));
+    }
+
+    #[tokio::test]
+    async fn unsupported_content_type() {
+        let req = Request::builder()
+            .method("POST")
+            .uri("/render")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("box"))
+            .unwrap();
+
+        let (status, _, _) = send(req).await;
+
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
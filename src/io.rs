@@ -0,0 +1,343 @@
+//! Rendering of pikchr files to SVG files.
+//!
+//! Unlike [`crate::render_and_save`], the errors keep reading and writing failures apart from
+//! render failures.
+
+use crate::{render_with, Options, PikchrOutput, PiktError};
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use thiserror::Error;
+
+/// The error returned when rendering a file.
+#[derive(Error, Debug)]
+pub enum RenderFileError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Render(#[from] PiktError),
+}
+
+/// Renders the pikchr file at `input` writing the SVG to `output`.
+///
+/// The SVG is written to a temporary file in the directory of `output` and then renamed, so
+/// `output` is never left partially written.
+///
+/// ## Example
+///
+/// ```no_run
+/// use pikt::io::render_file;
+/// use pikt::Options;
+///
+/// render_file("diagram.pikchr", "diagram.svg", Options::default()).unwrap();
+/// ```
+///
+/// ## Errors
+///
+/// Fails with [`RenderFileError::Io`] if `input` can't be read or `output` can't be written,
+/// and with [`RenderFileError::Render`] if the diagram fails to render. In both cases `output`
+/// is left unchanged.
+pub fn render_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    options: Options,
+) -> Result<PikchrOutput, RenderFileError> {
+    let rendered = render_path(input.as_ref(), options)?;
+    write_atomically(output.as_ref(), rendered.svg.as_bytes())?;
+
+    Ok(rendered)
+}
+
+/// Renders the pikchr file at `input`, returning the SVG.
+///
+/// ## Example
+///
+/// ```no_run
+/// use pikt::io::render_file_to_string;
+/// use pikt::Options;
+///
+/// let svg = render_file_to_string("diagram.pikchr", Options::default()).unwrap();
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_file`].
+pub fn render_file_to_string<P: AsRef<Path>>(
+    input: P,
+    options: Options,
+) -> Result<String, RenderFileError> {
+    Ok(render_path(input.as_ref(), options)?.svg)
+}
+
+/// Renders the pikchr file at `input` writing the SVG to the standard output.
+///
+/// ## Errors
+///
+/// See [`render_file`].
+pub fn render_file_to_stdout<P: AsRef<Path>>(
+    input: P,
+    options: Options,
+) -> Result<PikchrOutput, RenderFileError> {
+    render_file_to_writer(input.as_ref(), &mut std::io::stdout().lock(), options)
+}
+
+/// Renders every `.pikchr` file of `input_dir` to a `.svg` file with the same stem in
+/// `output_dir`, e.g. `flow.pikchr` to `flow.svg`.
+///
+/// `output_dir` is created if missing. Subdirectories are not visited. Every file is rendered
+/// even if some fail, and the results are paired with the input paths sorted by path. If
+/// `input_dir` can't be read or `output_dir` can't be created, the only result is the error
+/// paired with the directory.
+///
+/// ## Example
+///
+/// ```no_run
+/// use pikt::io::render_dir;
+/// use pikt::Options;
+///
+/// for (path, result) in render_dir("diagrams", "target/diagrams", &Options::default()) {
+///     if let Err(err) = result {
+///         eprintln!("{}: {}", path.display(), err);
+///     }
+/// }
+/// ```
+pub fn render_dir<P: AsRef<Path>, Q: AsRef<Path>>(
+    input_dir: P,
+    output_dir: Q,
+    options: &Options,
+) -> Vec<(PathBuf, Result<PikchrOutput, RenderFileError>)> {
+    let (input_dir, output_dir) = (input_dir.as_ref(), output_dir.as_ref());
+
+    let paths = match pikchr_files(input_dir) {
+        Ok(paths) => paths,
+        Err(err) => return vec![(input_dir.to_path_buf(), Err(err.into()))],
+    };
+
+    if let Err(err) = std::fs::create_dir_all(output_dir) {
+        return vec![(output_dir.to_path_buf(), Err(err.into()))];
+    }
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let mut output = output_dir.join(path.file_name().unwrap_or_default());
+            output.set_extension("svg");
+            let result = render_file(&path, output, options.clone());
+
+            (path, result)
+        })
+        .collect()
+}
+
+fn render_path(input: &Path, options: Options) -> Result<PikchrOutput, RenderFileError> {
+    let source = std::fs::read_to_string(input)?;
+
+    Ok(render_with(&source, options)?)
+}
+
+fn render_file_to_writer(
+    input: &Path,
+    writer: &mut impl Write,
+    options: Options,
+) -> Result<PikchrOutput, RenderFileError> {
+    let rendered = render_path(input, options)?;
+    writer.write_all(rendered.svg.as_bytes())?;
+    writer.flush()?;
+
+    Ok(rendered)
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it to `path`. The temporary
+/// file is removed if either step fails.
+fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    // Tells apart the temporary files of concurrent writes within the process.
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "the path has no file name",
+        )
+    })?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp = path.with_file_name(temp_name);
+
+    let result = std::fs::write(&temp, contents).and_then(|()| std::fs::rename(&temp, path));
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+
+    result
+}
+
+/// The `.pikchr` files directly in the directory, sorted.
+fn pikchr_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|extension| extension == "pikchr")
+        {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render, PiktErrorReason};
+
+    #[test]
+    fn renders_file() -> Result<(), RenderFileError> {
+        let dir = tempfile::tempdir()?;
+        let (input, output) = (dir.path().join("a.pikchr"), dir.path().join("a.svg"));
+        std::fs::write(&input, "box")?;
+
+        let actual = render_file(&input, &output, Options::default())?;
+
+        assert_eq!(actual, render("box")?);
+        assert_eq!(std::fs::read_to_string(&output)?, actual.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_errors() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (input, output) = (dir.path().join("a.pikchr"), dir.path().join("a.svg"));
+
+        assert!(matches!(
+            render_file(&input, &output, Options::default()),
+            Err(RenderFileError::Io(_))
+        ));
+
+        std::fs::write(&input, "circ \"1\"")?;
+
+        match render_file(&input, &output, Options::default()) {
+            Err(RenderFileError::Render(err)) => {
+                assert_eq!(err.reason(), &PiktErrorReason::SyntaxError)
+            }
+            other => panic!("expected a render error, got: {:?}", other),
+        }
+        assert!(!output.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn renders_file_to_string() -> Result<(), RenderFileError> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("a.pikchr");
+        std::fs::write(&input, "circle")?;
+
+        assert_eq!(
+            render_file_to_string(&input, Options::default())?,
+            render("circle")?.svg
+        );
+        assert!(matches!(
+            render_file_to_string(dir.path().join("missing.pikchr"), Options::default()),
+            Err(RenderFileError::Io(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_atomically() -> Result<(), RenderFileError> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("a.pikchr");
+        let output = dir.path().join("a.svg");
+        std::fs::write(&input, "box")?;
+        std::fs::create_dir(&output)?;
+
+        assert!(matches!(
+            render_file(&input, &output, Options::default()),
+            Err(RenderFileError::Io(_))
+        ));
+        assert!(output.is_dir());
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 2);
+
+        let output = dir.path().join("b.svg");
+        std::fs::write(&output, "old")?;
+        render_file(&input, &output, Options::default())?;
+
+        assert_eq!(std::fs::read_to_string(&output)?, render("box")?.svg);
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn renders_file_to_writer() -> Result<(), RenderFileError> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("a.pikchr");
+        std::fs::write(&input, "circle")?;
+        let mut stdout = Vec::new();
+
+        let actual = render_file_to_writer(&input, &mut stdout, Options::default())?;
+
+        assert_eq!(stdout, actual.svg.as_bytes());
+        assert!(matches!(
+            render_file_to_stdout(dir.path().join("missing.pikchr"), Options::default()),
+            Err(RenderFileError::Io(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn renders_dir() -> Result<(), RenderFileError> {
+        let dir = tempfile::tempdir()?;
+        let (input_dir, output_dir) = (dir.path().join("in"), dir.path().join("out/svg"));
+        std::fs::create_dir_all(input_dir.join("nested.pikchr"))?;
+        std::fs::write(input_dir.join("b.pikchr"), "circ \"1\"")?;
+        std::fs::write(input_dir.join("a.pikchr"), "box")?;
+        std::fs::write(input_dir.join("notes.txt"), "box")?;
+
+        let actual = render_dir(&input_dir, &output_dir, &Options::default());
+        let paths: Vec<_> = actual.iter().map(|(path, _)| path.clone()).collect();
+
+        assert_eq!(
+            paths,
+            vec![input_dir.join("a.pikchr"), input_dir.join("b.pikchr")]
+        );
+        assert_eq!(
+            std::fs::read_to_string(output_dir.join("a.svg"))?,
+            render("box")?.svg
+        );
+        assert!(matches!(actual[1].1, Err(RenderFileError::Render(_))));
+        assert!(!output_dir.join("b.svg").exists());
+        assert!(!output_dir.join("notes.svg").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let input_dir = dir.path().join("missing");
+
+        let actual = render_dir(&input_dir, dir.path(), &Options::default());
+
+        assert_eq!(actual.len(), 1);
+        assert_eq!(actual[0].0, input_dir);
+        assert!(matches!(actual[0].1, Err(RenderFileError::Io(_))));
+    }
+}
@@ -0,0 +1,115 @@
+//! The pikchr keywords, e.g. for autocompletion in an editor.
+
+/// The object classes, in alphabetical order.
+///
+/// `diamond` was added in later pikchr releases and is rejected by the bundled pikchr.
+pub const SHAPES: &[&str] = &[
+    "arc", "arrow", "box", "circle", "cylinder", "diamond", "dot", "ellipse", "file", "line",
+    "move", "oval", "spline", "text",
+];
+
+/// The most common attribute and direction keywords, in alphabetical order.
+pub const ATTRIBUTES: &[&str] = &[
+    "at",
+    "color",
+    "dashed",
+    "dotted",
+    "down",
+    "fill",
+    "from",
+    "go",
+    "height",
+    "invisible",
+    "italic",
+    "left",
+    "right",
+    "small",
+    "solid",
+    "then",
+    "thick",
+    "thin",
+    "to",
+    "top",
+    "up",
+    "width",
+];
+
+/// Whether the word is one of [`SHAPES`]. Keywords are case sensitive.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::keywords::is_shape;
+///
+/// assert!(is_shape("box"));
+/// assert!(!is_shape("Box"));
+/// ```
+pub fn is_shape(word: &str) -> bool {
+    SHAPES.binary_search(&word).is_ok()
+}
+
+/// Whether the word is one of [`ATTRIBUTES`]. Keywords are case sensitive.
+pub fn is_attribute(word: &str) -> bool {
+    ATTRIBUTES.binary_search(&word).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    #[test]
+    fn lists_are_sorted() {
+        assert!(SHAPES.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(ATTRIBUTES.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn shapes_are_pikchr_objects() {
+        // The bundled pikchr predates `diamond`.
+        for shape in SHAPES.iter().filter(|shape| **shape != "diamond") {
+            assert!(is_shape(shape));
+            // A lone `move` draws nothing, which pikchr reports as an error.
+            assert!(render(&format!("box; {}", shape)).is_ok(), "{}", shape);
+        }
+    }
+
+    #[test]
+    fn documented_keywords() {
+        for word in [
+            "box", "circle", "ellipse", "oval", "cylinder", "file", "diamond",
+        ] {
+            assert!(is_shape(word), "{}", word);
+        }
+        for word in ["arrow", "line", "spline", "arc", "move", "dot", "text"] {
+            assert!(is_shape(word), "{}", word);
+        }
+        for word in [
+            "color",
+            "fill",
+            "thick",
+            "thin",
+            "dashed",
+            "dotted",
+            "invisible",
+        ] {
+            assert!(is_attribute(word), "{}", word);
+        }
+        for word in [
+            "up", "down", "left", "right", "go", "then", "from", "to", "at",
+        ] {
+            assert!(is_attribute(word), "{}", word);
+        }
+        for word in ["width", "height", "italic", "small", "solid", "top"] {
+            assert!(is_attribute(word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn unknown_words() {
+        assert!(!is_shape("square"));
+        assert!(!is_shape("color"));
+        assert!(!is_attribute("box"));
+        assert!(!is_attribute(""));
+    }
+}
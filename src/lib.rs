@@ -1,55 +1,669 @@
-use pikchr_sys::{pikchr, PIKCHR_DARK_MODE, PIKCHR_PLAINTEXT_ERRORS};
-use std::ffi::{CStr, CString, NulError};
+use pikchr_sys::{pikchr, PikchrBuffer, PIKCHR_DARK_MODE, PIKCHR_PLAINTEXT_ERRORS};
+use std::ffi::{CString, NulError};
 use std::str::FromStr;
 use thiserror::Error;
 
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(feature = "cache")]
+pub use cache::{RenderCache, SharedRenderCache};
+mod alt_text;
+pub mod asciidoc;
+#[cfg(feature = "audit")]
+mod audit;
+mod batch;
+#[cfg(unix)]
+mod capture;
+mod cli;
+#[cfg(feature = "cmark")]
+pub mod cmark;
+pub mod colors;
+mod complexity;
+#[cfg(any(feature = "toml", feature = "json"))]
+mod config;
+mod contrast;
+mod diagnostic;
+mod diagram;
+#[cfg(feature = "similar")]
+mod diff;
+#[cfg(feature = "base64")]
+mod encoding;
+mod env;
+mod format;
+#[cfg(feature = "syntect")]
+mod highlight;
+mod html;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod io;
+pub mod keywords;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod macros;
+pub mod markdown;
+mod negotiate;
+mod postprocess;
+pub mod preprocess;
+#[cfg(any(feature = "png", feature = "image"))]
+mod raster;
+pub mod rst;
+mod sanitize;
+#[cfg(feature = "json")]
+mod sarif;
+#[cfg(feature = "serde")]
+mod serde_fields;
+pub mod span;
+mod sprite;
+pub mod svg;
+#[cfg(any(test, feature = "test-helpers"))]
+pub mod testing;
+/// The commonly used items, for a single glob import.
+///
+/// ```
+/// use pikt::prelude::*;
+///
+/// let output: pikt::Result<PikchrOutput> = render("box");
+///
+/// assert!(output.is_ok());
+/// ```
+///
+/// It includes the render functions, the options and their builder, the output and the errors.
+/// The raw FFI bindings are intentionally excluded, see [`pikchr_sys`].
+pub mod prelude {
+    pub use crate::{
+        render, render_dark, render_light, render_with, validate, Diagram, Flags, Options,
+        OptionsBuilder, PikchrOutput, PiktError, PiktErrorReason,
+    };
+}
+/// The raw bindings, re-exported from [`pikchr_sys`] so they don't need a separate dependency.
+///
+/// Prefer [`render_with`] unless you need to manage the native buffers yourself.
+#[cfg(feature = "sys")]
+pub mod sys {
+    pub use ::pikchr_sys::*;
+}
+
+pub use alt_text::generate_alt_text;
+#[cfg(feature = "audit")]
+pub use audit::{AuditEntry, AuditLogger};
+#[cfg(feature = "json")]
+pub use batch::render_many_to_jsonl;
+pub use batch::{
+    render_files, render_with_label, LabeledError, LabeledPiktError, MultiRenderError,
+};
+#[cfg(unix)]
+pub use capture::render_capturing_stderr;
+pub use cli::CliArgsError;
+pub use complexity::{
+    classify_complexity, estimate_complexity, ComplexityClass, DiagramComplexity,
+};
+pub use contrast::{check_contrast, ContrastResult};
+pub use diagnostic::{
+    format_diagnostic_rust_style, render_with_context_info, ContextualPiktError, ErrorContext,
+};
+pub use diagram::{
+    Diagram, DiagramBuilder, DiagramCollection, IncrementalDiagram, ValidatedDiagram,
+};
+#[cfg(feature = "similar")]
+pub use diff::{diff_sources, has_changed};
+#[cfg(feature = "base64")]
+pub use encoding::{render_to_base64, render_to_base64_url};
+pub use env::EnvConfigError;
+pub use format::{
+    format_source, normalize_line_endings, strip_bom, strip_trailing_whitespace,
+    validate_format_stable,
+};
+#[cfg(feature = "syntect")]
+pub use highlight::{highlight_pikchr, HighlightError};
+pub use html::{render_to_figure, render_to_html_fragment};
+pub use macros::{extract_macros, validate_macros, MacroDefinition};
+pub use markdown::{find_pikchr_blocks, replace_pikchr_blocks};
+pub use negotiate::{render_negotiated, RequestedFormat};
+#[cfg(feature = "image")]
+pub use raster::render_to_image;
+#[cfg(feature = "png")]
+pub use raster::render_to_png;
+#[cfg(any(feature = "png", feature = "image"))]
+pub use raster::PngRenderError;
+pub use sanitize::{
+    render_sanitized, sanitize, SanitizeConfig, SanitizeError, SanitizeOrRenderError,
+};
+#[cfg(feature = "json")]
+pub use sarif::build_sarif_log;
+pub use sprite::render_sprite_sheet;
+
 bitflags::bitflags! {
     /// Flags to configure the render behaviour.
     ///
     /// Note that `PIKCHR_PLAINTEXT_ERRORS` can't be switched off because errors
     /// are handled by pikt.
-    #[derive(Default)]
+    ///
+    /// `Flags` implements `Copy` so it can be freely passed by value.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
     pub struct Flags: u32 {
         // const PLAINTEXT_ERRORS = PIKCHR_PLAINTEXT_ERRORS;
         const DARK_MODE = PIKCHR_DARK_MODE;
     }
 }
 
+const _: () = {
+    const fn assert_copy<T: Copy>() {}
+    assert_copy::<Flags>();
+};
+
+/// The format of the rendered diagram.
+///
+/// Only [`OutputFormat::Svg`] is supported. The other formats are reserved for future backends
+/// and fail with [`PiktErrorReason::UnsupportedOutputFormat`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum OutputFormat {
+    #[default]
+    Svg,
+    Png,
+    Pdf,
+}
+
+/// The colour scheme of the rendered diagram, see [`OptionsBuilder::theme`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Reads the theme from the `PIKCHR_THEME` environment variable when building the options.
+    /// It is dark when set to `dark` and light otherwise.
+    System,
+}
+
+/// The environment variable read by [`Theme::System`].
+const THEME_VAR: &str = "PIKCHR_THEME";
+
+impl Theme {
+    /// Resolves [`Theme::System`] to a concrete theme.
+    fn resolve(self) -> Theme {
+        match self {
+            Theme::System => match std::env::var(THEME_VAR).as_deref() {
+                Ok("dark") => Theme::Dark,
+                _ => Theme::Light,
+            },
+            theme => theme,
+        }
+    }
+}
+
+/// A `viewBox` as set by [`OptionsBuilder::view_box`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ViewBox {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl ViewBox {
+    fn is_valid(&self) -> bool {
+        [self.x, self.y, self.width, self.height]
+            .iter()
+            .all(|value| value.is_finite())
+            && self.width > 0.0
+            && self.height > 0.0
+    }
+}
+
+/// Formats as the value of the attribute, e.g. `0 0 112.32 76.32`.
+impl std::fmt::Display for ViewBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            postprocess::format_number(self.x),
+            postprocess::format_number(self.y),
+            postprocess::format_number(self.width),
+            postprocess::format_number(self.height)
+        )
+    }
+}
+
 /// Represents the set of options the renderer can take.
 ///
-/// Use the [`OptionsBuilder`] to construct it.
+/// Use the [`OptionsBuilder`] to construct it or [`Options::default`] for the defaults.
+///
+/// With the `serde` feature it is deserialized as an [`OptionsBuilder`] and built with
+/// [`OptionsBuilder::try_build`], so invalid options fail to deserialize.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "OptionsBuilder")
+)]
 pub struct Options {
     flags: Flags,
     width: u32,
     height: u32,
     class: String,
+    svg_attributes: Vec<(String, String)>,
+    data_attributes: Vec<(String, String)>,
+    title: Option<String>,
+    description: Option<String>,
+    aria_label: Option<String>,
+    css_variables: Vec<(String, String)>,
+    css: Option<String>,
+    background_color: Option<String>,
+    id_prefix: Option<String>,
+    namespace: Option<String>,
+    view_box: Option<ViewBox>,
+    minify: bool,
+    pretty_print: Option<usize>,
+    max_output_bytes: Option<usize>,
+    max_input_bytes: usize,
+    max_complexity: Option<ComplexityClass>,
+    inject_dimensions: bool,
+    annotate_elements: bool,
+    output_format: OutputFormat,
+    standalone: bool,
 }
 
+// Accessors and thin wrappers are `#[inline]` so they cost no more than a field access or a
+// direct call from other crates. `render_with` is not, the native call dominates its cost.
 impl Options {
+    #[inline]
     pub fn flags(&self) -> Flags {
         self.flags
     }
 
+    #[inline]
     pub fn width(&self) -> u32 {
         self.width
     }
 
+    #[inline]
     pub fn height(&self) -> u32 {
         self.height
     }
 
+    #[inline]
     pub fn class(&self) -> &str {
         &self.class
     }
+
+    /// The extra attributes for the root SVG element, in insertion order.
+    #[inline]
+    pub fn svg_attributes(&self) -> &[(String, String)] {
+        &self.svg_attributes
+    }
+
+    /// The `data-*` attributes for the root SVG element, in insertion order.
+    #[inline]
+    pub fn data_attributes(&self) -> &[(String, String)] {
+        &self.data_attributes
+    }
+
+    /// The accessible title of the SVG, if any.
+    #[inline]
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// The accessible description of the SVG, if any.
+    #[inline]
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The accessible label of the SVG, if any.
+    #[inline]
+    pub fn aria_label(&self) -> Option<&str> {
+        self.aria_label.as_deref()
+    }
+
+    /// The CSS custom properties declared in the SVG, in insertion order.
+    #[inline]
+    pub fn css_variables(&self) -> &[(String, String)] {
+        &self.css_variables
+    }
+
+    /// The CSS injected in the SVG, if any.
+    #[inline]
+    pub fn css(&self) -> Option<&str> {
+        self.css.as_deref()
+    }
+
+    /// The fill of the background rectangle, if any.
+    #[inline]
+    pub fn background_color(&self) -> Option<&str> {
+        self.background_color.as_deref()
+    }
+
+    /// The prefix of the ids added to the SVG elements, if any.
+    #[inline]
+    pub fn id_prefix(&self) -> Option<&str> {
+        self.id_prefix.as_deref()
+    }
+
+    /// The prefix of the ids of the SVG and of the references to them, if any.
+    #[inline]
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// The `viewBox` replacing the one computed by pikchr, if any.
+    #[inline]
+    pub fn view_box(&self) -> Option<ViewBox> {
+        self.view_box
+    }
+
+    /// Whether the SVG is minified.
+    #[inline]
+    pub fn minify(&self) -> bool {
+        self.minify
+    }
+
+    /// The indentation per nesting level when the SVG is pretty-printed.
+    #[inline]
+    pub fn pretty_print(&self) -> Option<usize> {
+        self.pretty_print
+    }
+
+    /// The maximum size of the SVG in bytes, if any.
+    #[inline]
+    pub fn max_output_bytes(&self) -> Option<usize> {
+        self.max_output_bytes
+    }
+
+    /// The maximum size of the input in bytes.
+    #[inline]
+    pub fn max_input_bytes(&self) -> usize {
+        self.max_input_bytes
+    }
+
+    /// The highest [`ComplexityClass`] rendered, if limited.
+    #[inline]
+    pub fn max_complexity(&self) -> Option<ComplexityClass> {
+        self.max_complexity
+    }
+
+    /// Whether the `width` and `height` computed by pikchr are set on the SVG element.
+    #[inline]
+    pub fn inject_dimensions(&self) -> bool {
+        self.inject_dimensions
+    }
+
+    /// Whether the SVG elements are preceded by a comment with the source line that likely
+    /// generated them.
+    #[inline]
+    pub fn annotate_elements(&self) -> bool {
+        self.annotate_elements
+    }
+
+    /// The format of the rendered diagram.
+    #[inline]
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+
+    /// Whether the SVG keeps its `xmlns` attribute.
+    #[inline]
+    pub fn standalone(&self) -> bool {
+        self.standalone
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        OptionsBuilder::default().build()
+    }
+}
+
+/// Same as [`OptionsBuilder::try_build`].
+impl std::convert::TryFrom<OptionsBuilder> for Options {
+    type Error = PiktError;
+
+    fn try_from(builder: OptionsBuilder) -> Result<Self> {
+        builder.into_options()
+    }
+}
+
+/// A one line summary for logs with the class, the dark mode and the dimensions, e.g.
+/// `Options(class="pikchr", dark=false, 0x0)`.
+impl std::fmt::Display for Options {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_summary(f, &self.class, self.flags, self.width, self.height)
+    }
+}
+
+fn write_summary(
+    f: &mut std::fmt::Formatter<'_>,
+    class: &str,
+    flags: Flags,
+    width: u32,
+    height: u32,
+) -> std::fmt::Result {
+    write!(
+        f,
+        "Options(class={:?}, dark={}, {}x{})",
+        class,
+        flags.contains(Flags::DARK_MODE),
+        width,
+        height
+    )
+}
+
+/// Merges `$other` into `$target`, the fields shared by [`Options`] and [`OptionsBuilder`], for
+/// `Options |= Options` and [`OptionsBuilder::merge_from`].
+macro_rules! merge_options {
+    ($target:ident, $other:ident) => {
+        $target.flags |= $other.flags;
+
+        let class = $other.class.trim();
+        if !class.is_empty() && class != DEFAULT_CLASS {
+            $target.class = $other.class;
+        }
+
+        if $other.width != 0 {
+            $target.width = $other.width;
+        }
+
+        if $other.height != 0 {
+            $target.height = $other.height;
+        }
+
+        $target.svg_attributes.extend($other.svg_attributes);
+        $target.data_attributes.extend($other.data_attributes);
+        $target.title = $other.title.or($target.title.take());
+        $target.description = $other.description.or($target.description.take());
+        $target.aria_label = $other.aria_label.or($target.aria_label.take());
+
+        for (name, value) in $other.css_variables {
+            match $target.css_variables.iter_mut().find(|(n, _)| *n == name) {
+                Some(variable) => variable.1 = value,
+                None => $target.css_variables.push((name, value)),
+            }
+        }
+
+        $target.css = match ($target.css.take(), $other.css) {
+            (Some(css), Some(other)) => Some(format!("{}\n{}", css, other)),
+            (css, other) => other.or(css),
+        };
+        $target.background_color = $other.background_color.or($target.background_color.take());
+        $target.id_prefix = $other.id_prefix.or($target.id_prefix.take());
+        $target.namespace = $other.namespace.or($target.namespace.take());
+        $target.view_box = $other.view_box.or($target.view_box);
+
+        $target.minify |= $other.minify;
+        $target.pretty_print = $other.pretty_print.or($target.pretty_print);
+        $target.max_output_bytes = $other.max_output_bytes.or($target.max_output_bytes);
+
+        if $other.max_input_bytes != MAX_INPUT_BYTES {
+            $target.max_input_bytes = $other.max_input_bytes;
+        }
+        $target.max_complexity = $other.max_complexity.or($target.max_complexity);
+        $target.inject_dimensions |= $other.inject_dimensions;
+        $target.annotate_elements |= $other.annotate_elements;
+
+        if $other.output_format != OutputFormat::default() {
+            $target.output_format = $other.output_format;
+        }
+        $target.standalone &= $other.standalone;
+    };
+}
+
+/// Merges two sets of options, giving precedence to the right-hand side.
+///
+/// Flags are combined. The class, width and height of `rhs` are used unless they are unset,
+/// which for the class means empty or the default `pikchr`. Other options set on `rhs` take
+/// precedence, with SVG attributes and CSS variables added to the ones of `self`.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{Flags, Options, OptionsBuilder};
+///
+/// let mut builder = OptionsBuilder::default();
+/// builder.dark_mode();
+/// let options = Options::default() | builder.build();
+///
+/// assert!(options.flags().contains(Flags::DARK_MODE));
+/// ```
+impl std::ops::BitOr for Options {
+    type Output = Options;
+
+    fn bitor(mut self, rhs: Options) -> Options {
+        self |= rhs;
+        self
+    }
+}
+
+impl std::ops::BitOrAssign for Options {
+    fn bitor_assign(&mut self, rhs: Options) {
+        merge_options!(self, rhs);
+    }
+}
+
+/// The options are validated when built, so the coordinates of the `viewBox` are never NaN.
+impl Eq for Options {}
+
+/// Consistent with `==`, a `viewBox` coordinate of `-0.0` hashes like `0.0`.
+impl std::hash::Hash for Options {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // Destructured so a new field doesn't go unhashed.
+        let Options {
+            flags,
+            width,
+            height,
+            class,
+            svg_attributes,
+            data_attributes,
+            title,
+            description,
+            aria_label,
+            css_variables,
+            css,
+            background_color,
+            id_prefix,
+            namespace,
+            view_box,
+            minify,
+            pretty_print,
+            max_output_bytes,
+            max_input_bytes,
+            max_complexity,
+            inject_dimensions,
+            annotate_elements,
+            output_format,
+            standalone,
+        } = self;
+
+        flags.hash(state);
+        width.hash(state);
+        height.hash(state);
+        class.hash(state);
+        svg_attributes.hash(state);
+        data_attributes.hash(state);
+        title.hash(state);
+        description.hash(state);
+        aria_label.hash(state);
+        css_variables.hash(state);
+        css.hash(state);
+        background_color.hash(state);
+        id_prefix.hash(state);
+        namespace.hash(state);
+        view_box
+            .map(|v| [v.x, v.y, v.width, v.height].map(|value| (value + 0.0).to_bits()))
+            .hash(state);
+        minify.hash(state);
+        pretty_print.hash(state);
+        max_output_bytes.hash(state);
+        max_input_bytes.hash(state);
+        max_complexity.hash(state);
+        inject_dimensions.hash(state);
+        annotate_elements.hash(state);
+        output_format.hash(state);
+        standalone.hash(state);
+    }
+}
+
+/// The default maximum size of the input in bytes, 1 MiB. See [`OptionsBuilder::max_input_bytes`].
+pub const MAX_INPUT_BYTES: usize = 1_048_576;
+
+/// The class pikchr gives to the SVG element by default.
+const DEFAULT_CLASS: &str = "pikchr";
+
+/// Whether the prefix can be joined with a hyphen to a class, see
+/// [`OptionsBuilder::class_prefix`].
+fn is_valid_prefix(prefix: &str) -> bool {
+    !(prefix.contains(|c: char| c.is_whitespace() || c == '\0')
+        || prefix.starts_with('-')
+        || prefix.ends_with('-'))
+}
+
+/// Whether the namespace is an ASCII letter or underscore followed by ASCII letters, digits,
+/// underscores and hyphens, so the prefixed ids are valid XML names and the `#` references to
+/// them need no escaping. See [`OptionsBuilder::namespace`].
+fn is_valid_namespace(namespace: &str) -> bool {
+    let mut chars = namespace.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct OptionsBuilder {
     flags: Flags,
     width: u32,
     height: u32,
     class: String,
+    svg_attributes: Vec<(String, String)>,
+    data_attributes: Vec<(String, String)>,
+    title: Option<String>,
+    description: Option<String>,
+    aria_label: Option<String>,
+    css_variables: Vec<(String, String)>,
+    css: Option<String>,
+    background_color: Option<String>,
+    id_prefix: Option<String>,
+    namespace: Option<String>,
+    view_box: Option<ViewBox>,
+    minify: bool,
+    pretty_print: Option<usize>,
+    max_output_bytes: Option<usize>,
+    max_input_bytes: usize,
+    max_complexity: Option<ComplexityClass>,
+    inject_dimensions: bool,
+    annotate_elements: bool,
+    output_format: OutputFormat,
+    standalone: bool,
+    class_prefix: String,
+    theme: Option<Theme>,
 }
 
 impl Default for OptionsBuilder {
@@ -58,39 +672,233 @@ impl Default for OptionsBuilder {
             flags: Flags::empty(),
             width: 0,
             height: 0,
-            class: "pikchr".to_string(),
+            class: DEFAULT_CLASS.to_string(),
+            svg_attributes: Vec::new(),
+            data_attributes: Vec::new(),
+            title: None,
+            description: None,
+            aria_label: None,
+            css_variables: Vec::new(),
+            css: None,
+            background_color: None,
+            id_prefix: None,
+            namespace: None,
+            view_box: None,
+            minify: false,
+            pretty_print: None,
+            max_output_bytes: None,
+            max_input_bytes: MAX_INPUT_BYTES,
+            max_complexity: None,
+            inject_dimensions: false,
+            annotate_elements: false,
+            output_format: OutputFormat::Svg,
+            standalone: true,
+            class_prefix: String::new(),
+            theme: None,
         }
     }
 }
 
+/// The summary of the options built so far, see [`Options`], prefixed with `[draft]`.
+impl std::fmt::Display for OptionsBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[draft] ")?;
+        write_summary(f, &self.class, self.flags, self.width, self.height)
+    }
+}
+
 impl OptionsBuilder {
-    pub fn flags(&mut self, flags: Flags) {
+    /// A builder for SVGs inlined in an HTML page: without a class, without `xmlns` and sized
+    /// by the page as only the `viewBox` is set.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let output = render_with("box", OptionsBuilder::for_html_embed().build()).unwrap();
+    ///
+    /// assert!(output.svg.starts_with("<svg viewBox="));
+    /// ```
+    pub fn for_html_embed() -> OptionsBuilder {
+        let mut builder = OptionsBuilder::default();
+        builder.no_class().standalone(false);
+        builder
+    }
+
+    /// A builder for SVG files: with the `pikchr` class, `xmlns` and the `width` and `height`
+    /// computed by pikchr so that viewers show the diagram at its natural size.
+    pub fn for_standalone_svg() -> OptionsBuilder {
+        let mut builder = OptionsBuilder::default();
+        builder.standalone(true).inject_dimensions();
+        builder
+    }
+
+    /// Applies the fields set on `other` over the ones of `self`, e.g. to patch a shared base
+    /// builder with local overrides.
+    ///
+    /// Fields are merged as with `|` on [`Options`]: flags are combined, and the class, width and
+    /// height of `other` are used unless unset, which for the class means empty or the default
+    /// `pikchr`. The theme and the class prefix of `other` are used when set.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{Flags, OptionsBuilder};
+    ///
+    /// let mut base = OptionsBuilder::default();
+    /// base.class("diagram");
+    /// let mut local = OptionsBuilder::default();
+    /// local.dark_mode();
+    ///
+    /// let options = base.merge_from(&local).clone().build();
+    ///
+    /// assert_eq!(options.class(), "diagram");
+    /// assert_eq!(options.flags(), Flags::DARK_MODE);
+    /// ```
+    pub fn merge_from(&mut self, other: &OptionsBuilder) -> &mut Self {
+        let other = other.clone();
+        merge_options!(self, other);
+
+        if !other.class_prefix.is_empty() {
+            self.class_prefix = other.class_prefix;
+        }
+        self.theme = other.theme.or(self.theme);
+
+        self
+    }
+
+    /// Replaces the flags. See [`OptionsBuilder::dark_mode`] to add a flag.
+    pub fn flags(&mut self, flags: Flags) -> &mut Self {
         self.flags = flags;
+        self
+    }
+
+    /// Enables dark mode, keeping any other flag.
+    pub fn dark_mode(&mut self) -> &mut Self {
+        self.flags.insert(Flags::DARK_MODE);
+        self
+    }
+
+    /// Sets the theme, which takes precedence over the dark mode flag.
+    ///
+    /// [`Theme::System`] is resolved when building the options.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{Flags, OptionsBuilder, Theme};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.theme(Theme::Dark);
+    ///
+    /// assert!(builder.build().flags().contains(Flags::DARK_MODE));
+    /// ```
+    pub fn theme(&mut self, theme: Theme) -> &mut Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Calls `f` once with the builder, so it can be configured conditionally without breaking a
+    /// chain.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{Flags, OptionsBuilder};
+    ///
+    /// let dark = true;
+    /// let options = OptionsBuilder::default()
+    ///     .apply(|b| {
+    ///         if dark {
+    ///             b.dark_mode();
+    ///         }
+    ///     })
+    ///     .build();
+    ///
+    /// assert_eq!(options.flags(), Flags::DARK_MODE);
+    /// ```
+    pub fn apply<F: FnOnce(&mut OptionsBuilder)>(mut self, f: F) -> Self {
+        f(&mut self);
+        self
+    }
+
+    /// Calls `f` once with the builder if `condition` holds. See [`OptionsBuilder::apply`].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{Flags, OptionsBuilder};
+    ///
+    /// let (dark, custom) = (true, false);
+    /// let options = OptionsBuilder::default()
+    ///     .apply_if(dark, |b| {
+    ///         b.dark_mode();
+    ///     })
+    ///     .apply_if(custom, |b| {
+    ///         b.class("custom");
+    ///     })
+    ///     .build();
+    ///
+    /// assert_eq!(options.flags(), Flags::DARK_MODE);
+    /// assert_eq!(options.class(), "pikchr");
+    /// ```
+    pub fn apply_if<F: FnOnce(&mut OptionsBuilder)>(self, condition: bool, f: F) -> Self {
+        if condition {
+            self.apply(f)
+        } else {
+            self
+        }
     }
 
-    pub fn width(&mut self, width: u32) {
+    pub fn width(&mut self, width: u32) -> &mut Self {
         self.width = width;
+        self
     }
 
-    pub fn height(&mut self, height: u32) {
+    pub fn height(&mut self, height: u32) -> &mut Self {
         self.height = height;
+        self
     }
 
-    /// Replaces the entire value for `class`. See [`OptionsBuilder.classes`] to append a list of
-    /// values.
+    /// Replaces the entire value for `class`, including the default `pikchr` and any value
+    /// appended so far. See [`OptionsBuilder::classes`] to append a list of values.
     ///
     /// By default it already has the value `pikchr`.
-    pub fn class(&mut self, class: &str) {
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the class contains a nul byte. See [`OptionsBuilder::try_class`].
+    pub fn class(&mut self, class: &str) -> &mut Self {
+        self.try_class(class)
+            .expect("class must not contain nul bytes")
+    }
+
+    /// Removes the `class` attribute, including the default `pikchr`.
+    pub fn no_class(&mut self) -> &mut Self {
+        self.class("")
+    }
+
+    /// Replaces the entire value for `class` failing if it contains a nul byte.
+    pub fn try_class(&mut self, class: &str) -> Result<&mut Self, NulError> {
+        CString::new(class)?;
         self.class = class.to_string();
+
+        Ok(self)
     }
 
-    pub fn classes(&mut self, values: &[&str]) {
-        let s = values.join(" ");
-        self.class.push(' ');
-        self.class.push_str(&s);
+    /// Prefixes every class with `prefix-` when building, e.g. `myapp` turns `pikchr dark` into
+    /// `myapp-pikchr myapp-dark`. An empty prefix leaves the classes unchanged.
+    ///
+    /// The prefix is validated when building the options.
+    pub fn class_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.class_prefix = prefix.to_string();
+        self
     }
 
-    /// Builds the set of options.
+    /// Appends the given values to `class`, after the default `pikchr` unless replaced with
+    /// [`OptionsBuilder::class`]. Values are split on whitespace, and empty values and values
+    /// already in `class` are skipped. An empty list of values leaves `class` unchanged.
     ///
     /// ## Example
     ///
@@ -98,142 +906,2000 @@ impl OptionsBuilder {
     /// use pikt::OptionsBuilder;
     ///
     /// let mut builder = OptionsBuilder::default();
-    /// builder.width(300);
-    /// builder.height(150);
-    /// builder.classes(&vec!["foo", "bar"]);
-    /// let options = builder.build();
+    /// builder.classes(&["wide", "dark"]).classes(&["dark", "docs diagram"]);
     ///
-    /// assert_eq!(options.width(), 300);
-    /// assert_eq!(options.class(), "pikchr foo bar");
+    /// assert_eq!(builder.build().class(), "pikchr wide dark docs diagram");
     /// ```
-    pub fn build(self) -> Options {
-        Options {
-            flags: self.flags,
-            width: self.width,
-            height: self.height,
-            class: self.class,
+    ///
+    /// ## Panics
+    ///
+    /// Panics if any value contains a nul byte. See [`OptionsBuilder::try_classes`].
+    pub fn classes(&mut self, values: &[&str]) -> &mut Self {
+        self.try_classes(values)
+            .expect("classes must not contain nul bytes")
+    }
+
+    /// Appends the given values to `class` failing if any of them contains a nul byte.
+    pub fn try_classes(&mut self, values: &[&str]) -> Result<&mut Self, NulError> {
+        if values.is_empty() {
+            return Ok(self);
+        }
+
+        CString::new(values.join(" "))?;
+
+        for value in values.iter().flat_map(|value| value.split_whitespace()) {
+            if self.class.split_whitespace().any(|class| class == value) {
+                continue;
+            }
+
+            if !self.class.is_empty() {
+                self.class.push(' ');
+            }
+            self.class.push_str(value);
         }
+
+        Ok(self)
     }
-}
 
-/// Renders the given pikchr markup as SVG.
-///
-/// Use [`render_with`] if you want to change the default options.
-///
-/// ## Example
-///
-/// ```
-/// use pikt::render;
-///
-/// let markup = r#"
-/// circle "1"
-/// move
-/// circle "2"
-/// arrow from first circle.end to last circle.start
-/// "#;
-/// let svg = render(markup);
-///
-/// assert!(svg.is_ok());
-/// ```
-pub fn render(input: &str) -> Result<String, PiktError> {
-    let options = OptionsBuilder::default().build();
-    render_with(input, options)
-}
+    /// Adds an attribute to the root SVG element, e.g. `id`, `role` or `aria-label`.
+    ///
+    /// The value is escaped. An attribute already set by pikchr, such as `class`, is replaced.
+    /// The name is validated when building the options.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.svg_attribute("id", "diagram-1");
+    /// let output = render_with("box", builder.build()).unwrap();
+    ///
+    /// assert!(output.svg.contains("id=\"diagram-1\""));
+    /// ```
+    pub fn svg_attribute(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.svg_attributes.push((key.into(), value.into()));
+        self
+    }
 
-/// Renders the given pikchr markup as SVG with the given configuration.
-///
-/// ```
-/// use pikt::{render_with, OptionsBuilder, Flags};
-///
-/// let markup = r#"
-/// circle "1"
-/// move
-/// circle "2"
-/// arrow from first circle.end to last circle.start
-/// "#;
-/// let mut opt_builder = OptionsBuilder::default();
-/// opt_builder.flags(Flags::DARK_MODE);
-/// opt_builder.classes(&["foo", "bar"]);
-/// let options = opt_builder.build();
-/// let svg = render_with(markup, options);
-///
-/// assert!(svg.is_ok());
-/// ```
-///
-/// ## Errors
-///
-/// It can fail either because the given input has an unexpected NUL terminator or for any of the
-/// errors the native pikchr library handles. See [`PiktError`].
-pub fn render_with(input: &str, options: Options) -> Result<String, PiktError> {
-    use libc::free;
-    use std::os::raw::*;
-
-    let mut width: c_int = options.width() as i32;
-    let mut height: c_int = options.height() as i32;
-    let class = CString::new(options.class())?;
-    let input = CString::new(input)?;
-
-    let res: *mut c_char = unsafe {
-        pikchr(
-            input.as_ptr() as *const c_char,
-            class.as_ptr() as *const c_char,
-            options.flags().bits() | PIKCHR_PLAINTEXT_ERRORS,
-            &mut width as *mut c_int,
-            &mut height as *mut c_int,
-        )
-    };
+    /// Adds the given attributes to the root SVG element. See [`OptionsBuilder::svg_attribute`].
+    pub fn svg_attributes(&mut self, attrs: &[(&str, &str)]) -> &mut Self {
+        for (key, value) in attrs {
+            self.svg_attribute(*key, *value);
+        }
+
+        self
+    }
+
+    /// Adds a `data-*` attribute to the root SVG element, e.g. to hook scripts to the diagram.
+    ///
+    /// The name is prefixed with `data-` unless it already is. The value is escaped. The name is
+    /// validated when building the options.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.data_attribute("diagram-id", "123");
+    /// let output = render_with("box", builder.build()).unwrap();
+    ///
+    /// assert!(output.svg.contains("data-diagram-id=\"123\""));
+    /// ```
+    pub fn data_attribute(&mut self, name: &str, value: &str) -> &mut Self {
+        let name = if name.starts_with("data-") {
+            name.to_string()
+        } else {
+            format!("data-{}", name)
+        };
+        self.data_attributes.push((name, value.to_string()));
+        self
+    }
 
-    let cstr = unsafe { CStr::from_ptr(res) };
-    let output = String::from_utf8_lossy(cstr.to_bytes()).into_owned();
+    /// Sets the accessible title of the SVG.
+    ///
+    /// It is inserted as a `<title id="pikchr-title">` element referenced by the
+    /// `aria-labelledby` attribute of the root element, which also gets `role="img"`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.title("A box").description("A single box");
+    /// let output = render_with("box", builder.build()).unwrap();
+    ///
+    /// assert!(output.svg.contains("<title id=\"pikchr-title\">A box</title>"));
+    /// assert!(output.svg.contains("<desc>A single box</desc>"));
+    /// ```
+    pub fn title(&mut self, title: &str) -> &mut Self {
+        self.title = Some(title.to_string());
+        self
+    }
 
-    unsafe { free(res as *mut c_void) };
+    /// Sets the accessible description of the SVG, inserted as a `<desc>` element.
+    pub fn description(&mut self, desc: &str) -> &mut Self {
+        self.description = Some(desc.to_string());
+        self
+    }
 
-    if width < 0 {
-        let err = PiktError::from_str(&output).unwrap();
-        return Err(err);
+    /// Sets the accessible label of the SVG as an `aria-label` attribute along with `role="img"`.
+    ///
+    /// The label is ignored if a [title](OptionsBuilder::title) is set as the SVG is then
+    /// labelled by it.
+    pub fn aria_label(&mut self, label: &str) -> &mut Self {
+        self.aria_label = Some(label.to_string());
+        self
     }
 
-    Ok(output)
+    /// Declares a CSS custom property in a `<style>` element inserted as the first child of the
+    /// SVG, e.g. to theme the diagram from the page.
+    ///
+    /// Setting the same name again replaces its value. The name must start with `--`, it is
+    /// validated when building the options.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.css_variable("--stroke", "#333");
+    /// let output = render_with("box", builder.build()).unwrap();
+    ///
+    /// assert!(output.svg.contains("<style>:root { --stroke: #333; }</style>"));
+    /// ```
+    pub fn css_variable(&mut self, name: &str, value: &str) -> &mut Self {
+        match self.css_variables.iter_mut().find(|(n, _)| n == name) {
+            Some(variable) => variable.1 = value.to_string(),
+            None => self
+                .css_variables
+                .push((name.to_string(), value.to_string())),
+        }
+
+        self
+    }
+
+    /// Declares the given CSS custom properties. See [`OptionsBuilder::css_variable`].
+    pub fn css_variables(&mut self, vars: &[(&str, &str)]) -> &mut Self {
+        for (name, value) in vars {
+            self.css_variable(name, value);
+        }
+
+        self
+    }
+
+    /// Adds CSS to a `<style>` element inserted as the first child of the SVG element.
+    ///
+    /// Successive calls are joined with a newline in the same element. The CSS is written as
+    /// is, so it must come from a trusted source: a `</style>` in it, even in a comment, closes
+    /// the element early and lets the rest be interpreted as markup when the SVG is inlined in
+    /// HTML.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.inject_css("path { stroke: red; }");
+    /// let output = render_with("box", builder.build()).unwrap();
+    ///
+    /// assert!(output.svg.contains("<style>path { stroke: red; }</style>"));
+    /// ```
+    pub fn inject_css(&mut self, css: &str) -> &mut Self {
+        match &mut self.css {
+            Some(existing) => {
+                existing.push('\n');
+                existing.push_str(css);
+            }
+            None => self.css = Some(css.to_string()),
+        }
+        self
+    }
+
+    /// Minifies the SVG.
+    ///
+    /// Whitespace runs are collapsed to a single space and removed next to tags, comments are
+    /// stripped and attributes with an empty value such as `style=""` are dropped.
+    pub fn minify(&mut self) -> &mut Self {
+        self.minify = true;
+        self
+    }
+
+    /// Pretty-prints the SVG with one element per line indented `indent` spaces per nesting level.
+    ///
+    /// Elements holding only text, such as `<text>`, stay on a single line. It is applied after
+    /// [`OptionsBuilder::minify`] when both are set.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.pretty_print(2);
+    /// let output = render_with("box", builder.build()).unwrap();
+    ///
+    /// assert!(output.svg.contains("\n  <path "));
+    /// ```
+    pub fn pretty_print(&mut self, indent: usize) -> &mut Self {
+        self.pretty_print = Some(indent);
+        self
+    }
+
+    /// Limits the size of the SVG in bytes, post-processing included. A render producing a
+    /// larger SVG fails with [`PiktErrorReason::OutputTooLarge`].
+    pub fn max_output_bytes(&mut self, limit: usize) -> &mut Self {
+        self.max_output_bytes = Some(limit);
+        self
+    }
+
+    /// Limits the size of the input in bytes. Defaults to [`MAX_INPUT_BYTES`]. A larger input
+    /// fails with [`PiktErrorReason::InputTooLarge`] without being rendered.
+    pub fn max_input_bytes(&mut self, limit: usize) -> &mut Self {
+        self.max_input_bytes = limit;
+        self
+    }
+
+    /// Limits the [`ComplexityClass`] of the input, as computed by [`classify_complexity`]. A
+    /// more complex input fails with [`PiktErrorReason::TooComplex`] without being rendered.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, ComplexityClass, OptionsBuilder, PiktErrorReason};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.max_complexity(ComplexityClass::Simple);
+    /// let err = render_with(&"box\n".repeat(100), builder.build()).unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.reason(),
+    ///     &PiktErrorReason::TooComplex {
+    ///         actual: ComplexityClass::Complex,
+    ///         limit: ComplexityClass::Simple,
+    ///     }
+    /// );
+    /// ```
+    pub fn max_complexity(&mut self, class: ComplexityClass) -> &mut Self {
+        self.max_complexity = Some(class);
+        self
+    }
+
+    /// Sets the `width` and `height` attributes of the SVG element to the dimensions computed by
+    /// pikchr, see [`PikchrOutput::width`]. Otherwise the SVG only has a `viewBox`.
+    ///
+    /// Attributes set with [`OptionsBuilder::svg_attribute`] take precedence.
+    pub fn inject_dimensions(&mut self) -> &mut Self {
+        self.inject_dimensions = true;
+        self
+    }
+
+    /// Inserts a comment such as `<!-- pikchr line 3: box "hello" -->` before each `<path>` and
+    /// `<text>` element, to help find the markup behind an element when debugging.
+    ///
+    /// pikchr doesn't map elements to the source so this is a heuristic: the Nth `<path>` is
+    /// attributed to the Nth line that is neither blank nor a comment, and a `<text>` to the
+    /// same line as the `<path>` before it. It is accurate for diagrams with one shape per
+    /// line. Minifying the SVG removes the comments.
+    pub fn annotate_elements(&mut self) -> &mut Self {
+        self.annotate_elements = true;
+        self
+    }
+
+    /// Sets the format of the rendered diagram. Defaults to [`OutputFormat::Svg`].
+    pub fn output_format(&mut self, fmt: OutputFormat) -> &mut Self {
+        self.output_format = fmt;
+        self
+    }
+
+    /// Whether the SVG is a standalone document. Defaults to `true`.
+    ///
+    /// The `xmlns` attribute of the SVG element is removed when `false`, for SVGs embedded in an
+    /// HTML5 document where it is redundant.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.standalone(false);
+    /// let output = render_with("box", builder.build()).unwrap();
+    ///
+    /// assert!(!output.svg.contains("xmlns="));
+    /// ```
+    pub fn standalone(&mut self, standalone: bool) -> &mut Self {
+        self.standalone = standalone;
+        self
+    }
+
+    /// Builds the set of options. The builder is borrowed so the setters can be chained from
+    /// a temporary.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::OptionsBuilder;
+    ///
+    /// let options = OptionsBuilder::default()
+    ///     .width(300)
+    ///     .height(150)
+    ///     .classes(&["foo", "bar"])
+    ///     .build();
+    ///
+    /// assert_eq!(options.width(), 300);
+    /// assert_eq!(options.class(), "pikchr foo bar");
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// Panics if any of the options is invalid. See [`OptionsBuilder::try_build`].
+    #[inline]
+    pub fn build(&self) -> Options {
+        self.try_build()
+            .unwrap_or_else(|err| panic!("invalid options: {}", err))
+    }
+
+    /// Fills the background of the SVG with the given colour, as a `<rect>` covering the
+    /// `viewBox` drawn before the diagram.
+    ///
+    /// The colour is a name known to pikchr, see [`colors::NAMED_COLORS`], `#rgb`, `#rrggbb` or
+    /// `rgb(r, g, b)`. The dark mode doesn't change it so pick a dark colour for dark diagrams.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.background_color("white");
+    /// let output = render_with("box", builder.try_build().unwrap()).unwrap();
+    ///
+    /// assert!(output.svg.contains("<rect x=\"0\" y=\"0\""));
+    /// ```
+    pub fn background_color(&mut self, color: &str) -> &mut Self {
+        self.background_color = Some(color.to_string());
+        self
+    }
+
+    /// Adds an `id` to each element drawn by pikchr, as `prefix-N` with `N` counting from 1 in
+    /// document order, so scripts and stylesheets can target them. Elements added by the other
+    /// options are left unchanged. The prefix is escaped.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.id_prefix("flow");
+    /// let output = render_with("box \"a\"", builder.build()).unwrap();
+    ///
+    /// assert!(output.svg.contains("<path id=\"flow-1\""));
+    /// assert!(output.svg.contains("<text id=\"flow-2\""));
+    /// ```
+    pub fn id_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.id_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Prefixes every `id` of the SVG as `prefix-id`, along with the references to them in
+    /// `href="#id"`, `url(#id)`, `aria-labelledby` and `aria-describedby` attributes, so many
+    /// diagrams can be embedded in one page without their ids clashing. It applies to the ids
+    /// added by the other options too, e.g. [`OptionsBuilder::id_prefix`] or the `<title>` id,
+    /// but not to references in `<style>` elements.
+    ///
+    /// The prefix must be an ASCII letter or underscore followed by ASCII letters, digits,
+    /// underscores and hyphens. It is validated when building the options.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.namespace("intro").title("Intro");
+    /// let output = render_with("box", builder.build()).unwrap();
+    ///
+    /// assert!(output.svg.contains("aria-labelledby=\"intro-pikchr-title\""));
+    /// assert!(output.svg.contains("<title id=\"intro-pikchr-title\">"));
+    /// ```
+    pub fn namespace(&mut self, prefix: &str) -> &mut Self {
+        self.namespace = Some(prefix.to_string());
+        self
+    }
+
+    /// Replaces the `viewBox` computed by pikchr, e.g. to crop the diagram with a smaller box or
+    /// to pan it with a negative origin. The coordinates of the elements are unchanged.
+    ///
+    /// The dimensions are validated when building the options.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render_with, OptionsBuilder};
+    ///
+    /// let mut builder = OptionsBuilder::default();
+    /// builder.view_box(-10.0, -10.0, 132.5, 96.0);
+    /// let output = render_with("box", builder.try_build().unwrap()).unwrap();
+    ///
+    /// assert!(output.svg.contains("viewBox=\"-10 -10 132.5 96\""));
+    /// ```
+    pub fn view_box(&mut self, x: f64, y: f64, width: f64, height: f64) -> &mut Self {
+        self.view_box = Some(ViewBox {
+            x,
+            y,
+            width,
+            height,
+        });
+        self
+    }
+
+    /// Builds the set of options failing if any of them is invalid.
+    ///
+    /// ## Errors
+    ///
+    /// - [`PiktErrorReason::IncompatibleInput`] if the class or the injected CSS contains a nul
+    ///   byte.
+    /// - [`PiktErrorReason::InvalidFlags`] if the flags contain bits unknown to pikchr.
+    /// - [`PiktErrorReason::InvalidAttributeName`] if an SVG attribute name is empty or contains
+    ///   whitespace, quotes, `<`, `>`, `/` or `=`.
+    /// - [`PiktErrorReason::InvalidAttributeName`] if a `data-*` attribute name has anything
+    ///   other than lowercase ASCII letters, digits and hyphens after the prefix.
+    /// - [`PiktErrorReason::InvalidCssVariable`] if a CSS variable name doesn't start with `--`
+    ///   or the declaration would break out of the `<style>` element.
+    /// - [`PiktErrorReason::InvalidClassPrefix`] if the class prefix has whitespace or nul bytes,
+    ///   or starts or ends with a hyphen.
+    /// - [`PiktErrorReason::InvalidNamespace`] if the namespace doesn't start with an ASCII letter
+    ///   or underscore, or has anything other than ASCII letters, digits, underscores and
+    ///   hyphens.
+    /// - [`PiktErrorReason::InvalidColor`] if the background colour is not a known name nor a
+    ///   hex or `rgb()` colour.
+    /// - [`PiktErrorReason::InvalidViewBox`] if the `viewBox` has a coordinate that is not finite
+    ///   or a width or height that is not greater than zero.
+    pub fn try_build(&self) -> Result<Options> {
+        self.clone().into_options()
+    }
+
+    /// Same as [`OptionsBuilder::try_build`] without cloning the builder.
+    fn into_options(mut self) -> Result<Options> {
+        CString::new(self.class.as_str())?;
+
+        if let Some(css) = &self.css {
+            CString::new(css.as_str())?;
+        }
+
+        match self.theme.map(Theme::resolve) {
+            Some(Theme::Dark) => self.flags.insert(Flags::DARK_MODE),
+            Some(_) => self.flags.remove(Flags::DARK_MODE),
+            None => {}
+        }
+
+        if !self.class_prefix.is_empty() {
+            let prefix = &self.class_prefix;
+
+            if !is_valid_prefix(prefix) {
+                return Err(PiktError::new(PiktErrorReason::InvalidClassPrefix(
+                    prefix.clone(),
+                )));
+            }
+
+            self.class = self
+                .class
+                .split_whitespace()
+                .map(|class| format!("{}-{}", prefix, class))
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+
+        if let Some(namespace) = self
+            .namespace
+            .as_ref()
+            .filter(|namespace| !is_valid_namespace(namespace))
+        {
+            return Err(PiktError::new(PiktErrorReason::InvalidNamespace(
+                namespace.clone(),
+            )));
+        }
+
+        if let Some((name, _)) = self
+            .svg_attributes
+            .iter()
+            .find(|(name, _)| !postprocess::is_valid_attribute_name(name))
+        {
+            return Err(PiktError::new(PiktErrorReason::InvalidAttributeName(
+                name.clone(),
+            )));
+        }
+
+        if let Some((name, _)) = self
+            .data_attributes
+            .iter()
+            .find(|(name, _)| !postprocess::is_valid_data_attribute_name(name))
+        {
+            return Err(PiktError::new(PiktErrorReason::InvalidAttributeName(
+                name.clone(),
+            )));
+        }
+
+        if let Some((name, _)) = self
+            .css_variables
+            .iter()
+            .find(|(name, value)| !postprocess::is_valid_css_variable(name, value))
+        {
+            return Err(PiktError::new(PiktErrorReason::InvalidCssVariable(
+                name.clone(),
+            )));
+        }
+
+        if let Some(color) = self
+            .background_color
+            .as_ref()
+            .filter(|color| !postprocess::is_valid_color(color))
+        {
+            return Err(PiktError::new(PiktErrorReason::InvalidColor(color.clone())));
+        }
+
+        if let Some(view_box) = self.view_box.filter(|view_box| !view_box.is_valid()) {
+            return Err(PiktError::new(PiktErrorReason::InvalidViewBox(view_box)));
+        }
+
+        let unknown_bits = self.flags.bits() & !Flags::all().bits();
+        if unknown_bits != 0 {
+            return Err(PiktError::new(PiktErrorReason::InvalidFlags(unknown_bits)));
+        }
+
+        Ok(Options {
+            flags: self.flags,
+            width: self.width,
+            height: self.height,
+            class: self.class,
+            svg_attributes: self.svg_attributes,
+            data_attributes: self.data_attributes,
+            title: self.title,
+            description: self.description,
+            aria_label: self.aria_label,
+            css_variables: self.css_variables,
+            css: self.css,
+            background_color: self.background_color,
+            id_prefix: self.id_prefix,
+            namespace: self.namespace,
+            view_box: self.view_box,
+            minify: self.minify,
+            pretty_print: self.pretty_print,
+            max_output_bytes: self.max_output_bytes,
+            max_input_bytes: self.max_input_bytes,
+            max_complexity: self.max_complexity,
+            inject_dimensions: self.inject_dimensions,
+            annotate_elements: self.annotate_elements,
+            output_format: self.output_format,
+            standalone: self.standalone,
+        })
+    }
 }
 
-#[derive(Error, Debug, PartialEq)]
-#[error("line {line}, column {column}: {reason}")]
-pub struct PiktError {
-    line: usize,
-    column: usize,
-    reason: PiktErrorReason,
+/// Represents the result of a successful render.
+#[derive(Debug, Clone)]
+pub struct PikchrOutput {
+    /// The rendered SVG.
+    pub svg: String,
+    /// The width of the SVG as computed by pikchr.
+    pub width: u32,
+    /// The height of the SVG as computed by pikchr.
+    pub height: u32,
+    /// The time spent in the native pikchr call, excluding the conversion of the input and the
+    /// output.
+    #[cfg(feature = "metrics")]
+    pub render_duration: std::time::Duration,
 }
 
-#[derive(Error, Debug, PartialEq)]
-pub enum PiktErrorReason {
-    /// Raised when the given input has a nul byte.
-    #[error("incompatible input. Nul bytes are not allowed.")]
-    IncompatibleInput(NulError),
-    #[error("parser stack overflow")]
-    ParserStackOverflow,
-    #[error("out of memory")]
-    OutOfMemory,
-    #[error("division by zero")]
-    DivisionByZero,
-    #[error("syntax error")]
-    SyntaxError,
-    #[error("arc geometry error")]
-    ArcGeometryError,
-    #[error("unknown object")]
-    UnknownObject,
-    #[error("unknown object type")]
-    UnknownObjectType,
-    #[error("value already set")]
-    ValueAlreadySet,
-    #[error("value already fixed by prior constraints")]
-    ValueAlreadyFixed,
-    #[error("use with line-oriented objects only")]
-    OnlyWithLineOrientedObject,
-    #[error("no prior path points")]
-    NoPriorPathPoints,
-    #[error("headings should be between 0 and 360")]
-    HeadingOutOfBounds,
-    #[error("use `at` to position this object")]
+/// The name used by [`render_full`] and [`render_full_with`].
+pub type RenderOutput = PikchrOutput;
+
+/// Compares the SVG and the dimensions. Metrics such as the render duration are ignored.
+impl PartialEq for PikchrOutput {
+    fn eq(&self, other: &Self) -> bool {
+        self.svg == other.svg && self.width == other.width && self.height == other.height
+    }
+}
+
+impl PikchrOutput {
+    /// Computes a checksum of the output, dimensions included.
+    ///
+    /// The hash is computed with [`DefaultHasher`] which is not guaranteed to be stable across
+    /// Rust versions so it must not be persisted. Use it for in-process cache invalidation or
+    /// deduplication only.
+    ///
+    /// [`DefaultHasher`]: std::collections::hash_map::DefaultHasher
+    pub fn checksum(&self) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        self.svg.hash(&mut hasher);
+        self.width.hash(&mut hasher);
+        self.height.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Builds an output from its parts, e.g. to create fixtures without calling pikchr.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::PikchrOutput;
+    ///
+    /// let output = PikchrOutput::from_parts("<svg></svg>".to_string(), 10, 20);
+    ///
+    /// assert_eq!(output.into_parts(), ("<svg></svg>".to_string(), 10, 20));
+    /// ```
+    pub fn from_parts(svg: String, width: u32, height: u32) -> Self {
+        Self {
+            svg,
+            width,
+            height,
+            #[cfg(feature = "metrics")]
+            render_duration: std::time::Duration::ZERO,
+        }
+    }
+
+    /// Decomposes the output into `(svg, width, height)`.
+    pub fn into_parts(self) -> (String, u32, u32) {
+        (self.svg, self.width, self.height)
+    }
+
+    /// Compares the SVG of both outputs ignoring any other metadata.
+    pub fn content_eq(&self, other: &PikchrOutput) -> bool {
+        self.svg == other.svg
+    }
+
+    /// Parses the `viewBox` attribute of the SVG as `(min_x, min_y, width, height)`.
+    ///
+    /// Returns `None` if the attribute is missing or malformed, for example for an empty
+    /// diagram.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// let output = pikt::render("box").unwrap();
+    /// let (min_x, min_y, _, _) = output.view_box().unwrap();
+    ///
+    /// assert_eq!((min_x, min_y), (0.0, 0.0));
+    /// ```
+    pub fn view_box(&self) -> Option<(f64, f64, f64, f64)> {
+        postprocess::view_box(&self.svg)
+    }
+
+    /// The SVG after a leading `<?xml ...?>` declaration and the whitespace following it.
+    ///
+    /// pikchr doesn't write one but a post-processor might. The SVG is returned as is if there
+    /// is no declaration.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::PikchrOutput;
+    ///
+    /// let output = PikchrOutput::from_parts("<?xml version=\"1.0\"?>\n<svg></svg>".into(), 0, 0);
+    ///
+    /// assert_eq!(output.without_xml_header(), "<svg></svg>");
+    /// ```
+    pub fn without_xml_header(&self) -> &str {
+        strip_leading_markup(&self.svg, "<?xml", "?>")
+    }
+
+    /// The SVG after a leading `<!DOCTYPE ...>` declaration and the whitespace following it,
+    /// also skipping an XML declaration before it. See [`PikchrOutput::without_xml_header`].
+    pub fn without_doctype(&self) -> &str {
+        strip_leading_markup(self.without_xml_header(), "<!DOCTYPE", ">")
+    }
+}
+
+/// Returns the input after the markup from `start` to `end` and the whitespace after it, if the
+/// input starts with it after optional whitespace.
+fn strip_leading_markup<'a>(input: &'a str, start: &str, end: &str) -> &'a str {
+    let trimmed = input.trim_start();
+
+    if !trimmed.starts_with(start) {
+        return input;
+    }
+
+    match trimmed.find(end) {
+        Some(index) => trimmed[index + end.len()..].trim_start(),
+        None => input,
+    }
+}
+
+impl std::fmt::Display for PikchrOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.svg)
+    }
+}
+
+/// A pikchr diagram found in a larger document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PikchrBlock<'a> {
+    /// The pikchr markup. It is only owned when the markup had to be modified, for example to
+    /// remove indentation.
+    pub source: std::borrow::Cow<'a, str>,
+    /// The byte range of the whole block in the document, delimiters included.
+    pub byte_range: std::ops::Range<usize>,
+}
+
+/// Replaces each block in `text` with the result of `wrap` applied to its rendered SVG.
+///
+/// A block that fails to render is replaced with a `<pre class="pikchr-error">` element instead.
+fn replace_blocks<'a, I, F>(text: &str, blocks: I, options: &Options, wrap: F) -> String
+where
+    I: Iterator<Item = PikchrBlock<'a>>,
+    F: Fn(&str) -> String,
+{
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for block in blocks {
+        output.push_str(&text[last..block.byte_range.start]);
+
+        let html = match render_with(&block.source, options.clone()) {
+            Ok(rendered) => rendered.svg.trim_end().to_string(),
+            Err(err) => format!(
+                "<pre class=\"pikchr-error\">{}</pre>",
+                html::escape_html(&err.to_string())
+            ),
+        };
+        output.push_str(&wrap(&html));
+
+        last = block.byte_range.end;
+    }
+
+    output.push_str(&text[last..]);
+    output
+}
+
+/// Renders the given pikchr markup as SVG.
+///
+/// Use [`render_with`] if you want to change the default options.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::render;
+///
+/// let markup = r#"
+/// circle "1"
+/// move
+/// circle "2"
+/// arrow from first circle.end to last circle.start
+/// "#;
+/// let svg = render(markup);
+///
+/// assert!(svg.is_ok());
+/// ```
+#[inline]
+pub fn render(input: &str) -> Result<PikchrOutput> {
+    render_with(input, Options::default())
+}
+
+/// Same as [`render`], which already returns the dimensions computed by pikchr along with the
+/// SVG.
+///
+/// ## Example
+///
+/// ```
+/// let output = pikt::render_full("box").unwrap();
+///
+/// let img = format!("<img width=\"{}\" height=\"{}\">", output.width, output.height);
+/// ```
+#[inline]
+pub fn render_full(input: &str) -> Result<RenderOutput> {
+    render(input)
+}
+
+/// Same as [`render_with`]. See [`render_full`].
+#[inline]
+pub fn render_full_with(input: &str, options: Options) -> Result<RenderOutput> {
+    render_with(input, options)
+}
+
+/// Renders the given pikchr markup as SVG with the given configuration.
+///
+/// ```
+/// use pikt::{render_with, OptionsBuilder, Flags};
+///
+/// let markup = r#"
+/// circle "1"
+/// move
+/// circle "2"
+/// arrow from first circle.end to last circle.start
+/// "#;
+/// let mut opt_builder = OptionsBuilder::default();
+/// opt_builder.flags(Flags::DARK_MODE);
+/// opt_builder.classes(&["foo", "bar"]);
+/// let options = opt_builder.build();
+/// let svg = render_with(markup, options);
+///
+/// assert!(svg.is_ok());
+/// ```
+///
+/// ## Errors
+///
+/// It can fail either because the given input has an unexpected NUL terminator or for any of the
+/// errors the native pikchr library handles. See [`PiktError`].
+///
+/// A leading byte order mark is stripped and line endings are normalised to LF first, see
+/// [`strip_bom`] and [`normalize_line_endings`].
+pub fn render_with(input: &str, options: Options) -> Result<PikchrOutput> {
+    render_bytes_with(normalize_line_endings(strip_bom(input)).as_bytes(), options)
+}
+
+/// Renders the given pikchr markup as SVG with the given configuration, returning every error
+/// pikchr reported instead of a single one.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_with_all_errors, Options};
+///
+/// assert!(render_with_all_errors("box", Options::default()).is_ok());
+/// assert_eq!(render_with_all_errors("box 'x'", Options::default()).unwrap_err().len(), 1);
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`] and [`PiktError::all_from_str`]. Errors raised by pikt itself, e.g. on
+/// a nul byte in the input, are returned alone.
+pub fn render_with_all_errors(input: &str, options: Options) -> Result<String, Vec<PiktError>> {
+    match render_with(input, options) {
+        Ok(output) => Ok(output.svg),
+        Err(err) if err.raw_message.is_empty() => Err(vec![err]),
+        Err(err) => Err(PiktError::all_from_str(&err.raw_message)),
+    }
+}
+
+/// Renders the given pikchr markup as SVG with the given configuration, reusing the allocation
+/// of `input` for the buffer passed to pikchr.
+///
+/// [`render_with`] copies the input to add the nul terminator pikchr expects. Here it is
+/// appended in place, which only reallocates if `input` has no spare capacity.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_owned, Options};
+///
+/// let mut input = String::with_capacity(64);
+/// input.push_str("box \"pikchr\"");
+///
+/// assert!(render_owned(input, Options::default()).is_ok());
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`].
+pub fn render_owned(mut input: String, options: Options) -> Result<PikchrOutput> {
+    if input.starts_with('\u{FEFF}') {
+        input.drain(..'\u{FEFF}'.len_utf8());
+    }
+
+    if input.contains('\r') {
+        input = normalize_line_endings(&input).into_owned();
+    }
+
+    render_bytes_with(input.into_bytes(), options)
+}
+
+/// Renders the given pikchr markup as SVG in dark mode.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::render_dark;
+///
+/// let output = render_dark("box").unwrap();
+///
+/// assert!(output.svg.contains("rgb(255,255,255)"));
+/// ```
+pub fn render_dark(input: &str) -> Result<PikchrOutput> {
+    render_dark_with(input, Options::default())
+}
+
+/// Renders the given pikchr markup as SVG in dark mode with the given configuration, regardless
+/// of its flags.
+pub fn render_dark_with(input: &str, mut options: Options) -> Result<PikchrOutput> {
+    options.flags |= Flags::DARK_MODE;
+    render_with(input, options)
+}
+
+/// Renders the given pikchr markup as SVG in light mode, the same as [`render`].
+///
+/// Handy for symmetry where [`render_dark`] is also used.
+#[inline]
+pub fn render_light(input: &str) -> Result<PikchrOutput> {
+    render(input)
+}
+
+/// Renders the given pikchr markup as SVG without requiring it to be valid UTF-8.
+///
+/// pikchr treats the input as bytes so invalid UTF-8 sequences can only appear in the output as
+/// text labels, where they are replaced with `U+FFFD`.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::render_from_bytes;
+///
+/// assert!(render_from_bytes(b"box \"\xff\"").is_ok());
+/// assert!(render_from_bytes(b"box\0").is_err());
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`]. As there, a leading byte order mark is stripped and line endings are
+/// normalised to LF first.
+pub fn render_from_bytes(input: &[u8]) -> Result<PikchrOutput> {
+    render_bytes_with(format::normalize_bytes(input), Options::default())
+}
+
+/// Renders the given bytes. An owned input is reused as the buffer passed to pikchr.
+fn render_bytes_with<T>(input: T, options: Options) -> Result<PikchrOutput>
+where
+    T: AsRef<[u8]> + Into<Vec<u8>>,
+{
+    record_render(input.as_ref().len(), || {
+        render_bytes_unrecorded(input, options)
+    })
+}
+
+/// Emits the metrics of the render through the `metrics` facade:
+///
+/// - `pikt.renders.total`, a counter of renders.
+/// - `pikt.renders.errors`, a counter of failed renders labeled with the
+///   [`PiktErrorReason::error_code`] as `reason`.
+/// - `pikt.render.duration_us`, a histogram of the render durations in microseconds,
+///   post-processing included.
+/// - `pikt.render.input_bytes`, a histogram of the input sizes.
+#[cfg(feature = "metrics-recording")]
+fn record_render<T>(input_bytes: usize, render: impl FnOnce() -> Result<T>) -> Result<T> {
+    let start = std::time::Instant::now();
+    let result = render();
+    let duration = start.elapsed();
+
+    metrics::counter!("pikt.renders.total").increment(1);
+    metrics::histogram!("pikt.render.duration_us").record(duration.as_micros() as f64);
+    metrics::histogram!("pikt.render.input_bytes").record(input_bytes as f64);
+
+    if let Err(err) = &result {
+        metrics::counter!("pikt.renders.errors", "reason" => err.reason().error_code().to_string())
+            .increment(1);
+    }
+
+    result
+}
+
+#[cfg(not(feature = "metrics-recording"))]
+#[inline]
+fn record_render<T>(_input_bytes: usize, render: impl FnOnce() -> Result<T>) -> Result<T> {
+    render()
+}
+
+fn render_bytes_unrecorded<T>(input: T, options: Options) -> Result<PikchrOutput>
+where
+    T: AsRef<[u8]> + Into<Vec<u8>>,
+{
+    let native = render_native(input, &options)?;
+    let mut svg = native.buffer.to_string_lossy().into_owned();
+
+    if options.annotate_elements() {
+        let source = String::from_utf8_lossy(native.args.input.as_bytes());
+        postprocess::annotate_elements(&mut svg, &source);
+    }
+
+    postprocess::apply(&mut svg, (native.width, native.height), &options);
+    check_output_size(svg.len(), &options)?;
+
+    Ok(PikchrOutput {
+        svg,
+        width: native.width,
+        height: native.height,
+        #[cfg(feature = "metrics")]
+        render_duration: native.render_duration,
+    })
+}
+
+/// The result of a successful native pikchr call, before any post-processing.
+struct NativeRender {
+    args: PikchrArgs,
+    buffer: PikchrBuffer,
+    width: u32,
+    height: u32,
+    #[cfg(feature = "metrics")]
+    render_duration: std::time::Duration,
+}
+
+/// Checks the input against the limits of `options` and calls pikchr, turning the errors it
+/// reports into a [`PiktError`].
+fn render_native<T>(input: T, options: &Options) -> Result<NativeRender>
+where
+    T: AsRef<[u8]> + Into<Vec<u8>>,
+{
+    let len = input.as_ref().len();
+
+    match options.output_format() {
+        OutputFormat::Svg => {}
+        OutputFormat::Png => return Err(unsupported_output_format("Png")),
+        OutputFormat::Pdf => return Err(unsupported_output_format("Pdf")),
+    }
+
+    if len > options.max_input_bytes() {
+        return Err(PiktError::new(PiktErrorReason::InputTooLarge {
+            actual: len,
+            limit: options.max_input_bytes(),
+        }));
+    }
+
+    if let Some(limit) = options.max_complexity() {
+        let actual = classify_complexity(&String::from_utf8_lossy(input.as_ref()));
+
+        if actual > limit {
+            return Err(PiktError::new(PiktErrorReason::TooComplex {
+                actual,
+                limit,
+            }));
+        }
+    }
+
+    let args = PikchrArgs::from_options(input, options)?;
+
+    #[cfg(feature = "metrics")]
+    let start = std::time::Instant::now();
+    let (buffer, width, height) = args.call();
+    #[cfg(feature = "metrics")]
+    let render_duration = start.elapsed();
+
+    let buffer = match buffer {
+        Some(buffer) => buffer,
+        None => return Err(PiktError::new(PiktErrorReason::OutOfMemory)),
+    };
+
+    if width < 0 {
+        let err = PiktError::from_str(&buffer.to_string_lossy()).unwrap();
+        return Err(err);
+    }
+
+    Ok(NativeRender {
+        args,
+        buffer,
+        width: width as u32,
+        height: height as u32,
+        #[cfg(feature = "metrics")]
+        render_duration,
+    })
+}
+
+fn check_output_size(len: usize, options: &Options) -> Result<()> {
+    match options.max_output_bytes() {
+        Some(limit) if len > limit => Err(PiktError::new(PiktErrorReason::OutputTooLarge {
+            actual: len,
+            limit,
+        })),
+        _ => Ok(()),
+    }
+}
+
+fn unsupported_output_format(name: &str) -> PiktError {
+    PiktError::new(PiktErrorReason::UnsupportedOutputFormat(name.to_string()))
+}
+
+/// The arguments of the native [`pikchr`] call.
+#[derive(Debug)]
+struct PikchrArgs {
+    input: CString,
+    /// `None` when the class is empty so pikchr omits the attribute.
+    class: Option<CString>,
+    flags: u32,
+    width: std::os::raw::c_int,
+    height: std::os::raw::c_int,
+}
+
+impl PikchrArgs {
+    fn from_options(input: impl Into<Vec<u8>>, options: &Options) -> Result<Self> {
+        let class = if options.class().is_empty() {
+            None
+        } else {
+            // The builder guarantees the class has no nul bytes.
+            Some(CString::new(options.class()).expect("class must not contain nul bytes"))
+        };
+
+        Ok(Self {
+            input: CString::new(input)?,
+            class,
+            flags: options.flags().bits() | PIKCHR_PLAINTEXT_ERRORS,
+            width: options.width() as i32,
+            height: options.height() as i32,
+        })
+    }
+
+    /// Calls pikchr returning the buffer along with the width and height it computed. The
+    /// buffer is `None` if pikchr ran out of memory.
+    fn call(
+        &self,
+    ) -> (
+        Option<PikchrBuffer>,
+        std::os::raw::c_int,
+        std::os::raw::c_int,
+    ) {
+        let mut width = self.width;
+        let mut height = self.height;
+        let class = self
+            .class
+            .as_ref()
+            .map_or(std::ptr::null(), |class| class.as_ptr());
+
+        // SAFETY: The input and class are nul-terminated and outlive the call, and the buffer
+        // is owned by `PikchrBuffer` which frees it.
+        let buffer = unsafe {
+            PikchrBuffer::from_raw(pikchr(
+                self.input.as_ptr(),
+                class,
+                self.flags,
+                &mut width,
+                &mut height,
+            ))
+        };
+
+        (buffer, width, height)
+    }
+}
+
+/// Renders the pikchr markup read from `reader` as SVG with the given configuration.
+///
+/// The entire input is buffered before rendering.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_from_reader, Options};
+///
+/// let reader = std::io::Cursor::new(b"box".as_ref());
+/// let output = render_from_reader(reader, Options::default());
+///
+/// assert!(output.is_ok());
+/// ```
+///
+/// ## Errors
+///
+/// Fails with [`PiktErrorReason::Io`] if the input cannot be read or is not valid UTF-8, or with
+/// any of the errors of [`render_with`].
+pub fn render_from_reader<R: std::io::Read>(
+    mut reader: R,
+    options: Options,
+) -> Result<PikchrOutput> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+
+    render_with(&input, options)
+}
+
+/// Renders the given pikchr markup as SVG with the given configuration and writes it to
+/// `writer`, returning the width and height of the diagram.
+///
+/// When `options` needs no post-processing the buffer returned by pikchr is written as is,
+/// without the intermediate `String` of [`render_with`].
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_to, Options};
+///
+/// let mut svg = Vec::new();
+/// let (width, height) = render_to("box", Options::default(), &mut svg).unwrap();
+///
+/// assert!(svg.starts_with(b"<svg"));
+/// assert!(width > 0 && height > 0);
+/// ```
+///
+/// ## Errors
+///
+/// Fails with [`PiktErrorReason::Io`] if writing fails, or with any of the errors of
+/// [`render_with`]. Nothing is written if the rendering fails.
+pub fn render_to<W: std::io::Write>(
+    input: &str,
+    options: Options,
+    writer: &mut W,
+) -> Result<(u32, u32)> {
+    let input = normalize_line_endings(strip_bom(input));
+
+    if !postprocess::is_identity(&options) {
+        let output = render_bytes_with(input.as_bytes(), options)?;
+        writer.write_all(output.svg.as_bytes())?;
+
+        return Ok((output.width, output.height));
+    }
+
+    record_render(input.len(), || {
+        let native = render_native(input.as_bytes(), &options)?;
+        let svg = native.buffer.to_bytes();
+        check_output_size(svg.len(), &options)?;
+        writer.write_all(svg)?;
+
+        Ok((native.width, native.height))
+    })
+}
+
+/// Renders the given pikchr markup as SVG with the given configuration and writes it to `path`,
+/// replacing the file if it exists.
+///
+/// ## Example
+///
+/// ```no_run
+/// use pikt::{render_and_save, Options};
+///
+/// render_and_save("box", "diagram.svg", Options::default()).unwrap();
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`]. It also fails with [`PiktErrorReason::Io`] if the file can't be
+/// written.
+pub fn render_and_save<P: AsRef<std::path::Path>>(
+    input: &str,
+    path: P,
+    options: Options,
+) -> Result<PikchrOutput> {
+    let output = render_with(input, options)?;
+    std::fs::write(path, output.svg.as_bytes())?;
+
+    Ok(output)
+}
+
+/// The environment variable making [`render_and_compare`] and `testing::assert_svg_eq` update
+/// the golden files.
+pub(crate) const UPDATE_GOLDEN_VAR: &str = "PIKCHR_UPDATE_GOLDEN";
+
+/// Renders the given pikchr markup as SVG and compares it byte for byte with the golden file at
+/// `expected_path`.
+///
+/// When `PIKCHR_UPDATE_GOLDEN=1` is set, the file is written with the rendered SVG instead and
+/// the result is always `true`.
+///
+/// ## Example
+///
+/// ```no_run
+/// use pikt::{render_and_compare, Options};
+///
+/// assert!(render_and_compare("box", "tests/golden/box.svg", Options::default()).unwrap());
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`]. It also fails with [`PiktErrorReason::Io`] if the file can't be read or
+/// written.
+pub fn render_and_compare<P: AsRef<std::path::Path>>(
+    input: &str,
+    expected_path: P,
+    options: Options,
+) -> Result<bool> {
+    let update = std::env::var(UPDATE_GOLDEN_VAR).as_deref() == Ok("1");
+
+    compare_golden(input, expected_path.as_ref(), options, update)
+}
+
+fn compare_golden(
+    input: &str,
+    path: &std::path::Path,
+    options: Options,
+    update: bool,
+) -> Result<bool> {
+    let output = render_with(input, options)?;
+
+    if update {
+        std::fs::write(path, output.svg.as_bytes())?;
+        return Ok(true);
+    }
+
+    Ok(std::fs::read(path)? == output.svg.as_bytes())
+}
+
+/// Renders the given pikchr markup as SVG failing if it takes longer than `timeout`.
+///
+/// The render happens in a background thread. A native render cannot be cancelled so, on
+/// timeout, the thread keeps running until the render finishes and its result is discarded.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_with_timeout, Options};
+/// use std::time::Duration;
+///
+/// let output = render_with_timeout("box", Options::default(), Duration::from_secs(30));
+///
+/// assert!(output.is_ok());
+/// ```
+///
+/// ## Errors
+///
+/// Fails with [`PiktErrorReason::Timeout`] if the timeout elapses, with [`PiktErrorReason::Io`]
+/// if the thread cannot be spawned, or with any of the errors of [`render_with`].
+pub fn render_with_timeout(
+    input: &str,
+    options: Options,
+    timeout: std::time::Duration,
+) -> Result<PikchrOutput> {
+    let (receiver, _) = spawn_render(input, options)?;
+
+    receive_with_timeout(&receiver, timeout)
+}
+
+type RenderReceiver = std::sync::mpsc::Receiver<Result<PikchrOutput>>;
+
+/// Waits up to `timeout` for the result sent by a render thread.
+fn receive_with_timeout(
+    receiver: &RenderReceiver,
+    timeout: std::time::Duration,
+) -> Result<PikchrOutput> {
+    use std::sync::mpsc::RecvTimeoutError;
+
+    let reason = match receiver.recv_timeout(timeout) {
+        Ok(result) => return result,
+        Err(RecvTimeoutError::Timeout) => PiktErrorReason::Timeout,
+        Err(RecvTimeoutError::Disconnected) => {
+            PiktErrorReason::Other("render thread panicked".to_string())
+        }
+    };
+
+    Err(PiktError::new(reason))
+}
+
+/// Renders in a background thread sending the result through the returned channel.
+fn spawn_render(
+    input: &str,
+    options: Options,
+) -> std::io::Result<(RenderReceiver, std::thread::JoinHandle<()>)> {
+    let input = input.to_string();
+
+    spawn_render_fn(move || render_with(&input, options))
+}
+
+/// Runs `render` in a background thread sending the result through the returned channel.
+fn spawn_render_fn(
+    render: impl FnOnce() -> Result<PikchrOutput> + Send + 'static,
+) -> std::io::Result<(RenderReceiver, std::thread::JoinHandle<()>)> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    let handle = std::thread::Builder::new()
+        .name("pikt-render".to_string())
+        .spawn(move || {
+            // The receiver is gone if the render timed out.
+            let _ = sender.send(render());
+        })?;
+
+    Ok((receiver, handle))
+}
+
+/// Renders the given pikchr markup as SVG scaled by the given factor.
+///
+/// The `width` and `height` attributes of the SVG element are set to the `viewBox` dimensions
+/// multiplied by `scale`. The `viewBox` itself is kept so the drawing scales with the element.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_with_scale, Options};
+///
+/// let output = render_with_scale("box", 2.0, Options::default()).unwrap();
+///
+/// assert!(output.svg.contains("width=\"224.64\""));
+/// ```
+///
+/// ## Errors
+///
+/// Fails with [`PiktErrorReason::InvalidScale`] if `scale` is not a finite number greater than
+/// zero, or with any of the errors of [`render_with`].
+pub fn render_with_scale(input: &str, scale: f64, options: Options) -> Result<PikchrOutput> {
+    if !(scale > 0.0 && scale.is_finite()) {
+        return Err(PiktError::new(PiktErrorReason::InvalidScale(scale)));
+    }
+
+    let mut output = render_with(input, options)?;
+
+    if let Some((_, _, width, height)) = postprocess::view_box(&output.svg) {
+        let width = width * scale;
+        let height = height * scale;
+
+        postprocess::set_root_attribute(
+            &mut output.svg,
+            "width",
+            &postprocess::format_number(width),
+        );
+        postprocess::set_root_attribute(
+            &mut output.svg,
+            "height",
+            &postprocess::format_number(height),
+        );
+        output.width = width.round() as u32;
+        output.height = height.round() as u32;
+    }
+
+    Ok(output)
+}
+
+/// Renders the given pikchr markup as SVG scaled down to fit in `max_width` by `max_height`,
+/// keeping its aspect ratio.
+///
+/// A diagram that already fits is returned as rendered by [`render_with`]: it is never scaled
+/// up. Otherwise it is scaled as with [`render_with_scale`].
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_scaled_to_fit, Options};
+///
+/// let output = render_scaled_to_fit("box wid 5in ht 1in", 200, 200, Options::default()).unwrap();
+///
+/// assert!(output.width <= 200 && output.height <= 200);
+/// ```
+///
+/// ## Errors
+///
+/// Fails with [`PiktErrorReason::InvalidScale`] if a maximum is zero and the diagram isn't
+/// empty, or with any of the errors of [`render_with`].
+pub fn render_scaled_to_fit(
+    input: &str,
+    max_width: u32,
+    max_height: u32,
+    options: Options,
+) -> Result<PikchrOutput> {
+    let output = render_with(input, options.clone())?;
+    let (width, height) = match postprocess::view_box(&output.svg) {
+        Some((_, _, width, height)) => (width, height),
+        None => return Ok(output),
+    };
+
+    if width <= max_width as f64 && height <= max_height as f64 {
+        return Ok(output);
+    }
+
+    let scale = (max_width as f64 / width).min(max_height as f64 / height);
+
+    render_with_scale(input, scale, options)
+}
+
+/// Renders each of the given inputs with the same configuration, collecting every result.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_many, Options};
+///
+/// let results = render_many(["box", "box 'pikchr'"], &Options::default());
+///
+/// assert!(results[0].is_ok());
+/// assert!(results[1].is_err());
+/// ```
+pub fn render_many<'a, I>(inputs: I, options: &Options) -> Vec<Result<PikchrOutput>>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    inputs
+        .into_iter()
+        .map(|input| render_with(input, options.clone()))
+        .collect()
+}
+
+/// Renders each of the given inputs like [`render_many`], calling `progress` with
+/// `(completed, total)` after each render, e.g. to drive a progress bar.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_many_with_progress, Options};
+///
+/// let inputs = ["box", "circle"];
+/// let results = render_many_with_progress(inputs.iter().copied(), &Options::default(), |done, total| {
+///     eprintln!("{}/{}", done, total);
+/// });
+///
+/// assert_eq!(results.len(), 2);
+/// ```
+pub fn render_many_with_progress<'a, I, F>(
+    inputs: I,
+    options: &Options,
+    progress: F,
+) -> Vec<Result<PikchrOutput>>
+where
+    I: ExactSizeIterator<Item = &'a str>,
+    F: Fn(usize, usize),
+{
+    let total = inputs.len();
+
+    inputs
+        .enumerate()
+        .map(|(index, input)| {
+            let result = render_with(input, options.clone());
+            progress(index + 1, total);
+            result
+        })
+        .collect()
+}
+
+/// Renders each of the given inputs like [`render_many`] on the rayon thread pool.
+///
+/// The inputs must be an indexed parallel iterator, e.g. from a slice or a `Vec`, so the results
+/// are in the same order as the inputs whatever order the renders finish in.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_par_ordered, Options};
+///
+/// let inputs = vec!["box", "box 'pikchr'", "circle"];
+/// let results = render_par_ordered(inputs, &Options::default());
+///
+/// assert!(results[0].is_ok());
+/// assert!(results[1].is_err());
+/// assert!(results[2].is_ok());
+/// ```
+#[cfg(feature = "rayon")]
+pub fn render_par_ordered<'a, I>(inputs: I, options: &Options) -> Vec<Result<PikchrOutput>>
+where
+    I: rayon::iter::IntoParallelIterator<Item = &'a str> + Send,
+    I::Iter: rayon::iter::IndexedParallelIterator,
+{
+    use rayon::iter::ParallelIterator;
+
+    inputs
+        .into_par_iter()
+        .map(|input| render_with(input, options.clone()))
+        .collect()
+}
+
+/// Renders each `(input, options)` pair to its SVG, in order. Same as mapping [`render_with`]
+/// over the pairs; see [`render_all`] for the parallel version.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_all_sequential, Options};
+///
+/// let results = render_all_sequential(&[("box", Options::default()), ("box 'x'", Options::default())]);
+///
+/// assert!(results[0].as_ref().unwrap().starts_with("<svg"));
+/// assert!(results[1].is_err());
+/// ```
+pub fn render_all_sequential(inputs: &[(&str, Options)]) -> Vec<Result<String>> {
+    inputs
+        .iter()
+        .map(|(input, options)| render_with(input, options.clone()).map(|output| output.svg))
+        .collect()
+}
+
+/// Renders each `(input, options)` pair to its SVG on the rayon thread pool. The results are
+/// the ones of [`render_all_sequential`], in the same order.
+///
+/// pikchr keeps no global state so renders can run on many threads at once.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_all, Options};
+///
+/// let inputs = vec![("box", Options::default()); 8];
+///
+/// assert!(render_all(&inputs).iter().all(Result::is_ok));
+/// ```
+#[cfg(feature = "rayon")]
+pub fn render_all(inputs: &[(&str, Options)]) -> Vec<Result<String>> {
+    use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+    inputs
+        .par_iter()
+        .map(|(input, options)| render_with(input, options.clone()).map(|output| output.svg))
+        .collect()
+}
+
+/// Renders each of the given inputs with the same configuration, stopping at the first error.
+///
+/// This is the fail-fast counterpart of [`render_many`]. Inputs after the failing one are not
+/// rendered.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_many_fail_fast, Options};
+///
+/// let (index, _) = render_many_fail_fast(["box", "box 'pikchr'", "circle"], &Options::default())
+///     .unwrap_err();
+///
+/// assert_eq!(index, 1);
+/// ```
+///
+/// ## Errors
+///
+/// Fails with the 0-based index of the first input that fails to render along with its error.
+pub fn render_many_fail_fast<'a, I>(
+    inputs: I,
+    options: &Options,
+) -> Result<Vec<PikchrOutput>, (usize, PiktError)>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    inputs
+        .into_iter()
+        .enumerate()
+        .map(|(index, input)| render_with(input, options.clone()).map_err(|err| (index, err)))
+        .collect()
+}
+
+/// Renders the given pikchr markup as SVG, falling back to `fallback_svg` on any error.
+///
+/// The fallback output has a width and height of `0`.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_or_default, Options};
+///
+/// let output = render_or_default("box 'x'", Options::default(), "<svg></svg>");
+///
+/// assert_eq!(output.svg, "<svg></svg>");
+/// ```
+pub fn render_or_default(input: &str, options: Options, fallback_svg: &str) -> PikchrOutput {
+    render_with(input, options)
+        .unwrap_or_else(|_| PikchrOutput::from_parts(fallback_svg.to_string(), 0, 0))
+}
+
+/// The SVG used by [`render_or_empty`] when the render fails.
+const EMPTY_SVG: &str = "<svg xmlns='http://www.w3.org/2000/svg'></svg>";
+
+/// Renders the given pikchr markup as SVG, falling back to an empty SVG element on any error.
+/// See [`render_or_default`].
+pub fn render_or_empty(input: &str, options: Options) -> PikchrOutput {
+    render_or_default(input, options, EMPTY_SVG)
+}
+
+/// Builds an SVG showing the error message in a red box, e.g. to display in place of a diagram
+/// that fails to render.
+///
+/// The SVG has the given `width` and `height`, and a matching `viewBox`. The message is escaped
+/// and centred on a single line so it may overflow a small box.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render, render_error_as_svg};
+///
+/// let err = render("box 'x'").unwrap_err();
+/// let svg = render_error_as_svg(&err, 300, 40);
+///
+/// assert!(svg.starts_with("<svg"));
+/// assert!(svg.contains("unknown token"));
+/// ```
+pub fn render_error_as_svg(error: &PiktError, width: u32, height: u32) -> String {
+    format!(
+        concat!(
+            "<svg xmlns='http://www.w3.org/2000/svg' class=\"pikchr-error\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            "<rect x=\"1\" y=\"1\" width=\"{rw}\" height=\"{rh}\" style=\"fill:rgb(255,240,240);stroke:rgb(255,0,0);stroke-width:2\" />\n",
+            "<text x=\"{cx}\" y=\"{cy}\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"rgb(255,0,0)\">{message}</text>\n",
+            "</svg>\n"
+        ),
+        w = width,
+        h = height,
+        rw = width.saturating_sub(2),
+        rh = height.saturating_sub(2),
+        cx = postprocess::format_number(width as f64 / 2.0),
+        cy = postprocess::format_number(height as f64 / 2.0),
+        message = html::escape_html(&error.to_string()),
+    )
+}
+
+/// Renders the given pikchr markup as SVG with a `<metadata>` element holding each `(key, value)`
+/// pair as a Dublin Core term, e.g. `("creator", "Ada")` as `<dc:creator>Ada</dc:creator>`.
+///
+/// The metadata is the first child of the root element and the values are escaped. Nothing is
+/// added when `meta` is empty.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_with_metadata, Options};
+///
+/// let output = render_with_metadata("box", Options::default(), &[("title", "A box")]).unwrap();
+///
+/// assert!(output.svg.contains("<dc:title>A box</dc:title>"));
+/// ```
+///
+/// ## Errors
+///
+/// Fails with [`PiktErrorReason::InvalidAttributeName`] if a key is not a valid element name.
+/// See also [`render_with`].
+pub fn render_with_metadata(
+    input: &str,
+    options: Options,
+    meta: &[(&str, &str)],
+) -> Result<PikchrOutput> {
+    if let Some((key, _)) = meta.iter().find(|(key, _)| !is_valid_metadata_key(key)) {
+        return Err(PiktError::new(PiktErrorReason::InvalidAttributeName(
+            key.to_string(),
+        )));
+    }
+
+    let mut output = render_with(input, options)?;
+
+    if !meta.is_empty() {
+        let terms: String = meta
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "<dc:{key}>{}</dc:{key}>",
+                    html::escape_html(value),
+                    key = key
+                )
+            })
+            .collect();
+
+        postprocess::insert_first_child(
+            &mut output.svg,
+            &format!(
+                "<metadata><rdf:RDF xmlns:rdf=\"{}\" xmlns:dc=\"{}\"><rdf:Description>{}</rdf:Description></rdf:RDF></metadata>",
+                RDF_NAMESPACE, DUBLIN_CORE_NAMESPACE, terms
+            ),
+        );
+    }
+
+    Ok(output)
+}
+
+const RDF_NAMESPACE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const DUBLIN_CORE_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+
+/// Renders the given pikchr markup as SVG with a grey text drawn diagonally over the centre of
+/// the diagram, e.g. to mark drafts.
+///
+/// The watermark is the last child of the root element so it is drawn over the diagram. The
+/// text is escaped and `opacity` is clamped to `[0.0, 1.0]`, with NaN taken as `1.0` like SVG
+/// does for an invalid opacity. Nothing is added when `watermark` is empty.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_with_watermark, Options};
+///
+/// let output = render_with_watermark("box", Options::default(), "DRAFT", 0.3).unwrap();
+///
+/// assert!(output.svg.contains("fill=\"rgba(128,128,128,0.3)\""));
+/// assert!(output.svg.contains(">DRAFT</text>\n</svg>"));
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`].
+pub fn render_with_watermark(
+    input: &str,
+    options: Options,
+    watermark: &str,
+    opacity: f32,
+) -> Result<PikchrOutput> {
+    let mut output = render_with(input, options)?;
+
+    if watermark.is_empty() {
+        return Ok(output);
+    }
+
+    let (x, y, width, height) = postprocess::view_box(&output.svg).unwrap_or_default();
+    let opacity = if opacity.is_nan() {
+        1.0
+    } else {
+        opacity.clamp(0.0, 1.0)
+    };
+
+    postprocess::insert_last_child(
+        &mut output.svg,
+        &format!(
+            "<text x=\"50%\" y=\"50%\" text-anchor=\"middle\" dominant-baseline=\"middle\" fill=\"rgba(128,128,128,{})\" font-size=\"24\" transform=\"rotate(-30, {}, {})\">{}</text>",
+            postprocess::format_number(opacity.into()),
+            postprocess::format_number(x + width / 2.0),
+            postprocess::format_number(y + height / 2.0),
+            html::escape_html(watermark)
+        ),
+    );
+
+    Ok(output)
+}
+
+/// Whether the key can be used as the local name of the `dc:` element.
+fn is_valid_metadata_key(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphabetic())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Renders the given pikchr markup as SVG returning the output along with any errors.
+///
+/// pikchr either renders the whole diagram or fails so, for now, `Some(output)` always comes
+/// with no errors and `None` with exactly one. The signature leaves room for partial renders
+/// with warnings.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_lenient, Options};
+///
+/// let (output, errors) = render_lenient("box 'x'", Options::default());
+///
+/// assert!(output.is_none());
+/// assert_eq!(errors.len(), 1);
+/// ```
+pub fn render_lenient(input: &str, options: Options) -> (Option<PikchrOutput>, Vec<PiktError>) {
+    match render_with(input, options) {
+        Ok(output) => (Some(output), Vec::new()),
+        Err(err) => (None, vec![err]),
+    }
+}
+
+/// Renders the given pikchr template as SVG after replacing each `{{key}}` with its value.
+///
+/// Values are inserted verbatim so they can contain any pikchr markup.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_template, Options};
+/// use std::collections::HashMap;
+///
+/// let vars = HashMap::from([("label", "\"pikchr\"")]);
+/// let output = render_template("box {{label}}", &vars, Options::default()).unwrap();
+///
+/// assert!(output.svg.contains(">pikchr</text>"));
+/// ```
+///
+/// ## Errors
+///
+/// Fails with [`PiktErrorReason::UnknownTemplateVariable`] if the template uses a key missing
+/// from `vars`, or with any of the errors of [`render_with`].
+pub fn render_template(
+    input: &str,
+    vars: &std::collections::HashMap<&str, &str>,
+    options: Options,
+) -> Result<PikchrOutput> {
+    // A single scan so inserted values are never substituted again.
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = match after.find("}}") {
+            Some(end) => end,
+            None => break,
+        };
+        let key = &after[..end];
+        let value = vars.get(key).ok_or_else(|| {
+            PiktError::new(PiktErrorReason::UnknownTemplateVariable(key.to_string()))
+        })?;
+
+        output.push_str(&rest[..start]);
+        output.push_str(value);
+        rest = &after[end + 2..];
+    }
+
+    output.push_str(rest);
+
+    render_with(&output, options)
+}
+
+/// Checks the given pikchr markup renders without errors, discarding the output.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::validate;
+///
+/// assert!(validate("box \"pikchr\"").is_ok());
+/// assert!(validate("box 'pikchr'").is_err());
+/// ```
+pub fn validate(input: &str) -> Result<()> {
+    render(input).map(|_| ())
+}
+
+/// The error of the crate, for `use pikt::Error`.
+pub type Error = PiktError;
+
+/// A `Result` failing with a [`PiktError`] by default.
+///
+/// ## Example
+///
+/// ```
+/// fn diagram() -> pikt::Result<pikt::PikchrOutput> {
+///     pikt::render("box")
+/// }
+///
+/// assert!(diagram().is_ok());
+/// ```
+pub type Result<T, E = PiktError> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PiktError {
+    line: usize,
+    column: usize,
+    reason: PiktErrorReason,
+    #[cfg_attr(feature = "serde", serde(default))]
+    raw_message: String,
+}
+
+/// The raw message is not compared as it only records how the error was reported.
+impl PartialEq for PiktError {
+    fn eq(&self, other: &Self) -> bool {
+        self.line == other.line && self.column == other.column && self.reason == other.reason
+    }
+}
+
+impl std::fmt::Display for PiktError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.reason
+        )
+    }
+}
+
+/// The source is the underlying error of the reason, if any. For example, the [`NulError`] of
+/// [`PiktErrorReason::IncompatibleInput`].
+impl std::error::Error for PiktError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        std::error::Error::source(&self.reason)
+    }
+}
+
+/// With the `serde` feature, reasons are tagged with a `type` field and the field of a newtype
+/// variant is written as `value`, e.g. `{"type": "InvalidColor", "value": "x"}`. Variants
+/// holding a standard library error can only be serialized: `IncompatibleInput` is written as
+/// its tag alone and `Io` and `InvalidUtf8` with the `message` of the error.
+#[derive(Error, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type")
+)]
+pub enum PiktErrorReason {
+    /// Raised when the given input has a nul byte.
+    #[error("incompatible input. Nul bytes are not allowed.")]
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
+    IncompatibleInput(
+        #[source]
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serde_fields::omit"))]
+        NulError,
+    ),
+    #[error("parser stack overflow")]
+    ParserStackOverflow,
+    #[error("out of memory")]
+    OutOfMemory,
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("syntax error")]
+    SyntaxError,
+    #[error("arc geometry error")]
+    ArcGeometryError,
+    #[error("unknown object")]
+    UnknownObject,
+    #[error("unknown object type")]
+    UnknownObjectType,
+    #[error("value already set")]
+    ValueAlreadySet,
+    #[error("value already fixed by prior constraints")]
+    ValueAlreadyFixed,
+    #[error("use with line-oriented objects only")]
+    OnlyWithLineOrientedObject,
+    #[error("no prior path points")]
+    NoPriorPathPoints,
+    #[error("headings should be between 0 and 360")]
+    HeadingOutOfBounds,
+    #[error("use `at` to position this object")]
     MissingAt,
     #[error("use `from` and `to` to position this object")]
     MissingFromTo,
@@ -276,220 +2942,4521 @@ pub enum PiktErrorReason {
     #[error("recursive macro definition")]
     MacroRecursive,
 
-    /// Raised when the given pikchr input cannot be parsed by Pikchr for an unknown reason.
-    #[error("other")]
-    Other(String),
-}
+    /// Raised when the flags contain bits unknown to pikchr.
+    #[error("unknown flags: {0:#x}")]
+    InvalidFlags(#[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] u32),
+
+    /// Raised when the scale is not a finite number greater than zero.
+    #[error("invalid scale: {0}")]
+    InvalidScale(#[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] f64),
+
+    /// Raised when a `viewBox` has a coordinate that is not finite or is empty.
+    #[error("invalid viewBox: {0}")]
+    InvalidViewBox(ViewBox),
+
+    /// Raised when an SVG attribute name would produce invalid markup.
+    #[error("invalid attribute name: {0:?}")]
+    InvalidAttributeName(
+        #[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] String,
+    ),
+
+    /// Raised when a CSS variable declaration would produce invalid markup.
+    #[error("invalid CSS variable: {0:?}")]
+    InvalidCssVariable(#[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] String),
+
+    /// Raised when the class prefix would produce invalid class names.
+    #[error("invalid class prefix: {0:?}")]
+    InvalidClassPrefix(#[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] String),
+
+    /// Raised when the namespace would produce invalid ids.
+    #[error("invalid namespace: {0:?}")]
+    InvalidNamespace(#[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] String),
+
+    /// Raised when a template uses a variable without a value.
+    #[error("unknown template variable: {0:?}")]
+    UnknownTemplateVariable(
+        #[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] String,
+    ),
+
+    /// Raised when the output format has no backend yet.
+    #[error("unsupported output format: {0}")]
+    UnsupportedOutputFormat(
+        #[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] String,
+    ),
+
+    /// Raised when the input is larger than the configured limit.
+    #[error("input too large: {actual} bytes, limit is {limit}")]
+    InputTooLarge { actual: usize, limit: usize },
+
+    /// Raised when the input is more complex than the configured limit.
+    #[error("input too complex: {actual}, limit is {limit}")]
+    TooComplex {
+        actual: ComplexityClass,
+        limit: ComplexityClass,
+    },
+
+    /// Raised when the SVG is larger than the configured limit.
+    #[error("output too large: {actual} bytes, limit is {limit}")]
+    OutputTooLarge { actual: usize, limit: usize },
+
+    /// Raised when the render didn't finish in time.
+    #[error("render timed out")]
+    Timeout,
+
+    /// Raised when a [`DiagramBuilder`] is rendered before any markup is written.
+    #[error("empty input")]
+    EmptyInput,
+
+    /// Raised when the input cannot be read or the output cannot be written.
+    #[error("io error: {0}")]
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
+    Io(
+        #[source]
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serde_fields::message"))]
+        std::io::Error,
+    ),
+
+    /// Raised when the given input is not valid UTF-8.
+    #[error("invalid utf-8: {0}")]
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
+    InvalidUtf8(
+        #[source]
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serde_fields::message"))]
+        std::str::Utf8Error,
+    ),
+
+    /// Raised when a colour is not a known name nor a hex or `rgb()` colour.
+    #[error("invalid color: {0:?}")]
+    InvalidColor(#[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] String),
+
+    /// Raised by [`validate_macros`] when a macro is defined more than once.
+    #[error("duplicate macro definition: {0:?}")]
+    DuplicateMacro(#[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] String),
+
+    /// Raised by [`validate_macros`] when a macro body uses more arguments than declared.
+    #[error("macro {name:?} declares {declared} argument(s) but uses {used}")]
+    MacroArgumentMismatch {
+        name: String,
+        declared: usize,
+        used: usize,
+    },
+
+    /// Raised when one of several diagrams rendered together fails, with the id of the
+    /// diagram.
+    #[error("diagram {id:?} failed: {reason}")]
+    DiagramFailed {
+        id: String,
+        #[source]
+        reason: Box<PiktErrorReason>,
+    },
+
+    /// Raised when the given pikchr input cannot be parsed by Pikchr for an unknown reason.
+    #[error("other")]
+    Other(#[cfg_attr(feature = "serde", serde(with = "serde_fields::value"))] String),
+}
+
+/// IO errors are compared by kind and message as [`std::io::Error`] doesn't implement
+/// `PartialEq`.
+impl PartialEq for PiktErrorReason {
+    fn eq(&self, other: &Self) -> bool {
+        use PiktErrorReason::*;
+
+        match (self, other) {
+            (IncompatibleInput(a), IncompatibleInput(b)) => a == b,
+            (InvalidFlags(a), InvalidFlags(b)) => a == b,
+            (InvalidScale(a), InvalidScale(b)) => a == b,
+            (InvalidViewBox(a), InvalidViewBox(b)) => a == b,
+            (InvalidAttributeName(a), InvalidAttributeName(b)) => a == b,
+            (InvalidCssVariable(a), InvalidCssVariable(b)) => a == b,
+            (InvalidClassPrefix(a), InvalidClassPrefix(b)) => a == b,
+            (InvalidNamespace(a), InvalidNamespace(b)) => a == b,
+            (UnknownTemplateVariable(a), UnknownTemplateVariable(b)) => a == b,
+            (UnsupportedOutputFormat(a), UnsupportedOutputFormat(b)) => a == b,
+            (
+                OutputTooLarge { actual, limit },
+                OutputTooLarge {
+                    actual: other_actual,
+                    limit: other_limit,
+                },
+            ) => actual == other_actual && limit == other_limit,
+            (
+                InputTooLarge { actual, limit },
+                InputTooLarge {
+                    actual: other_actual,
+                    limit: other_limit,
+                },
+            ) => actual == other_actual && limit == other_limit,
+            (
+                TooComplex { actual, limit },
+                TooComplex {
+                    actual: other_actual,
+                    limit: other_limit,
+                },
+            ) => actual == other_actual && limit == other_limit,
+            (Io(a), Io(b)) => a.kind() == b.kind() && a.to_string() == b.to_string(),
+            (InvalidUtf8(a), InvalidUtf8(b)) => a == b,
+            (InvalidColor(a), InvalidColor(b)) => a == b,
+            (DuplicateMacro(a), DuplicateMacro(b)) => a == b,
+            (
+                MacroArgumentMismatch {
+                    name,
+                    declared,
+                    used,
+                },
+                MacroArgumentMismatch {
+                    name: other_name,
+                    declared: other_declared,
+                    used: other_used,
+                },
+            ) => name == other_name && declared == other_declared && used == other_used,
+            (
+                DiagramFailed { id, reason },
+                DiagramFailed {
+                    id: other_id,
+                    reason: other_reason,
+                },
+            ) => id == other_id && reason == other_reason,
+            (Other(a), Other(b)) => a == b,
+            (IncompatibleInput(_), _)
+            | (InvalidFlags(_), _)
+            | (InvalidScale(_), _)
+            | (InvalidViewBox(_), _)
+            | (InvalidAttributeName(_), _)
+            | (InvalidCssVariable(_), _)
+            | (InvalidClassPrefix(_), _)
+            | (InvalidNamespace(_), _)
+            | (UnknownTemplateVariable(_), _)
+            | (UnsupportedOutputFormat(_), _)
+            | (OutputTooLarge { .. }, _)
+            | (InputTooLarge { .. }, _)
+            | (TooComplex { .. }, _)
+            | (Io(_), _)
+            | (InvalidUtf8(_), _)
+            | (InvalidColor(_), _)
+            | (DuplicateMacro(_), _)
+            | (MacroArgumentMismatch { .. }, _)
+            | (DiagramFailed { .. }, _)
+            | (Other(_), _) => false,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+/// How serious a [`PiktErrorReason`] is.
+///
+/// Every reason is an [`Severity::Error`] for now, the other levels are reserved for
+/// diagnostics that don't stop the render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// A coarse classification of [`PiktErrorReason`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PiktErrorKind {
+    /// The markup cannot be tokenised or parsed.
+    Syntax,
+    /// Objects are placed, sized or connected in an impossible way.
+    Geometry,
+    /// The markup references an object, variable or color that doesn't exist.
+    Definition,
+    /// A macro is defined or expanded incorrectly.
+    Macro,
+    /// The input or the options are not accepted by pikt.
+    Input,
+    /// The render ran out of resources or failed outside of pikchr.
+    System,
+    /// pikchr reported an error pikt doesn't know about.
+    Unknown,
+}
+
+impl PiktErrorReason {
+    /// A numeric code identifying the reason.
+    ///
+    /// Reasons raised by pikchr start at `1001`, `Other` is `9000` and reasons raised by pikt
+    /// itself start at `9901`. Codes are stable across patch and minor versions of pikt: new
+    /// reasons get new codes and codes are never reused.
+    pub fn error_code(&self) -> u32 {
+        use PiktErrorReason::*;
+
+        match self {
+            SyntaxError => 1001,
+            DivisionByZero => 1002,
+            ArcGeometryError => 1003,
+            UnknownObject => 1004,
+            UnknownObjectType => 1005,
+            ValueAlreadySet => 1006,
+            ValueAlreadyFixed => 1007,
+            OnlyWithLineOrientedObject => 1008,
+            NoPriorPathPoints => 1009,
+            HeadingOutOfBounds => 1010,
+            MissingAt => 1011,
+            MissingFromTo => 1012,
+            ClosedPolygon => 1013,
+            StartLineAlreadyFixed => 1014,
+            TooFewVertexes => 1015,
+            PositionAlreadyFixedByAt => 1016,
+            AttributeTooManyTerms => 1017,
+            AttributeMissingText => 1018,
+            UnknownColorName => 1019,
+            UnknownVariable => 1020,
+            OrdinalOutOfBounds => 1021,
+            MissingPriorObjectType => 1022,
+            NotALine => 1023,
+            VertexUnknown => 1024,
+            NegativeSqrt => 1025,
+            MacroTooManyArguments => 1026,
+            MacroUnterminatedArgumentList => 1027,
+            TokenTooLong => 1028,
+            TokenUnknown => 1029,
+            MacroTooDeep => 1030,
+            MacroRecursive => 1031,
+            ParserStackOverflow => 1032,
+            OutOfMemory => 1033,
+            Other(_) => 9000,
+            IncompatibleInput(_) => 9901,
+            InvalidFlags(_) => 9902,
+            InvalidScale(_) => 9903,
+            InvalidAttributeName(_) => 9904,
+            InvalidCssVariable(_) => 9905,
+            Io(_) => 9906,
+            OutputTooLarge { .. } => 9907,
+            Timeout => 9908,
+            InvalidClassPrefix(_) => 9909,
+            UnknownTemplateVariable(_) => 9910,
+            UnsupportedOutputFormat(_) => 9911,
+            InputTooLarge { .. } => 9912,
+            InvalidUtf8(_) => 9913,
+            DiagramFailed { .. } => 9914,
+            DuplicateMacro(_) => 9915,
+            MacroArgumentMismatch { .. } => 9916,
+            InvalidColor(_) => 9917,
+            TooComplex { .. } => 9918,
+            InvalidViewBox(_) => 9919,
+            EmptyInput => 9920,
+            InvalidNamespace(_) => 9921,
+        }
+    }
+
+    /// The reason for the given [`PiktErrorReason::error_code`].
+    ///
+    /// Returns `None` for unknown codes and for the reasons carrying data, such as `Other`,
+    /// as the data cannot be recovered from the code.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::PiktErrorReason;
+    ///
+    /// assert_eq!(
+    ///     PiktErrorReason::from_error_code(1001),
+    ///     Some(PiktErrorReason::SyntaxError)
+    /// );
+    /// assert_eq!(PiktErrorReason::from_error_code(9000), None);
+    /// ```
+    pub fn from_error_code(code: u32) -> Option<PiktErrorReason> {
+        use PiktErrorReason::*;
+
+        let reason = match code {
+            1001 => SyntaxError,
+            1002 => DivisionByZero,
+            1003 => ArcGeometryError,
+            1004 => UnknownObject,
+            1005 => UnknownObjectType,
+            1006 => ValueAlreadySet,
+            1007 => ValueAlreadyFixed,
+            1008 => OnlyWithLineOrientedObject,
+            1009 => NoPriorPathPoints,
+            1010 => HeadingOutOfBounds,
+            1011 => MissingAt,
+            1012 => MissingFromTo,
+            1013 => ClosedPolygon,
+            1014 => StartLineAlreadyFixed,
+            1015 => TooFewVertexes,
+            1016 => PositionAlreadyFixedByAt,
+            1017 => AttributeTooManyTerms,
+            1018 => AttributeMissingText,
+            1019 => UnknownColorName,
+            1020 => UnknownVariable,
+            1021 => OrdinalOutOfBounds,
+            1022 => MissingPriorObjectType,
+            1023 => NotALine,
+            1024 => VertexUnknown,
+            1025 => NegativeSqrt,
+            1026 => MacroTooManyArguments,
+            1027 => MacroUnterminatedArgumentList,
+            1028 => TokenTooLong,
+            1029 => TokenUnknown,
+            1030 => MacroTooDeep,
+            1031 => MacroRecursive,
+            1032 => ParserStackOverflow,
+            1033 => OutOfMemory,
+            9908 => Timeout,
+            9920 => EmptyInput,
+            _ => return None,
+        };
+
+        Some(reason)
+    }
+
+    /// Shorthand for [`PiktErrorReason::from_error_code`].
+    #[inline]
+    pub fn from_code(code: u32) -> Option<PiktErrorReason> {
+        Self::from_error_code(code)
+    }
+
+    /// How serious the reason is. See [`Severity`].
+    pub fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    /// The coarse classification of the reason.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{render, PiktErrorKind};
+    ///
+    /// let err = render("circ \"1\"").unwrap_err();
+    ///
+    /// assert_eq!(err.reason().kind(), PiktErrorKind::Syntax);
+    /// ```
+    pub fn kind(&self) -> PiktErrorKind {
+        use PiktErrorReason::*;
+
+        match self {
+            SyntaxError | TokenTooLong | TokenUnknown => PiktErrorKind::Syntax,
+            DivisionByZero
+            | ArcGeometryError
+            | ValueAlreadySet
+            | ValueAlreadyFixed
+            | OnlyWithLineOrientedObject
+            | NoPriorPathPoints
+            | HeadingOutOfBounds
+            | MissingAt
+            | MissingFromTo
+            | ClosedPolygon
+            | StartLineAlreadyFixed
+            | TooFewVertexes
+            | PositionAlreadyFixedByAt
+            | AttributeTooManyTerms
+            | AttributeMissingText
+            | NotALine
+            | VertexUnknown
+            | NegativeSqrt => PiktErrorKind::Geometry,
+            UnknownObject
+            | UnknownObjectType
+            | UnknownColorName
+            | UnknownVariable
+            | OrdinalOutOfBounds
+            | MissingPriorObjectType => PiktErrorKind::Definition,
+            MacroTooManyArguments
+            | MacroUnterminatedArgumentList
+            | MacroTooDeep
+            | MacroRecursive
+            | DuplicateMacro(_)
+            | MacroArgumentMismatch { .. } => PiktErrorKind::Macro,
+            IncompatibleInput(_)
+            | InvalidFlags(_)
+            | InvalidScale(_)
+            | InvalidViewBox(_)
+            | InvalidAttributeName(_)
+            | InvalidCssVariable(_)
+            | InvalidClassPrefix(_)
+            | InvalidNamespace(_)
+            | UnknownTemplateVariable(_)
+            | UnsupportedOutputFormat(_)
+            | InputTooLarge { .. }
+            | TooComplex { .. }
+            | InvalidUtf8(_)
+            | EmptyInput
+            | InvalidColor(_) => PiktErrorKind::Input,
+            ParserStackOverflow | OutOfMemory | OutputTooLarge { .. } | Timeout | Io(_) => {
+                PiktErrorKind::System
+            }
+            DiagramFailed { reason, .. } => reason.kind(),
+            Other(_) => PiktErrorKind::Unknown,
+        }
+    }
+
+    /// The [`std::io::ErrorKind`] used when converting to [`std::io::Error`].
+    fn io_error_kind(&self) -> std::io::ErrorKind {
+        use std::io::ErrorKind;
+        use PiktErrorReason::*;
+
+        match self {
+            IncompatibleInput(_)
+            | InvalidFlags(_)
+            | InvalidScale(_)
+            | InvalidViewBox(_)
+            | InvalidAttributeName(_)
+            | InvalidCssVariable(_)
+            | InvalidClassPrefix(_)
+            | InvalidNamespace(_)
+            | UnknownTemplateVariable(_)
+            | InvalidColor(_)
+            | InputTooLarge { .. }
+            | EmptyInput
+            | TooComplex { .. } => ErrorKind::InvalidInput,
+            DivisionByZero
+            | SyntaxError
+            | ArcGeometryError
+            | UnknownObject
+            | UnknownObjectType
+            | ValueAlreadySet
+            | ValueAlreadyFixed
+            | OnlyWithLineOrientedObject
+            | NoPriorPathPoints
+            | HeadingOutOfBounds
+            | MissingAt
+            | MissingFromTo
+            | ClosedPolygon
+            | StartLineAlreadyFixed
+            | TooFewVertexes
+            | PositionAlreadyFixedByAt
+            | AttributeTooManyTerms
+            | AttributeMissingText
+            | UnknownColorName
+            | UnknownVariable
+            | OrdinalOutOfBounds
+            | MissingPriorObjectType
+            | NotALine
+            | VertexUnknown
+            | NegativeSqrt
+            | MacroTooManyArguments
+            | MacroUnterminatedArgumentList
+            | TokenTooLong
+            | TokenUnknown
+            | MacroTooDeep
+            | MacroRecursive
+            | InvalidUtf8(_)
+            | DuplicateMacro(_)
+            | MacroArgumentMismatch { .. } => ErrorKind::InvalidData,
+            OutOfMemory => ErrorKind::OutOfMemory,
+            Io(err) => err.kind(),
+            Timeout => ErrorKind::TimedOut,
+            UnsupportedOutputFormat(_) => ErrorKind::Unsupported,
+            ParserStackOverflow | OutputTooLarge { .. } | Other(_) => ErrorKind::Other,
+            DiagramFailed { reason, .. } => reason.io_error_kind(),
+        }
+    }
+}
+
+impl PiktError {
+    /// An error without a location, e.g. for options rejected before rendering.
+    pub fn new(reason: PiktErrorReason) -> Self {
+        Self {
+            line: 0,
+            column: 0,
+            reason,
+            raw_message: String::new(),
+        }
+    }
+
+    /// The line where the error happened, starting at 1. It is `0` when the error has no
+    /// location.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column where the error happened, starting at 1. It is `0` when the error has no
+    /// location.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn reason(&self) -> &PiktErrorReason {
+        &self.reason
+    }
+
+    /// The coarse classification of the reason. See [`PiktErrorReason::kind`].
+    pub fn kind(&self) -> PiktErrorKind {
+        self.reason.kind()
+    }
+
+    /// The error text as reported by pikchr, before parsing. It is empty for errors raised by
+    /// pikt itself.
+    pub fn raw_message(&self) -> &str {
+        &self.raw_message
+    }
+
+    /// Parses every error of the pikchr error text, in order, while [`FromStr`] only keeps the
+    /// last one. Each error block is the context lines, the caret line and the `ERROR:` line.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::{PiktError, PiktErrorReason};
+    ///
+    /// let text = "/*    1 */  circ\n             ^^^^\nERROR: syntax error\n\
+    ///             /*    2 */  box color x\n                       ^\nERROR: no such variable\n";
+    /// let errors = PiktError::all_from_str(text);
+    ///
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(errors[0].reason(), &PiktErrorReason::SyntaxError);
+    /// assert_eq!(errors[1].line(), 2);
+    /// ```
+    pub fn all_from_str(s: &str) -> Vec<PiktError> {
+        parse_errors(s)
+    }
+
+    /// The location of the error in the source.
+    pub fn position(&self) -> span::Position {
+        span::Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    /// The source range of the error. It is currently empty, starting and ending at
+    /// [`PiktError::position`].
+    pub fn span(&self) -> span::Span {
+        span::Span {
+            start: self.position(),
+            end: self.position(),
+        }
+    }
+
+    /// Shifts the location of the error, e.g. to make it relative to the document a pikchr
+    /// block was extracted from.
+    ///
+    /// The column offset is only added on the first line of the block, where the block may not
+    /// start at the beginning of the line. Errors without a location, at line 0, are unchanged.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// let mut err = pikt::render("box\nbox 'x'").unwrap_err();
+    /// // The block starts after the third line of the document.
+    /// err.adjust_offset(3, 0);
+    ///
+    /// assert_eq!(err.line(), 5);
+    /// ```
+    pub fn adjust_offset(&mut self, line_offset: usize, column_offset: usize) {
+        if self.line == 0 {
+            return;
+        }
+
+        if self.line == 1 {
+            self.column += column_offset;
+        }
+
+        self.line += line_offset;
+    }
+
+    /// Consuming version of [`PiktError::adjust_offset`].
+    pub fn with_offset(mut self, line_offset: usize, column_offset: usize) -> Self {
+        self.adjust_offset(line_offset, column_offset);
+        self
+    }
+
+    /// Computes the byte offset of the error location in the given source.
+    ///
+    /// Returns `None` if the error has no location (e.g. a nul byte in the input) or if the
+    /// location falls outside the source.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::render;
+    ///
+    /// let source = "box 'pikchr'";
+    /// let err = render(source).unwrap_err();
+    ///
+    /// assert_eq!(err.byte_offset(source), Some(4));
+    /// ```
+    pub fn byte_offset(&self, source: &str) -> Option<usize> {
+        self.offsets(source).map(|(byte, _)| byte)
+    }
+
+    /// Computes the character offset of the error location in the given source.
+    ///
+    /// See [`PiktError::byte_offset`].
+    pub fn char_offset(&self, source: &str) -> Option<usize> {
+        self.offsets(source).map(|(_, char)| char)
+    }
+
+    /// Formats the error as a GitHub Actions workflow command.
+    ///
+    /// The file is escaped as a property, so commas and colons in it don't end it early.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::render;
+    ///
+    /// let err = render("box 'pikchr'").unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_github_annotation("diagram.pikchr"),
+    ///     "::error file=diagram.pikchr,line=1,col=5::unknown token"
+    /// );
+    /// ```
+    pub fn to_github_annotation(&self, file: &str) -> String {
+        let message = self
+            .reason
+            .to_string()
+            .replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A");
+
+        if file.is_empty() {
+            format!(
+                "::error line={},col={}::{}",
+                self.line, self.column, message
+            )
+        } else {
+            let file = file
+                .replace('%', "%25")
+                .replace('\r', "%0D")
+                .replace('\n', "%0A")
+                .replace(':', "%3A")
+                .replace(',', "%2C");
+
+            format!(
+                "::error file={},line={},col={}::{}",
+                file, self.line, self.column, message
+            )
+        }
+    }
+
+    /// Formats the error like GCC does: `<file>:<line>:<col>: error: <message>`.
+    pub fn to_gcc_diagnostic(&self, file: &str) -> String {
+        let location = format!("{}:{}", self.line, self.column);
+
+        if file.is_empty() {
+            format!("{}: error: {}", location, self.reason)
+        } else {
+            format!("{}:{}: error: {}", file, location, self.reason)
+        }
+    }
+
+    /// Formats the error like MSVC does: `<file>(<line>,<col>): error: <message>`.
+    pub fn to_msvc_diagnostic(&self, file: &str) -> String {
+        format!(
+            "{}({},{}): error: {}",
+            file, self.line, self.column, self.reason
+        )
+    }
+
+    /// Walks the source up to the error location returning both the byte and char offsets.
+    fn offsets(&self, source: &str) -> Option<(usize, usize)> {
+        if self.line == 0 {
+            return None;
+        }
+
+        let mut byte_offset = 0;
+        let mut char_offset = 0;
+
+        for (index, line) in source.split_inclusive('\n').enumerate() {
+            if index + 1 == self.line {
+                let content = line.trim_end_matches('\n').trim_end_matches('\r');
+                let column = self.column.saturating_sub(1);
+
+                if column == content.chars().count() {
+                    return Some((byte_offset + content.len(), char_offset + column));
+                }
+
+                return content
+                    .char_indices()
+                    .nth(column)
+                    .map(|(byte, _)| (byte_offset + byte, char_offset + column));
+            }
+
+            byte_offset += line.len();
+            char_offset += line.chars().count();
+        }
+
+        None
+    }
+}
+
+impl FromStr for PiktError {
+    type Err = PiktError;
+
+    /// Parses the last error of the pikchr error text. See [`PiktError::all_from_str`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(parse_errors(s).pop().expect("there is always an error"))
+    }
+}
+
+/// Parses each error in the pikchr error text, in order. There is always at least one error:
+/// text without an `ERROR:` line is reported as a single error without a position.
+fn parse_errors(s: &str) -> Vec<PiktError> {
+    use PiktErrorReason::*;
+
+    let error = |line, column, reason| PiktError {
+        line,
+        column,
+        reason,
+        raw_message: s.to_string(),
+    };
+
+    if s.contains("parser stack overflow") {
+        return vec![error(0, 0, ParserStackOverflow)];
+    }
+    if s.contains("Out of memory") {
+        return vec![error(0, 0, OutOfMemory)];
+    }
+
+    let line_padding = 12;
+    let mut errors = Vec::new();
+    let mut fallback = "unknown error";
+    let mut context = (0, 0);
+
+    for line in s.lines() {
+        let line = line.trim_end_matches('\r');
+
+        // markup lines are formatted like:
+        //
+        // /*    1 */  circle "1"
+        //
+        // the last one is the line with the error.
+        if let Some(rest) = line.strip_prefix("/*") {
+            if let Some(number) = rest
+                .split_once("*/")
+                .and_then(|(number, _)| number.trim().parse().ok())
+            {
+                context.0 = number;
+            }
+            continue;
+        }
+
+        // caret lines underline the token with the error. the rightmost caret is used.
+        if line.ends_with('^') && line.trim_start().chars().all(|c| c == '^') {
+            context.1 = (line.len() + 1).saturating_sub(line_padding);
+            continue;
+        }
+
+        // the context is followed by a line like:
+        //
+        // ERROR: <error message>
+        //
+        // macro expansions add more context after it which is ignored unless another
+        // error follows.
+        if let Some(msg) = line.strip_prefix("ERROR:") {
+            errors.push(error(context.0, context.1, reason_from_message(msg.trim())));
+            context = (0, 0);
+        } else if !line.trim().is_empty() && errors.is_empty() {
+            fallback = line.trim();
+        }
+    }
+
+    if errors.is_empty() {
+        errors.push(error(0, 0, reason_from_message(fallback)));
+    }
+
+    errors
+}
+
+/// Maps the message of an `ERROR:` line to its reason.
+fn reason_from_message(message: &str) -> PiktErrorReason {
+    use PiktErrorReason::*;
+
+    match message {
+        "division by zero" => DivisionByZero,
+        "syntax error" => SyntaxError,
+        "arc geometry error" => ArcGeometryError,
+        "unknown object type" => UnknownObjectType,
+        "no such object" => UnknownObject,
+        "value is already set" => ValueAlreadySet,
+        "value already fixed by prior constraints" => ValueAlreadyFixed,
+        "use with line-oriented objects only" => OnlyWithLineOrientedObject,
+        "no prior path points" => NoPriorPathPoints,
+        "too many path elements" => NoPriorPathPoints,
+        "headings should be between 0 and 360" => HeadingOutOfBounds,
+        "use \"at\" to position this object" => MissingAt,
+        "use \"from\" and \"to\" to position this object" => MissingFromTo,
+        "polygon is closed" => ClosedPolygon,
+        "need at least 3 vertexes in order to close the polygon" => TooFewVertexes,
+        "line start location already fixed" => StartLineAlreadyFixed,
+        "location fixed by prior \"at\"" => PositionAlreadyFixedByAt,
+        "too many text terms" => AttributeTooManyTerms,
+        "no text to fit to" => AttributeMissingText,
+        "not a known color name" => UnknownColorName,
+        "no such variable" => UnknownVariable,
+        "value too big - max '1000th'" => OrdinalOutOfBounds,
+        "no prior objects of the same type" => MissingPriorObjectType,
+        "object is not a line" => NotALine,
+        "no such vertex" => VertexUnknown,
+        "sqrt of negative value" => NegativeSqrt,
+        "too many macro arguments - max 9" => MacroTooManyArguments,
+        "unterminated macro argument list" => MacroUnterminatedArgumentList,
+        "token is too long - max length 50000 bytes" => TokenTooLong,
+        "unrecognized token" => TokenUnknown,
+        "macros nested too deep" => MacroTooDeep,
+        "recursive macro definition" => MacroRecursive,
+        msg => Other(msg.to_string()),
+    }
+}
+
+impl From<std::io::Error> for PiktError {
+    fn from(err: std::io::Error) -> Self {
+        Self::new(PiktErrorReason::Io(err))
+    }
+}
+
+impl From<NulError> for PiktError {
+    fn from(err: NulError) -> Self {
+        Self::new(PiktErrorReason::IncompatibleInput(err))
+    }
+}
+
+impl From<NulError> for PiktErrorReason {
+    fn from(err: NulError) -> Self {
+        PiktErrorReason::IncompatibleInput(err)
+    }
+}
+
+impl From<std::io::Error> for PiktErrorReason {
+    fn from(err: std::io::Error) -> Self {
+        PiktErrorReason::Io(err)
+    }
+}
+
+impl From<std::str::Utf8Error> for PiktErrorReason {
+    fn from(err: std::str::Utf8Error) -> Self {
+        PiktErrorReason::InvalidUtf8(err)
+    }
+}
+
+/// Converts the error into an [`std::io::Error`] keeping the message.
+///
+/// Errors raised by pikchr when processing the markup map to [`std::io::ErrorKind::InvalidData`].
+///
+/// Note that `Box<dyn std::error::Error + Send + Sync>` is already covered by the standard
+/// library blanket implementation.
+impl From<PiktError> for std::io::Error {
+    fn from(err: PiktError) -> Self {
+        std::io::Error::new(err.reason.io_error_kind(), err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn simple_box() -> Result<(), PiktError> {
+        let source = "box \"pikchr\"";
+
+        let actual = render(source)?;
+
+        insta::assert_snapshot!(actual.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn input_with_nul() {
+        let source = "box \"pikchr\"\0";
+
+        let actual = render(source);
+
+        assert!(actual.is_err(), "expected a nul pointer error");
+    }
+
+    #[test]
+    fn malformed_input() {
+        let source = "box 'pikchr'";
+
+        let actual = render(source);
+
+        assert_eq!(
+            actual.expect_err("expected unknown token"),
+            PiktError {
+                line: 1,
+                column: 5,
+                reason: PiktErrorReason::TokenUnknown,
+                raw_message: String::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let source = r#"box "pikchr"
+        arrow from first box to (0/0, 0)
+        "#;
+
+        let actual = render(source);
+
+        assert_eq!(
+            actual.expect_err("expected div by zero err"),
+            PiktError {
+                line: 2,
+                column: 36,
+                reason: PiktErrorReason::DivisionByZero,
+                raw_message: String::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn syntax_error() {
+        let source = r#"circ "1""#;
+
+        let actual = render(source);
+
+        assert_eq!(
+            actual.expect_err("expected syntax error"),
+            PiktError {
+                line: 1,
+                column: 8,
+                reason: PiktErrorReason::SyntaxError,
+                raw_message: String::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_object() {
+        let source = r#"arrow from A to B"#;
+
+        let actual = render(source);
+
+        assert_eq!(
+            actual.expect_err("expected unknown object"),
+            PiktError {
+                line: 1,
+                column: 12,
+                reason: PiktErrorReason::UnknownObject,
+                raw_message: String::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn classes_on_empty_class() {
+        let mut builder = OptionsBuilder::default();
+        builder.class("");
+        builder.classes(&["foo"]);
+
+        assert_eq!(builder.build().class(), "foo");
+    }
+
+    #[test]
+    fn classes_with_no_values() {
+        let mut builder = OptionsBuilder::default();
+        builder.classes(&[]);
+        builder.classes(&[]);
+
+        assert_eq!(builder.build().class(), "pikchr");
+    }
+
+    #[test]
+    fn classes_on_default_class() {
+        let mut builder = OptionsBuilder::default();
+        builder.classes(&["foo", "bar"]);
+
+        assert_eq!(builder.build().class(), "pikchr foo bar");
+    }
+
+    #[test]
+    fn classes_skip_duplicates_and_blanks() {
+        let mut builder = OptionsBuilder::default();
+        builder
+            .classes(&["foo", "", "pikchr"])
+            .classes(&["foo  bar", " "]);
+
+        assert_eq!(builder.build().class(), "pikchr foo bar");
+    }
+
+    #[test]
+    fn class_after_classes_replaces_all() {
+        let mut builder = OptionsBuilder::default();
+        builder.classes(&["foo"]).class("bar").classes(&["baz"]);
+
+        assert_eq!(builder.build().class(), "bar baz");
+    }
+
+    #[test]
+    fn setters_chain() {
+        let mut builder = OptionsBuilder::default();
+        builder
+            .flags(Flags::DARK_MODE)
+            .width(300)
+            .height(150)
+            .classes(&["foo"]);
+        let actual = builder.build();
+
+        assert_eq!(actual.flags(), Flags::DARK_MODE);
+        assert_eq!((actual.width(), actual.height()), (300, 150));
+        assert_eq!(actual.class(), "pikchr foo");
+    }
+
+    #[test]
+    fn classes_accumulate() {
+        let mut builder = OptionsBuilder::default();
+        builder.classes(&["foo"]);
+        builder.classes(&["bar", "baz"]);
+
+        assert_eq!(builder.build().class(), "pikchr foo bar baz");
+    }
+
+    #[test]
+    fn box_dark_mode() -> Result<(), PiktError> {
+        let source = "box \"pikchr\"";
+        let mut flags = Flags::default();
+        flags.insert(Flags::DARK_MODE);
+
+        let mut builder = OptionsBuilder::default();
+        builder.flags(flags);
+        let options = builder.build();
+
+        let actual = render_with(source, options)?;
+
+        insta::assert_snapshot!(actual.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn circle_diagram() -> Result<()> {
+        let actual = render("circle \"pikchr\"")?;
+
+        insta::assert_snapshot!(actual.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn arrow_diagram() -> Result<()> {
+        let actual = render("arrow right 2cm \"label\" above")?;
+
+        insta::assert_snapshot!(actual.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn multi_element_diagram() -> Result<()> {
+        let source = r#"
+        box "one"
+        arrow
+        circle "two" fit
+        arrow
+        oval "three"
+        line from first box.s down 1cm then right until even with last oval
+        "#;
+
+        let actual = render(source)?;
+
+        insta::assert_snapshot!(actual.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_snapshots() {
+        insta::assert_debug_snapshot!("error_nul", render("box\0").unwrap_err());
+        insta::assert_debug_snapshot!("error_syntax", render("circ \"1\"").unwrap_err());
+        insta::assert_debug_snapshot!("error_unknown_token", render("box 'pikchr'").unwrap_err());
+        insta::assert_debug_snapshot!(
+            "error_division_by_zero",
+            render("box \"pikchr\"\narrow from first box to (0/0, 0)\n").unwrap_err()
+        );
+        insta::assert_debug_snapshot!(
+            "error_invalid_scale",
+            render_with_scale("box", 0.0, Options::default()).unwrap_err()
+        );
+        insta::assert_debug_snapshot!(
+            "error_output_too_large",
+            render_with("box", limited_options(1)).unwrap_err()
+        );
+    }
+
+    fn error_with(reason: PiktErrorReason) -> PiktError {
+        PiktError {
+            line: 1,
+            column: 5,
+            reason,
+            raw_message: String::new(),
+        }
+    }
+
+    #[test]
+    fn io_error_kind_for_markup_errors() {
+        use std::io::ErrorKind;
+
+        let reasons = vec![
+            PiktErrorReason::SyntaxError,
+            PiktErrorReason::TokenUnknown,
+            PiktErrorReason::DivisionByZero,
+            PiktErrorReason::UnknownObject,
+        ];
+
+        for reason in reasons {
+            let err: std::io::Error = error_with(reason).into();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn io_error_kind_for_system_errors() {
+        use std::io::ErrorKind;
+
+        let err: std::io::Error = error_with(PiktErrorReason::OutOfMemory).into();
+        assert_eq!(err.kind(), ErrorKind::OutOfMemory);
+
+        let err: std::io::Error = error_with(PiktErrorReason::ParserStackOverflow).into();
+        assert_eq!(err.kind(), ErrorKind::Other);
+
+        let err: std::io::Error = error_with(PiktErrorReason::Other("boom".to_string())).into();
+        assert_eq!(err.kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn io_error_kind_for_invalid_options() {
+        use std::io::ErrorKind;
+
+        let reasons = vec![
+            PiktErrorReason::InvalidAttributeName("a b".to_string()),
+            PiktErrorReason::InvalidCssVariable("a b".to_string()),
+            PiktErrorReason::InvalidClassPrefix("a b".to_string()),
+            PiktErrorReason::InvalidNamespace("a b".to_string()),
+            PiktErrorReason::InvalidColor("nope".to_string()),
+        ];
+
+        for reason in reasons {
+            let err: std::io::Error = error_with(reason).into();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn io_error_kind_for_failed_diagram() {
+        use std::io::ErrorKind;
+
+        let failed = |reason| PiktErrorReason::DiagramFailed {
+            id: "d1".to_string(),
+            reason: Box::new(reason),
+        };
+
+        let err: std::io::Error = error_with(failed(PiktErrorReason::SyntaxError)).into();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let err: std::io::Error = error_with(failed(PiktErrorReason::Timeout)).into();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+        let err: std::io::Error = error_with(failed(PiktErrorReason::EmptyInput)).into();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn io_error_kind_for_incompatible_input() {
+        let err: std::io::Error = render("box\0").unwrap_err().into();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn io_error_preserves_message() {
+        let err = render("circ \"1\"").unwrap_err();
+        let expected = err.to_string();
+        let io_err: std::io::Error = err.into();
+
+        assert_eq!(io_err.to_string(), expected);
+    }
+
+    #[test]
+    fn boxed_error() {
+        fn run() -> Result<PikchrOutput, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(render("circ \"1\"")?)
+        }
+
+        let err = run().unwrap_err();
+
+        assert_eq!(err.to_string(), "line 1, column 8: syntax error");
+    }
+
+    #[test]
+    fn checksum_is_deterministic() -> Result<()> {
+        let a = render("box \"pikchr\"")?;
+        let b = render("box \"pikchr\"")?;
+
+        assert_eq!(a.checksum(), b.checksum());
+
+        Ok(())
+    }
+
+    #[test]
+    fn checksum_differs_for_different_input() -> Result<()> {
+        let a = render("box \"pikchr\"")?;
+        let b = render("circle \"pikchr\"")?;
+
+        assert_ne!(a.checksum(), b.checksum());
+
+        Ok(())
+    }
+
+    #[test]
+    fn content_eq_same_input() -> Result<()> {
+        let a = render("box \"pikchr\"")?;
+        let b = render("box \"pikchr\"")?;
+
+        assert!(a.content_eq(&b));
+
+        Ok(())
+    }
+
+    #[test]
+    fn content_eq_different_class() -> Result<()> {
+        let a = render("box \"pikchr\"")?;
+        let mut builder = OptionsBuilder::default();
+        builder.class("diagram");
+        let b = render_with("box \"pikchr\"", builder.build())?;
+
+        assert!(!a.content_eq(&b));
+
+        Ok(())
+    }
+
+    #[test]
+    fn offsets_single_line() {
+        let source = "box 'pikchr'";
+        let err = render(source).unwrap_err();
+
+        assert_eq!(err.byte_offset(source), Some(4));
+        assert_eq!(err.char_offset(source), Some(4));
+    }
+
+    #[test]
+    fn offsets_multiple_lines() {
+        let source = r#"box "pikchr"
+        arrow from first box to (0/0, 0)
+        "#;
+        let err = render(source).unwrap_err();
+        let offset = err.byte_offset(source).expect("expected an offset");
+
+        assert_eq!(offset, 48);
+        assert_eq!(&source[offset..offset + 1], "0");
+        assert_eq!(err.char_offset(source), Some(48));
+    }
+
+    #[test]
+    fn offsets_end_of_line() {
+        let source = r#"circ "1""#;
+        let err = render(source).unwrap_err();
+
+        assert_eq!(err.byte_offset(source), Some(7));
+    }
+
+    #[test]
+    fn offsets_with_multibyte_chars() {
+        let source = "box \"é\"\nbox 'x'";
+        let err = PiktError {
+            line: 2,
+            column: 5,
+            reason: PiktErrorReason::TokenUnknown,
+            raw_message: String::new(),
+        };
+
+        assert_eq!(err.byte_offset(source), Some(13));
+        assert_eq!(err.char_offset(source), Some(12));
+    }
+
+    #[test]
+    fn offsets_without_location() {
+        let source = "box\0";
+        let err = render(source).unwrap_err();
+
+        assert_eq!(err.byte_offset(source), None);
+        assert_eq!(err.char_offset(source), None);
+    }
+
+    #[test]
+    fn offsets_out_of_bounds() {
+        let source = "box";
+        let beyond_line = PiktError {
+            line: 2,
+            column: 1,
+            reason: PiktErrorReason::SyntaxError,
+            raw_message: String::new(),
+        };
+        let beyond_column = PiktError {
+            line: 1,
+            column: 10,
+            reason: PiktErrorReason::SyntaxError,
+            raw_message: String::new(),
+        };
+
+        assert_eq!(beyond_line.byte_offset(source), None);
+        assert_eq!(beyond_column.byte_offset(source), None);
+    }
+
+    #[test]
+    fn github_annotation() {
+        let err = render("box 'pikchr'").unwrap_err();
+
+        assert_eq!(
+            err.to_github_annotation("diagram.pikchr"),
+            "::error file=diagram.pikchr,line=1,col=5::unknown token"
+        );
+        assert_eq!(
+            err.to_github_annotation(""),
+            "::error line=1,col=5::unknown token"
+        );
+        assert_eq!(
+            err.to_github_annotation("C:\\a,b%.pikchr"),
+            "::error file=C%3A\\a%2Cb%25.pikchr,line=1,col=5::unknown token"
+        );
+    }
+
+    #[test]
+    fn gcc_diagnostic() {
+        let err = render("circ \"1\"").unwrap_err();
+        let actual = err.to_gcc_diagnostic("diagram.pikchr");
+        let parts: Vec<&str> = actual.splitn(5, ':').collect();
+
+        assert_eq!(actual, "diagram.pikchr:1:8: error: syntax error");
+        assert_eq!(
+            parts,
+            vec!["diagram.pikchr", "1", "8", " error", " syntax error"]
+        );
+        assert_eq!(err.to_gcc_diagnostic(""), "1:8: error: syntax error");
+    }
+
+    #[test]
+    fn msvc_diagnostic() {
+        let err = render("circ \"1\"").unwrap_err();
+        let actual = err.to_msvc_diagnostic("diagram.pikchr");
+        let (location, message) = actual.split_once("): ").unwrap();
+        let (file, position) = location.split_once('(').unwrap();
+
+        assert_eq!(actual, "diagram.pikchr(1,8): error: syntax error");
+        assert_eq!(file, "diagram.pikchr");
+        assert_eq!(position, "1,8");
+        assert_eq!(message, "error: syntax error");
+        assert_eq!(err.to_msvc_diagnostic(""), "(1,8): error: syntax error");
+    }
+
+    #[test]
+    fn try_class_with_nul() {
+        let mut builder = OptionsBuilder::default();
+
+        assert!(builder.try_class("foo\0").is_err());
+        assert_eq!(builder.build().class(), "pikchr");
+    }
+
+    #[test]
+    fn try_classes_with_nul() {
+        let mut builder = OptionsBuilder::default();
+
+        assert!(builder.try_classes(&["foo", "bar\0"]).is_err());
+        assert_eq!(builder.build().class(), "pikchr");
+    }
+
+    #[test]
+    #[should_panic(expected = "class must not contain nul bytes")]
+    fn class_with_nul() {
+        OptionsBuilder::default().class("foo\0");
+    }
+
+    #[test]
+    fn try_class_chains() -> Result<(), NulError> {
+        let mut builder = OptionsBuilder::default();
+        builder.try_class("foo")?.try_classes(&["bar"])?;
+
+        assert_eq!(builder.build().class(), "foo bar");
+
+        Ok(())
+    }
+
+    #[test]
+    fn try_build_with_invalid_class() {
+        let builder = OptionsBuilder {
+            class: "foo\0".to_string(),
+            ..OptionsBuilder::default()
+        };
+
+        let actual = builder.try_build().expect_err("expected a nul error");
+
+        assert!(matches!(
+            actual.reason,
+            PiktErrorReason::IncompatibleInput(_)
+        ));
+    }
+
+    #[test]
+    fn try_build_with_unknown_flags() {
+        let mut builder = OptionsBuilder::default();
+        builder.flags(Flags::from_bits_retain(0x10 | PIKCHR_DARK_MODE));
+
+        let actual = builder.try_build().expect_err("expected a flags error");
+
+        assert_eq!(actual.reason, PiktErrorReason::InvalidFlags(0x10));
+    }
+
+    #[test]
+    fn try_build_with_valid_options() {
+        for flags in [Flags::empty(), Flags::DARK_MODE] {
+            for class in ["", "pikchr", "foo bar"] {
+                let mut builder = OptionsBuilder::default();
+                builder.flags(flags);
+                builder.width(300);
+                builder.height(150);
+                builder.class(class);
+
+                let options = builder.try_build().expect("expected valid options");
+
+                assert_eq!(options.flags(), flags);
+                assert_eq!(options.class(), class);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid options: line 0, column 0: incompatible input")]
+    fn build_with_invalid_class() {
+        let builder = OptionsBuilder {
+            class: "foo\0".to_string(),
+            ..OptionsBuilder::default()
+        };
+
+        builder.build();
+    }
+
+    #[test]
+    fn options_default() {
+        let actual = Options::default();
+        let expected = OptionsBuilder::default().build();
+
+        assert_eq!(actual, expected);
+        assert_eq!(actual.flags(), expected.flags());
+        assert_eq!(actual.width(), expected.width());
+        assert_eq!(actual.height(), expected.height());
+        assert_eq!(actual.class(), expected.class());
+    }
+
+    #[test]
+    fn options_default_in_derived_default() {
+        #[derive(Default)]
+        struct Config {
+            options: Options,
+        }
+
+        let config = Config::default();
+
+        assert_eq!(config.options.class(), "pikchr");
+        assert_eq!(config.options.flags(), Flags::empty());
+    }
+
+    #[test]
+    fn scale_half() -> Result<()> {
+        let original = render("box")?;
+        let actual = render_with_scale("box", 0.5, Options::default())?;
+
+        assert!(actual.svg.contains("viewBox=\"0 0 112.32 76.32\""));
+        assert!(actual.svg.contains("width=\"56.16\""));
+        assert!(actual.svg.contains("height=\"38.16\""));
+        assert_eq!(actual.width, 56);
+        assert_eq!(actual.height, 38);
+        assert_ne!(actual.svg, original.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn scale_one() -> Result<()> {
+        let original = render("box")?;
+        let actual = render_with_scale("box", 1.0, Options::default())?;
+
+        assert!(actual.svg.contains("width=\"112.32\""));
+        assert!(actual.svg.contains("height=\"76.32\""));
+        assert_eq!(actual.width, original.width);
+        assert_eq!(actual.height, original.height);
+
+        Ok(())
+    }
+
+    #[test]
+    fn scale_double() -> Result<()> {
+        let actual = render_with_scale("box", 2.0, Options::default())?;
+
+        assert!(actual.svg.contains("viewBox=\"0 0 112.32 76.32\""));
+        assert!(actual.svg.contains("width=\"224.64\""));
+        assert!(actual.svg.contains("height=\"152.64\""));
+        assert_eq!(actual.width, 225);
+        assert_eq!(actual.height, 153);
+
+        Ok(())
+    }
+
+    #[test]
+    fn scale_zero() {
+        let actual = render_with_scale("box", 0.0, Options::default()).unwrap_err();
+
+        assert_eq!(actual.reason, PiktErrorReason::InvalidScale(0.0));
+        assert_eq!(actual.reason.error_code(), 9903);
+    }
+
+    #[test]
+    fn scale_negative() {
+        let actual = render_with_scale("box", -1.0, Options::default()).unwrap_err();
+
+        assert_eq!(actual.reason, PiktErrorReason::InvalidScale(-1.0));
+    }
+
+    #[test]
+    fn scale_not_finite() {
+        let actual = render_with_scale("box", f64::INFINITY, Options::default()).unwrap_err();
+
+        assert_eq!(actual.reason, PiktErrorReason::InvalidScale(f64::INFINITY));
+    }
+
+    #[test]
+    fn svg_attribute_single() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.svg_attribute("id", "diagram-1");
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual.svg.starts_with("<svg "));
+        assert!(actual.svg.contains(" id=\"diagram-1\">"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn svg_attribute_multiple() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder
+            .svg_attributes(&[("role", "img"), ("aria-label", "A \"box\"")])
+            .svg_attribute("data-diagram-id", String::from("42"));
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual.svg.contains(" role=\"img\""));
+        assert!(actual.svg.contains(" aria-label=\"A &quot;box&quot;\""));
+        assert!(actual.svg.contains(" data-diagram-id=\"42\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn svg_attribute_invalid_name() {
+        for name in &["", "a>b", "a\"b"] {
+            let mut builder = OptionsBuilder::default();
+            builder.svg_attribute(*name, "x");
+
+            let actual = builder.try_build().unwrap_err();
+
+            assert_eq!(
+                actual.reason,
+                PiktErrorReason::InvalidAttributeName(name.to_string())
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid options")]
+    fn svg_attribute_invalid_name_panics_on_build() {
+        let mut builder = OptionsBuilder::default();
+        builder.svg_attribute("a>b", "x");
+
+        builder.build();
+    }
+
+    #[test]
+    fn title_and_description() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.title("A box").description("A single box");
+        let actual = render_with("box", builder.build())?;
+        let (root, children) = actual.svg.split_once('>').unwrap();
+
+        assert!(root.contains(" role=\"img\""));
+        assert!(root.contains(" aria-labelledby=\"pikchr-title\""));
+        assert!(children.starts_with(
+            "\n<title id=\"pikchr-title\">A box</title>\n<desc>A single box</desc>\n"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn title_and_description_are_escaped() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.title("<b> & </b>").description("1 < 2 > 0");
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual
+            .svg
+            .contains("<title id=\"pikchr-title\">&lt;b&gt; &amp; &lt;/b&gt;</title>"));
+        assert!(actual.svg.contains("<desc>1 &lt; 2 &gt; 0</desc>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn description_without_title() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.description("A single box");
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual.svg.contains("<desc>A single box</desc>"));
+        assert!(actual.svg.contains(" role=\"img\""));
+        assert!(!actual.svg.contains("aria-labelledby"));
+        assert!(!actual.svg.contains("<title"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn css_variable_single() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.css_variable("--stroke", "#333");
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual
+            .svg
+            .contains("<style>:root { --stroke: #333; }</style>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn css_variable_multiple() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder
+            .css_variable("--stroke", "#333")
+            .css_variables(&[("--fill", "none"), ("--font", "serif")]);
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual
+            .svg
+            .contains("<style>:root { --stroke: #333; --fill: none; --font: serif; }</style>"));
+        assert_eq!(actual.svg.matches("<style>").count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn css_variable_duplicate_uses_last_value() {
+        let mut builder = OptionsBuilder::default();
+        builder
+            .css_variable("--stroke", "#333")
+            .css_variable("--fill", "none")
+            .css_variable("--stroke", "red");
+        let actual = builder.build();
+
+        assert_eq!(
+            actual.css_variables(),
+            &[
+                ("--stroke".to_string(), "red".to_string()),
+                ("--fill".to_string(), "none".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn css_variable_is_first_child() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.css_variable("--stroke", "#333").title("A box");
+        let actual = render_with("box", builder.build())?;
+        let (_, children) = actual.svg.split_once('>').unwrap();
+
+        assert!(children.starts_with("\n<style>:root { --stroke: #333; }</style>\n<title"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn css_variable_invalid_name() {
+        let mut builder = OptionsBuilder::default();
+        builder.css_variable("stroke", "#333");
+
+        let actual = builder.try_build().unwrap_err();
+
+        assert_eq!(
+            actual.reason,
+            PiktErrorReason::InvalidCssVariable("stroke".to_string())
+        );
+    }
+
+    #[test]
+    fn minify_keeps_elements() -> Result<()> {
+        let markup = r#"
+        box "one"
+        arrow
+        circle "two" fit
+        "#;
+        let mut builder = OptionsBuilder::default();
+        builder.minify();
+        let original = render(markup)?;
+        let actual = render_with(markup, builder.build())?;
+
+        assert!(actual.svg.len() < original.svg.len());
+        assert!(!actual.svg.contains('\n'));
+        assert!(!actual.svg.contains("> <"));
+        for element in &["<svg", "<path", "<polygon", "<circle", "<text"] {
+            assert_eq!(
+                actual.svg.matches(element).count(),
+                original.svg.matches(element).count()
+            );
+        }
+        assert!(actual.svg.contains(">one</text>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn minify_never_grows() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.minify();
+        let options = builder.build();
+
+        for markup in &["box", "circle \"1\"; move; circle \"2\"", "", "oval; line"] {
+            let original = render(markup)?;
+            let actual = render_with(markup, options.clone())?;
+
+            assert!(actual.svg.len() <= original.svg.len());
+        }
+
+        Ok(())
+    }
+
+    fn pretty_options(indent: usize) -> Options {
+        let mut builder = OptionsBuilder::default();
+        builder.pretty_print(indent);
+        builder.build()
+    }
+
+    #[test]
+    fn pretty_print_zero_indent() -> Result<()> {
+        let actual = render_with("box \"one\"; arrow", pretty_options(0))?;
+
+        assert!(actual.svg.lines().all(|line| line.starts_with('<')));
+        assert_eq!(actual.svg.lines().count(), 6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pretty_print_two_spaces() -> Result<()> {
+        let actual = render_with("box \"one\"", pretty_options(2))?;
+        let lines: Vec<&str> = actual.svg.lines().collect();
+
+        assert!(lines[0].starts_with("<svg "));
+        assert!(lines[1].starts_with("  <path "));
+        assert!(lines[2].starts_with("  <text ") && lines[2].ends_with(">one</text>"));
+        assert_eq!(lines[3], "</svg>");
+
+        Ok(())
+    }
+
+    #[test]
+    fn pretty_print_four_spaces() -> Result<()> {
+        let actual = render_with("box", pretty_options(4))?;
+
+        insta::assert_snapshot!(actual.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pretty_print_after_minify() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.minify().pretty_print(2);
+        let actual = render_with("box", builder.build())?;
+
+        assert_eq!(actual.svg.lines().count(), 3);
+        assert!(actual.svg.contains("\n  <path d="));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reader_valid() -> Result<()> {
+        let reader = std::io::Cursor::new(b"box \"pikchr\"".as_ref());
+
+        let actual = render_from_reader(reader, Options::default())?;
+
+        assert_eq!(actual, render("box \"pikchr\"")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reader_invalid_utf8() {
+        let reader = std::io::Cursor::new(b"box \"\xff\"".as_ref());
+
+        let actual = render_from_reader(reader, Options::default()).unwrap_err();
+
+        match actual.reason() {
+            PiktErrorReason::Io(err) => assert_eq!(err.kind(), std::io::ErrorKind::InvalidData),
+            reason => panic!("unexpected reason {:?}", reason),
+        }
+    }
+
+    #[test]
+    fn reader_file() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("pikt-reader-{}.pikchr", std::process::id()));
+        std::fs::write(&path, "circle \"1\"\nmove\ncircle \"2\"\n")?;
+
+        let file = std::fs::File::open(&path)?;
+        let actual = render_from_reader(std::io::BufReader::new(file), Options::default());
+        std::fs::remove_file(&path)?;
+
+        assert!(actual?.svg.contains(">2</text>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reader_error() {
+        struct FailingReader;
+
+        impl std::io::Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::BrokenPipe,
+                    "broken",
+                ))
+            }
+        }
+
+        let actual = render_from_reader(FailingReader, Options::default()).unwrap_err();
+        let expected = PiktErrorReason::Io(std::io::Error::new(
+            std::io::ErrorKind::BrokenPipe,
+            "broken",
+        ));
+
+        assert_eq!(actual.reason, expected);
+        assert_eq!(actual.reason.error_code(), 9906);
+        assert_eq!(actual.to_string(), "line 0, column 0: io error: broken");
+        assert_eq!(
+            std::io::Error::from(actual).kind(),
+            std::io::ErrorKind::BrokenPipe
+        );
+    }
+
+    fn limited_options(limit: usize) -> Options {
+        let mut builder = OptionsBuilder::default();
+        builder.max_output_bytes(limit);
+        builder.build()
+    }
+
+    #[test]
+    fn output_limit_exceeded() {
+        let expected = render("box").unwrap().svg.len();
+
+        let actual = render_with("box", limited_options(1)).unwrap_err();
+
+        assert_eq!(
+            actual.reason,
+            PiktErrorReason::OutputTooLarge {
+                actual: expected,
+                limit: 1
+            }
+        );
+        assert_eq!(
+            actual.to_string(),
+            format!(
+                "line 0, column 0: output too large: {} bytes, limit is 1",
+                expected
+            )
+        );
+    }
+
+    #[test]
+    fn output_limit_never_reached() {
+        assert!(render_with("box; arrow; circle", limited_options(usize::MAX)).is_ok());
+    }
+
+    #[test]
+    fn output_limit_plausible() {
+        let markup = r#"
+        circle "1"
+        move
+        circle "2"
+        arrow from first circle.end to last circle.start
+        "#;
+
+        assert!(render_with(markup, limited_options(1024 * 1024)).is_ok());
+    }
+
+    #[test]
+    fn timeout_elapsed() {
+        // The render waits for the timeout to elapse before answering.
+        let (release, gate) = std::sync::mpsc::channel::<()>();
+        let (receiver, handle) = spawn_render_fn(move || {
+            let _ = gate.recv();
+            render("box")
+        })
+        .unwrap();
+
+        let actual =
+            receive_with_timeout(&receiver, std::time::Duration::from_nanos(1)).unwrap_err();
+        release.send(()).unwrap();
+        handle.join().unwrap();
+
+        assert_eq!(actual.reason, PiktErrorReason::Timeout);
+        assert_eq!(
+            std::io::Error::from(actual).kind(),
+            std::io::ErrorKind::TimedOut
+        );
+    }
+
+    #[test]
+    fn timeout_not_elapsed() -> Result<()> {
+        let actual = render_with_timeout(
+            "box",
+            Options::default(),
+            std::time::Duration::from_secs(30),
+        )?;
+
+        assert_eq!(actual, render("box")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn timeout_thread_finishes() {
+        let (receiver, handle) = spawn_render("box", Options::default()).unwrap();
+        drop(receiver);
+
+        assert!(handle.join().is_ok());
+    }
+
+    #[test]
+    fn timeout_propagates_render_errors() {
+        let actual = render_with_timeout(
+            "box 'pikchr'",
+            Options::default(),
+            std::time::Duration::from_secs(30),
+        )
+        .unwrap_err();
+
+        assert_eq!(actual.reason, PiktErrorReason::TokenUnknown);
+    }
+
+    #[test]
+    fn bytes_invalid_utf8() -> Result<()> {
+        let actual = render_from_bytes(b"box \"\xff\"")?;
+
+        assert!(actual.svg.contains(">\u{fffd}</text>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bytes_same_as_str() -> Result<()> {
+        assert_eq!(render_from_bytes(b"box")?, render("box")?);
+        assert_eq!(
+            render_from_bytes(b"box 'pikchr'").unwrap_err(),
+            render("box 'pikchr'").unwrap_err()
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "sys")]
+    #[test]
+    fn sys_pikchr_is_callable() {
+        use std::os::raw::*;
+
+        let input = CString::new("box").unwrap();
+        let mut width: c_int = 0;
+        let mut height: c_int = 0;
+
+        let buffer = unsafe {
+            sys::PikchrBuffer::from_raw(sys::pikchr(
+                input.as_ptr(),
+                std::ptr::null(),
+                sys::PIKCHR_PLAINTEXT_ERRORS,
+                &mut width,
+                &mut height,
+            ))
+        }
+        .unwrap();
+        let output = buffer.to_string_lossy();
+
+        assert!(output.starts_with("<svg"));
+        assert!(width > 0);
+    }
+
+    #[cfg(feature = "sys")]
+    #[test]
+    fn sys_dark_mode_matches_flags() {
+        assert_eq!(sys::PIKCHR_DARK_MODE, Flags::DARK_MODE.bits());
+    }
+
+    #[cfg(feature = "sys")]
+    #[test]
+    fn sys_names_do_not_conflict() {
+        #[allow(unused_imports)]
+        mod both {
+            pub use crate::sys::*;
+            pub use crate::*;
+        }
+
+        // Names in both namespaces would be ambiguous and fail to compile.
+        let _ = both::render;
+        let _ = both::pikchr;
+        let _ = both::PIKCHR_DARK_MODE;
+        let _ = both::Flags::DARK_MODE;
+    }
+
+    #[test]
+    fn output_parts_round_trip() -> Result<()> {
+        let output = render("box")?;
+
+        let (svg, width, height) = output.clone().into_parts();
+
+        assert_eq!(PikchrOutput::from_parts(svg, width, height), output);
+
+        Ok(())
+    }
+
+    #[test]
+    fn output_from_empty_parts() {
+        let output = PikchrOutput::from_parts(String::new(), 0, 0);
+
+        assert_eq!(output.svg, "");
+        assert_eq!(output.width, 0);
+        assert_eq!(output.height, 0);
+    }
+
+    #[test]
+    fn output_from_parts_keeps_svg() {
+        let svg = "<svg></svg>".to_string();
+
+        assert_eq!(PikchrOutput::from_parts(svg.clone(), 1, 2).svg, svg);
+    }
+
+    #[test]
+    fn apply_calls_closure_once() {
+        let mut calls = 0;
+
+        let options = OptionsBuilder::default()
+            .apply(|b| {
+                calls += 1;
+                b.class("diagram");
+            })
+            .build();
+
+        assert_eq!(calls, 1);
+        assert_eq!(options.class(), "diagram");
+    }
+
+    #[test]
+    fn apply_identity() {
+        let actual = OptionsBuilder::default().apply(|_| {}).build();
+
+        assert_eq!(actual, Options::default());
+    }
+
+    #[test]
+    fn apply_if_true_calls_closure_once() {
+        let mut calls = 0;
+
+        let options = OptionsBuilder::default()
+            .apply_if(true, |b| {
+                calls += 1;
+                b.class("diagram");
+            })
+            .build();
+
+        assert_eq!(calls, 1);
+        assert_eq!(options.class(), "diagram");
+    }
+
+    #[test]
+    fn apply_if_false() {
+        let mut called = false;
+
+        let actual = OptionsBuilder::default()
+            .apply_if(false, |b| {
+                called = true;
+                b.dark_mode();
+            })
+            .build();
+
+        assert!(!called);
+        assert_eq!(actual, Options::default());
+    }
+
+    #[test]
+    fn apply_dark_mode() -> Result<()> {
+        let applied = OptionsBuilder::default().apply(|b| {
+            b.dark_mode();
+        });
+        let mut direct = OptionsBuilder::default();
+        direct.dark_mode();
+
+        let a = render_with("box", applied.build())?;
+        let b = render_with("box", direct.build())?;
+
+        assert_eq!(a, b);
+        assert!(a.svg.contains("stroke:rgb(255,255,255)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn source_for_incompatible_input() {
+        use std::error::Error;
+
+        let err = render("box\0").unwrap_err();
+
+        assert!(err.source().is_some());
+        assert!(err.source().unwrap().downcast_ref::<NulError>().is_some());
+    }
+
+    #[test]
+    fn source_for_io() {
+        use std::error::Error;
+
+        let err = PiktError::from(std::io::Error::new(
+            std::io::ErrorKind::BrokenPipe,
+            "broken",
+        ));
+
+        assert!(err
+            .source()
+            .unwrap()
+            .downcast_ref::<std::io::Error>()
+            .is_some());
+    }
+
+    #[test]
+    fn source_for_syntax_error() {
+        use std::error::Error;
+
+        let err = render("circ \"1\"").unwrap_err();
+
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn source_for_all_reasons() {
+        use std::error::Error;
+
+        let nul = CString::new("a\0").unwrap_err();
+        let with_source = vec![
+            PiktErrorReason::IncompatibleInput(nul),
+            PiktErrorReason::Io(std::io::Error::other("io")),
+        ];
+        let without_source = vec![
+            PiktErrorReason::SyntaxError,
+            PiktErrorReason::ParserStackOverflow,
+            PiktErrorReason::MacroRecursive,
+            PiktErrorReason::InvalidFlags(0x10),
+            PiktErrorReason::InvalidScale(0.0),
+            PiktErrorReason::InvalidAttributeName(String::new()),
+            PiktErrorReason::InvalidCssVariable(String::new()),
+            PiktErrorReason::OutputTooLarge {
+                actual: 2,
+                limit: 1,
+            },
+            PiktErrorReason::Timeout,
+            PiktErrorReason::EmptyInput,
+            PiktErrorReason::Other("other".to_string()),
+        ];
+
+        for reason in with_source {
+            assert!(error_with(reason).source().is_some());
+        }
+
+        for reason in without_source {
+            assert!(error_with(reason).source().is_none());
+        }
+    }
+
+    #[test]
+    fn class_prefix_default_class() {
+        let mut builder = OptionsBuilder::default();
+        builder.class_prefix("myapp");
+
+        assert_eq!(builder.build().class(), "myapp-pikchr");
+    }
+
+    #[test]
+    fn class_prefix_multiple_classes() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.classes(&["dark"]).class_prefix("myapp");
+        let options = builder.build();
+
+        assert_eq!(options.class(), "myapp-pikchr myapp-dark");
+        assert!(render_with("box", options)?
+            .svg
+            .contains("class=\"myapp-pikchr myapp-dark\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn class_prefix_empty() {
+        let mut builder = OptionsBuilder::default();
+        builder.class_prefix("");
+
+        assert_eq!(builder.build(), Options::default());
+    }
+
+    #[test]
+    fn namespace_invalid() {
+        for namespace in &[
+            "",
+            "my app",
+            "my\0app",
+            "-myapp",
+            "1app",
+            "a#b",
+            "a)b",
+            "a'b",
+            "a\"b",
+            "caf\u{e9}",
+        ] {
+            let mut builder = OptionsBuilder::default();
+            builder.namespace(namespace);
+
+            let actual = builder.try_build().unwrap_err();
+
+            assert_eq!(
+                actual.reason,
+                PiktErrorReason::InvalidNamespace(namespace.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn namespace_valid() {
+        for namespace in &["a", "_a", "my-app_2", "myapp-"] {
+            let mut builder = OptionsBuilder::default();
+            builder.namespace(namespace);
+
+            assert!(builder.try_build().is_ok(), "{:?}", namespace);
+        }
+    }
+
+    #[test]
+    fn class_prefix_invalid() {
+        for prefix in &["my app", "my\0app", "-myapp", "myapp-"] {
+            let mut builder = OptionsBuilder::default();
+            builder.class_prefix(prefix);
+
+            let actual = builder.try_build().unwrap_err();
+
+            assert_eq!(
+                actual.reason,
+                PiktErrorReason::InvalidClassPrefix(prefix.to_string())
+            );
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn render_duration_is_recorded() -> Result<()> {
+        let output = render("box \"pikchr\"; arrow; circle")?;
+
+        assert!(output.render_duration > std::time::Duration::ZERO);
+        assert!(output.render_duration < std::time::Duration::from_secs(1));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn render_duration_is_ignored_by_eq() -> Result<()> {
+        let a = render("box")?;
+        let mut b = a.clone();
+        b.render_duration += std::time::Duration::from_millis(1);
+
+        assert_eq!(a, b);
+        assert!(a.content_eq(&b));
+
+        Ok(())
+    }
+
+    #[test]
+    fn args_always_use_plaintext_errors() -> Result<()> {
+        for flags in &[Flags::empty(), Flags::DARK_MODE, Flags::all()] {
+            let mut builder = OptionsBuilder::default();
+            builder.flags(*flags);
+
+            let args = PikchrArgs::from_options(b"box", &builder.build())?;
+
+            assert_eq!(
+                args.flags & PIKCHR_PLAINTEXT_ERRORS,
+                PIKCHR_PLAINTEXT_ERRORS
+            );
+            assert_eq!(args.flags, flags.bits() | PIKCHR_PLAINTEXT_ERRORS);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn args_without_class() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.class("");
+        let options = builder.build();
+
+        let args = PikchrArgs::from_options(b"box", &options)?;
+
+        assert!(args.class.is_none());
+        assert!(!render_with("box", options)?.svg.contains("class="));
+
+        Ok(())
+    }
+
+    #[test]
+    fn args_with_class() -> Result<()> {
+        let args = PikchrArgs::from_options(b"box", &Options::default())?;
+
+        assert_eq!(args.class, Some(CString::new("pikchr").unwrap()));
+        assert_eq!(args.input, CString::new("box").unwrap());
+
+        Ok(())
+    }
+
+    /// Run under AddressSanitizer to check the buffer is freed while unwinding, see
+    /// `CONTRIBUTING.md`.
+    #[test]
+    #[should_panic(expected = "after the native call")]
+    fn buffer_freed_on_panic() {
+        let args = PikchrArgs::from_options(b"box", &Options::default()).unwrap();
+        let (buffer, _, _) = args.call();
+        let _buffer = buffer.expect("pikchr ran out of memory");
+
+        panic!("after the native call");
+    }
+
+    #[test]
+    fn args_reject_nul_in_input() {
+        let actual = PikchrArgs::from_options(b"box\0", &Options::default()).unwrap_err();
+
+        assert!(matches!(
+            actual.reason,
+            PiktErrorReason::IncompatibleInput(_)
+        ));
+    }
+
+    #[test]
+    fn inject_dimensions_enabled() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.inject_dimensions();
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual.svg.contains(&format!(
+            " width=\"{}\" height=\"{}\">",
+            actual.width, actual.height
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn inject_dimensions_disabled() -> Result<()> {
+        let actual = render("box")?;
+        let (root, _) = actual.svg.split_once('>').unwrap();
+
+        assert!(!root.contains(" width="));
+        assert!(!root.contains(" height="));
+
+        Ok(())
+    }
+
+    #[test]
+    fn inject_dimensions_no_duplicates() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder
+            .inject_dimensions()
+            .svg_attribute("width", "100%")
+            .svg_attribute("height", "auto");
+        let actual = render_with("box", builder.build())?;
+
+        assert_eq!(actual.svg.matches(" width=").count(), 1);
+        assert_eq!(actual.svg.matches(" height=").count(), 1);
+        assert!(actual.svg.contains(" width=\"100%\" height=\"auto\">"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_many_collects_all_results() {
+        let actual = render_many(["box", "box 'pikchr'", "circle"], &Options::default());
+
+        assert_eq!(actual.len(), 3);
+        assert!(actual[0].is_ok());
+        assert!(actual[1].is_err());
+        assert!(actual[2].is_ok());
+    }
+
+    #[test]
+    fn fail_fast_all_succeed() -> Result<(), (usize, PiktError)> {
+        let actual = render_many_fail_fast(["box", "circle", "oval"], &Options::default())?;
+
+        assert_eq!(actual.len(), 3);
+        assert_eq!(actual[1], render("circle").unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fail_fast_first_fails() {
+        let (index, err) =
+            render_many_fail_fast(["box 'pikchr'", "box"], &Options::default()).unwrap_err();
+
+        assert_eq!(index, 0);
+        assert_eq!(err.reason, PiktErrorReason::TokenUnknown);
+    }
+
+    #[test]
+    fn fail_fast_middle_fails() {
+        let (index, _) =
+            render_many_fail_fast(["box", "box 'pikchr'", "circle"], &Options::default())
+                .unwrap_err();
+
+        assert_eq!(index, 1);
+    }
+
+    #[test]
+    fn fail_fast_last_fails() {
+        let (index, _) =
+            render_many_fail_fast(["box", "circle", "box 'pikchr'"], &Options::default())
+                .unwrap_err();
+
+        assert_eq!(index, 2);
+    }
+
+    #[test]
+    fn fail_fast_empty() {
+        let actual = render_many_fail_fast(std::iter::empty(), &Options::default());
+
+        assert_eq!(actual, Ok(vec![]));
+    }
+
+    #[test]
+    fn flags_are_copy() {
+        let f = Flags::DARK_MODE;
+        let g = f;
+        let h = f;
+
+        assert_eq!(g, h);
+    }
+
+    fn class_options(class: &str) -> Options {
+        let mut builder = OptionsBuilder::default();
+        builder.class(class);
+        builder.build()
+    }
+
+    fn size_options(width: u32, height: u32) -> Options {
+        let mut builder = OptionsBuilder::default();
+        builder.width(width);
+        builder.height(height);
+        builder.build()
+    }
+
+    #[test]
+    fn bitor_dark_mode() {
+        let mut builder = OptionsBuilder::default();
+        builder.dark_mode();
+        let actual = Options::default() | builder.build();
+
+        assert!(actual.flags().contains(Flags::DARK_MODE));
+    }
+
+    #[test]
+    fn bitor_keeps_custom_class() {
+        let actual = class_options("diagram") | Options::default();
+
+        assert_eq!(actual.class(), "diagram");
+    }
+
+    #[test]
+    fn bitor_blank_class_is_unset() {
+        let actual = class_options("diagram") | class_options("  ");
+
+        assert_eq!(actual.class(), "diagram");
+    }
+
+    #[test]
+    fn bitor_width() {
+        let actual = Options::default() | size_options(300, 0);
+
+        assert_eq!(actual.width(), 300);
+        assert_eq!(actual.height(), 0);
+    }
+
+    #[test]
+    fn bitor_assign() {
+        let mut actual = size_options(100, 200);
+        actual |= class_options("diagram");
+
+        assert_eq!(actual.class(), "diagram");
+        assert_eq!(actual.width(), 100);
+        assert_eq!(actual.height(), 200);
+    }
+
+    #[test]
+    fn bitor_defaults() {
+        assert_eq!(Options::default() | Options::default(), Options::default());
+    }
+
+    #[test]
+    fn bitor_associative() {
+        let mut builder = OptionsBuilder::default();
+        builder.dark_mode();
+        let a = builder.build();
+        let b = class_options("diagram");
+        let c = size_options(300, 150);
+
+        assert_eq!((a.clone() | b.clone()) | c.clone(), a | (b | c));
+    }
+
+    #[test]
+    fn template_substitution() -> Result<()> {
+        let vars = HashMap::from([("label", "\"one\""), ("shape", "circle")]);
+        let actual = render_template(
+            "box {{label}}; {{shape}} {{label}}",
+            &vars,
+            Options::default(),
+        )?;
+
+        assert_eq!(actual, render("box \"one\"; circle \"one\"")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn template_missing_variable() {
+        let vars = HashMap::from([("label", "\"one\"")]);
+        let actual = render_template("box {{label}} {{color}}", &vars, Options::default());
+
+        assert_eq!(
+            actual.unwrap_err().reason,
+            PiktErrorReason::UnknownTemplateVariable("color".to_string())
+        );
+    }
+
+    #[test]
+    fn template_value_with_keywords() -> Result<()> {
+        let vars = HashMap::from([("next", "arrow; circle \"end\"")]);
+        let actual = render_template("box; {{next}}", &vars, Options::default())?;
+
+        assert_eq!(actual, render("box; arrow; circle \"end\"")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn template_values_are_not_substituted() -> Result<()> {
+        let vars = HashMap::from([("a", "\"{{b}}\""), ("b", "\"x\"")]);
+        let actual = render_template("box {{a}}; box {{b}}", &vars, Options::default())?;
+
+        assert_eq!(actual, render("box \"{{b}}\"; box \"x\"")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn template_without_vars() -> Result<()> {
+        let actual = render_template("box", &HashMap::new(), Options::default())?;
+
+        assert_eq!(actual, render("box")?);
+        assert!(render_template("box {{label}}", &HashMap::new(), Options::default()).is_err());
+
+        Ok(())
+    }
+
+    fn format_options(fmt: OutputFormat) -> Options {
+        let mut builder = OptionsBuilder::default();
+        builder.output_format(fmt);
+        builder.build()
+    }
+
+    #[test]
+    fn output_format_svg() -> Result<()> {
+        let actual = render_with("box", format_options(OutputFormat::Svg))?;
+
+        assert_eq!(actual, render("box")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn output_format_unsupported() {
+        for (fmt, name) in [(OutputFormat::Png, "Png"), (OutputFormat::Pdf, "Pdf")] {
+            let actual = render_with("box", format_options(fmt)).unwrap_err();
+
+            assert_eq!(
+                actual.reason,
+                PiktErrorReason::UnsupportedOutputFormat(name.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn raw_message_preserved() {
+        let source = "box\ncircle 'x'";
+        let err = render(source).unwrap_err();
+
+        assert!(err.raw_message().contains("/*    2 */  circle 'x'"));
+        assert!(err.raw_message().contains("ERROR: unrecognized token"));
+        assert_eq!(render("box\0").unwrap_err().raw_message(), "");
+    }
+
+    #[test]
+    fn error_line_beyond_context() {
+        let source = "box\n".repeat(10) + "circle 'x'";
+        let err = render(&source).unwrap_err();
+
+        assert_eq!(err.line(), 11);
+        assert_eq!(err.column(), 9);
+    }
+
+    #[test]
+    fn from_str_last_error() {
+        let raw = "/*    1 */  box 'x'\n                ^\nERROR: unrecognized token\n\
+                   /*    3 */  circle 1/0\n                    ^\nERROR: division by zero\n";
+        let err = PiktError::from_str(raw).unwrap();
+
+        assert_eq!(err.line(), 3);
+        assert_eq!(err.column(), 10);
+        assert_eq!(err.reason, PiktErrorReason::DivisionByZero);
+        assert_eq!(err.raw_message(), raw);
+    }
+
+    #[test]
+    fn from_str_without_context() {
+        let err = PiktError::from_str("\nERROR: syntax error\n").unwrap();
+
+        assert_eq!(err.line(), 0);
+        assert_eq!(err.column(), 0);
+        assert_eq!(err.reason, PiktErrorReason::SyntaxError);
+    }
+
+    #[test]
+    fn from_str_without_error_prefix() {
+        let err = PiktError::from_str("\nsomething went wrong").unwrap();
+
+        assert_eq!(err.line(), 0);
+        assert_eq!(
+            err.reason,
+            PiktErrorReason::Other("something went wrong".to_string())
+        );
+    }
+
+    #[test]
+    fn from_str_rightmost_caret() {
+        let err = render("box color nosuch").unwrap_err();
+
+        assert!(err.raw_message().contains("^^^^^^\n"));
+        assert_eq!(err.line(), 1);
+        assert_eq!(err.column(), 16);
+        assert_eq!(err.reason, PiktErrorReason::UnknownVariable);
+    }
+
+    #[test]
+    fn from_str_crlf() {
+        let raw = "/*    1 */  box\r\n/*    2 */  circle 'x'\r\n                   ^\r\nERROR: unrecognized token\r\n";
+        let err = PiktError::from_str(raw).unwrap();
+
+        assert_eq!(err.line(), 2);
+        assert_eq!(err.column(), 9);
+        assert_eq!(err.reason, PiktErrorReason::TokenUnknown);
+    }
+
+    #[test]
+    fn from_str_ignores_macro_context() {
+        let source = "define m { box 'x' }\nbox\nm";
+        let err = render(source).unwrap_err();
+
+        assert!(err.raw_message().contains("Called from:"));
+        assert_eq!(err.line(), 1);
+    }
+
+    /// Every reason without data, which [`PiktErrorReason::from_error_code`] can recover.
+    fn unit_reasons() -> Vec<PiktErrorReason> {
+        use PiktErrorReason::*;
+
+        vec![
+            SyntaxError,
+            DivisionByZero,
+            ArcGeometryError,
+            UnknownObject,
+            UnknownObjectType,
+            ValueAlreadySet,
+            ValueAlreadyFixed,
+            OnlyWithLineOrientedObject,
+            NoPriorPathPoints,
+            HeadingOutOfBounds,
+            MissingAt,
+            MissingFromTo,
+            ClosedPolygon,
+            StartLineAlreadyFixed,
+            TooFewVertexes,
+            PositionAlreadyFixedByAt,
+            AttributeTooManyTerms,
+            AttributeMissingText,
+            UnknownColorName,
+            UnknownVariable,
+            OrdinalOutOfBounds,
+            MissingPriorObjectType,
+            NotALine,
+            VertexUnknown,
+            NegativeSqrt,
+            MacroTooManyArguments,
+            MacroUnterminatedArgumentList,
+            TokenTooLong,
+            TokenUnknown,
+            MacroTooDeep,
+            MacroRecursive,
+            ParserStackOverflow,
+            OutOfMemory,
+            Timeout,
+            EmptyInput,
+        ]
+    }
+
+    fn utf8_error() -> std::str::Utf8Error {
+        let bytes = vec![0xff];
+        std::str::from_utf8(&bytes).unwrap_err()
+    }
+
+    /// Every reason carrying data, with placeholder values.
+    fn data_reasons() -> Vec<PiktErrorReason> {
+        vec![
+            PiktErrorReason::IncompatibleInput(CString::new("\0").unwrap_err()),
+            PiktErrorReason::InvalidFlags(0),
+            PiktErrorReason::InvalidScale(0.0),
+            PiktErrorReason::InvalidViewBox(ViewBox {
+                x: 0.0,
+                y: 0.0,
+                width: 0.0,
+                height: 0.0,
+            }),
+            PiktErrorReason::InvalidAttributeName(String::new()),
+            PiktErrorReason::InvalidCssVariable(String::new()),
+            PiktErrorReason::InvalidClassPrefix(String::new()),
+            PiktErrorReason::InvalidNamespace(String::new()),
+            PiktErrorReason::UnknownTemplateVariable(String::new()),
+            PiktErrorReason::UnsupportedOutputFormat(String::new()),
+            PiktErrorReason::OutputTooLarge {
+                actual: 0,
+                limit: 0,
+            },
+            PiktErrorReason::InputTooLarge {
+                actual: 0,
+                limit: 0,
+            },
+            PiktErrorReason::TooComplex {
+                actual: ComplexityClass::Simple,
+                limit: ComplexityClass::Simple,
+            },
+            PiktErrorReason::Io(std::io::ErrorKind::Other.into()),
+            PiktErrorReason::InvalidUtf8(utf8_error()),
+            PiktErrorReason::InvalidColor(String::new()),
+            PiktErrorReason::DuplicateMacro(String::new()),
+            PiktErrorReason::MacroArgumentMismatch {
+                name: String::new(),
+                declared: 0,
+                used: 0,
+            },
+            PiktErrorReason::DiagramFailed {
+                id: String::new(),
+                reason: Box::new(PiktErrorReason::SyntaxError),
+            },
+            PiktErrorReason::Other(String::new()),
+        ]
+    }
+
+    #[test]
+    fn error_codes_are_unique() {
+        use std::collections::HashSet;
+
+        let reasons: Vec<_> = unit_reasons().into_iter().chain(data_reasons()).collect();
+        let codes: HashSet<u32> = reasons.iter().map(PiktErrorReason::error_code).collect();
+
+        assert_eq!(codes.len(), reasons.len());
+    }
+
+    #[test]
+    fn error_code_roundtrip() {
+        for reason in unit_reasons() {
+            assert_eq!(
+                PiktErrorReason::from_code(reason.error_code()),
+                PiktErrorReason::from_error_code(reason.error_code())
+            );
+            assert_eq!(
+                PiktErrorReason::from_error_code(reason.error_code()),
+                Some(reason)
+            );
+        }
+    }
+
+    #[test]
+    fn from_unknown_error_code() {
+        assert_eq!(PiktErrorReason::from_error_code(0), None);
+        assert_eq!(PiktErrorReason::from_error_code(9000), None);
+        assert_eq!(PiktErrorReason::from_error_code(9903), None);
+    }
+
+    #[test]
+    fn severity_is_error() {
+        for reason in unit_reasons().into_iter().chain(data_reasons()) {
+            assert_eq!(reason.severity(), Severity::Error, "{:?}", reason);
+        }
+    }
+
+    #[test]
+    fn error_kinds() {
+        let cases = [
+            ("circ \"1\"", PiktErrorKind::Syntax),
+            ("box 'x'", PiktErrorKind::Syntax),
+            (
+                "box\narrow from first box to (0/0, 0)",
+                PiktErrorKind::Geometry,
+            ),
+            ("arrow from first oval", PiktErrorKind::Definition),
+            ("define m { m }\nm", PiktErrorKind::Macro),
+            ("box\0", PiktErrorKind::Input),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(render(source).unwrap_err().kind(), expected, "{}", source);
+        }
+
+        assert_eq!(
+            PiktErrorReason::Other(String::new()).kind(),
+            PiktErrorKind::Unknown
+        );
+        assert_eq!(PiktErrorReason::Timeout.kind(), PiktErrorKind::System);
+    }
+
+    #[test]
+    fn error_kind_is_copy() {
+        let kind = PiktErrorKind::Macro;
+        let copy = kind;
+
+        assert_eq!(kind, copy);
+    }
+
+    #[test]
+    fn lenient_success() -> Result<()> {
+        let (output, errors) = render_lenient("box", Options::default());
+
+        assert_eq!(output, Some(render("box")?));
+        assert!(errors.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn lenient_failure() {
+        let (output, errors) = render_lenient("circ \"1\"", Options::default());
+
+        assert_eq!(output, None);
+        assert_eq!(errors, vec![render("circ \"1\"").unwrap_err()]);
+    }
+
+    /// Valid markup of exactly `len` bytes.
+    fn markup_of_len(len: usize) -> String {
+        let mut markup = "box\n#".to_string();
+        markup.push_str(&"x".repeat(len - markup.len()));
+        markup
+    }
+
+    #[test]
+    fn input_within_limit() -> Result<()> {
+        let actual = render(&markup_of_len(MAX_INPUT_BYTES - 1))?;
+
+        assert_eq!(actual, render("box")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn input_too_large() {
+        let actual = render(&markup_of_len(MAX_INPUT_BYTES + 1)).unwrap_err();
+
+        assert_eq!(
+            actual.reason,
+            PiktErrorReason::InputTooLarge {
+                actual: MAX_INPUT_BYTES + 1,
+                limit: MAX_INPUT_BYTES
+            }
+        );
+    }
+
+    #[test]
+    fn custom_input_limit() {
+        let mut builder = OptionsBuilder::default();
+        builder.max_input_bytes(3);
+
+        assert!(render_with("box", builder.clone().build()).is_ok());
+        assert!(matches!(
+            render_with("oval", builder.build()).unwrap_err().reason,
+            PiktErrorReason::InputTooLarge {
+                actual: 4,
+                limit: 3
+            }
+        ));
+    }
+
+    #[test]
+    fn crlf_input() -> Result<()> {
+        assert_eq!(render("box \"pikchr\"\r\n")?, render("box \"pikchr\"\n")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lone_cr_input() -> Result<()> {
+        let actual = render("box\rcircle 'x'").unwrap_err();
+
+        assert_eq!(render("box\rcircle\r")?, render("box\ncircle\n")?);
+        assert_eq!(actual.line(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bom_input() -> Result<()> {
+        assert_eq!(render("\u{FEFF}box \"pikchr\"")?, render("box \"pikchr\"")?);
+        assert_eq!(render("\u{FEFF}")?, render("")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn standalone_keeps_xmlns() -> Result<()> {
+        let actual = render("box")?;
+
+        assert!(Options::default().standalone());
+        assert!(actual.svg.contains("xmlns="));
+
+        Ok(())
+    }
+
+    #[test]
+    fn embedded_strips_xmlns() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.standalone(false);
+        let embedded = render_with("box", builder.build())?;
+        let standalone = render("box")?;
+
+        assert!(!embedded.svg.contains("xmlns="));
+        assert!(embedded.svg.starts_with("<svg class=\"pikchr\""));
+
+        // Adding the namespace back gives the standalone document.
+        let restored = embedded
+            .svg
+            .replacen("<svg", "<svg xmlns='http://www.w3.org/2000/svg'", 1);
+
+        assert_eq!(restored, standalone.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bitor_embedded() {
+        let mut builder = OptionsBuilder::default();
+        builder.standalone(false);
+
+        assert!(!(Options::default() | builder.build()).standalone());
+    }
+
+    #[test]
+    fn aria_label() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.aria_label("A box");
+        let actual = render_with("box", builder.build())?;
+        let (root, _) = actual.svg.split_once('>').unwrap();
+
+        assert!(root.contains(" aria-label=\"A box\""));
+        assert!(root.contains(" role=\"img\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn aria_label_is_escaped() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.aria_label("\"A\" & B");
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual.svg.contains(" aria-label=\"&quot;A&quot; &amp; B\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn aria_label_with_title() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.aria_label("A label").title("A title");
+        let actual = render_with("box", builder.build())?;
+        let (root, _) = actual.svg.split_once('>').unwrap();
+
+        assert!(root.contains(" aria-labelledby=\"pikchr-title\""));
+        assert!(root.contains(" role=\"img\""));
+        assert!(!root.contains("aria-label="));
+
+        Ok(())
+    }
+
+    #[test]
+    fn data_attribute() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder
+            .data_attribute("data-id", "123")
+            .data_attribute("diagram-id", "\"a\"");
+        let actual = render_with("box", builder.build())?;
+        let (root, _) = actual.svg.split_once('>').unwrap();
+
+        assert!(root.contains(" data-id=\"123\""));
+        assert!(root.contains(" data-diagram-id=\"&quot;a&quot;\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn data_attribute_invalid_name() {
+        for name in ["diagram id", "diagramId", "data-", ""] {
+            let mut builder = OptionsBuilder::default();
+            builder.data_attribute(name, "x");
+
+            assert!(matches!(
+                builder.try_build().unwrap_err().reason,
+                PiktErrorReason::InvalidAttributeName(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn render_dark_and_light() -> Result<()> {
+        let light = render_light("box")?;
+
+        assert_eq!(light, render("box")?);
+        assert!(!light.svg.contains("rgb(255,255,255)"));
+        assert!(render_dark("box")?.svg.contains("rgb(255,255,255)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_dark_with_forces_dark_mode() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.class("diagram");
+        let actual = render_dark_with("box", builder.build())?;
+
+        assert!(actual.svg.contains("rgb(255,255,255)"));
+        assert!(actual.svg.contains("class=\"diagram\""));
+
+        Ok(())
+    }
+
+    fn theme_flags(theme: Theme) -> Flags {
+        let mut builder = OptionsBuilder::default();
+        builder.theme(theme);
+        builder.build().flags()
+    }
+
+    #[test]
+    fn theme_dark_and_light() {
+        assert!(theme_flags(Theme::Dark).contains(Flags::DARK_MODE));
+        assert!(!theme_flags(Theme::Light).contains(Flags::DARK_MODE));
+
+        let mut builder = OptionsBuilder::default();
+        builder.dark_mode().theme(Theme::Light);
+
+        assert!(!builder.build().flags().contains(Flags::DARK_MODE));
+    }
+
+    // The only test touching the variable so it can't race with another one.
+    #[test]
+    fn theme_system() {
+        std::env::set_var(THEME_VAR, "dark");
+        assert!(theme_flags(Theme::System).contains(Flags::DARK_MODE));
+
+        std::env::set_var(THEME_VAR, "blue");
+        assert!(!theme_flags(Theme::System).contains(Flags::DARK_MODE));
+
+        std::env::remove_var(THEME_VAR);
+        assert!(!theme_flags(Theme::System).contains(Flags::DARK_MODE));
+    }
+
+    #[test]
+    fn output_view_box() -> Result<()> {
+        let actual = render("box")?;
+        let (min_x, min_y, width, height) = actual.view_box().unwrap();
+
+        assert_eq!((min_x, min_y), (0.0, 0.0));
+        assert!(width > 0.0 && height > 0.0);
+        assert!((width - actual.width as f64).abs() < 1.0);
+        assert!((height - actual.height as f64).abs() < 1.0);
+
+        let malformed = PikchrOutput::from_parts("<svg viewBox=\"0 0 a b\"></svg>".into(), 0, 0);
+
+        assert_eq!(malformed.view_box(), None);
+        assert_eq!(render("")?.view_box(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn output_without_xml_header() -> Result<()> {
+        let plain = render("box")?;
+
+        assert_eq!(plain.without_xml_header(), plain.svg);
+        assert_eq!(plain.without_doctype(), plain.svg);
+
+        let prolog = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE svg PUBLIC \"-//W3C//DTD SVG 1.1//EN\" \"http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd\">\n";
+        let actual = PikchrOutput::from_parts(format!("{}{}", prolog, plain.svg), 0, 0);
+
+        assert!(actual.without_xml_header().starts_with("<!DOCTYPE svg"));
+        assert_eq!(actual.without_doctype(), plain.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn adjust_offset() {
+        let mut err = render("box 'x'").unwrap_err();
+        let (line, column) = (err.line, err.column);
+
+        err.adjust_offset(3, 4);
+
+        assert_eq!((err.line, err.column), (line + 3, column + 4));
+        assert_eq!(err.with_offset(1, 0).line, line + 4);
+    }
+
+    #[test]
+    fn adjust_offset_keeps_errors_without_location() {
+        let err = render("box\0").unwrap_err();
+
+        assert_eq!(render("box\0").unwrap_err().with_offset(3, 4), err);
+    }
+
+    #[test]
+    fn adjust_offset_column_on_first_line_only() {
+        let err = render("box\nbox 'x'").unwrap_err();
+        let (line, column) = (err.line, err.column);
+        let err = err.with_offset(3, 4);
+
+        assert_eq!(line, 2);
+        assert_eq!((err.line, err.column), (line + 3, column));
+    }
+
+    #[test]
+    fn zero_offset_is_a_no_op() {
+        let err = render("box 'x'").unwrap_err();
+
+        assert_eq!(render("box 'x'").unwrap_err().with_offset(0, 0), err);
+    }
+
+    #[test]
+    fn render_or_default_success() -> Result<()> {
+        let actual = render_or_default("box", Options::default(), "<svg></svg>");
+
+        assert_eq!(actual, render("box")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_or_default_fallback() {
+        let fallback = "<svg class='broken'></svg>";
+
+        for input in ["box 'x'", "box\0"] {
+            let actual = render_or_default(input, Options::default(), fallback);
+
+            assert_eq!(actual.into_parts(), (fallback.to_string(), 0, 0));
+        }
+    }
+
+    #[test]
+    fn render_or_empty_fallback() {
+        let actual = render_or_empty("box 'x'", Options::default());
+
+        assert_eq!(actual.svg, "<svg xmlns='http://www.w3.org/2000/svg'></svg>");
+    }
+
+    #[test]
+    fn inject_css() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.inject_css("path { stroke: red; }");
+        let actual = render_with("box", builder.build())?;
+        let (_, children) = actual.svg.split_once('>').unwrap();
+
+        assert!(children.starts_with("\n<style>path { stroke: red; }</style>\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn inject_css_accumulates() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder
+            .inject_css("path { stroke: red; }")
+            .inject_css("text { fill: blue; }");
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual
+            .svg
+            .contains("<style>path { stroke: red; }\ntext { fill: blue; }</style>"));
+        assert_eq!(actual.svg.matches("<style>").count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn inject_css_empty() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.inject_css("");
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual.svg.contains("<style></style>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn inject_css_is_verbatim() -> Result<()> {
+        // Not escaped, see `OptionsBuilder::inject_css`.
+        let css = "/* </style><script>alert(1)</script> */";
+        let mut builder = OptionsBuilder::default();
+        builder.inject_css(css);
+        let actual = render_with("box", builder.build())?;
+
+        assert!(actual.svg.contains(&format!("<style>{}</style>", css)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn inject_css_nul() {
+        let mut builder = OptionsBuilder::default();
+        builder.inject_css("a\0b");
+
+        assert!(matches!(
+            builder.try_build().unwrap_err().reason,
+            PiktErrorReason::IncompatibleInput(_)
+        ));
+    }
+
+    #[test]
+    fn render_and_save_writes_the_svg() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("box.svg");
+
+        let actual = render_and_save("box", &path, Options::default())?;
+
+        assert_eq!(actual, render_with("box", Options::default())?);
+        assert_eq!(std::fs::read_to_string(&path)?, render("box")?.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_and_save_to_a_directory() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+
+        let actual = render_and_save("box", dir.path(), Options::default()).unwrap_err();
+
+        assert!(matches!(actual.reason, PiktErrorReason::Io(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_and_compare_match() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("box.svg");
+        std::fs::write(&path, render("box")?.svg)?;
+
+        assert!(render_and_compare("box", &path, Options::default())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_and_compare_mismatch() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("box.svg");
+        std::fs::write(&path, render("circle")?.svg)?;
+
+        assert!(!render_and_compare("box", &path, Options::default())?);
+        assert!(render_and_compare("box 'x'", &path, Options::default()).is_err());
+
+        Ok(())
+    }
+
+    // Going through `compare_golden` as setting the variable would race with the other tests.
+    #[test]
+    fn render_and_compare_update() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("box.svg");
+        std::fs::write(&path, "outdated")?;
+
+        assert!(compare_golden("box", &path, Options::default(), true)?);
+        assert_eq!(std::fs::read_to_string(&path)?, render("box")?.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reason_from_lower_level_errors() {
+        use std::error::Error;
+
+        let nul = CString::new("\0").unwrap_err();
+        let reason = PiktErrorReason::from(nul.clone());
+        assert_eq!(reason, PiktErrorReason::IncompatibleInput(nul));
+        assert!(reason.source().unwrap().is::<NulError>());
+
+        let reason = PiktErrorReason::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(
+            matches!(reason, PiktErrorReason::Io(ref err) if err.kind() == std::io::ErrorKind::NotFound)
+        );
+        assert!(reason.source().unwrap().is::<std::io::Error>());
+
+        let utf8 = utf8_error();
+        let reason = PiktErrorReason::from(utf8);
+        assert_eq!(reason, PiktErrorReason::InvalidUtf8(utf8));
+        assert!(reason.source().unwrap().is::<std::str::Utf8Error>());
+    }
+
+    #[test]
+    fn options_display() {
+        assert_eq!(
+            Options::default().to_string(),
+            "Options(class=\"pikchr\", dark=false, 0x0)"
+        );
+
+        let mut builder = OptionsBuilder::default();
+        builder.class("diagram").dark_mode();
+        builder.width(300);
+        builder.height(200);
+
+        assert_eq!(
+            builder.to_string(),
+            "[draft] Options(class=\"diagram\", dark=true, 300x200)"
+        );
+        assert_eq!(
+            builder.build().to_string(),
+            "Options(class=\"diagram\", dark=true, 300x200)"
+        );
+    }
+
+    #[test]
+    fn render_owned_matches_render_with() -> Result<()> {
+        for input in ["box \"pikchr\"", "\u{FEFF}box\r\ncircle", ""] {
+            assert_eq!(
+                render_owned(input.to_string(), Options::default())?,
+                render_with(input, Options::default())?
+            );
+        }
+
+        let err = render_owned("box\0".to_string(), Options::default()).unwrap_err();
+
+        assert!(matches!(err.reason, PiktErrorReason::IncompatibleInput(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn annotate_elements() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.annotate_elements();
+        let input = "# A comment\nbox \"hello\"\n\narrow\nbox";
+        let actual = render_with(input, builder.build())?;
+
+        assert_eq!(actual.svg.matches("<!-- pikchr line").count(), 4);
+        assert!(actual
+            .svg
+            .contains("<!-- pikchr line 2: box \"hello\" -->\n<path"));
+        assert!(actual
+            .svg
+            .contains("<!-- pikchr line 2: box \"hello\" -->\n<text"));
+        assert!(actual.svg.contains("<!-- pikchr line 5: box -->\n<path"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn annotate_elements_disabled() -> Result<()> {
+        assert!(!render("box \"hello\"")?.svg.contains("<!--"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn metadata_first_child() -> Result<()> {
+        let expected = render("box")?;
+        let actual = render_with_metadata(
+            "box",
+            Options::default(),
+            &[("title", "A <box>"), ("creator", "Ada & Charles")],
+        )?;
+        let root_end = actual.svg.find('>').unwrap() + 1;
+
+        assert!(actual.svg[root_end..].starts_with(
+            "\n<metadata><rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\"><rdf:Description>"
+        ));
+        assert!(actual.svg.contains("<dc:title>A &lt;box&gt;</dc:title>"));
+        assert!(actual
+            .svg
+            .contains("<dc:creator>Ada &amp; Charles</dc:creator>"));
+
+        let metadata_end = actual.svg.find("</metadata>").unwrap() + "</metadata>".len();
+        let rest = format!("{}{}", &actual.svg[..root_end], &actual.svg[metadata_end..]);
+        assert_eq!(rest, expected.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn metadata_empty() -> Result<()> {
+        assert_eq!(
+            render_with_metadata("box", Options::default(), &[])?,
+            render("box")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn metadata_invalid_key() {
+        let actual = render_with_metadata("box", Options::default(), &[("a b", "c")]).unwrap_err();
+
+        assert_eq!(
+            actual.reason(),
+            &PiktErrorReason::InvalidAttributeName("a b".to_string())
+        );
+    }
+
+    #[test]
+    fn render_many_reports_progress() {
+        use std::cell::RefCell;
+
+        let calls = RefCell::new(Vec::new());
+        let inputs = ["box", "box 'x'", "circle"];
+        let results = render_many_with_progress(
+            inputs.iter().copied(),
+            &Options::default(),
+            |done, total| calls.borrow_mut().push((done, total)),
+        );
+
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_err());
+        assert_eq!(calls.into_inner(), [(1, 3), (2, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn render_many_progress_empty() {
+        let results = render_many_with_progress(std::iter::empty(), &Options::default(), |_, _| {
+            panic!("no progress without inputs")
+        });
+
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn no_class() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.no_class();
+
+        assert!(!render_with("box", builder.build())?.svg.contains("class="));
+
+        Ok(())
+    }
+
+    #[test]
+    fn html_embed_preset() -> Result<()> {
+        let actual = render_with("box", OptionsBuilder::for_html_embed().build())?;
+        let root = postprocess::root_tag(&actual.svg).unwrap();
+        let root = &actual.svg[root];
+
+        assert!(!root.contains("class="));
+        assert!(!root.contains("xmlns="));
+        assert!(!root.contains("width="));
+        assert!(!root.contains("height="));
+        assert!(root.contains("viewBox="));
+
+        Ok(())
+    }
+
+    #[test]
+    fn standalone_svg_preset() -> Result<()> {
+        let actual = render_with("box", OptionsBuilder::for_standalone_svg().build())?;
+        let root = postprocess::root_tag(&actual.svg).unwrap();
+        let root = &actual.svg[root];
+
+        assert!(root.contains("class=\"pikchr\""));
+        assert!(root.contains("xmlns="));
+        assert!(root.contains(&format!(" width=\"{}\"", actual.width)));
+        assert!(root.contains(&format!(" height=\"{}\"", actual.height)));
+
+        let embed = OptionsBuilder::for_html_embed().build();
+        let standalone = OptionsBuilder::for_standalone_svg().build();
+
+        assert_ne!(embed.class(), standalone.class());
+        assert!(!embed.standalone() && standalone.standalone());
+        assert!(!embed.inject_dimensions() && standalone.inject_dimensions());
+        assert_eq!(embed.flags(), standalone.flags());
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_as_svg() {
+        let err = render("box \"<a>\"\ncirc 'x'").unwrap_err();
+        let actual = render_error_as_svg(&err, 301, 50);
+
+        assert!(actual.starts_with("<svg"));
+        assert!(actual.contains(&html::escape_html(&err.to_string())));
+        assert!(actual.contains("<text x=\"150.5\" y=\"25\""));
+        assert_eq!(actual.matches("<rect ").count(), 1);
+        assert!(actual.contains("stroke:rgb(255,0,0)"));
+
+        let root = &actual[postprocess::root_tag(&actual).unwrap()];
+        assert!(root.contains(" width=\"301\" height=\"50\""));
+        assert_eq!(
+            postprocess::view_box(&actual),
+            Some((0.0, 0.0, 301.0, 50.0))
+        );
+    }
+
+    #[test]
+    fn error_as_svg_zero_size() {
+        let err = render("box 'x'").unwrap_err();
+
+        assert!(render_error_as_svg(&err, 0, 0)
+            .contains("<rect x=\"1\" y=\"1\" width=\"0\" height=\"0\""));
+    }
+
+    #[test]
+    fn background_color() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.background_color("rgb(250, 250, 250)");
+        let actual = render_with("box \"a\"", builder.try_build()?)?;
+        let (_, _, width, height) = actual.view_box().unwrap();
+        let root_end = postprocess::root_tag(&actual.svg).unwrap().end;
+
+        assert!(actual.svg[root_end..].starts_with(&format!(
+            "\n<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"rgb(250, 250, 250)\"/>\n<path",
+            postprocess::format_number(width),
+            postprocess::format_number(height)
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn background_color_dark_mode() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.dark_mode().background_color("#111");
+        let actual = render_with("box", builder.try_build()?)?;
+
+        assert!(actual.svg.contains("fill=\"#111\"/>"));
+        assert!(actual.svg.contains("stroke:rgb(255,255,255)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn background_color_invalid() {
+        for color in ["nocolor", "rgb(256,0,0)", "#12", "red\"/><script>"] {
+            let mut builder = OptionsBuilder::default();
+            builder.background_color(color);
+
+            assert_eq!(
+                builder.try_build().unwrap_err().reason(),
+                &PiktErrorReason::InvalidColor(color.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn scaled_to_fit_keeps_small_diagrams() -> Result<()> {
+        assert_eq!(
+            render_scaled_to_fit("box", 1000, 1000, Options::default())?,
+            render("box")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn scaled_to_fit_shrinks_large_diagrams() -> Result<()> {
+        let input = "box wid 8in ht 2in";
+        let natural = render(input)?;
+        let actual = render_scaled_to_fit(input, 400, 300, Options::default())?;
+        let (_, _, width, height) = natural.view_box().unwrap();
+
+        assert!(natural.width > 400);
+        assert!(actual.width <= 400 && actual.height <= 300);
+        assert_eq!(actual.width, 400);
+        assert!((actual.width as f64 / actual.height as f64 - width / height).abs() < 0.05);
+
+        let tall = render_scaled_to_fit("box wid 1in ht 8in", 400, 300, Options::default())?;
+        assert_eq!(tall.height, 300);
+        assert!(tall.width < 400);
+
+        Ok(())
+    }
+
+    #[test]
+    fn scaled_to_fit_zero() {
+        assert!(matches!(
+            render_scaled_to_fit("box", 0, 100, Options::default())
+                .unwrap_err()
+                .reason(),
+            PiktErrorReason::InvalidScale(_)
+        ));
+    }
+
+    #[test]
+    fn merge_from_defaults() {
+        let mut actual = OptionsBuilder::default();
+        actual.merge_from(&OptionsBuilder::default());
+
+        assert_eq!(actual.build(), Options::default());
+    }
+
+    #[test]
+    fn merge_from_adds_dark_mode() {
+        let mut base = OptionsBuilder::default();
+        base.class("diagram").width(100);
+        let mut local = OptionsBuilder::default();
+        local.dark_mode().height(50);
+
+        let actual = base.merge_from(&local).clone().build();
+
+        assert_eq!(actual.flags(), Flags::DARK_MODE);
+        assert_eq!(actual.class(), "diagram");
+        assert_eq!((actual.width(), actual.height()), (100, 50));
+    }
+
+    #[test]
+    fn merge_from_keeps_class() {
+        let mut base = OptionsBuilder::default();
+        base.class("diagram");
+        let mut local = OptionsBuilder::default();
+        local.no_class();
+
+        assert_eq!(base.merge_from(&local).clone().build().class(), "diagram");
+
+        let mut local = OptionsBuilder::default();
+        local.class("override");
+
+        assert_eq!(base.merge_from(&local).clone().build().class(), "override");
+    }
+
+    #[test]
+    fn max_complexity() -> Result<()> {
+        let input = "box\n".repeat(30);
+        let mut builder = OptionsBuilder::default();
+        builder.max_complexity(ComplexityClass::Moderate);
+
+        assert_eq!(render_with(&input, builder.clone().build()), render(&input));
+
+        builder.max_complexity(ComplexityClass::Simple);
+        let err = render_with(&input, builder.build()).unwrap_err();
+
+        assert_eq!(
+            err.reason(),
+            &PiktErrorReason::TooComplex {
+                actual: ComplexityClass::Moderate,
+                limit: ComplexityClass::Simple,
+            }
+        );
+        assert_eq!(
+            err.reason().to_string(),
+            "input too complex: moderate, limit is simple"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn watermark() -> Result<()> {
+        let output = render_with_watermark("box", Options::default(), "<draft>", 0.25)?;
+        let (x, y, width, height) = postprocess::view_box(&output.svg).unwrap();
+
+        assert!(output.svg.contains("fill=\"rgba(128,128,128,0.25)\""));
+        assert!(output.svg.contains(&format!(
+            "transform=\"rotate(-30, {}, {})\"",
+            postprocess::format_number(x + width / 2.0),
+            postprocess::format_number(y + height / 2.0)
+        )));
+        assert!(output.svg.ends_with(">&lt;draft&gt;</text>\n</svg>\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn watermark_opacity_is_clamped() -> Result<()> {
+        let opaque = render_with_watermark("box", Options::default(), "a", 2.0)?;
+        let transparent = render_with_watermark("box", Options::default(), "a", -1.0)?;
+        let nan = render_with_watermark("box", Options::default(), "a", f32::NAN)?;
+
+        assert!(opaque.svg.contains("rgba(128,128,128,1)"));
+        assert!(transparent.svg.contains("rgba(128,128,128,0)"));
+        assert!(nan.svg.contains("rgba(128,128,128,1)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_watermark() -> Result<()> {
+        assert_eq!(
+            render_with_watermark("box", Options::default(), "", 0.5)?,
+            render("box")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn id_prefix() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.id_prefix("my_diagram").title("t");
+        let actual = render_with("box \"a\"; arrow; circle \"b\"", builder.build())?;
+        let ids: Vec<&str> = actual
+            .svg
+            .split(" id=\"")
+            .skip(1)
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        let elements = actual.svg.matches("<path").count()
+            + actual.svg.matches("<text").count()
+            + actual.svg.matches("<circle").count()
+            + actual.svg.matches("<polygon").count();
 
-impl FromStr for PiktError {
-    type Err = PiktError;
+        assert_eq!(ids.len(), elements + 1);
+        assert!(ids.contains(&"pikchr-title"));
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        use PiktErrorReason::*;
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
 
-        if s.contains("parser stack overflow") {
-            return Ok(PiktError {
-                line: 0,
-                column: 0,
-                reason: ParserStackOverflow,
-            });
-        }
-        if s.contains("Out of memory") {
-            return Ok(PiktError {
-                line: 0,
-                column: 0,
-                reason: OutOfMemory,
-            });
+        assert_eq!(unique.len(), ids.len());
+
+        for (n, id) in ids.iter().filter(|id| **id != "pikchr-title").enumerate() {
+            assert_eq!(*id, format!("my_diagram-{}", n + 1));
         }
 
-        let line_padding = 12;
-        let lines = s.lines();
-        let mut message = "unknown error";
-        let mut err = PiktError {
-            line: 0,
-            column: 0,
-            reason: Other(message.to_string()),
-        };
+        Ok(())
+    }
 
-        for line in lines {
-            // markup lines are formatted like:
-            //
-            // /*    1 */  circle "1"
-            if line.starts_with("/*") {
-                err.line += 1;
-            }
+    #[cfg(feature = "metrics-recording")]
+    type RecordedMetric = (
+        String,
+        Vec<(String, String)>,
+        metrics_util::debugging::DebugValue,
+    );
 
-            // caret lines always end with a caret. multiple carets are ignored.
-            if line.ends_with('^') {
-                err.column = line.len() + 1 - line_padding;
-            }
+    /// Renders with a local recorder returning each metric as `(name, labels, value)`.
+    #[cfg(feature = "metrics-recording")]
+    fn recorded_metrics(render: impl FnOnce()) -> Vec<RecordedMetric> {
+        let recorder = metrics_util::debugging::DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, render);
 
-            // the last line always follow a pattern like:
-            //
-            // ERROR: <error message>
-            if line.starts_with("ERROR:") {
-                if let Some((_, msg)) = line.split_once(' ') {
-                    message = msg
-                };
-            }
+        let mut metrics: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let key = key.key();
+                let labels = key
+                    .labels()
+                    .map(|label| (label.key().to_string(), label.value().to_string()))
+                    .collect();
+
+                (key.name().to_string(), labels, value)
+            })
+            .collect();
+        metrics.sort_by(|a, b| a.0.cmp(&b.0));
+
+        metrics
+    }
+
+    #[cfg(feature = "metrics-recording")]
+    #[test]
+    fn render_metrics() {
+        use metrics_util::debugging::DebugValue;
+
+        let metrics = recorded_metrics(|| {
+            render("box").unwrap();
+        });
+        let names: Vec<_> = metrics.iter().map(|(name, _, _)| name.as_str()).collect();
+
+        assert_eq!(
+            names,
+            [
+                "pikt.render.duration_us",
+                "pikt.render.input_bytes",
+                "pikt.renders.total"
+            ]
+        );
+        assert_eq!(metrics[1].2, DebugValue::Histogram(vec![3.0.into()]));
+        assert_eq!(metrics[2].2, DebugValue::Counter(1));
+    }
+
+    #[cfg(feature = "metrics-recording")]
+    #[test]
+    fn render_error_metrics() {
+        use metrics_util::debugging::DebugValue;
+
+        let metrics = recorded_metrics(|| {
+            render("circ \"1\"").unwrap_err();
+        });
+        let errors = metrics
+            .iter()
+            .find(|(name, _, _)| name == "pikt.renders.errors")
+            .unwrap();
+
+        assert_eq!(errors.1, [("reason".to_string(), "1001".to_string())]);
+        assert_eq!(errors.2, DebugValue::Counter(1));
+        assert!(metrics.iter().any(
+            |(name, _, value)| name == "pikt.renders.total" && *value == DebugValue::Counter(1)
+        ));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn render_par_ordered_keeps_order() {
+        let inputs: Vec<String> = (0..100)
+            .map(|n| match n % 4 {
+                0 => format!("box \"{}\"", n),
+                1 => format!("circle \"{}\" radius {}", n, 1 + n % 7),
+                2 => format!("box 'invalid {}'", n),
+                _ => "arrow; ".repeat(n % 9 + 1),
+            })
+            .collect();
+        let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+
+        let actual = render_par_ordered(inputs.clone(), &Options::default());
+        let expected = render_many(inputs, &Options::default());
+
+        assert_eq!(actual, expected);
+
+        for (n, result) in actual.iter().enumerate() {
+            assert_eq!(result.is_err(), n % 4 == 2, "input {}", n);
         }
+    }
 
-        err.reason = match message {
-            "division by zero" => DivisionByZero,
-            "syntax error" => SyntaxError,
-            "arc geometry error" => ArcGeometryError,
-            "unknown object type" => UnknownObjectType,
-            "no such object" => UnknownObject,
-            "value is already set" => ValueAlreadySet,
-            "value already fixed by prior constraints" => ValueAlreadyFixed,
-            "use with line-oriented objects only" => OnlyWithLineOrientedObject,
-            "no prior path points" => NoPriorPathPoints,
-            "too many path elements" => NoPriorPathPoints,
-            "headings should be between 0 and 360" => HeadingOutOfBounds,
-            "use \"at\" to position this object" => MissingAt,
-            "use \"from\" and \"to\" to position this object" => MissingFromTo,
-            "polygon is closed" => ClosedPolygon,
-            "need at least 3 vertexes in order to close the polygon" => TooFewVertexes,
-            "line start location already fixed" => StartLineAlreadyFixed,
-            "location fixed by prior \"at\"" => PositionAlreadyFixedByAt,
-            "too many text terms" => AttributeTooManyTerms,
-            "no text to fit to" => AttributeMissingText,
-            "not a known color name" => UnknownColorName,
-            "no such variable" => UnknownVariable,
-            "value too big - max '1000th'" => OrdinalOutOfBounds,
-            "no prior objects of the same type" => MissingPriorObjectType,
-            "object is not a line" => NotALine,
-            "no such vertex" => VertexUnknown,
-            "sqrt of negative value" => NegativeSqrt,
-            "too many macro arguments - max 9" => MacroTooManyArguments,
-            "unterminated macro argument list" => MacroUnterminatedArgumentList,
-            "token is too long - max length 50000 bytes" => TokenTooLong,
-            "unrecognized token" => TokenUnknown,
-            "macros nested too deep" => MacroTooDeep,
-            "recursive macro definition" => MacroRecursive,
-            msg => Other(msg.to_string()),
-        };
+    #[test]
+    fn view_box() -> Result<()> {
+        let expected = render("box \"a\"")?;
+        let mut builder = OptionsBuilder::default();
+        builder
+            .view_box(-20.0, -5.5, 50.0, 40.25)
+            .background_color("white");
+        let actual = render_with("box \"a\"", builder.try_build()?)?;
+
+        assert_eq!(
+            postprocess::root_attribute(&actual.svg, "viewBox"),
+            Some("-20 -5.5 50 40.25")
+        );
+        assert!(actual
+            .svg
+            .contains("<rect x=\"-20\" y=\"-5.5\" width=\"50\" height=\"40.25\""));
+
+        let content = |svg: &str| svg[postprocess::root_tag(svg).unwrap().end..].to_string();
+
+        assert!(content(&actual.svg).ends_with(&content(&expected.svg)));
+        assert_eq!(
+            postprocess::root_attribute(&actual.svg, "class"),
+            Some("pikchr")
+        );
 
-        Ok(err)
+        Ok(())
     }
-}
 
-impl From<NulError> for PiktError {
-    fn from(err: NulError) -> Self {
-        Self {
-            line: 0,
-            column: 0,
-            reason: PiktErrorReason::IncompatibleInput(err),
+    #[test]
+    fn view_box_invalid() {
+        for (width, height) in [(0.0, 10.0), (10.0, -1.0), (f64::NAN, 10.0)] {
+            let mut builder = OptionsBuilder::default();
+            builder.view_box(0.0, 0.0, width, height);
+
+            assert!(matches!(
+                builder.try_build().unwrap_err().reason(),
+                PiktErrorReason::InvalidViewBox(_)
+            ));
         }
+
+        let mut builder = OptionsBuilder::default();
+        builder.view_box(f64::INFINITY, 0.0, 1.0, 1.0);
+
+        assert!(builder.try_build().is_err());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn render_full_dimensions() -> Result<()> {
+        let actual = render_full("box")?;
+        let (_, _, width, height) = actual.view_box().unwrap();
+
+        assert_eq!(
+            (actual.width, actual.height),
+            (width.round() as u32, height.round() as u32)
+        );
+        assert_eq!(actual, render("box")?);
+
+        let mut builder = OptionsBuilder::default();
+        builder.dark_mode();
+
+        assert_eq!(
+            render_full_with("box", builder.clone().build())?,
+            render_with("box", builder.build())?
+        );
+
+        Ok(())
+    }
 
     #[test]
-    fn simple_box() -> Result<(), PiktError> {
-        let source = "box \"pikchr\"";
-        let expected = "<svg xmlns='http://www.w3.org/2000/svg' class=\"pikchr\" viewBox=\"0 0 112.32 76.32\">\n<path d=\"M2,74L110,74L110,2L2,2Z\"  style=\"fill:none;stroke-width:2.16;stroke:rgb(0,0,0);\" />\n<text x=\"56\" y=\"38\" text-anchor=\"middle\" fill=\"rgb(0,0,0)\" dominant-baseline=\"central\">pikchr</text>\n</svg>\n";
+    fn render_to_writes_same_svg() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder.title("diagram").minify();
 
-        let actual = render(source)?;
+        for options in [Options::default(), builder.build()] {
+            let mut svg = Vec::new();
+            let dimensions = render_to("\u{feff}box\r\narrow", options.clone(), &mut svg)?;
+            let expected = render_with("box\narrow", options)?;
 
-        assert_eq!(&actual, expected);
+            assert_eq!(svg, expected.svg.as_bytes());
+            assert_eq!(dimensions, (expected.width, expected.height));
+        }
 
         Ok(())
     }
 
     #[test]
-    fn input_with_nul() {
-        let source = "box \"pikchr\"\0";
+    fn render_to_applies_each_option() -> Result<()> {
+        let setters: Vec<fn(&mut OptionsBuilder)> = vec![
+            |b| {
+                b.dark_mode();
+            },
+            |b| {
+                b.width(300).height(200);
+            },
+            |b| {
+                b.class("a b");
+            },
+            |b| {
+                b.svg_attribute("role", "figure");
+            },
+            |b| {
+                b.data_attribute("data-x", "1");
+            },
+            |b| {
+                b.title("t").description("d");
+            },
+            |b| {
+                b.aria_label("l");
+            },
+            |b| {
+                b.css_variable("--fg", "red");
+            },
+            |b| {
+                b.inject_css("path { fill: red }");
+            },
+            |b| {
+                b.minify();
+            },
+            |b| {
+                b.pretty_print(2);
+            },
+            |b| {
+                b.inject_dimensions();
+            },
+            |b| {
+                b.annotate_elements();
+            },
+            |b| {
+                b.standalone(false);
+            },
+            |b| {
+                b.background_color("white");
+            },
+            |b| {
+                b.id_prefix("d");
+            },
+            |b| {
+                b.namespace("n");
+            },
+            |b| {
+                b.view_box(0.0, 0.0, 10.0, 10.0);
+            },
+        ];
 
-        let actual = render(source);
+        for set in setters {
+            let mut builder = OptionsBuilder::default();
+            set(&mut builder);
+            let options = builder.try_build()?;
+            let mut svg = Vec::new();
+            render_to("box \"a\"\narrow", options.clone(), &mut svg)?;
 
-        assert!(actual.is_err(), "expected a nul pointer error");
+            assert_eq!(
+                svg,
+                render_with("box \"a\"\narrow", options)?.svg.as_bytes()
+            );
+        }
+
+        Ok(())
     }
 
     #[test]
-    fn malformed_input() {
-        let source = "box 'pikchr'";
+    fn render_to_writes_nothing_on_error() {
+        let mut svg = Vec::new();
+        let err = render_to("circ \"1\"", Options::default(), &mut svg).unwrap_err();
 
-        let actual = render(source);
+        assert_eq!(err, render("circ \"1\"").unwrap_err());
+        assert!(svg.is_empty());
+    }
+
+    #[test]
+    fn render_to_checks_output_size() {
+        let mut builder = OptionsBuilder::default();
+        builder.max_output_bytes(10);
+        let mut svg = Vec::new();
+        let err = render_to("box", builder.build(), &mut svg).unwrap_err();
+
+        assert!(matches!(
+            err.reason(),
+            PiktErrorReason::OutputTooLarge { limit: 10, .. }
+        ));
+        assert!(svg.is_empty());
+    }
+
+    #[test]
+    fn all_from_str_parses_each_block() {
+        let text = "/*    1 */  box\n\
+                    /*    2 */  circ \"1\"\n                 ^^^\nERROR: syntax error\n\
+                    /*    3 */  box color nosuch\n                       ^^^^^^\n\
+                    ERROR: no such variable\nCalled from:\n/*    5 */  m\n            ^\n";
+        let errors = PiktError::all_from_str(text);
 
         assert_eq!(
-            actual.expect_err("expected unknown token"),
-            PiktError {
-                line: 1,
-                column: 5,
-                reason: PiktErrorReason::TokenUnknown,
-            }
+            errors
+                .iter()
+                .map(|err| (err.line(), err.column(), err.reason()))
+                .collect::<Vec<_>>(),
+            vec![
+                (2, 9, &PiktErrorReason::SyntaxError),
+                (3, 18, &PiktErrorReason::UnknownVariable),
+            ]
         );
+        assert!(errors.iter().all(|err| err.raw_message() == text));
+        assert_eq!(PiktError::from_str(text).unwrap(), errors[1]);
     }
 
     #[test]
-    fn division_by_zero() {
-        let source = r#"box "pikchr"
-        arrow from first box to (0/0, 0)
-        "#;
+    fn all_from_str_without_error_line() {
+        let errors = PiktError::all_from_str("something went wrong\n");
 
-        let actual = render(source);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].reason(),
+            &PiktErrorReason::Other("something went wrong".to_string())
+        );
+        assert_eq!((errors[0].line(), errors[0].column()), (0, 0));
+    }
 
+    #[test]
+    fn render_with_all_errors_reports_errors() {
         assert_eq!(
-            actual.expect_err("expected div by zero err"),
-            PiktError {
-                line: 2,
-                column: 36,
-                reason: PiktErrorReason::DivisionByZero,
-            }
+            render_with_all_errors("box", Options::default()).unwrap(),
+            render("box").unwrap().svg
+        );
+        assert_eq!(
+            render_with_all_errors("box\ncirc \"1\"", Options::default()).unwrap_err(),
+            vec![render("box\ncirc \"1\"").unwrap_err()]
+        );
+        assert_eq!(
+            render_with_all_errors("box\0", Options::default()).unwrap_err(),
+            vec![render("box\0").unwrap_err()]
         );
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn syntax_error() {
-        let source = r#"circ "1""#;
+    fn serde_error_roundtrip() {
+        let reasons = unit_reasons()
+            .into_iter()
+            .chain(data_reasons())
+            .filter(|reason| {
+                !matches!(
+                    reason,
+                    PiktErrorReason::IncompatibleInput(_)
+                        | PiktErrorReason::Io(_)
+                        | PiktErrorReason::InvalidUtf8(_)
+                )
+            });
 
-        let actual = render(source);
+        for reason in reasons {
+            let err = PiktError {
+                line: 2,
+                column: 3,
+                reason,
+                raw_message: "raw".to_string(),
+            };
+            let json = serde_json::to_string(&err).unwrap();
+            let actual: PiktError = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(actual, err, "{}", json);
+            assert_eq!(actual.raw_message(), "raw");
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_error_format() {
+        use serde_json::json;
+
+        let err = render("circ \"1\"").unwrap_err();
 
         assert_eq!(
-            actual.expect_err("expected syntax error"),
-            PiktError {
-                line: 1,
-                column: 8,
-                reason: PiktErrorReason::SyntaxError,
-            }
+            serde_json::to_value(&err).unwrap(),
+            json!({
+                "line": err.line(),
+                "column": err.column(),
+                "reason": { "type": "SyntaxError" },
+                "raw_message": err.raw_message(),
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(PiktErrorReason::InvalidColor("x".to_string())).unwrap(),
+            json!({ "type": "InvalidColor", "value": "x" })
+        );
+        assert_eq!(
+            serde_json::to_value(PiktErrorReason::InputTooLarge {
+                actual: 2,
+                limit: 1
+            })
+            .unwrap(),
+            json!({ "type": "InputTooLarge", "actual": 2, "limit": 1 })
         );
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn unknown_object() {
-        let source = r#"arrow from A to B"#;
+    fn serde_serialize_only_reasons() {
+        use serde_json::json;
 
-        let actual = render(source);
+        let nul = PiktErrorReason::IncompatibleInput(CString::new("\0").unwrap_err());
+        let io = PiktErrorReason::Io(std::io::Error::other("gone"));
 
         assert_eq!(
-            actual.expect_err("expected unknown object"),
-            PiktError {
-                line: 1,
-                column: 12,
-                reason: PiktErrorReason::UnknownObject,
-            }
+            serde_json::to_value(&nul).unwrap(),
+            json!({ "type": "IncompatibleInput" })
+        );
+        assert_eq!(
+            serde_json::to_value(&io).unwrap(),
+            json!({ "type": "Io", "message": "gone" })
+        );
+        assert_eq!(
+            serde_json::to_value(PiktErrorReason::InvalidUtf8(utf8_error())).unwrap(),
+            json!({ "type": "InvalidUtf8", "message": utf8_error().to_string() })
+        );
+        assert!(
+            serde_json::from_value::<PiktErrorReason>(json!({ "type": "IncompatibleInput" }))
+                .is_err()
         );
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn box_dark_mode() -> Result<(), PiktError> {
-        let source = "box \"pikchr\"";
-        let expected = "<svg xmlns='http://www.w3.org/2000/svg' class=\"pikchr\" viewBox=\"0 0 112.32 76.32\">\n<path d=\"M2,74L110,74L110,2L2,2Z\"  style=\"fill:none;stroke-width:2.16;stroke:rgb(255,255,255);\" />\n<text x=\"56\" y=\"38\" text-anchor=\"middle\" fill=\"rgb(255,255,255)\" dominant-baseline=\"central\">pikchr</text>\n</svg>\n";
-        let mut flags = Flags::default();
-        flags.insert(Flags::DARK_MODE);
+    fn serde_options_roundtrip() -> Result<()> {
+        let mut builder = OptionsBuilder::default();
+        builder
+            .flags(Flags::DARK_MODE)
+            .width(300)
+            .classes(&["a", "b"])
+            .title("diagram")
+            .css_variable("--fg", "red")
+            .view_box(0.0, 0.0, 10.0, 10.0)
+            .max_complexity(ComplexityClass::Moderate)
+            .output_format(OutputFormat::Svg);
+        let options = builder.clone().try_build()?;
+
+        let json = serde_json::to_string(&options).unwrap();
+        assert!(json.contains("\"flags\":\"DARK_MODE\""));
+        assert_eq!(serde_json::from_str::<Options>(&json).unwrap(), options);
+
+        let json = serde_json::to_string(&builder).unwrap();
+        let actual: OptionsBuilder = serde_json::from_str(&json).unwrap();
+        assert_eq!(actual.try_build()?, options);
 
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_options_defaults_and_validation() -> Result<()> {
+        let options: Options = serde_json::from_str("{\"width\": 300}").unwrap();
         let mut builder = OptionsBuilder::default();
-        builder.flags(flags);
-        let options = builder.build();
+        builder.width(300);
 
-        let actual = render_with(source, options)?;
+        assert_eq!(options, builder.try_build()?);
+        assert!(serde_json::from_str::<Options>("{\"class\": \"a\\u0000\"}")
+            .unwrap_err()
+            .to_string()
+            .contains("incompatible input"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn namespaces_keep_ids_unique() -> Result<()> {
+        let source = "box \"a\"; arrow; circle \"b\"";
+        let mut svg = String::new();
 
-        assert_eq!(&actual, expected);
+        for namespace in ["first", "second"] {
+            let mut builder = OptionsBuilder::default();
+            builder.id_prefix("d").title("t").namespace(namespace);
+            svg.push_str(&render_with(source, builder.build())?.svg);
+        }
+
+        let ids: Vec<&str> = svg
+            .split(" id=\"")
+            .skip(1)
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
+
+        assert!(ids.len() > 2);
+        assert_eq!(unique.len(), ids.len());
+        assert!(ids.contains(&"first-d-1") && ids.contains(&"second-d-1"));
+        assert!(svg.contains("aria-labelledby=\"second-pikchr-title\""));
 
         Ok(())
     }
+
+    /// The inputs are leaked so they can be borrowed for the whole test.
+    fn batch_inputs() -> Vec<(&'static str, Options)> {
+        let mut builder = OptionsBuilder::default();
+        builder.flags(Flags::DARK_MODE);
+        let dark = builder.build();
+
+        (0..50)
+            .map(|n| {
+                let (input, options) = match n % 3 {
+                    0 => (format!("box \"{}\"", n), Options::default()),
+                    1 => (format!("circle \"{}\"", n), dark.clone()),
+                    _ => (format!("box '{}'", n), Options::default()),
+                };
+                let input: &'static str = Box::leak(input.into_boxed_str());
+
+                (input, options)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn render_all_sequential_maps_render_with() {
+        let pairs = batch_inputs();
+        let actual = render_all_sequential(&pairs);
+
+        assert_eq!(actual.len(), pairs.len());
+
+        for ((input, options), result) in pairs.iter().zip(actual) {
+            assert_eq!(
+                result,
+                render_with(input, options.clone()).map(|output| output.svg)
+            );
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn render_all_matches_sequential() {
+        let pairs = batch_inputs();
+
+        assert_eq!(render_all(&pairs), render_all_sequential(&pairs));
+    }
+
+    #[test]
+    fn build_from_chained_temporary() {
+        let options = OptionsBuilder::default()
+            .flags(Flags::DARK_MODE)
+            .classes(&["foo"])
+            .build();
+
+        assert_eq!(options.flags(), Flags::DARK_MODE);
+        assert_eq!(options.class(), "pikchr foo");
+        assert!(OptionsBuilder::default().width(10).try_build().is_ok());
+    }
+}
+
+/// Tests for the code that doesn't call pikchr, which Miri cannot run. Run them with
+/// `cargo miri-test`, see `CONTRIBUTING.md`.
+#[cfg(miri)]
+mod miri_tests {
+    use super::*;
+
+    fn raw_error(message: &str) -> String {
+        format!(
+            "/*    1 */  box\n/*    2 */  circle 'x'\n                   ^\nERROR: {}\n",
+            message
+        )
+    }
+
+    #[test]
+    fn from_str_known_messages() {
+        use PiktErrorReason::*;
+
+        let cases = [
+            ("division by zero", DivisionByZero),
+            ("syntax error", SyntaxError),
+            ("arc geometry error", ArcGeometryError),
+            ("unknown object type", UnknownObjectType),
+            ("no such object", UnknownObject),
+            ("value is already set", ValueAlreadySet),
+            (
+                "value already fixed by prior constraints",
+                ValueAlreadyFixed,
+            ),
+            (
+                "use with line-oriented objects only",
+                OnlyWithLineOrientedObject,
+            ),
+            ("no prior path points", NoPriorPathPoints),
+            ("too many path elements", NoPriorPathPoints),
+            ("headings should be between 0 and 360", HeadingOutOfBounds),
+            ("use \"at\" to position this object", MissingAt),
+            (
+                "use \"from\" and \"to\" to position this object",
+                MissingFromTo,
+            ),
+            ("polygon is closed", ClosedPolygon),
+            (
+                "need at least 3 vertexes in order to close the polygon",
+                TooFewVertexes,
+            ),
+            ("line start location already fixed", StartLineAlreadyFixed),
+            ("location fixed by prior \"at\"", PositionAlreadyFixedByAt),
+            ("too many text terms", AttributeTooManyTerms),
+            ("no text to fit to", AttributeMissingText),
+            ("not a known color name", UnknownColorName),
+            ("no such variable", UnknownVariable),
+            ("value too big - max '1000th'", OrdinalOutOfBounds),
+            ("no prior objects of the same type", MissingPriorObjectType),
+            ("object is not a line", NotALine),
+            ("no such vertex", VertexUnknown),
+            ("sqrt of negative value", NegativeSqrt),
+            ("too many macro arguments - max 9", MacroTooManyArguments),
+            (
+                "unterminated macro argument list",
+                MacroUnterminatedArgumentList,
+            ),
+            ("token is too long - max length 50000 bytes", TokenTooLong),
+            ("unrecognized token", TokenUnknown),
+            ("macros nested too deep", MacroTooDeep),
+            ("recursive macro definition", MacroRecursive),
+        ];
+
+        for (message, expected) in cases {
+            let actual = PiktError::from_str(&raw_error(message)).unwrap();
+
+            assert_eq!(actual.reason, expected, "{}", message);
+            assert_eq!((actual.line, actual.column), (2, 9));
+        }
+    }
+
+    #[test]
+    fn from_str_special_messages() {
+        let unknown = PiktError::from_str(&raw_error("something new")).unwrap();
+        let overflow = PiktError::from_str("parser stack overflow").unwrap();
+        let oom = PiktError::from_str("\nOut of memory\n").unwrap();
+
+        assert_eq!(
+            unknown.reason,
+            PiktErrorReason::Other("something new".to_string())
+        );
+        assert_eq!(overflow.reason, PiktErrorReason::ParserStackOverflow);
+        assert_eq!(oom.reason, PiktErrorReason::OutOfMemory);
+    }
+
+    #[test]
+    fn builder_setters_and_accessors() {
+        let mut builder = OptionsBuilder::default();
+        builder.flags(Flags::DARK_MODE);
+        builder.width(300);
+        builder.height(150);
+        builder
+            .class("diagram")
+            .classes(&["wide"])
+            .svg_attribute("role", "img")
+            .title("Title")
+            .description("Description")
+            .css_variable("--stroke", "#333")
+            .minify()
+            .pretty_print(2)
+            .max_output_bytes(1024)
+            .inject_dimensions()
+            .output_format(OutputFormat::Svg);
+        let options = builder.build();
+
+        assert_eq!(options.flags(), Flags::DARK_MODE);
+        assert_eq!(options.width(), 300);
+        assert_eq!(options.height(), 150);
+        assert_eq!(options.class(), "diagram wide");
+        assert_eq!(
+            options.svg_attributes(),
+            &[("role".to_string(), "img".to_string())]
+        );
+        assert_eq!(options.title(), Some("Title"));
+        assert_eq!(options.description(), Some("Description"));
+        assert_eq!(
+            options.css_variables(),
+            &[("--stroke".to_string(), "#333".to_string())]
+        );
+        assert!(options.minify());
+        assert_eq!(options.pretty_print(), Some(2));
+        assert_eq!(options.max_output_bytes(), Some(1024));
+        assert!(options.inject_dimensions());
+        assert_eq!(options.output_format(), OutputFormat::Svg);
+    }
+
+    #[test]
+    fn builder_validation() {
+        let mut builder = OptionsBuilder::default();
+        builder.class_prefix("bad prefix");
+
+        assert!(matches!(
+            builder.try_build().unwrap_err().reason,
+            PiktErrorReason::InvalidClassPrefix(_)
+        ));
+    }
+
+    #[test]
+    fn flags_operations() {
+        let flags = Flags::empty() | Flags::DARK_MODE;
+
+        assert!(flags.contains(Flags::DARK_MODE));
+        assert_eq!(flags & Flags::DARK_MODE, Flags::DARK_MODE);
+        assert_eq!(flags - Flags::DARK_MODE, Flags::empty());
+        assert_eq!(Flags::from_bits(flags.bits()), Some(Flags::DARK_MODE));
+        assert_eq!(Flags::default(), Flags::empty());
+    }
+
+    #[test]
+    fn display() {
+        let err = PiktError {
+            line: 1,
+            column: 5,
+            reason: PiktErrorReason::TokenUnknown,
+            raw_message: String::new(),
+        };
+
+        assert_eq!(err.to_string(), "line 1, column 5: unknown token");
+        assert_eq!(
+            PiktErrorReason::InvalidScale(0.0).to_string(),
+            "invalid scale: 0"
+        );
+        assert_eq!(
+            PiktErrorReason::OutputTooLarge {
+                actual: 2,
+                limit: 1
+            }
+            .to_string(),
+            "output too large: 2 bytes, limit is 1"
+        );
+    }
 }
@@ -0,0 +1,127 @@
+//! Conversion of errors into Language Server Protocol diagnostics.
+
+use crate::PiktError;
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
+
+impl PiktError {
+    /// Converts the error into an LSP diagnostic for the given source.
+    ///
+    /// The range starts at the error location and ends at the start of the next token, or the
+    /// end of the line if there is none. Positions are 0-indexed and columns are counted in
+    /// characters.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::render;
+    ///
+    /// let source = "box 'pikchr'";
+    /// let err = render(source).unwrap_err();
+    /// let diagnostic = err.to_lsp_diagnostic(source);
+    ///
+    /// assert_eq!(diagnostic.range.start.character, 4);
+    /// ```
+    pub fn to_lsp_diagnostic(&self, source: &str) -> Diagnostic {
+        let line = self.line.saturating_sub(1);
+        let character = self.column.saturating_sub(1);
+        let end_character = source
+            .lines()
+            .nth(line)
+            .map(|content| next_token_start(content, character))
+            .unwrap_or(character);
+
+        Diagnostic {
+            range: Range {
+                start: Position {
+                    line: line as u32,
+                    character: character as u32,
+                },
+                end: Position {
+                    line: line as u32,
+                    character: end_character as u32,
+                },
+            },
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String(format!(
+                "PIKCHR{:04}",
+                self.reason.error_code()
+            ))),
+            source: Some("pikchr".to_string()),
+            message: self.to_string(),
+            ..Diagnostic::default()
+        }
+    }
+}
+
+/// Finds the character index of the token following the one at `start`.
+fn next_token_start(line: &str, start: usize) -> usize {
+    let mut chars = line.chars().enumerate().skip(start).peekable();
+
+    while chars.next_if(|(_, c)| !c.is_whitespace()).is_some() {}
+    while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+
+    chars
+        .peek()
+        .map(|(index, _)| *index)
+        .unwrap_or_else(|| line.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render;
+
+    #[test]
+    fn diagnostic_range() {
+        let source = "box 'pikchr' fill red";
+        let err = render(source).unwrap_err();
+
+        let diagnostic = err.to_lsp_diagnostic(source);
+
+        assert_eq!(diagnostic.range.start.line, 0);
+        assert_eq!(diagnostic.range.start.character, 4);
+        assert_eq!(diagnostic.range.end.line, 0);
+        assert_eq!(diagnostic.range.end.character, 13);
+    }
+
+    #[test]
+    fn diagnostic_range_on_second_line() {
+        let source = "box \"pikchr\"\narrow from first box to (0/0, 0)";
+        let err = render(source).unwrap_err();
+
+        let diagnostic = err.to_lsp_diagnostic(source);
+
+        assert_eq!(diagnostic.range.start.line, 1);
+        assert_eq!(diagnostic.range.start.character, 27);
+        assert_eq!(diagnostic.range.end.character, 30);
+    }
+
+    #[test]
+    fn diagnostic_range_at_end_of_line() {
+        let source = "circ \"1\"";
+        let err = render(source).unwrap_err();
+
+        let diagnostic = err.to_lsp_diagnostic(source);
+
+        assert_eq!(diagnostic.range.start.character, 7);
+        assert_eq!(diagnostic.range.end.character, 8);
+    }
+
+    #[test]
+    fn diagnostic_metadata() {
+        let source = "circ \"1\"";
+        let err = render(source).unwrap_err();
+
+        let diagnostic = err.to_lsp_diagnostic(source);
+
+        assert_eq!(diagnostic.message, err.to_string());
+        assert_eq!(diagnostic.source.as_deref(), Some("pikchr"));
+        assert_eq!(
+            diagnostic.severity,
+            Some(lsp_types::DiagnosticSeverity::ERROR)
+        );
+        assert_eq!(
+            diagnostic.code,
+            Some(lsp_types::NumberOrString::String("PIKCHR1001".to_string()))
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! Extraction and checks of the macros defined in a pikchr source, e.g. to list the macros of
+//! a shared library of definitions.
+
+use crate::{PiktError, PiktErrorReason, Result};
+use std::collections::HashSet;
+
+/// A `define NAME { body }` statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroDefinition {
+    pub name: String,
+    /// The number of arguments in the `(a, b)` list after the name or, without a list, the
+    /// highest `$N` used in the body.
+    pub arg_count: usize,
+    /// The text between the braces, trimmed.
+    pub body: String,
+    /// The 1-based line of the `define` keyword.
+    pub source_line: usize,
+}
+
+/// Extracts the macro definitions, in source order.
+///
+/// This is a scan of the source rather than a parse: strings and comments are skipped and
+/// malformed definitions are ignored. Bodies can span many lines and contain nested braces.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::extract_macros;
+///
+/// let defs = extract_macros("define labeled { box $1 }\nlabeled(\"a\")");
+///
+/// assert_eq!(defs[0].name, "labeled");
+/// assert_eq!(defs[0].arg_count, 1);
+/// assert_eq!(defs[0].body, "box $1");
+/// ```
+pub fn extract_macros(input: &str) -> Vec<MacroDefinition> {
+    let bytes = input.as_bytes();
+    let mut defs = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => {
+                line += 1;
+                i += 1;
+            }
+            b'"' => i = skip_string(bytes, i, &mut line),
+            b'#' => i = skip_line(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'/') => i = skip_line(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_block_comment(bytes, i, &mut line),
+            b'd' if input[i..].starts_with("define")
+                && (i == 0 || !is_identifier_byte(bytes[i - 1]))
+                && !bytes.get(i + 6).copied().is_some_and(is_identifier_byte) =>
+            {
+                let source_line = line;
+                let mut lines = 0;
+
+                match parse_definition(input, i + 6, &mut lines) {
+                    Some((mut def, end)) => {
+                        def.source_line = source_line;
+                        defs.push(def);
+                        line += lines;
+                        i = end;
+                    }
+                    None => i += 6,
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    defs
+}
+
+/// Checks that the macros are defined once, don't use themselves and only use the arguments
+/// they declare.
+///
+/// ## Errors
+///
+/// Fails on the first invalid definition, at its line. The reason is
+/// [`PiktErrorReason::DuplicateMacro`], [`PiktErrorReason::MacroRecursive`] or
+/// [`PiktErrorReason::MacroArgumentMismatch`].
+pub fn validate_macros(defs: &[MacroDefinition]) -> Result<()> {
+    let mut names = HashSet::new();
+
+    for def in defs {
+        let fail = |reason| PiktError {
+            line: def.source_line,
+            column: 1,
+            reason,
+            raw_message: String::new(),
+        };
+
+        if !names.insert(def.name.as_str()) {
+            return Err(fail(PiktErrorReason::DuplicateMacro(def.name.clone())));
+        }
+
+        if identifiers(&def.body).any(|word| word == def.name) {
+            return Err(fail(PiktErrorReason::MacroRecursive));
+        }
+
+        let used = max_argument(&def.body);
+
+        if used > def.arg_count {
+            return Err(fail(PiktErrorReason::MacroArgumentMismatch {
+                name: def.name.clone(),
+                declared: def.arg_count,
+                used,
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the name, arguments and body after a `define` keyword ending at `start`. Returns the
+/// definition and the index after the closing brace, counting the newlines in `lines`.
+fn parse_definition(
+    input: &str,
+    start: usize,
+    lines: &mut usize,
+) -> Option<(MacroDefinition, usize)> {
+    let bytes = input.as_bytes();
+    let mut i = skip_whitespace(bytes, start, lines);
+
+    let name_start = i;
+    while i < bytes.len() && (is_identifier_byte(bytes[i]) || (i == name_start && bytes[i] == b'$'))
+    {
+        i += 1;
+    }
+
+    if i == name_start {
+        return None;
+    }
+
+    let name = &input[name_start..i];
+    i = skip_whitespace(bytes, i, lines);
+
+    let mut declared = None;
+
+    if bytes.get(i) == Some(&b'(') {
+        let end = i + input[i..].find(')')?;
+        let args = &input[i + 1..end];
+        *lines += args.matches('\n').count();
+        declared = Some(args.split(',').filter(|arg| !arg.trim().is_empty()).count());
+        i = skip_whitespace(bytes, end + 1, lines);
+    }
+
+    if bytes.get(i) != Some(&b'{') {
+        return None;
+    }
+
+    let body_start = i + 1;
+    let mut depth = 1;
+    i += 1;
+
+    while depth > 0 {
+        match bytes.get(i)? {
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            b'\n' => *lines += 1,
+            b'"' => {
+                i = skip_string(bytes, i, lines);
+                continue;
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    let body = input[body_start..i - 1].trim().to_string();
+
+    Some((
+        MacroDefinition {
+            name: name.to_string(),
+            arg_count: declared.unwrap_or_else(|| max_argument(&body)),
+            body,
+            source_line: 0,
+        },
+        i,
+    ))
+}
+
+/// The highest `$N` argument used in the body, `0` if none.
+fn max_argument(body: &str) -> usize {
+    body.split('$')
+        .skip(1)
+        .filter_map(|rest| rest.chars().next()?.to_digit(10))
+        .max()
+        .unwrap_or(0) as usize
+}
+
+fn identifiers(body: &str) -> impl Iterator<Item = &str> {
+    body.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+}
+
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+fn skip_whitespace(bytes: &[u8], mut i: usize, lines: &mut usize) -> usize {
+    while let Some(byte) = bytes.get(i).filter(|byte| byte.is_ascii_whitespace()) {
+        if *byte == b'\n' {
+            *lines += 1;
+        }
+        i += 1;
+    }
+
+    i
+}
+
+/// Returns the index after the string starting at `start`.
+fn skip_string(bytes: &[u8], start: usize, lines: &mut usize) -> usize {
+    let mut i = start + 1;
+
+    while let Some(byte) = bytes.get(i) {
+        match byte {
+            b'\\' => i += 1,
+            b'"' => return i + 1,
+            b'\n' => *lines += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    bytes.len()
+}
+
+/// Returns the index of the newline ending the comment starting at `start`.
+fn skip_line(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(bytes.len(), |end| start + end)
+}
+
+fn skip_block_comment(bytes: &[u8], start: usize, lines: &mut usize) -> usize {
+    let mut i = start + 2;
+
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"*/") {
+            return i + 2;
+        }
+        if bytes[i] == b'\n' {
+            *lines += 1;
+        }
+        i += 1;
+    }
+
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn def(name: &str, arg_count: usize, body: &str, source_line: usize) -> MacroDefinition {
+        MacroDefinition {
+            name: name.to_string(),
+            arg_count,
+            body: body.to_string(),
+            source_line,
+        }
+    }
+
+    #[test]
+    fn extracts_definitions() {
+        let input = r#"# define commented { box }
+define labeled { box $1 fit }
+box "define quoted { x }"
+define pair(a, b) {
+  box $1
+  box $2 { }
+}
+labeled("a"); pair("b", "c")
+"#;
+
+        assert_eq!(
+            extract_macros(input),
+            vec![
+                def("labeled", 1, "box $1 fit", 2),
+                def("pair", 2, "box $1\n  box $2 { }", 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_malformed_definitions() {
+        assert_eq!(extract_macros("define { box }\nundefined x { y }"), vec![]);
+        assert_eq!(extract_macros("define open { box"), vec![]);
+    }
+
+    #[test]
+    fn valid_definitions() {
+        let defs = extract_macros("define a { box $1 }\ndefine b(x, y) { a($1); circle $2 }");
+
+        assert_eq!(validate_macros(&defs), Ok(()));
+    }
+
+    #[test]
+    fn duplicate_name() {
+        let defs = extract_macros("define a { box }\n\ndefine a { circle }");
+        let err = validate_macros(&defs).unwrap_err();
+
+        assert_eq!(
+            err.reason(),
+            &PiktErrorReason::DuplicateMacro("a".to_string())
+        );
+        assert_eq!(err.line(), 3);
+    }
+
+    #[test]
+    fn recursive_definition() {
+        let defs = extract_macros("define node { box; node }");
+
+        assert_eq!(
+            validate_macros(&defs).unwrap_err().reason(),
+            &PiktErrorReason::MacroRecursive
+        );
+    }
+
+    #[test]
+    fn argument_count_mismatch() {
+        let defs = extract_macros("define a(x) { box $1; box $2 }");
+
+        assert_eq!(
+            validate_macros(&defs).unwrap_err().reason(),
+            &PiktErrorReason::MacroArgumentMismatch {
+                name: "a".to_string(),
+                declared: 1,
+                used: 2,
+            }
+        );
+    }
+}
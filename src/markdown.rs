@@ -0,0 +1,398 @@
+//! Support for pikchr diagrams embedded in Markdown.
+//!
+//! Diagrams are fenced code blocks with `pikchr` as the language identifier:
+//!
+//! ````markdown
+//! ```pikchr
+//! box "pikchr"
+//! ```
+//! ````
+//!
+//! Both backtick and tilde fences are recognised and the language identifier is
+//! case-insensitive.
+
+use crate::{render_with, replace_blocks, Options, PikchrBlock, PiktError, Result};
+use std::borrow::Cow;
+
+/// Finds the pikchr fenced code blocks in the given Markdown text.
+///
+/// A fence left open runs until the end of the text as per CommonMark.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::find_pikchr_blocks;
+///
+/// let text = "# Title\n\n```pikchr\nbox\n```\n";
+/// let blocks: Vec<_> = find_pikchr_blocks(text).collect();
+///
+/// assert_eq!(blocks.len(), 1);
+/// assert_eq!(blocks[0].source, "box\n");
+/// assert_eq!(&text[blocks[0].byte_range.clone()], "```pikchr\nbox\n```");
+/// ```
+pub fn find_pikchr_blocks(text: &str) -> impl Iterator<Item = PikchrBlock<'_>> {
+    Blocks { text, offset: 0 }
+}
+
+/// Replaces every pikchr block in the given Markdown text with its rendered SVG.
+///
+/// A block that fails to render is replaced with a `<pre class="pikchr-error">` element
+/// containing the error message so the rest of the blocks are still rendered.
+pub fn replace_pikchr_blocks(text: &str, options: Options) -> Result<String> {
+    Ok(replace_blocks(
+        text,
+        find_pikchr_blocks(text),
+        &options,
+        ToString::to_string,
+    ))
+}
+
+/// Replaces every pikchr block in the given Markdown text with its rendered SVG, failing if any
+/// block fails.
+///
+/// Unlike [`replace_pikchr_blocks`], the SVG is inserted as is, without a trailing newline, and
+/// the rest of the text is kept byte for byte.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::markdown::process_markdown;
+/// use pikt::Options;
+///
+/// let text = "# Title\n\n```pikchr\nbox\n```\n";
+/// let actual = process_markdown(text, Options::default()).unwrap();
+///
+/// assert!(actual.starts_with("# Title\n\n<svg"));
+/// assert!(actual.ends_with("</svg>\n"));
+/// ```
+///
+/// ## Errors
+///
+/// Every block is rendered and the errors of all the failing blocks are returned, in document
+/// order. Their lines are the ones in `input` rather than in the block.
+pub fn process_markdown(input: &str, options: Options) -> Result<String, Vec<PiktError>> {
+    let mut output = String::with_capacity(input.len());
+    let mut errors = Vec::new();
+    let mut last = 0;
+
+    for block in find_pikchr_blocks(input) {
+        output.push_str(&input[last..block.byte_range.start]);
+
+        match render_with(&block.source, options.clone()) {
+            Ok(rendered) => output.push_str(rendered.svg.trim_end()),
+            Err(err) => {
+                // The markup starts on the line after the opening fence.
+                let offset = input[..block.byte_range.start].matches('\n').count() + 1;
+                errors.push(err.with_offset(offset, 0));
+            }
+        }
+
+        last = block.byte_range.end;
+    }
+
+    output.push_str(&input[last..]);
+
+    if errors.is_empty() {
+        Ok(output)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Renders the pikchr blocks of the given Markdown text parsed as CommonMark, re-serialising the
+/// document to Markdown.
+///
+/// Unlike [`replace_pikchr_blocks`], every block the CommonMark parser recognises is rendered,
+/// e.g. fences in list items or block quotes, but the rest of the document is written back by
+/// the serialiser so it may be formatted differently from the original. A block that fails to
+/// render is replaced with a `<pre class="pikchr-error">` element.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::markdown::process_markdown_roundtrip;
+/// use pikt::Options;
+///
+/// let text = "> ```pikchr\n> box\n> ```";
+/// let actual = process_markdown_roundtrip(text, Options::default()).unwrap();
+///
+/// assert!(actual.contains("> <svg"));
+/// ```
+///
+/// ## Errors
+///
+/// Fails with [`crate::PiktErrorReason::Other`] if the document can't be serialised.
+#[cfg(feature = "markdown-roundtrip")]
+pub fn process_markdown_roundtrip(input: &str, options: Options) -> Result<String> {
+    use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
+
+    let mut events = Vec::new();
+    let mut source: Option<String> = None;
+
+    for event in Parser::new(input) {
+        match (&mut source, event) {
+            (None, Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))))
+                if is_pikchr_info(&info) =>
+            {
+                source = Some(String::new());
+            }
+            (Some(markup), Event::Text(text)) => markup.push_str(&text),
+            (Some(markup), Event::End(TagEnd::CodeBlock)) => {
+                let html = match crate::render_with(markup, options.clone()) {
+                    Ok(output) => output.svg,
+                    Err(err) => format!(
+                        "<pre class=\"pikchr-error\">{}</pre>\n",
+                        crate::html::escape_html(&err.to_string())
+                    ),
+                };
+                events.push(Event::Start(Tag::HtmlBlock));
+                events.push(Event::Html(html.into()));
+                events.push(Event::End(TagEnd::HtmlBlock));
+                source = None;
+            }
+            (_, event) => events.push(event),
+        }
+    }
+
+    let mut output = String::with_capacity(input.len());
+    pulldown_cmark_to_cmark::cmark(events.iter(), &mut output)
+        .map_err(|err| crate::PiktError::new(crate::PiktErrorReason::Other(err.to_string())))?;
+
+    Ok(output)
+}
+
+/// Whether the info string of a fence marks a pikchr block.
+#[cfg(any(feature = "markdown-roundtrip", feature = "cmark"))]
+pub(crate) fn is_pikchr_info(info: &str) -> bool {
+    info.split_whitespace()
+        .next()
+        .is_some_and(|lang| lang.eq_ignore_ascii_case("pikchr"))
+}
+
+struct Blocks<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+struct Fence {
+    marker: char,
+    len: usize,
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = PikchrBlock<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.offset < self.text.len() {
+            let start = self.offset;
+            let line = next_line(self.text, start);
+            self.offset += line.len();
+
+            let fence = match opening_fence(line) {
+                Some((fence, info)) => {
+                    let lang = info.split_whitespace().next().unwrap_or("");
+                    if !lang.eq_ignore_ascii_case("pikchr") {
+                        self.skip_block(&fence);
+                        continue;
+                    }
+                    fence
+                }
+                None => continue,
+            };
+
+            let content_start = self.offset;
+
+            while self.offset < self.text.len() {
+                let line_start = self.offset;
+                let line = next_line(self.text, line_start);
+                self.offset += line.len();
+
+                if is_closing_fence(line, &fence) {
+                    let end = line_start + line.trim_end_matches(&['\n', '\r'][..]).len();
+
+                    return Some(PikchrBlock {
+                        source: Cow::Borrowed(&self.text[content_start..line_start]),
+                        byte_range: start..end,
+                    });
+                }
+            }
+
+            return Some(PikchrBlock {
+                source: Cow::Borrowed(&self.text[content_start..]),
+                byte_range: start..self.text.len(),
+            });
+        }
+
+        None
+    }
+}
+
+impl<'a> Blocks<'a> {
+    /// Moves past a fenced code block that is not a pikchr block.
+    fn skip_block(&mut self, fence: &Fence) {
+        while self.offset < self.text.len() {
+            let line = next_line(self.text, self.offset);
+            self.offset += line.len();
+
+            if is_closing_fence(line, fence) {
+                break;
+            }
+        }
+    }
+}
+
+/// Returns the line starting at `offset`, line ending included.
+fn next_line(text: &str, offset: usize) -> &str {
+    let rest = &text[offset..];
+
+    match rest.find('\n') {
+        Some(index) => &rest[..=index],
+        None => rest,
+    }
+}
+
+/// Strips up to three spaces of indentation as allowed by CommonMark.
+fn strip_indent(line: &str) -> Option<&str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+
+    if indent > 3 {
+        None
+    } else {
+        Some(&line[indent..])
+    }
+}
+
+fn opening_fence(line: &str) -> Option<(Fence, &str)> {
+    let line = strip_indent(line)?;
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.len() - line.trim_start_matches(marker).len();
+
+    if len < 3 {
+        return None;
+    }
+
+    let info = line[len..].trim();
+
+    if marker == '`' && info.contains('`') {
+        return None;
+    }
+
+    Some((Fence { marker, len }, info))
+}
+
+fn is_closing_fence(line: &str, fence: &Fence) -> bool {
+    match strip_indent(line) {
+        Some(line) => {
+            let len = line.len() - line.trim_start_matches(fence.marker).len();
+            len >= fence.len && line[len..].trim().is_empty()
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_blocks() {
+        let text = "# Title\n\nSome text.\n\n```rust\nfn main() {}\n```\n";
+
+        assert_eq!(find_pikchr_blocks(text).count(), 0);
+    }
+
+    #[test]
+    fn multiple_blocks() {
+        let text = "```pikchr\nbox\n```\n\ntext\n\n```PikChr\ncircle\n```\n";
+        let blocks: Vec<_> = find_pikchr_blocks(text).collect();
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].source, "box\n");
+        assert_eq!(blocks[0].byte_range, 0..17);
+        assert_eq!(blocks[1].source, "circle\n");
+        assert_eq!(
+            &text[blocks[1].byte_range.clone()],
+            "```PikChr\ncircle\n```"
+        );
+    }
+
+    #[test]
+    fn tilde_fences() {
+        let text = "~~~~ pikchr\nbox\n```\n~~~~\n";
+        let blocks: Vec<_> = find_pikchr_blocks(text).collect();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].source, "box\n```\n");
+    }
+
+    #[test]
+    fn pikchr_inside_other_block_is_ignored() {
+        let text = "````markdown\n```pikchr\nbox\n```\n````\n";
+
+        assert_eq!(find_pikchr_blocks(text).count(), 0);
+    }
+
+    #[test]
+    fn replace_blocks() -> Result<()> {
+        let text = "before\n\n```pikchr\nbox\n```\n\nafter\n";
+        let options = Options::default();
+
+        let actual = replace_pikchr_blocks(text, options)?;
+
+        assert!(actual.starts_with("before\n\n<svg"));
+        assert!(actual.ends_with("</svg>\n\nafter\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn replace_mixed_blocks() -> Result<()> {
+        let text = "```pikchr\nbox\n```\n\n```pikchr\nbox 'x'\n```\n\n```pikchr\ncircle\n```\n";
+        let options = Options::default();
+
+        let actual = replace_pikchr_blocks(text, options)?;
+
+        assert_eq!(actual.matches("<svg").count(), 2);
+        assert!(
+            actual.contains("<pre class=\"pikchr-error\">line 1, column 5: unknown token</pre>")
+        );
+        assert!(!actual.contains("```"));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "markdown-roundtrip")]
+    #[test]
+    fn roundtrip_replaces_blocks() -> Result<()> {
+        let text = "# Title\n\nBefore.\n\n```pikchr\nbox\n```\n\nAfter.";
+        let actual = process_markdown_roundtrip(text, Options::default())?;
+        let svg = crate::render("box")?.svg;
+
+        assert_eq!(actual, format!("# Title\n\nBefore.\n\n{}\nAfter.", svg));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "markdown-roundtrip")]
+    #[test]
+    fn roundtrip_without_blocks() -> Result<()> {
+        let text = "# Title\n\nSome *text*.\n\n````rust\nlet a = 1;\n````";
+
+        assert_eq!(process_markdown_roundtrip(text, Options::default())?, text);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "markdown-roundtrip")]
+    #[test]
+    fn roundtrip_error_placeholder() -> Result<()> {
+        let text = "```pikchr\nbox 'x'\n```";
+        let actual = process_markdown_roundtrip(text, Options::default())?;
+
+        assert!(
+            actual.contains("<pre class=\"pikchr-error\">line 1, column 5: unknown token</pre>")
+        );
+
+        Ok(())
+    }
+}
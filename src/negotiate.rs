@@ -0,0 +1,174 @@
+//! Rendering to the representation requested by an HTTP client.
+
+use crate::postprocess::minify;
+use crate::{render_with, Options, Result};
+use std::fmt::Write;
+
+/// The representations supported by [`render_negotiated`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestedFormat {
+    Svg,
+    /// The SVG without comments and insignificant whitespace.
+    SvgMinified,
+    /// A `data:image/svg+xml` URI with the percent-encoded SVG.
+    DataUri,
+    /// A `{"svg": "...", "width": N, "height": N}` object.
+    Json,
+}
+
+impl RequestedFormat {
+    /// The MIME type of the representation.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            RequestedFormat::Svg | RequestedFormat::SvgMinified => "image/svg+xml; charset=utf-8",
+            RequestedFormat::DataUri => "text/plain",
+            RequestedFormat::Json => "application/json",
+        }
+    }
+}
+
+/// Renders the given pikchr markup in the given representation, returning the body and its
+/// content type.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_negotiated, Options, RequestedFormat};
+///
+/// let (body, content_type) =
+///     render_negotiated("box", RequestedFormat::DataUri, Options::default()).unwrap();
+///
+/// assert_eq!(content_type, "text/plain");
+/// assert!(body.starts_with(b"data:image/svg+xml,"));
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`].
+pub fn render_negotiated(
+    input: &str,
+    format: RequestedFormat,
+    options: Options,
+) -> Result<(Vec<u8>, &'static str)> {
+    let output = render_with(input, options)?;
+
+    let body = match format {
+        RequestedFormat::Svg => output.svg,
+        RequestedFormat::SvgMinified => minify(&output.svg),
+        RequestedFormat::DataUri => format!("data:image/svg+xml,{}", percent_encode(&output.svg)),
+        RequestedFormat::Json => format!(
+            "{{\"svg\":\"{}\",\"width\":{},\"height\":{}}}",
+            escape_json(&output.svg),
+            output.width,
+            output.height
+        ),
+    };
+
+    Ok((body.into_bytes(), format.content_type()))
+}
+
+/// Percent-encodes every byte but the unreserved characters of RFC 3986.
+fn percent_encode(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+
+    for byte in input.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            output.push(byte as char);
+        } else {
+            let _ = write!(output, "%{:02X}", byte);
+        }
+    }
+
+    output
+}
+
+/// Escapes the given text as the content of a JSON string.
+fn escape_json(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+
+    for c in input.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(output, "\\u{:04x}", c as u32);
+            }
+            c => output.push(c),
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    fn negotiate(format: RequestedFormat) -> Result<(String, &'static str)> {
+        let (body, content_type) = render_negotiated("box \"a\"", format, Options::default())?;
+
+        Ok((String::from_utf8(body).unwrap(), content_type))
+    }
+
+    #[test]
+    fn svg() -> Result<()> {
+        let (body, content_type) = negotiate(RequestedFormat::Svg)?;
+
+        assert_eq!(content_type, "image/svg+xml; charset=utf-8");
+        assert_eq!(body, render("box \"a\"")?.svg);
+
+        Ok(())
+    }
+
+    #[test]
+    fn svg_minified() -> Result<()> {
+        let (body, content_type) = negotiate(RequestedFormat::SvgMinified)?;
+
+        assert_eq!(content_type, "image/svg+xml; charset=utf-8");
+        assert!(body.starts_with("<svg"));
+        assert!(!body.contains('\n'));
+
+        Ok(())
+    }
+
+    #[test]
+    fn data_uri() -> Result<()> {
+        let (body, content_type) = negotiate(RequestedFormat::DataUri)?;
+
+        assert_eq!(content_type, "text/plain");
+        assert!(body.starts_with("data:image/svg+xml,%3Csvg%20"));
+        assert!(!body[5..].contains(['<', '"', ' ', '#', '\n']));
+
+        Ok(())
+    }
+
+    #[test]
+    fn json() -> Result<()> {
+        let expected = render("box \"a\"")?;
+        let (body, content_type) = negotiate(RequestedFormat::Json)?;
+
+        assert_eq!(content_type, "application/json");
+        assert!(body.starts_with("{\"svg\":\"<svg "));
+        assert!(body.ends_with(&format!(
+            "\",\"width\":{},\"height\":{}}}",
+            expected.width, expected.height
+        )));
+        assert!(!body.contains('\n'));
+
+        Ok(())
+    }
+
+    #[test]
+    fn escapes_json() {
+        assert_eq!(escape_json("a\"b\\c\nd\u{1}"), "a\\\"b\\\\c\\nd\\u0001");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(render_negotiated("box 'a'", RequestedFormat::Json, Options::default()).is_err());
+    }
+}
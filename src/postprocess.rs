@@ -0,0 +1,923 @@
+//! Helpers to post-process the SVG produced by pikchr.
+//!
+//! These work on the known shape of the pikchr output rather than being a general purpose XML
+//! parser. In particular, attribute values in the root element never contain `>`.
+
+use crate::html::escape_html;
+use crate::Options;
+use std::ops::Range;
+
+/// Whether [`apply`] and [`annotate_elements`] leave the SVG unchanged for `options`.
+///
+/// The options are compared with the defaults except for the ones used before post-processing,
+/// so an option missing from this list only makes the SVG go through [`apply`].
+pub(crate) fn is_identity(options: &Options) -> bool {
+    *options
+        == Options {
+            flags: options.flags,
+            width: options.width,
+            height: options.height,
+            class: options.class.clone(),
+            max_output_bytes: options.max_output_bytes,
+            max_input_bytes: options.max_input_bytes,
+            max_complexity: options.max_complexity,
+            output_format: options.output_format,
+            ..Options::default()
+        }
+}
+
+/// Applies the post-processing requested by the options to a rendered SVG.
+///
+/// The dimensions are the ones computed by pikchr.
+pub(crate) fn apply(svg: &mut String, (width, height): (u32, u32), options: &Options) {
+    // First so the elements inserted below, e.g. the title or the background, get no id.
+    if let Some(prefix) = options.id_prefix() {
+        add_element_ids(svg, &escape_html(prefix));
+    }
+
+    if let Some(view_box) = options.view_box() {
+        set_root_attribute(svg, "viewBox", &view_box.to_string());
+    }
+
+    if options.inject_dimensions() {
+        set_root_attribute(svg, "width", &width.to_string());
+        set_root_attribute(svg, "height", &height.to_string());
+    }
+
+    if !options.standalone() {
+        remove_root_attribute(svg, "xmlns");
+    }
+
+    for (name, value) in options.svg_attributes() {
+        set_root_attribute(svg, name, &escape_html(value));
+    }
+
+    for (name, value) in options.data_attributes() {
+        set_root_attribute(svg, name, &escape_html(value));
+    }
+
+    if options.title().is_some()
+        || options.description().is_some()
+        || options.aria_label().is_some()
+    {
+        set_root_attribute(svg, "role", "img");
+    }
+
+    if let (Some(label), None) = (options.aria_label(), options.title()) {
+        set_root_attribute(svg, "aria-label", &escape_html(label));
+    }
+
+    // Children are inserted first so the last one inserted ends up first.
+    if let Some(color) = options.background_color() {
+        if let Some((min_x, min_y, width, height)) = view_box(svg) {
+            insert_first_child(
+                svg,
+                &format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    format_number(min_x),
+                    format_number(min_y),
+                    format_number(width),
+                    format_number(height),
+                    color
+                ),
+            );
+        }
+    }
+
+    if let Some(description) = options.description() {
+        insert_first_child(svg, &format!("<desc>{}</desc>", escape_html(description)));
+    }
+
+    if let Some(title) = options.title() {
+        set_root_attribute(svg, "aria-labelledby", TITLE_ID);
+        insert_first_child(
+            svg,
+            &format!("<title id=\"{}\">{}</title>", TITLE_ID, escape_html(title)),
+        );
+    }
+
+    if !options.css_variables().is_empty() {
+        let declarations: Vec<String> = options
+            .css_variables()
+            .iter()
+            .map(|(name, value)| format!("{}: {};", name, value))
+            .collect();
+        insert_first_child(
+            svg,
+            &format!("<style>:root {{ {} }}</style>", declarations.join(" ")),
+        );
+    }
+
+    if let Some(css) = options.css() {
+        insert_first_child(svg, &format!("<style>{}</style>", css));
+    }
+
+    // Last so the ids added above are namespaced too.
+    if let Some(namespace) = options.namespace() {
+        namespace_ids(svg, &escape_html(namespace));
+    }
+
+    if options.minify() {
+        *svg = minify(svg);
+    }
+
+    if let Some(indent) = options.pretty_print() {
+        *svg = pretty_print(svg, indent);
+    }
+}
+
+/// Inserts a comment before each `<path>` and `<text>` element with the source line that likely
+/// generated it. See [`crate::OptionsBuilder::annotate_elements`].
+pub(crate) fn annotate_elements(svg: &mut String, source: &str) {
+    let mut lines = source.lines().enumerate().filter(|(_, line)| {
+        let line = line.trim();
+        !line.is_empty() && !line.starts_with('#') && !line.starts_with("//")
+    });
+    let mut current: Option<String> = None;
+    let mut output = String::with_capacity(svg.len());
+    let mut rest = svg.as_str();
+
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with("<path") {
+            current = lines.next().map(|(number, line)| {
+                // A comment can't contain `--`.
+                let line = line.trim().replace("--", "- -");
+                format!("<!-- pikchr line {}: {} -->\n", number + 1, line)
+            });
+        }
+
+        if rest.starts_with("<path") || rest.starts_with("<text") {
+            if let Some(comment) = &current {
+                output.push_str(comment);
+            }
+        }
+
+        output.push('<');
+        rest = &rest[1..];
+    }
+
+    output.push_str(rest);
+    *svg = output;
+}
+
+/// Adds an `id` attribute numbered from 1, as `prefix-N`, to every element without one except the
+/// root. The prefix is written as is so it must already be escaped.
+pub(crate) fn add_element_ids(svg: &mut String, prefix: &str) {
+    let root_start = root_tag(svg).map(|tag| tag.start);
+    let mut count = 0;
+    let mut output = String::with_capacity(svg.len());
+    let mut offset = 0;
+
+    while let Some(index) = svg[offset..].find('<') {
+        let start = offset + index;
+
+        if svg[start..].starts_with("<!--") {
+            let end = svg[start..]
+                .find("-->")
+                .map_or(svg.len(), |end| start + end + 3);
+            output.push_str(&svg[offset..end]);
+            offset = end;
+            continue;
+        }
+
+        let name_len = svg[start + 1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == ':' || c == '-'))
+            .unwrap_or(svg.len() - start - 1);
+        let end = svg[start..].find('>').map_or(svg.len(), |end| start + end);
+        let is_element =
+            name_len > 0 && svg[start + 1..].starts_with(|c: char| c.is_ascii_alphabetic());
+
+        output.push_str(&svg[offset..start + 1 + name_len]);
+        offset = start + 1 + name_len;
+
+        if is_element
+            && Some(start) != root_start
+            && attribute_value_range(&svg[start..end], "id").is_none()
+        {
+            count += 1;
+            output.push_str(&format!(" id=\"{}-{}\"", prefix, count));
+        }
+    }
+
+    output.push_str(&svg[offset..]);
+    *svg = output;
+}
+
+/// Prefixes the ids of the SVG and the references to them with `namespace`. See
+/// [`crate::OptionsBuilder::namespace`].
+pub(crate) fn namespace_ids(svg: &mut String, namespace: &str) {
+    let mut output = String::with_capacity(svg.len());
+    let mut rest = svg.as_str();
+
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(rest.len(), |end| end + 3);
+            output.push_str(&rest[..end]);
+            end
+        } else {
+            let end = tag_end(rest).unwrap_or(rest.len());
+            namespace_tag(&mut output, &rest[..end], namespace);
+            end
+        };
+
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+    *svg = output;
+}
+
+/// Writes `tag` to `output` with the id values and references of its attributes namespaced.
+fn namespace_tag(output: &mut String, tag: &str, namespace: &str) {
+    let mut rest = tag;
+
+    while let Some(eq) = rest.find('=') {
+        let quote = match rest[eq + 1..].chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            _ => {
+                output.push_str(&rest[..eq + 1]);
+                rest = &rest[eq + 1..];
+                continue;
+            }
+        };
+        let value_start = eq + 2;
+        let value_end = match rest[value_start..].find(quote) {
+            Some(len) => value_start + len,
+            None => break,
+        };
+        let name = rest[..eq].trim_end();
+        let name = name
+            .rfind(char::is_whitespace)
+            .map_or(name, |space| &name[space + 1..]);
+        let value = &rest[value_start..value_end];
+
+        output.push_str(&rest[..value_start]);
+
+        match name {
+            "id" => output.push_str(&format!("{}-{}", namespace, value)),
+            "aria-labelledby" | "aria-describedby" => {
+                let ids: Vec<String> = value
+                    .split_whitespace()
+                    .map(|id| format!("{}-{}", namespace, id))
+                    .collect();
+                output.push_str(&ids.join(" "));
+            }
+            _ if name.ends_with("href") && value.starts_with('#') => {
+                output.push_str(&format!("#{}-{}", namespace, &value[1..]))
+            }
+            _ => output.push_str(&value.replace("url(#", &format!("url(#{}-", namespace))),
+        }
+
+        rest = &rest[value_end..];
+    }
+
+    output.push_str(rest);
+}
+
+/// The id of the `<title>` element referenced by `aria-labelledby`.
+const TITLE_ID: &str = "pikchr-title";
+
+/// Whether the given name can be used as an attribute without breaking the markup.
+pub(crate) fn is_valid_attribute_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>' | '/' | '='))
+}
+
+/// Whether the given name is a `data-*` attribute with only lowercase ASCII letters, digits and
+/// hyphens after the prefix.
+pub(crate) fn is_valid_data_attribute_name(name: &str) -> bool {
+    match name.strip_prefix("data-") {
+        Some(rest) => {
+            !rest.is_empty()
+                && rest
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        }
+        None => false,
+    }
+}
+
+/// Finds the byte range of the root `<svg ...>` opening tag.
+pub(crate) fn root_tag(svg: &str) -> Option<Range<usize>> {
+    let start = svg.find("<svg")?;
+    let end = start + svg[start..].find('>')? + 1;
+
+    Some(start..end)
+}
+
+/// Returns the value of the given attribute of the root element.
+pub(crate) fn root_attribute<'a>(svg: &'a str, name: &str) -> Option<&'a str> {
+    let tag = &svg[root_tag(svg)?];
+
+    attribute_value_range(tag, name).map(|range| &tag[range])
+}
+
+/// Sets the given attribute of the root element, replacing its value if already present.
+///
+/// The value is written as is so it must already be escaped.
+pub(crate) fn set_root_attribute(svg: &mut String, name: &str, value: &str) {
+    let tag = match root_tag(svg) {
+        Some(tag) => tag,
+        None => return,
+    };
+
+    match attribute_value_range(&svg[tag.clone()], name) {
+        Some(range) => {
+            svg.replace_range(tag.start + range.start..tag.start + range.end, value);
+        }
+        None => {
+            let insert_at = if svg[..tag.end].ends_with("/>") {
+                tag.end - 2
+            } else {
+                tag.end - 1
+            };
+            svg.insert_str(insert_at, &format!(" {}=\"{}\"", name, value));
+        }
+    }
+}
+
+/// Removes the given attribute from the root element, if present.
+pub(crate) fn remove_root_attribute(svg: &mut String, name: &str) {
+    let tag = match root_tag(svg) {
+        Some(tag) => tag,
+        None => return,
+    };
+
+    if let Some(range) = attribute_value_range(&svg[tag.clone()], name) {
+        // The attribute spans from the space before its name to the closing quote.
+        let start = tag.start + range.start - name.len() - 3;
+        let end = tag.start + range.end + 1;
+        svg.replace_range(start..end, "");
+    }
+}
+
+/// Whether the given CSS variable can be declared without breaking the `<style>` element.
+///
+/// The value is written as is because entities are not decoded in a `<style>` element when the
+/// SVG is inlined in HTML.
+pub(crate) fn is_valid_css_variable(name: &str, value: &str) -> bool {
+    name.len() > 2
+        && name.starts_with("--")
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, ':' | ';' | '{' | '}' | '<' | '>'))
+        && !value
+            .chars()
+            .any(|c| matches!(c, ';' | '{' | '}' | '<' | '>'))
+}
+
+/// Whether the colour is a name known to pikchr, `#rgb`, `#rrggbb` or `rgb(r, g, b)` with
+/// channels from 0 to 255.
+pub(crate) fn is_valid_color(color: &str) -> bool {
+    if let Some(hex) = color.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+
+    if let Some(channels) = color
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let channels: Vec<_> = channels.split(',').map(str::trim).collect();
+
+        return channels.len() == 3
+            && channels
+                .iter()
+                .all(|channel| !channel.starts_with('+') && channel.parse::<u8>().is_ok());
+    }
+
+    crate::colors::is_valid_color_name(color)
+}
+
+/// Inserts the given markup as the first child of the root element, on its own line.
+pub(crate) fn insert_first_child(svg: &mut String, markup: &str) {
+    if let Some(tag) = root_tag(svg) {
+        let content = format!("\n{}", markup);
+        let content = if svg[tag.end..].starts_with('\n') {
+            content
+        } else {
+            content + "\n"
+        };
+        svg.insert_str(tag.end, &content);
+    }
+}
+
+/// Inserts the given markup as the last child of the root element, on its own line.
+pub(crate) fn insert_last_child(svg: &mut String, markup: &str) {
+    if let Some(end) = svg.rfind("</svg>") {
+        let content = if svg[..end].ends_with('\n') {
+            format!("{}\n", markup)
+        } else {
+            format!("\n{}\n", markup)
+        };
+        svg.insert_str(end, &content);
+    }
+}
+
+/// Minifies the given markup.
+///
+/// Whitespace runs are collapsed to a single space and removed next to tags, comments are
+/// stripped and attributes with an empty value are dropped. Attribute values are kept as is.
+pub(crate) fn minify(svg: &str) -> String {
+    enum State {
+        Text,
+        Tag,
+        Quoted(char),
+        Comment,
+    }
+
+    let mut output = String::with_capacity(svg.len());
+    let mut state = State::Text;
+    let mut pending_space = false;
+    let mut tag = String::new();
+    let mut rest = svg;
+
+    while let Some(c) = rest.chars().next() {
+        let mut len = c.len_utf8();
+
+        match state {
+            State::Text if rest.starts_with("<!--") => {
+                state = State::Comment;
+                len = 4;
+            }
+            State::Text if c == '<' => {
+                pending_space = false;
+                state = State::Tag;
+                tag.push(c);
+            }
+            State::Text if c.is_whitespace() => {
+                pending_space = !output.is_empty() && !output.ends_with('>');
+            }
+            State::Text => {
+                if pending_space {
+                    output.push(' ');
+                    pending_space = false;
+                }
+                output.push(c);
+            }
+            State::Tag if c == '>' => {
+                tag.push(c);
+                output.push_str(&minify_tag(&tag));
+                tag.clear();
+                state = State::Text;
+            }
+            State::Tag => {
+                if c == '"' || c == '\'' {
+                    state = State::Quoted(c);
+                }
+                tag.push(c);
+            }
+            State::Quoted(quote) => {
+                if c == quote {
+                    state = State::Tag;
+                }
+                tag.push(c);
+            }
+            State::Comment => {
+                if rest.starts_with("-->") {
+                    state = State::Text;
+                    len = 3;
+                }
+            }
+        }
+
+        rest = &rest[len..];
+    }
+
+    // An unterminated tag is kept as is.
+    output.push_str(&tag);
+    output
+}
+
+/// Collapses the whitespace of a complete tag and drops attributes with an empty value.
+fn minify_tag(tag: &str) -> String {
+    let inner = &tag[1..tag.len() - 1];
+    let (inner, self_closing) = match inner.trim_end().strip_suffix('/') {
+        Some(inner) => (inner, true),
+        None => (inner, false),
+    };
+
+    let mut parts = Vec::new();
+    let mut rest = inner.trim();
+
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        let name = &rest[..end];
+        rest = rest[end..].trim_start();
+
+        match rest.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'');
+                let value_end = match quote {
+                    Some(quote) => value[1..].find(quote).map_or(value.len(), |i| i + 2),
+                    None => value.find(char::is_whitespace).unwrap_or(value.len()),
+                };
+                let (value, remaining) = value.split_at(value_end);
+
+                if !(quote.is_some() && value.len() == 2) {
+                    parts.push(format!("{}={}", name, value));
+                }
+                rest = remaining.trim_start();
+            }
+            None => parts.push(name.to_string()),
+        }
+    }
+
+    format!(
+        "<{}{}>",
+        parts.join(" "),
+        if self_closing { "/" } else { "" }
+    )
+}
+
+/// A piece of markup as seen by [`pretty_print`].
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    /// Self-closing elements, comments, declarations and processing instructions.
+    Leaf(&'a str),
+    Text(&'a str),
+}
+
+/// Splits the given markup into tags and non-blank text, trimmed.
+fn tokenize(svg: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = svg;
+
+    while !rest.is_empty() {
+        let start = rest.find('<').unwrap_or(rest.len());
+        let text = rest[..start].trim();
+
+        if !text.is_empty() {
+            tokens.push(Token::Text(text));
+        }
+
+        rest = &rest[start..];
+
+        if rest.is_empty() {
+            break;
+        }
+
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|i| i + 3)
+        } else {
+            tag_end(rest)
+        }
+        .unwrap_or(rest.len());
+        let tag = &rest[..end];
+
+        tokens.push(if tag.starts_with("</") {
+            Token::Close(tag)
+        } else if tag.starts_with("<!") || tag.starts_with("<?") || tag.ends_with("/>") {
+            Token::Leaf(tag)
+        } else {
+            Token::Open(tag)
+        });
+
+        rest = &rest[end..];
+    }
+
+    tokens
+}
+
+/// Finds the end of the tag at the start of `markup`, past its `>`, skipping quoted values.
+fn tag_end(markup: &str) -> Option<usize> {
+    let mut quote = None;
+
+    for (i, c) in markup.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return Some(i + 1),
+            None => {}
+        }
+    }
+
+    None
+}
+
+/// Puts each element on its own line indented by `indent` spaces per nesting level.
+///
+/// Elements holding only text are kept on a single line.
+pub(crate) fn pretty_print(svg: &str, indent: usize) -> String {
+    let tokens = tokenize(svg);
+    let mut output = String::with_capacity(svg.len());
+    let mut depth = 0;
+    let mut i = 0;
+
+    let mut push_line = |depth: usize, parts: &[&str]| {
+        output.push_str(&" ".repeat(depth * indent));
+        for part in parts {
+            output.push_str(part);
+        }
+        output.push('\n');
+    };
+
+    while i < tokens.len() {
+        match tokens[i] {
+            Token::Open(tag) => match (tokens.get(i + 1), tokens.get(i + 2)) {
+                (Some(Token::Text(text)), Some(Token::Close(close))) => {
+                    push_line(depth, &[tag, text, close]);
+                    i += 2;
+                }
+                (Some(Token::Close(close)), _) => {
+                    push_line(depth, &[tag, close]);
+                    i += 1;
+                }
+                _ => {
+                    push_line(depth, &[tag]);
+                    depth += 1;
+                }
+            },
+            Token::Close(tag) => {
+                depth = depth.saturating_sub(1);
+                push_line(depth, &[tag]);
+            }
+            Token::Leaf(tag) | Token::Text(tag) => push_line(depth, &[tag]),
+        }
+
+        i += 1;
+    }
+
+    output
+}
+
+/// Parses the `viewBox` attribute of the root element as `(min_x, min_y, width, height)`.
+pub(crate) fn view_box(svg: &str) -> Option<(f64, f64, f64, f64)> {
+    let value = root_attribute(svg, "viewBox")?;
+    let mut numbers = value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|part| !part.is_empty())
+        .map(str::parse::<f64>);
+
+    let view_box = (
+        numbers.next()?.ok()?,
+        numbers.next()?.ok()?,
+        numbers.next()?.ok()?,
+        numbers.next()?.ok()?,
+    );
+
+    if numbers.next().is_some() {
+        return None;
+    }
+
+    Some(view_box)
+}
+
+/// Formats a number with at most two decimals, dropping trailing zeros.
+pub(crate) fn format_number(value: f64) -> String {
+    let formatted = format!("{:.2}", value);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+
+    if formatted == "-0" {
+        "0".to_string()
+    } else {
+        formatted.to_string()
+    }
+}
+
+/// Finds the byte range of the value of the given attribute within a tag.
+pub(crate) fn attribute_value_range(tag: &str, name: &str) -> Option<Range<usize>> {
+    let mut offset = 0;
+
+    while let Some(index) = tag[offset..].find(name) {
+        let start = offset + index;
+        let after = start + name.len();
+        let preceded_by_space = tag[..start].ends_with(char::is_whitespace);
+        let rest = &tag[after..];
+
+        if preceded_by_space && rest.starts_with('=') {
+            let quote = rest[1..].chars().next()?;
+
+            if quote == '"' || quote == '\'' {
+                let value_start = after + 2;
+                let value_end = value_start + tag[value_start..].find(quote)?;
+
+                return Some(value_start..value_end);
+            }
+        }
+
+        offset = after;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = "<svg xmlns='http://www.w3.org/2000/svg' class=\"pikchr\" viewBox=\"0 0 112.32 76.32\">\n<path d=\"M2,74Z\" />\n</svg>\n";
+
+    #[test]
+    fn reads_attributes() {
+        assert_eq!(root_attribute(SVG, "class"), Some("pikchr"));
+        assert_eq!(
+            root_attribute(SVG, "xmlns"),
+            Some("http://www.w3.org/2000/svg")
+        );
+        assert_eq!(root_attribute(SVG, "width"), None);
+    }
+
+    #[test]
+    fn sets_new_attribute() {
+        let mut svg = SVG.to_string();
+        set_root_attribute(&mut svg, "width", "100");
+
+        assert!(svg.starts_with("<svg xmlns='http://www.w3.org/2000/svg' class=\"pikchr\" viewBox=\"0 0 112.32 76.32\" width=\"100\">\n"));
+    }
+
+    #[test]
+    fn replaces_existing_attribute() {
+        let mut svg = SVG.to_string();
+        set_root_attribute(&mut svg, "class", "diagram");
+
+        assert_eq!(root_attribute(&svg, "class"), Some("diagram"));
+        assert_eq!(svg.matches("class=").count(), 1);
+    }
+
+    #[test]
+    fn removes_attribute() {
+        let mut svg = SVG.to_string();
+        remove_root_attribute(&mut svg, "xmlns");
+        remove_root_attribute(&mut svg, "width");
+
+        assert!(svg.starts_with("<svg class=\"pikchr\" viewBox=\"0 0 112.32 76.32\">\n"));
+    }
+
+    #[test]
+    fn annotates_elements() {
+        let mut svg =
+            "<svg>\n<path d='1'/>\n<text>a</text>\n<path d='2'/>\n<path d='3'/>\n</svg>\n"
+                .to_string();
+        annotate_elements(&mut svg, "\n// comment\nbox \"a\"\n  arrow --> \n");
+
+        assert_eq!(
+            svg,
+            "<svg>\n<!-- pikchr line 3: box \"a\" -->\n<path d='1'/>\n<!-- pikchr line 3: box \"a\" -->\n<text>a</text>\n<!-- pikchr line 4: arrow - -> -->\n<path d='2'/>\n<path d='3'/>\n</svg>\n"
+        );
+    }
+
+    #[test]
+    fn inserts_first_child() {
+        let mut svg = SVG.to_string();
+        insert_first_child(&mut svg, "<title>t</title>");
+
+        assert!(svg.contains("76.32\">\n<title>t</title>\n<path"));
+    }
+
+    #[test]
+    fn adds_element_ids() {
+        let mut svg = "<svg id=\"root\">\n<path d=\"M0\"/>\n<text x=\"1\">a</text>\n<!-- c <path/> -->\n<g id=\"kept\"><polygon/></g>\n</svg>\n".to_string();
+        add_element_ids(&mut svg, "d");
+
+        assert_eq!(
+            svg,
+            "<svg id=\"root\">\n<path id=\"d-1\" d=\"M0\"/>\n<text id=\"d-2\" x=\"1\">a</text>\n<!-- c <path/> -->\n<g id=\"kept\"><polygon id=\"d-3\"/></g>\n</svg>\n"
+        );
+    }
+
+    #[test]
+    fn namespaces_ids() {
+        let mut svg = "<svg aria-labelledby=\"t d\">\n<title id='t'>a = b</title>\n<!-- id=\"c\" -->\n<linearGradient id=\"g\"/>\n<path fill=\"url(#g)\" stroke='url(#g)'/>\n<use href=\"#g\" xlink:href='#g' data-x=\"#g\"/>\n</svg>\n".to_string();
+        namespace_ids(&mut svg, "ns");
+
+        assert_eq!(
+            svg,
+            "<svg aria-labelledby=\"ns-t ns-d\">\n<title id='ns-t'>a = b</title>\n<!-- id=\"c\" -->\n<linearGradient id=\"ns-g\"/>\n<path fill=\"url(#ns-g)\" stroke='url(#ns-g)'/>\n<use href=\"#ns-g\" xlink:href='#ns-g' data-x=\"#g\"/>\n</svg>\n"
+        );
+    }
+
+    #[test]
+    fn inserts_last_child() {
+        let mut svg = SVG.to_string();
+        insert_last_child(&mut svg, "<text>t</text>");
+
+        assert!(svg.ends_with("/>\n<text>t</text>\n</svg>\n"));
+
+        let mut svg = "<svg></svg>".to_string();
+        insert_last_child(&mut svg, "<g/>");
+
+        assert_eq!(svg, "<svg>\n<g/>\n</svg>");
+    }
+
+    #[test]
+    fn validates_attribute_names() {
+        assert!(is_valid_attribute_name("id"));
+        assert!(is_valid_attribute_name("aria-label"));
+        assert!(is_valid_attribute_name("data-diagram-id"));
+        assert!(!is_valid_attribute_name(""));
+        assert!(!is_valid_attribute_name("a>b"));
+        assert!(!is_valid_attribute_name("a\"b"));
+        assert!(!is_valid_attribute_name("a b"));
+        assert!(!is_valid_attribute_name("a=b"));
+    }
+
+    #[test]
+    fn validates_data_attribute_names() {
+        assert!(is_valid_data_attribute_name("data-id"));
+        assert!(is_valid_data_attribute_name("data-diagram-2"));
+        assert!(!is_valid_data_attribute_name("data-"));
+        assert!(!is_valid_data_attribute_name("data-Id"));
+        assert!(!is_valid_data_attribute_name("data-a b"));
+        assert!(!is_valid_data_attribute_name("id"));
+    }
+
+    #[test]
+    fn validates_colors() {
+        for color in [
+            "red",
+            "SteelBlue",
+            "#fff",
+            "#A0b1C2",
+            "rgb(0,0,0)",
+            "rgb( 1, 2 , 255 )",
+        ] {
+            assert!(is_valid_color(color), "{}", color);
+        }
+
+        for color in [
+            "",
+            "reddish",
+            "#ffff",
+            "#ggg",
+            "rgb(1,2)",
+            "rgb(1,2,3,4)",
+            "rgb(+1,2,3)",
+        ] {
+            assert!(!is_valid_color(color), "{}", color);
+        }
+    }
+
+    #[test]
+    fn validates_css_variables() {
+        assert!(is_valid_css_variable("--stroke", "#333"));
+        assert!(is_valid_css_variable("--font", "\"Fira Sans\", serif"));
+        assert!(!is_valid_css_variable("stroke", "#333"));
+        assert!(!is_valid_css_variable("--", "#333"));
+        assert!(!is_valid_css_variable("--a b", "#333"));
+        assert!(!is_valid_css_variable(
+            "--stroke",
+            "red; } svg { display: none"
+        ));
+        assert!(!is_valid_css_variable("--stroke", "</style>"));
+    }
+
+    #[test]
+    fn minifies() {
+        let svg = "<svg  class=\"pikchr\"\n viewBox=\"0 0 1 1\">\n<!-- comment -->\n<path d=\"M2,74Z\"  style=\"\" />\n<text x=\"1\">  a \n b  </text>\n</svg>\n";
+
+        assert_eq!(
+            minify(svg),
+            "<svg class=\"pikchr\" viewBox=\"0 0 1 1\"><path d=\"M2,74Z\"/><text x=\"1\">a b</text></svg>"
+        );
+    }
+
+    #[test]
+    fn minify_keeps_quoted_values() {
+        let svg = "<text title=\"a  >  b\" data-x='  '>x</text>";
+
+        assert_eq!(minify(svg), svg);
+    }
+
+    #[test]
+    fn pretty_prints_nested_elements() {
+        let svg = "<svg><g><!-- c --><path d='M>0' /><g><text>a b</text></g></g><g></g></svg>";
+
+        assert_eq!(
+            pretty_print(svg, 2),
+            "<svg>\n  <g>\n    <!-- c -->\n    <path d='M>0' />\n    <g>\n      <text>a b</text>\n    </g>\n  </g>\n  <g></g>\n</svg>\n"
+        );
+    }
+
+    #[test]
+    fn parses_view_box() {
+        assert_eq!(view_box(SVG), Some((0.0, 0.0, 112.32, 76.32)));
+        assert_eq!(
+            view_box("<svg viewBox='1,2, 3 4'></svg>"),
+            Some((1.0, 2.0, 3.0, 4.0))
+        );
+        assert_eq!(view_box("<svg viewBox='1 2 3'></svg>"), None);
+        assert_eq!(view_box("<svg viewBox='1 2 3 x'></svg>"), None);
+        assert_eq!(view_box("<!-- empty pikchr diagram -->\n"), None);
+    }
+
+    #[test]
+    fn formats_numbers() {
+        assert_eq!(format_number(56.160000000000004), "56.16");
+        assert_eq!(format_number(224.0), "224");
+        assert_eq!(format_number(0.5), "0.5");
+    }
+}
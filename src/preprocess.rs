@@ -0,0 +1,183 @@
+//! Transformations of the pikchr markup before rendering, composed in a [`Pipeline`].
+//!
+//! ## Example
+//!
+//! ```
+//! use pikt::preprocess::{NormalizeLf, Pipeline, StripBom, Validate};
+//!
+//! let pipeline = Pipeline::new().add(StripBom).add(NormalizeLf).add(Validate);
+//!
+//! assert_eq!(pipeline.run("\u{FEFF}box\r\n").unwrap(), "box\n");
+//! ```
+
+use crate::{normalize_line_endings, strip_bom, validate, Result};
+
+/// A step of a [`Pipeline`].
+pub trait Transform: Send + Sync {
+    fn apply(&self, input: &str) -> Result<String>;
+}
+
+/// A sequence of transforms applied in insertion order.
+#[derive(Default)]
+pub struct Pipeline {
+    steps: Vec<Box<dyn Transform>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a transform.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, t: impl Transform + 'static) -> Self {
+        self.steps.push(Box::new(t));
+        self
+    }
+
+    /// Applies every transform in order, stopping at the first error.
+    pub fn run(&self, input: &str) -> Result<String> {
+        self.steps
+            .iter()
+            .try_fold(input.to_string(), |input, step| step.apply(&input))
+    }
+}
+
+impl std::fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("steps", &self.steps.len())
+            .finish()
+    }
+}
+
+/// Normalises the line endings to LF, see [`normalize_line_endings`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NormalizeLf;
+
+impl Transform for NormalizeLf {
+    fn apply(&self, input: &str) -> Result<String> {
+        Ok(normalize_line_endings(input).into_owned())
+    }
+}
+
+/// Strips a leading byte order mark, see [`strip_bom`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripBom;
+
+impl Transform for StripBom {
+    fn apply(&self, input: &str) -> Result<String> {
+        Ok(strip_bom(input).to_string())
+    }
+}
+
+/// Strips line comments starting with `comment_char` outside of strings, e.g. for markup
+/// written with a comment syntax pikchr doesn't support.
+#[derive(Debug, Clone, Copy)]
+pub struct StripComments {
+    pub comment_char: char,
+}
+
+impl Transform for StripComments {
+    fn apply(&self, input: &str) -> Result<String> {
+        let mut output = String::with_capacity(input.len());
+
+        for line in input.split_inclusive('\n') {
+            let mut in_string = false;
+            let mut escaped = false;
+            let end = line
+                .char_indices()
+                .find(|&(_, c)| {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' if in_string => escaped = true,
+                        '"' => in_string = !in_string,
+                        c if c == self.comment_char && !in_string => return true,
+                        _ => {}
+                    }
+                    false
+                })
+                .map(|(i, _)| i);
+
+            match end {
+                Some(end) => {
+                    output.push_str(&line[..end]);
+                    if line.ends_with('\n') {
+                        output.push('\n');
+                    }
+                }
+                None => output.push_str(line),
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// Fails if the markup doesn't render, see [`validate`]. The markup is passed on unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Validate;
+
+impl Transform for Validate {
+    fn apply(&self, input: &str) -> Result<String> {
+        validate(input)?;
+        Ok(input.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_pipeline() {
+        assert_eq!(Pipeline::new().run("box 'x'\r\n").unwrap(), "box 'x'\r\n");
+    }
+
+    #[test]
+    fn steps_run_in_order() {
+        let strip = StripComments { comment_char: '%' };
+        let strip_first = Pipeline::new().add(strip).add(Validate);
+        let validate_first = Pipeline::new().add(Validate).add(strip);
+
+        assert_eq!(strip_first.run("box % note\n").unwrap(), "box \n");
+        assert!(validate_first.run("box % note\n").is_err());
+    }
+
+    #[test]
+    fn bom_and_line_endings() {
+        let pipeline = Pipeline::new().add(StripBom).add(NormalizeLf);
+
+        assert_eq!(
+            pipeline.run("\u{FEFF}box\r\ncircle\r").unwrap(),
+            "box\ncircle\n"
+        );
+    }
+
+    #[test]
+    fn failing_step_short_circuits() {
+        struct Fail;
+
+        impl Transform for Fail {
+            fn apply(&self, _: &str) -> Result<String> {
+                panic!("the pipeline should have stopped")
+            }
+        }
+
+        let pipeline = Pipeline::new().add(Validate).add(Fail);
+
+        assert!(pipeline.run("box 'x'").is_err());
+    }
+
+    #[test]
+    fn strip_comments_keeps_strings() {
+        let strip = StripComments { comment_char: '%' };
+
+        assert_eq!(
+            strip
+                .apply("box \"100%\" % size\ncircle \"\\\"%\"")
+                .unwrap(),
+            "box \"100%\" \ncircle \"\\\"%\""
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Rasterisation of rendered diagrams to PNG or to an [`image::DynamicImage`].
+
+use crate::{render_with, Options, PiktError};
+use std::sync::{Arc, OnceLock};
+use thiserror::Error;
+use usvg::fontdb;
+
+/// The error returned when rasterising a diagram.
+#[derive(Error, Debug)]
+pub enum PngRenderError {
+    #[error(transparent)]
+    Render(#[from] PiktError),
+    #[error("invalid svg: {0}")]
+    Svg(#[from] usvg::Error),
+    /// Raised when the diagram is empty so there is nothing to rasterise.
+    #[error("empty image")]
+    EmptyImage,
+    #[error("png encoding failed: {0}")]
+    Encode(String),
+}
+
+/// The system fonts, loaded once as it is slow.
+fn fonts() -> Arc<fontdb::Database> {
+    static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+
+    FONTS
+        .get_or_init(|| {
+            let mut db = fontdb::Database::new();
+            db.load_system_fonts();
+            Arc::new(db)
+        })
+        .clone()
+}
+
+/// Renders the given pikchr markup as a PNG image at the size of the SVG.
+///
+/// Text is drawn with the system fonts, so labels are missing if none is installed.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_to_png, Options};
+///
+/// let png = render_to_png("box", Options::default()).unwrap();
+///
+/// assert!(png.starts_with(b"\x89PNG"));
+/// ```
+#[cfg(feature = "png")]
+pub fn render_to_png(input: &str, options: Options) -> Result<Vec<u8>, PngRenderError> {
+    rasterize(input, options)?
+        .encode_png()
+        .map_err(|err| PngRenderError::Encode(err.to_string()))
+}
+
+/// Renders the given pikchr markup as an RGBA image at the size of the SVG.
+///
+/// Text is drawn with the system fonts, so labels are missing if none is installed.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_to_image, Options};
+///
+/// let image = render_to_image("box", Options::default()).unwrap();
+///
+/// assert!(image.width() > 0);
+/// ```
+#[cfg(feature = "image")]
+pub fn render_to_image(
+    input: &str,
+    options: Options,
+) -> Result<image::DynamicImage, PngRenderError> {
+    let pixmap = rasterize(input, options)?;
+    // The pixmap is premultiplied by alpha while the image crate expects straight alpha.
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    let buffer = image::RgbaImage::from_raw(pixmap.width(), pixmap.height(), data)
+        .expect("the pixmap has 4 bytes per pixel");
+
+    Ok(image::DynamicImage::ImageRgba8(buffer))
+}
+
+/// Renders the given pikchr markup and draws it on a pixmap of the size of the SVG.
+fn rasterize(input: &str, options: Options) -> Result<tiny_skia::Pixmap, PngRenderError> {
+    let output = render_with(input, options)?;
+    let svg_options = usvg::Options {
+        fontdb: fonts(),
+        ..usvg::Options::default()
+    };
+    let tree = usvg::Tree::from_str(&output.svg, &svg_options)?;
+    let size = tree.size().to_int_size();
+    let mut pixmap =
+        tiny_skia::Pixmap::new(size.width(), size.height()).ok_or(PngRenderError::EmptyImage)?;
+
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+    Ok(pixmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_MAGIC: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_magic_bytes() -> Result<(), PngRenderError> {
+        let actual = render_to_png("box \"pikchr\"", Options::default())?;
+
+        assert!(actual.len() > PNG_MAGIC.len());
+        assert_eq!(actual[..8], PNG_MAGIC);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn invalid_markup() {
+        let actual = render_to_png("box 'x'", Options::default()).unwrap_err();
+
+        assert!(matches!(
+            actual,
+            PngRenderError::Render(PiktError {
+                reason: crate::PiktErrorReason::TokenUnknown,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn empty_diagram() {
+        assert!(rasterize("", Options::default()).is_err());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_dimensions() -> Result<(), PngRenderError> {
+        let output = crate::render("box \"pikchr\"")?;
+        let image = render_to_image("box \"pikchr\"", Options::default())?;
+
+        assert!(image.width().abs_diff(output.width) <= 1);
+        assert!(image.height().abs_diff(output.height) <= 1);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_reencodes_as_png() -> Result<(), Box<dyn std::error::Error>> {
+        let image = render_to_image("box", Options::default())?;
+        let mut png = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut png, image::ImageFormat::Png)?;
+
+        assert_eq!(png.get_ref()[..8], PNG_MAGIC);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_dark_mode() -> Result<(), PngRenderError> {
+        let mut builder = crate::OptionsBuilder::default();
+        builder.dark_mode();
+        let light = render_to_image("box", Options::default())?;
+        let dark = render_to_image("box", builder.build())?;
+
+        assert_ne!(light.as_bytes(), dark.as_bytes());
+
+        Ok(())
+    }
+}
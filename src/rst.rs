@@ -0,0 +1,294 @@
+//! Support for pikchr diagrams embedded in reStructuredText.
+//!
+//! Diagrams are `pikchr` directives with the markup as the indented body:
+//!
+//! ```rst
+//! .. pikchr::
+//!    :class: foo
+//!
+//!    box "pikchr"
+//! ```
+//!
+//! Option lines such as `:class: foo` are skipped.
+
+use crate::{render_with, replace_blocks, Options, PikchrBlock, Result};
+use std::borrow::Cow;
+
+/// Finds the pikchr directives in the given reStructuredText.
+///
+/// The body has its common leading indentation stripped. Directives without a body, including
+/// those whose body is not indented, are ignored.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::rst::find_pikchr_directives;
+///
+/// let text = "Title\n=====\n\n.. pikchr::\n\n   box\n   circle\n";
+/// let blocks: Vec<_> = find_pikchr_directives(text).collect();
+///
+/// assert_eq!(blocks.len(), 1);
+/// assert_eq!(blocks[0].source, "box\ncircle\n");
+/// ```
+pub fn find_pikchr_directives(text: &str) -> impl Iterator<Item = PikchrBlock<'_>> {
+    Directives { text, offset: 0 }
+}
+
+/// Replaces every pikchr directive in the given reStructuredText with a `raw` HTML directive
+/// containing the rendered SVG.
+///
+/// A directive that fails to render is replaced with a `<pre class="pikchr-error">` element
+/// containing the error message so the rest of the directives are still rendered.
+pub fn replace_pikchr_directives(text: &str, options: Options) -> Result<String> {
+    Ok(replace_blocks(
+        text,
+        find_pikchr_directives(text),
+        &options,
+        raw_html_directive,
+    ))
+}
+
+/// Renders the given pikchr markup as SVG wrapped in a `.. raw:: html` directive, with every
+/// line of the SVG indented by three spaces.
+///
+/// The output ends with a newline.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{rst::render_to_rst_raw_directive, Options};
+///
+/// let rst = render_to_rst_raw_directive("box", Options::default()).unwrap();
+///
+/// assert!(rst.starts_with(".. raw:: html\n\n   <svg"));
+/// assert!(rst.ends_with("   </svg>\n"));
+/// ```
+///
+/// ## Errors
+///
+/// See [`render_with`].
+pub fn render_to_rst_raw_directive(input: &str, options: Options) -> Result<String> {
+    let output = render_with(input, options)?;
+    let mut directive = raw_html_directive(&output.svg);
+    directive.push('\n');
+
+    Ok(directive)
+}
+
+/// Wraps the given HTML in a `.. raw:: html` directive.
+pub(crate) fn raw_html_directive(html: &str) -> String {
+    let mut output = String::from(".. raw:: html\n\n");
+
+    for line in html.lines() {
+        if !line.is_empty() {
+            output.push_str("   ");
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+
+    output.truncate(output.trim_end_matches('\n').len());
+    output
+}
+
+struct Directives<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> Iterator for Directives<'a> {
+    type Item = PikchrBlock<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.offset < self.text.len() {
+            let start = self.offset;
+            let line = self.next_line();
+            let directive_indent = indentation(line);
+
+            if line.trim() != ".. pikchr::" {
+                continue;
+            }
+
+            let mut body_lines = Vec::new();
+            let mut end = start + line.trim_end().len();
+            let mut in_options = true;
+
+            while self.offset < self.text.len() {
+                let line_start = self.offset;
+                let rest = &self.text[line_start..];
+                let line = rest.split_inclusive('\n').next().unwrap_or(rest);
+
+                if !line.trim().is_empty() && indentation(line) <= directive_indent {
+                    break;
+                }
+
+                self.offset += line.len();
+
+                if line.trim().is_empty() {
+                    in_options = false;
+                    if !body_lines.is_empty() {
+                        body_lines.push(line);
+                    }
+                    continue;
+                }
+
+                if in_options && is_option(line) {
+                    end = line_start + line.trim_end().len();
+                    continue;
+                }
+
+                in_options = false;
+                body_lines.push(line);
+                end = line_start + line.trim_end().len();
+            }
+
+            while body_lines.last().is_some_and(|line| line.trim().is_empty()) {
+                body_lines.pop();
+            }
+
+            if body_lines.is_empty() {
+                continue;
+            }
+
+            return Some(PikchrBlock {
+                source: Cow::Owned(dedent(&body_lines)),
+                byte_range: start..end,
+            });
+        }
+
+        None
+    }
+}
+
+impl<'a> Directives<'a> {
+    /// Consumes the next line, line ending included.
+    fn next_line(&mut self) -> &'a str {
+        let rest = &self.text[self.offset..];
+        let line = rest.split_inclusive('\n').next().unwrap_or(rest);
+        self.offset += line.len();
+
+        line
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_option(line: &str) -> bool {
+    match line.trim().strip_prefix(':') {
+        Some(rest) => rest.find(':').is_some_and(|index| index > 0),
+        None => false,
+    }
+}
+
+/// Joins the lines removing the indentation they all have in common.
+fn dedent(lines: &[&str]) -> String {
+    let common = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| indentation(line))
+        .min()
+        .unwrap_or(0);
+    let mut output = String::new();
+
+    for line in lines {
+        let line = line.trim_end_matches(&['\n', '\r'][..]);
+        output.push_str(line.get(common..).unwrap_or(""));
+        output.push('\n');
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_directive() {
+        let text = "Intro.\n\n.. pikchr::\n\n   box \"a\"\n     arrow\n\nOutro.\n";
+        let blocks: Vec<_> = find_pikchr_directives(text).collect();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].source, "box \"a\"\n  arrow\n");
+        assert_eq!(
+            &text[blocks[0].byte_range.clone()],
+            ".. pikchr::\n\n   box \"a\"\n     arrow"
+        );
+    }
+
+    #[test]
+    fn directive_with_options() {
+        let text = ".. pikchr::\n   :class: foo\n   :align: center\n\n   box\n";
+        let blocks: Vec<_> = find_pikchr_directives(text).collect();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].source, "box\n");
+    }
+
+    #[test]
+    fn unindented_body_is_ignored() {
+        let text = ".. pikchr::\n\nbox\n";
+
+        assert_eq!(find_pikchr_directives(text).count(), 0);
+    }
+
+    #[test]
+    fn multiple_directives() {
+        let text = ".. pikchr::\n\n   box\n\n.. note::\n\n   text\n\n.. pikchr::\n\n    circle\n";
+        let blocks: Vec<_> = find_pikchr_directives(text).collect();
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].source, "box\n");
+        assert_eq!(blocks[1].source, "circle\n");
+    }
+
+    #[test]
+    fn raw_directive() -> Result<()> {
+        let svg = crate::render("box \"a\"; arrow")?.svg;
+        let actual = render_to_rst_raw_directive("box \"a\"; arrow", Options::default())?;
+        let (head, body) = actual.split_once("\n\n").unwrap();
+
+        assert_eq!(head, ".. raw:: html");
+        assert!(svg.lines().count() > 1);
+        assert!(body.lines().all(|line| line.starts_with("   <")));
+
+        let dedented: String = body
+            .lines()
+            .map(|line| format!("{}\n", &line[3..]))
+            .collect();
+        assert_eq!(dedented, svg);
+        assert!(actual.ends_with("</svg>\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn replace_directives() -> Result<()> {
+        let text = "Intro.\n\n.. pikchr::\n\n   box\n\nOutro.\n";
+        let options = Options::default();
+
+        let actual = replace_pikchr_directives(text, options)?;
+
+        assert!(actual.starts_with("Intro.\n\n.. raw:: html\n\n   <svg"));
+        assert!(actual.ends_with("   </svg>\n\nOutro.\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn replace_with_error_recovery() -> Result<()> {
+        let text = ".. pikchr::\n\n   box 'x'\n\n.. pikchr::\n\n   box\n";
+        let options = Options::default();
+
+        let actual = replace_pikchr_directives(text, options)?;
+
+        assert!(actual.starts_with(
+            ".. raw:: html\n\n   <pre class=\"pikchr-error\">line 1, column 5: unknown token</pre>\n"
+        ));
+        assert_eq!(actual.matches("<svg").count(), 1);
+
+        Ok(())
+    }
+}
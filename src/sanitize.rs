@@ -0,0 +1,179 @@
+//! Limits on the pikchr input to reject oversized or suspicious markup before rendering.
+
+use crate::{classify_complexity, render_with, ComplexityClass, Options, PikchrOutput, PiktError};
+use thiserror::Error;
+
+/// The limits enforced by [`sanitize`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitizeConfig {
+    /// The maximum size of the input in bytes.
+    pub max_input_bytes: usize,
+    /// The maximum number of lines of the input.
+    pub max_lines: usize,
+}
+
+/// Defaults to 1 MiB and 10,000 lines.
+impl Default for SanitizeConfig {
+    fn default() -> Self {
+        Self {
+            max_input_bytes: 1024 * 1024,
+            max_lines: 10_000,
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SanitizeError {
+    #[error("input too large: {actual} bytes, limit is {limit}")]
+    TooLarge { actual: usize, limit: usize },
+    #[error("too many lines: {count}, limit is {limit}")]
+    TooManyLines { count: usize, limit: usize },
+    #[error("input too complex")]
+    TooComplex,
+}
+
+/// The error returned by [`render_sanitized`].
+#[derive(Error, Debug, PartialEq)]
+pub enum SanitizeOrRenderError {
+    #[error(transparent)]
+    Sanitize(#[from] SanitizeError),
+    #[error(transparent)]
+    Render(#[from] PiktError),
+}
+
+/// Checks the given input is within the limits. Limits are inclusive.
+///
+/// Inputs classified as [`ComplexityClass::Excessive`] by [`classify_complexity`] are always
+/// rejected.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{sanitize, SanitizeConfig, SanitizeError};
+///
+/// let config = SanitizeConfig {
+///     max_input_bytes: 8,
+///     max_lines: 2,
+/// };
+///
+/// assert!(sanitize("box", &config).is_ok());
+/// assert_eq!(
+///     sanitize("box\nbox\nbox", &config),
+///     Err(SanitizeError::TooLarge { actual: 11, limit: 8 })
+/// );
+/// ```
+pub fn sanitize(input: &str, config: &SanitizeConfig) -> Result<(), SanitizeError> {
+    if input.len() > config.max_input_bytes {
+        return Err(SanitizeError::TooLarge {
+            actual: input.len(),
+            limit: config.max_input_bytes,
+        });
+    }
+
+    let count = input.lines().count();
+    if count > config.max_lines {
+        return Err(SanitizeError::TooManyLines {
+            count,
+            limit: config.max_lines,
+        });
+    }
+
+    if classify_complexity(input) == ComplexityClass::Excessive {
+        return Err(SanitizeError::TooComplex);
+    }
+
+    Ok(())
+}
+
+/// Renders the given input if it is within the limits. pikchr is not called otherwise.
+pub fn render_sanitized(
+    input: &str,
+    options: Options,
+    config: &SanitizeConfig,
+) -> Result<PikchrOutput, SanitizeOrRenderError> {
+    sanitize(input, config)?;
+
+    Ok(render_with(input, options)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_input_bytes: usize, max_lines: usize) -> SanitizeConfig {
+        SanitizeConfig {
+            max_input_bytes,
+            max_lines,
+        }
+    }
+
+    #[test]
+    fn under_limit() {
+        assert_eq!(sanitize("box", &config(4, 2)), Ok(()));
+        assert_eq!(sanitize("box\nbox", &config(100, 3)), Ok(()));
+    }
+
+    #[test]
+    fn at_limit() {
+        assert_eq!(sanitize("box", &config(3, 1)), Ok(()));
+        assert_eq!(sanitize("box\nbox\n", &config(100, 2)), Ok(()));
+    }
+
+    #[test]
+    fn over_byte_limit() {
+        assert_eq!(
+            sanitize("box\n", &config(3, 10)),
+            Err(SanitizeError::TooLarge {
+                actual: 4,
+                limit: 3
+            })
+        );
+    }
+
+    #[test]
+    fn over_line_limit() {
+        assert_eq!(
+            sanitize("box\nbox\nbox", &config(100, 2)),
+            Err(SanitizeError::TooManyLines { count: 3, limit: 2 })
+        );
+    }
+
+    #[test]
+    fn render_skips_pikchr_when_over_limit() {
+        // Invalid markup proves pikchr is never called.
+        let actual = render_sanitized("box 'pikchr'", Options::default(), &config(3, 10));
+
+        assert_eq!(
+            actual,
+            Err(SanitizeOrRenderError::Sanitize(SanitizeError::TooLarge {
+                actual: 12,
+                limit: 3
+            }))
+        );
+    }
+
+    #[test]
+    fn render_within_limits() {
+        let defaults = SanitizeConfig::default();
+
+        assert!(render_sanitized("box", Options::default(), &defaults).is_ok());
+        assert!(matches!(
+            render_sanitized("box 'pikchr'", Options::default(), &defaults),
+            Err(SanitizeOrRenderError::Render(_))
+        ));
+    }
+
+    #[test]
+    fn render_skips_pikchr_when_excessive() {
+        let input = format!("{}box 'pikchr'{}", "{ ".repeat(16), " }".repeat(16));
+
+        assert_eq!(
+            sanitize(&input, &SanitizeConfig::default()),
+            Err(SanitizeError::TooComplex)
+        );
+        assert_eq!(
+            render_sanitized(&input, Options::default(), &SanitizeConfig::default()),
+            Err(SanitizeOrRenderError::Sanitize(SanitizeError::TooComplex))
+        );
+    }
+}
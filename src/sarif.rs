@@ -0,0 +1,131 @@
+//! Conversion of errors into SARIF 2.1.0, e.g. for GitHub Code Scanning.
+
+use crate::PiktError;
+use serde_json::{json, Value};
+
+impl PiktError {
+    /// Converts the error into a SARIF `result` located in `artifact_uri`.
+    ///
+    /// The region is omitted when the error has no location.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use pikt::render;
+    ///
+    /// let err = render("box 'pikchr'").unwrap_err();
+    /// let result = err.to_sarif_result("diagram.pikchr");
+    ///
+    /// assert_eq!(result["ruleId"], "PIKCHR1029");
+    /// assert_eq!(result["locations"][0]["physicalLocation"]["region"]["startColumn"], 5);
+    /// ```
+    pub fn to_sarif_result(&self, artifact_uri: &str) -> Value {
+        let mut location = json!({
+            "artifactLocation": { "uri": artifact_uri },
+        });
+
+        if self.line > 0 {
+            location["region"] = json!({
+                "startLine": self.line,
+                "startColumn": self.column.max(1),
+            });
+        }
+
+        json!({
+            "ruleId": format!("PIKCHR{:04}", self.reason.error_code()),
+            "level": "error",
+            "message": { "text": self.reason.to_string() },
+            "locations": [{ "physicalLocation": location }],
+        })
+    }
+}
+
+/// Builds a SARIF log with a single run holding a result for each `(artifact_uri, error)`.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{build_sarif_log, render};
+///
+/// let err = render("box 'pikchr'").unwrap_err();
+/// let log = build_sarif_log(&[("diagram.pikchr", &err)]);
+///
+/// assert_eq!(log["version"], "2.1.0");
+/// assert_eq!(log["runs"][0]["results"].as_array().unwrap().len(), 1);
+/// ```
+pub fn build_sarif_log(errors: &[(&str, &PiktError)]) -> Value {
+    let results: Vec<Value> = errors
+        .iter()
+        .map(|(uri, err)| err.to_sarif_result(uri))
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "pikt",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    #[test]
+    fn result_structure() {
+        let err = render("box\ncirc \"1\"").unwrap_err();
+        let actual = err.to_sarif_result("docs/diagram.pikchr");
+        let location = &actual["locations"][0]["physicalLocation"];
+
+        assert_eq!(actual["ruleId"], "PIKCHR1001");
+        assert_eq!(actual["level"], "error");
+        assert_eq!(actual["message"]["text"], "syntax error");
+        assert_eq!(location["artifactLocation"]["uri"], "docs/diagram.pikchr");
+        assert_eq!(location["region"]["startLine"], 2);
+        assert!(location["region"].get("startColumn").is_some());
+    }
+
+    #[test]
+    fn result_without_location() {
+        let err = render("box\0").unwrap_err();
+        let actual = err.to_sarif_result("diagram.pikchr");
+        let location = &actual["locations"][0]["physicalLocation"];
+
+        assert!(location.get("artifactLocation").is_some());
+        assert!(location.get("region").is_none());
+    }
+
+    #[test]
+    fn log_structure() {
+        let a = render("box 'x'").unwrap_err();
+        let b = render("circ \"1\"").unwrap_err();
+        let actual = build_sarif_log(&[("a.pikchr", &a), ("b.pikchr", &b)]);
+        let run = &actual["runs"][0];
+
+        assert_eq!(actual["version"], "2.1.0");
+        assert!(actual.get("$schema").is_some());
+        assert_eq!(run["tool"]["driver"]["name"], "pikt");
+        assert!(run["tool"]["driver"].get("version").is_some());
+        assert_eq!(run["results"].as_array().map(Vec::len), Some(2));
+        assert_eq!(
+            run["results"][1]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "b.pikchr"
+        );
+    }
+
+    #[test]
+    fn empty_log() {
+        let actual = build_sarif_log(&[]);
+
+        assert_eq!(actual["runs"][0]["results"], json!([]));
+    }
+}
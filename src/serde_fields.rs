@@ -0,0 +1,55 @@
+//! Serde representations of the [`crate::PiktErrorReason`] fields that don't fit an internally
+//! tagged enum.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Display;
+
+/// Wraps the field of a newtype variant in a `value` key, as an internally tagged variant can
+/// only hold a struct or a map.
+pub(crate) mod value {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct ValueRef<'a, T> {
+        value: &'a T,
+    }
+
+    #[derive(Deserialize)]
+    struct Value<T> {
+        value: T,
+    }
+
+    pub(crate) fn serialize<T: Serialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        ValueRef { value }.serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        Value::deserialize(deserializer).map(|wrapper| wrapper.value)
+    }
+}
+
+/// Omits the field, leaving only the tag.
+pub(crate) fn omit<T, S: Serializer>(_: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_unit()
+}
+
+/// Writes the field as its `message`.
+pub(crate) fn message<T: Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct Message {
+        message: String,
+    }
+
+    Message {
+        message: value.to_string(),
+    }
+    .serialize(serializer)
+}
@@ -0,0 +1,61 @@
+//! Locations in the pikchr source.
+//!
+//! Lines and columns are 1-based like the ones in [`PiktError`](crate::PiktError), with `0`
+//! meaning unknown.
+
+/// A single location in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A range of the source between two positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_from_reader;
+    use crate::Options;
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    #[test]
+    fn positions_are_ordered_by_line_first() {
+        let a = Position { line: 1, column: 9 };
+        let b = Position { line: 2, column: 1 };
+
+        assert!(a < b);
+    }
+
+    #[test]
+    fn errors_convert_to_a_zero_length_span() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/regression/invalid");
+        let mut count = 0;
+
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+
+            if path.extension() != Some("pikchr".as_ref()) {
+                continue;
+            }
+
+            let err =
+                render_from_reader(File::open(&path).unwrap(), Options::default()).unwrap_err();
+            let position = err.position();
+            let span = err.span();
+
+            assert_eq!((position.line, position.column), (err.line, err.column));
+            assert_eq!(span.start, position);
+            assert_eq!(span.end, position);
+            count += 1;
+        }
+
+        assert!(count > 0);
+    }
+}
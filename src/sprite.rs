@@ -0,0 +1,118 @@
+//! Rendering of many diagrams into a single SVG sprite sheet.
+
+use crate::html::escape_html;
+use crate::postprocess::{root_attribute, root_tag};
+use crate::{render_with, Options, PiktError, PiktErrorReason, Result};
+
+/// Renders each `(id, input)` pair into a `<symbol>` of a single SVG.
+///
+/// The root element has zero dimensions so the sheet can be inlined in a page without taking
+/// space, and each diagram is then displayed with `<use href="#id"/>`. The symbols keep the
+/// `viewBox` of the diagrams and are written in the given order. Ids are escaped but not
+/// checked for uniqueness.
+///
+/// ## Example
+///
+/// ```
+/// use pikt::{render_sprite_sheet, Options};
+///
+/// let sheet = render_sprite_sheet(&[("a", "box"), ("b", "circle")], Options::default()).unwrap();
+///
+/// assert!(sheet.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"0\" height=\"0\">"));
+/// assert_eq!(sheet.matches("<symbol id=").count(), 2);
+/// ```
+///
+/// ## Errors
+///
+/// Fails on the first diagram that fails to render, with a
+/// [`PiktErrorReason::DiagramFailed`] reason holding its id. The line and column are the ones
+/// of the diagram.
+pub fn render_sprite_sheet(items: &[(&str, &str)], options: Options) -> Result<String> {
+    let mut sheet =
+        String::from("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"0\" height=\"0\">\n");
+
+    for (id, input) in items {
+        let output = render_with(input, options.clone()).map_err(|err| PiktError {
+            reason: PiktErrorReason::DiagramFailed {
+                id: id.to_string(),
+                reason: Box::new(err.reason),
+            },
+            ..err
+        })?;
+
+        sheet.push_str(&format!("<symbol id=\"{}\"", escape_html(id)));
+
+        if let Some(view_box) = root_attribute(&output.svg, "viewBox") {
+            sheet.push_str(&format!(" viewBox=\"{}\"", view_box));
+        }
+
+        sheet.push('>');
+
+        // An empty diagram has no root element and so no content.
+        if let Some(tag) = root_tag(&output.svg) {
+            let content = &output.svg[tag.end..];
+            let content = content
+                .rfind("</svg>")
+                .map_or(content, |end| &content[..end]);
+            sheet.push_str(content);
+        }
+
+        sheet.push_str("</symbol>\n");
+    }
+
+    sheet.push_str("</svg>\n");
+
+    Ok(sheet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    #[test]
+    fn symbol_per_item() -> Result<()> {
+        let sheet = render_sprite_sheet(&[("a", "box"), ("b", "circle")], Options::default())?;
+
+        assert_eq!(sheet.matches("<symbol ").count(), 2);
+        assert_eq!(sheet.matches("</symbol>").count(), 2);
+        assert_eq!(sheet.matches("<svg").count(), 1);
+        assert!(sheet.contains("width=\"0\" height=\"0\""));
+
+        for (id, input) in [("a", "box"), ("b", "circle")] {
+            let output = render(input)?;
+            let view_box = root_attribute(&output.svg, "viewBox").unwrap();
+
+            assert!(sheet.contains(&format!("<symbol id=\"{}\" viewBox=\"{}\">", id, view_box)));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn escapes_ids() -> Result<()> {
+        let sheet = render_sprite_sheet(&[("a\"b", "box")], Options::default())?;
+
+        assert!(sheet.contains("<symbol id=\"a&quot;b\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_failing_diagram() {
+        let err = render_sprite_sheet(&[("a", "box"), ("b", "circ \"1\"")], Options::default())
+            .unwrap_err();
+        let expected = render("circ \"1\"").unwrap_err();
+
+        assert_eq!(err.line(), expected.line());
+        assert_eq!(err.column(), expected.column());
+        assert_eq!(
+            err.reason(),
+            &PiktErrorReason::DiagramFailed {
+                id: "b".to_string(),
+                reason: Box::new(expected.reason),
+            }
+        );
+        assert!(err.to_string().contains("diagram \"b\""));
+    }
+}
@@ -0,0 +1,283 @@
+//! Helpers to inspect the structure of a rendered SVG, mostly useful in tests.
+//!
+//! Like the post-processing, these scan the known shape of the pikchr output rather than parse
+//! it as XML.
+
+use crate::postprocess::attribute_value_range;
+
+/// An element of the SVG as returned by [`extract_elements`]. Values are kept as written, with
+/// their entities.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SvgElement {
+    pub tag: String,
+    pub id: Option<String>,
+    pub class: Option<String>,
+    /// The content of a `<text>` element, `None` for other elements and self-closing tags.
+    pub text_content: Option<String>,
+}
+
+/// Counts the opening and self-closing tags, ignoring closing tags, comments and declarations.
+///
+/// ## Example
+///
+/// ```
+/// let output = pikt::render("box").unwrap();
+///
+/// // The `<svg>` and `<path>` elements.
+/// assert_eq!(pikt::svg::count_elements(&output.svg), 2);
+/// ```
+pub fn count_elements(svg: &str) -> usize {
+    svg.as_bytes()
+        .windows(2)
+        .filter(|pair| pair[0] == b'<' && pair[1].is_ascii_alphabetic())
+        .count()
+}
+
+/// Returns each element, the root `<svg>` included, in document order. Comments are skipped.
+///
+/// ## Example
+///
+/// ```
+/// let output = pikt::render("box \"hello\"").unwrap();
+/// let elements = pikt::svg::extract_elements(&output.svg);
+/// let tags: Vec<_> = elements.iter().map(|element| element.tag.as_str()).collect();
+///
+/// assert_eq!(tags, ["svg", "path", "text"]);
+/// assert_eq!(elements[2].text_content.as_deref(), Some("hello"));
+/// ```
+pub fn extract_elements(svg: &str) -> Vec<SvgElement> {
+    let mut elements = Vec::new();
+    let mut offset = 0;
+
+    while let Some(index) = svg[offset..].find('<') {
+        let start = offset + index;
+        offset = start + 1;
+
+        if svg[offset..].starts_with("!--") {
+            match svg[offset..].find("-->") {
+                Some(end) => offset += end + 3,
+                None => break,
+            }
+            continue;
+        }
+
+        if !svg[offset..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+
+        let end = match svg[start..].find('>') {
+            Some(end) => start + end + 1,
+            None => break,
+        };
+        let tag = &svg[start..end];
+        let name_len = tag[1..]
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(tag.len() - 1);
+        let name = &tag[1..1 + name_len];
+        let attribute = |attribute: &str| {
+            attribute_value_range(tag, attribute).map(|range| tag[range].to_string())
+        };
+        let text_content = if name == "text" && !tag.ends_with("/>") {
+            svg[end..]
+                .find("</text>")
+                .map(|close| svg[end..end + close].to_string())
+        } else {
+            None
+        };
+
+        elements.push(SvgElement {
+            tag: name.to_string(),
+            id: attribute("id"),
+            class: attribute("class"),
+            text_content,
+        });
+        offset = end;
+    }
+
+    elements
+}
+
+/// Returns the content of each `<text>` element, in document order.
+///
+/// Entities are kept as pikchr wrote them, e.g. `&lt;` for `<`.
+///
+/// ## Example
+///
+/// ```
+/// let output = pikt::render("box \"hello\"").unwrap();
+///
+/// assert_eq!(pikt::svg::text_contents(&output.svg), vec!["hello"]);
+/// ```
+pub fn text_contents(svg: &str) -> Vec<String> {
+    let mut contents = Vec::new();
+    let mut rest = svg;
+
+    while let Some(start) = find_tag(rest, "text") {
+        rest = &rest[start..];
+
+        let open_end = match rest.find('>') {
+            Some(end) => end + 1,
+            None => break,
+        };
+        rest = &rest[open_end..];
+
+        let close = match rest.find("</text>") {
+            Some(close) => close,
+            None => break,
+        };
+        contents.push(rest[..close].to_string());
+        rest = &rest[close..];
+    }
+
+    contents
+}
+
+/// Counts the `<path>` elements.
+pub fn path_count(svg: &str) -> usize {
+    let mut count = 0;
+    let mut rest = svg;
+
+    while let Some(start) = find_tag(rest, "path") {
+        count += 1;
+        rest = &rest[start + 1..];
+    }
+
+    count
+}
+
+/// Finds the start of the next opening tag with the given name.
+fn find_tag(svg: &str, name: &str) -> Option<usize> {
+    let pattern = format!("<{}", name);
+    let mut offset = 0;
+
+    while let Some(index) = svg[offset..].find(&pattern) {
+        let start = offset + index;
+        let next = svg[start + pattern.len()..].chars().next();
+
+        if matches!(next, Some(c) if c.is_whitespace() || c == '>' || c == '/') {
+            return Some(start);
+        }
+
+        offset = start + pattern.len();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render, Result};
+
+    #[test]
+    fn text_of_a_box() -> Result<()> {
+        let output = render("box \"hello\"")?;
+
+        assert_eq!(text_contents(&output.svg), vec!["hello"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn text_in_order() -> Result<()> {
+        let output = render("circle \"1\"; move; circle \"2\"")?;
+
+        assert_eq!(text_contents(&output.svg), vec!["1", "2"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn path_of_a_box() -> Result<()> {
+        let output = render("box")?;
+
+        assert_eq!(path_count(&output.svg), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn elements_grow_with_the_diagram() -> Result<()> {
+        let simple = render("box")?;
+        let complex = render("box \"a\"; arrow; circle \"b\"")?;
+
+        assert!(count_elements(&complex.svg) > count_elements(&simple.svg));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ignores_similar_names() {
+        let svg = "<svg><pathx/><path d='M0'/><!-- comment --></svg>";
+
+        assert_eq!(path_count(svg), 1);
+        assert_eq!(count_elements(svg), 3);
+        assert_eq!(
+            text_contents("<textPath>a</textPath>"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn elements_of_a_box() -> Result<()> {
+        let output = render("box \"hello\"")?;
+
+        let actual = extract_elements(&output.svg);
+
+        assert_eq!(actual.len(), 3);
+        assert_eq!(actual[0].class.as_deref(), Some("pikchr"));
+        assert_eq!(actual[1].tag, "path");
+        assert_eq!(actual[1].text_content, None);
+        assert_eq!(actual[2].tag, "text");
+        assert_eq!(actual[2].text_content.as_deref(), Some("hello"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn elements_in_order() -> Result<()> {
+        let output = render("box \"a\"; arrow; circle \"b\"")?;
+
+        let tags: Vec<_> = extract_elements(&output.svg)
+            .into_iter()
+            .map(|element| element.tag)
+            .collect();
+
+        assert_eq!(
+            tags,
+            ["svg", "path", "text", "polygon", "path", "circle", "text"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn self_closing_and_attributes() {
+        let svg = "<svg>\n<g id='g1' class=\"a b\"><text/><path d='M0'/></g>\n<text id=\"t\">x &amp; y</text>\n<!-- <path> -->\n</svg>";
+
+        assert_eq!(
+            extract_elements(svg),
+            vec![
+                element("svg", None, None, None),
+                element("g", Some("g1"), Some("a b"), None),
+                element("text", None, None, None),
+                element("path", None, None, None),
+                element("text", Some("t"), None, Some("x &amp; y")),
+            ]
+        );
+    }
+
+    fn element(
+        tag: &str,
+        id: Option<&str>,
+        class: Option<&str>,
+        text_content: Option<&str>,
+    ) -> SvgElement {
+        SvgElement {
+            tag: tag.to_string(),
+            id: id.map(str::to_string),
+            class: class.map(str::to_string),
+            text_content: text_content.map(str::to_string),
+        }
+    }
+}
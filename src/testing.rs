@@ -0,0 +1,233 @@
+//! Helpers to test diagrams.
+//!
+//! They panic with the render error or output so failures are easy to diagnose.
+//!
+//! ## Example
+//!
+//! ```
+//! use pikt::{assert_render_error, assert_renders, PiktErrorReason};
+//!
+//! assert_renders!("box \"pikchr\"");
+//! assert_render_error!("box 'pikchr'", PiktErrorReason::TokenUnknown);
+//! ```
+
+use crate::{render, PikchrOutput, PiktError, PiktErrorKind, Result, UPDATE_GOLDEN_VAR};
+use std::path::Path;
+
+/// Renders the given markup panicking with the error if it fails.
+pub fn render_ok(source: &str) -> PikchrOutput {
+    match render(source) {
+        Ok(output) => output,
+        Err(err) => panic!("expected {:?} to render, got: {}", source, err),
+    }
+}
+
+/// Renders the given markup panicking with the output if it succeeds.
+pub fn render_err(source: &str) -> PiktError {
+    match render(source) {
+        Ok(output) => panic!("expected {:?} to fail, got: {}", source, output.svg),
+        Err(err) => err,
+    }
+}
+
+/// Asserts the SVG is the same as the content of the golden file.
+///
+/// When `PIKCHR_UPDATE_GOLDEN=1` is set, the file is written with the SVG instead.
+pub fn assert_svg_eq(actual: &PikchrOutput, golden_path: impl AsRef<Path>) {
+    let update = std::env::var(UPDATE_GOLDEN_VAR).as_deref() == Ok("1");
+
+    check_golden(actual, golden_path.as_ref(), update);
+}
+
+fn check_golden(actual: &PikchrOutput, path: &Path, update: bool) {
+    if update {
+        if let Err(err) = std::fs::write(path, &actual.svg) {
+            panic!("cannot write golden file {}: {}", path.display(), err);
+        }
+        return;
+    }
+
+    let expected = match std::fs::read_to_string(path) {
+        Ok(expected) => expected,
+        Err(err) => panic!("cannot read golden file {}: {}", path.display(), err),
+    };
+
+    assert_eq!(
+        actual.svg,
+        expected,
+        "the SVG differs from the golden file {}, set {}=1 to update it",
+        path.display(),
+        UPDATE_GOLDEN_VAR
+    );
+}
+
+/// Asserts the SVG contains the given text.
+pub fn assert_svg_contains(actual: &PikchrOutput, text: &str) {
+    assert!(
+        actual.svg.contains(text),
+        "expected the SVG to contain {:?}, got: {}",
+        text,
+        actual.svg
+    );
+}
+
+/// Asserts the render failed with an error of the given kind.
+pub fn assert_error_kind(result: &Result<PikchrOutput>, kind: PiktErrorKind) {
+    match result {
+        Ok(output) => panic!("expected a {:?} error, got: {}", kind, output.svg),
+        Err(err) => assert_eq!(
+            err.kind(),
+            kind,
+            "expected a {:?} error, got: {}",
+            kind,
+            err
+        ),
+    }
+}
+
+/// Asserts the given markup renders.
+#[macro_export]
+macro_rules! assert_renders {
+    ($source:expr) => {
+        $crate::testing::render_ok($source);
+    };
+}
+
+/// Asserts the given markup renders exactly to the expected SVG.
+#[macro_export]
+macro_rules! assert_renders_to {
+    ($source:expr, $expected_svg:expr) => {
+        assert_eq!($crate::testing::render_ok($source).svg, $expected_svg);
+    };
+}
+
+/// Asserts the given markup fails with a reason matching the pattern.
+#[macro_export]
+macro_rules! assert_render_error {
+    ($source:expr, $reason:pat) => {
+        let err = $crate::testing::render_err($source);
+
+        assert!(
+            matches!(err.reason(), $reason),
+            "expected a {} error, got: {:?}",
+            stringify!($reason),
+            err.reason()
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PiktErrorReason;
+
+    #[test]
+    fn renders() {
+        assert_renders!("box");
+    }
+
+    #[test]
+    #[should_panic(expected = "to render")]
+    fn renders_fails() {
+        assert_renders!("box 'x'");
+    }
+
+    #[test]
+    fn renders_to() {
+        let expected = render("circle").unwrap().svg;
+
+        assert_renders_to!("circle", expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn renders_to_mismatch() {
+        assert_renders_to!("circle", "<svg></svg>");
+    }
+
+    #[test]
+    fn render_error() {
+        assert_render_error!("circ \"1\"", PiktErrorReason::SyntaxError);
+        assert_render_error!("box\0", PiktErrorReason::IncompatibleInput(_));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a PiktErrorReason::DivisionByZero error")]
+    fn render_error_mismatch() {
+        assert_render_error!("circ \"1\"", PiktErrorReason::DivisionByZero);
+    }
+
+    #[test]
+    #[should_panic(expected = "to fail")]
+    fn render_error_on_success() {
+        assert_render_error!("box", PiktErrorReason::SyntaxError);
+    }
+
+    #[test]
+    fn ok_and_err() {
+        assert!(render_ok("oval").svg.contains("<path"));
+        assert_eq!(render_err("box 'x'").line(), 1);
+    }
+
+    #[test]
+    fn svg_eq_golden() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("box.svg");
+        let output = render_ok("box");
+
+        check_golden(&output, &path, true);
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), output.svg);
+
+        // Going through `check_golden` above as setting the variable would race with the other
+        // tests.
+        assert_svg_eq(&output, &path);
+    }
+
+    #[test]
+    #[should_panic(expected = "differs from the golden file")]
+    fn svg_eq_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("box.svg");
+        std::fs::write(&path, "<svg></svg>").unwrap();
+
+        assert_svg_eq(&render_ok("box"), &path);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot read golden file")]
+    fn svg_eq_missing_golden() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert_svg_eq(&render_ok("box"), dir.path().join("missing.svg"));
+    }
+
+    #[test]
+    fn svg_contains() {
+        assert_svg_contains(&render_ok("box \"a\""), ">a</text>");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected the SVG to contain \"<circle\"")]
+    fn svg_contains_missing() {
+        assert_svg_contains(&render_ok("box"), "<circle");
+    }
+
+    #[test]
+    fn error_kind() {
+        assert_error_kind(&render("circ \"1\""), PiktErrorKind::Syntax);
+        assert_error_kind(&render("box\0"), PiktErrorKind::Input);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a Geometry error")]
+    fn error_kind_mismatch() {
+        assert_error_kind(&render("circ \"1\""), PiktErrorKind::Geometry);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a Syntax error, got: <svg")]
+    fn error_kind_on_success() {
+        assert_error_kind(&render("box"), PiktErrorKind::Syntax);
+    }
+}
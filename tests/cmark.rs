@@ -0,0 +1,72 @@
+//! Rendering the pikchr blocks of a `pulldown-cmark` event stream.
+#![cfg(feature = "cmark")]
+
+use pikt::cmark::{render_events, OnError};
+use pikt::{render, Options};
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
+
+const DOCUMENT: &str = r#"# Title
+
+```pikchr
+box "one"
+```
+
+```rust
+fn main() {}
+```
+"#;
+
+#[test]
+fn replaces_only_pikchr_blocks() {
+    let actual: Vec<Event> =
+        render_events(Parser::new(DOCUMENT), Options::default(), OnError::Fail).collect();
+    let expected: Vec<Event> = Parser::new(DOCUMENT)
+        .scan(false, |in_pikchr, event| {
+            Some(match event {
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref info)))
+                    if info.as_ref() == "pikchr" =>
+                {
+                    *in_pikchr = true;
+                    Some(Event::Html(render("box \"one\"\n").unwrap().svg.into()))
+                }
+                Event::End(_) if *in_pikchr => {
+                    *in_pikchr = false;
+                    None
+                }
+                _ if *in_pikchr => None,
+                event => Some(event),
+            })
+        })
+        .flatten()
+        .collect();
+
+    assert_eq!(actual, expected);
+    assert!(actual.contains(&Event::Text("fn main() {}\n".into())));
+}
+
+#[test]
+fn passthrough_keeps_failing_blocks() {
+    let document = "```pikchr\nbox 'x'\n```\n\n```pikchr\ncircle\n```\n";
+    let mut events = render_events(
+        Parser::new(document),
+        Options::default(),
+        OnError::Passthrough,
+    );
+    let actual: Vec<Event> = events.by_ref().collect();
+    let original: Vec<Event> = Parser::new(document).take(3).collect();
+
+    assert_eq!(&actual[..3], &original[..]);
+    assert!(matches!(&actual[3], Event::Html(svg) if svg.contains("<circle")));
+    assert_eq!(actual.len(), 4);
+    assert_eq!(events.errors(), &[render("box 'x'\n").unwrap_err()]);
+}
+
+#[test]
+fn fail_ends_the_stream() {
+    let document = "Before\n\n```pikchr\nbox 'x'\n```\n\nAfter\n";
+    let mut events = render_events(Parser::new(document), Options::default(), OnError::Fail);
+    let actual: Vec<Event> = events.by_ref().collect();
+
+    assert_eq!(actual, Parser::new(document).take(3).collect::<Vec<_>>());
+    assert_eq!(events.errors().len(), 1);
+}
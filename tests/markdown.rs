@@ -0,0 +1,60 @@
+//! Rendering the pikchr blocks of a Markdown document.
+
+use pikt::markdown::process_markdown;
+use pikt::{render, Options};
+
+const DOCUMENT: &str = r#"# Diagrams
+
+```pikchr
+box "one"
+```
+
+Some text.
+
+```pikchr
+box "two"
+circ "bad"
+```
+
+```pikchr
+circle "three"
+```
+
+```pikchr
+
+
+box 'bad'
+```
+"#;
+
+#[test]
+fn reports_all_errors_at_document_lines() {
+    let errors = process_markdown(DOCUMENT, Options::default()).unwrap_err();
+    let first = render("box \"two\"\ncirc \"bad\"\n").unwrap_err();
+    let second = render("\n\nbox 'bad'\n").unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].reason(), first.reason());
+    assert_eq!(errors[0].line(), 9 + first.line());
+    assert_eq!(errors[0].line(), 11);
+    assert_eq!(errors[1].reason(), second.reason());
+    assert_eq!(errors[1].line(), 18 + second.line());
+    assert_eq!(errors[1].line(), 21);
+}
+
+#[test]
+fn replaces_blocks_keeping_the_text() {
+    let text = "Before\n\n```pikchr\nbox \"one\"\n```\n\nBetween &amp;\n```pikchr\ncircle\n```";
+    let actual = process_markdown(text, Options::default()).unwrap();
+    let one = render("box \"one\"\n").unwrap().svg;
+    let circle = render("circle\n").unwrap().svg;
+
+    assert_eq!(
+        actual,
+        format!(
+            "Before\n\n{}\n\nBetween &amp;\n{}",
+            one.trim_end(),
+            circle.trim_end()
+        )
+    );
+}
@@ -0,0 +1,21 @@
+use pikt::prelude::*;
+
+#[test]
+fn prelude_items() -> pikt::Result<()> {
+    let mut builder = OptionsBuilder::default();
+    builder.flags(Flags::DARK_MODE);
+    let options: Options = builder.build();
+
+    let output: PikchrOutput = render_with("box", options)?;
+    assert!(output.svg.contains("rgb(255,255,255)"));
+
+    assert_eq!(render("box")?, Diagram::new("box").render()?);
+    assert_eq!(render_light("box")?, render("box")?);
+    assert!(render_dark("box")?.svg.contains("rgb(255,255,255)"));
+    assert!(validate("box").is_ok());
+
+    let err = render("box 'x'").unwrap_err();
+    assert_eq!(err.reason(), &PiktErrorReason::TokenUnknown);
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use pikt::{render, render_with, Flags, OptionsBuilder};
+use proptest::prelude::*;
+
+/// Generates a shape statement, optionally labelled.
+fn shape() -> impl Strategy<Value = String> {
+    let keyword = prop::sample::select(vec![
+        "box", "circle", "ellipse", "oval", "cylinder", "file", "arrow", "line",
+    ]);
+    let label = prop::option::of("[a-zA-Z0-9 ]{0,12}");
+
+    (keyword, label).prop_map(|(keyword, label)| match label {
+        Some(label) => format!("{} \"{}\"", keyword, label),
+        None => keyword.to_string(),
+    })
+}
+
+/// Generates plausible pikchr source made of one or more shape statements.
+fn diagram() -> impl Strategy<Value = String> {
+    prop::collection::vec(shape(), 1..8).prop_map(|shapes| shapes.join("\n"))
+}
+
+proptest! {
+    #[test]
+    fn never_panics(input in any::<String>()) {
+        // Either outcome is fine as long as it is one of the two.
+        let _ = render(&input);
+    }
+
+    #[test]
+    fn deterministic(input in diagram()) {
+        let first = render(&input).unwrap();
+        let second = render(&input).unwrap();
+
+        prop_assert_eq!(first.svg, second.svg);
+    }
+
+    #[test]
+    fn dark_mode_differs(input in diagram()) {
+        let mut builder = OptionsBuilder::default();
+        builder.flags(Flags::DARK_MODE);
+        let light = render(&input).unwrap();
+        let dark = render_with(&input, builder.build()).unwrap();
+
+        prop_assert!(light.svg.contains("stroke:rgb(0,0,0)"));
+        prop_assert!(dark.svg.contains("stroke:rgb(255,255,255)"));
+        prop_assert_ne!(light.svg, dark.svg);
+    }
+
+    #[test]
+    fn positive_dimensions(input in diagram()) {
+        let output = render(&input).unwrap();
+
+        prop_assert!(output.width > 0 && output.height > 0);
+    }
+}
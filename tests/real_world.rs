@@ -0,0 +1,400 @@
+//! Diagrams representative of real documentation. They are a smoke test for upgrades of the
+//! bundled pikchr.
+
+fn assert_renders(markup: &str) {
+    if let Err(err) = pikt::render(markup) {
+        panic!("failed to render: {}\n{}", err, markup);
+    }
+}
+
+#[test]
+fn flowchart_linear() {
+    assert_renders(
+        r#"
+        arrow right 200% "Markdown" "Source"
+        box rad 10px "Markdown" "Formatter" "(markdown.c)" fit
+        arrow right 200% "HTML+SVG" "Output"
+        arrow <-> down 70% from last box.s
+        box same "Pikchr" "Formatter" "(pikchr.c)" fit
+        "#,
+    );
+}
+
+#[test]
+fn flowchart_decision() {
+    assert_renders(
+        r#"
+        box "Start" rad 10px
+        arrow down
+        box "Read input"
+        arrow down
+        ellipse "Valid?"
+        arrow right 150% "yes" above
+        box "Render"
+        arrow from last ellipse.s down "no" ljust
+        box "Report error"
+        "#,
+    );
+}
+
+#[test]
+fn flowchart_loop() {
+    assert_renders(
+        r#"
+        down
+        box "Init"
+        arrow
+        B: box "Step"
+        arrow
+        box "Done?"
+        arrow from last box.e right 1cm then up until even with B then to B.e
+        "#,
+    );
+}
+
+#[test]
+fn flowchart_pipeline() {
+    assert_renders(
+        r#"
+        box "Lex" fit
+        arrow
+        box "Parse" fit
+        arrow
+        box "Layout" fit
+        arrow
+        box "Emit" fit
+        "#,
+    );
+}
+
+#[test]
+fn flowchart_branches() {
+    assert_renders(
+        r#"
+        A: box "Request"
+        B: box "Cache" at 2cm right of A + (0, 1cm)
+        C: box "Origin" at 2cm right of A - (0, 1cm)
+        arrow from A.e to B.w
+        arrow from A.e to C.w
+        "#,
+    );
+}
+
+#[test]
+fn sequence_two_actors() {
+    assert_renders(
+        r#"
+        A: box "Client" fit
+        B: box "Server" fit at 3cm right of A
+        L1: line down 3cm from A.s dashed
+        L2: line down 3cm from B.s dashed
+        arrow from 0.5cm below A.s to 0.5cm below B.s "request" above
+        arrow from 1.5cm below B.s to 1.5cm below A.s "response" above
+        "#,
+    );
+}
+
+#[test]
+fn sequence_three_actors() {
+    assert_renders(
+        r#"
+        A: box "Browser" fit
+        B: box "API" fit at 3cm right of A
+        C: box "DB" fit at 3cm right of B
+        line down 4cm from A.s dashed
+        line down 4cm from B.s dashed
+        line down 4cm from C.s dashed
+        arrow from 0.5cm below A.s to 0.5cm below B.s "GET" above
+        arrow from 1cm below B.s to 1cm below C.s "SELECT" above
+        arrow from 2cm below C.s to 2cm below B.s "rows" above
+        arrow from 2.5cm below B.s to 2.5cm below A.s "200 OK" above
+        "#,
+    );
+}
+
+#[test]
+fn sequence_self_call() {
+    assert_renders(
+        r#"
+        A: box "Worker" fit
+        line down 3cm from A.s dashed
+        arrow from 1cm below A.s right 1cm then down 0.5cm then left 1cm "retry" ljust
+        "#,
+    );
+}
+
+#[test]
+fn network_star() {
+    assert_renders(
+        r#"
+        S: circle "switch" fit
+        H1: box "host 1" fit at 2cm above S
+        H2: box "host 2" fit at 2cm right of S
+        H3: box "host 3" fit at 2cm below S
+        H4: box "host 4" fit at 2cm left of S
+        line from S to H1
+        line from S to H2
+        line from S to H3
+        line from S to H4
+        "#,
+    );
+}
+
+#[test]
+fn network_tiers() {
+    assert_renders(
+        r#"
+        LB: box "load balancer" fit
+        W1: box "web 1" fit at 1.5cm below LB - (1.5cm, 0)
+        W2: box "web 2" fit at 1.5cm below LB + (1.5cm, 0)
+        DB: cylinder "database" fit at 3cm below LB
+        arrow from LB.s to W1.n
+        arrow from LB.s to W2.n
+        arrow from W1.s to DB.n
+        arrow from W2.s to DB.n
+        "#,
+    );
+}
+
+#[test]
+fn network_ring() {
+    assert_renders(
+        r#"
+        A: circle "A"
+        B: circle "B" at 2cm right of A
+        C: circle "C" at 2cm below B
+        D: circle "D" at 2cm left of C
+        arrow from A.e to B.w
+        arrow from B.s to C.n
+        arrow from C.w to D.e
+        arrow from D.n to A.s
+        "#,
+    );
+}
+
+#[test]
+fn network_storage() {
+    assert_renders(
+        r#"
+        box "app"
+        arrow
+        cylinder "primary"
+        arrow "replicate" above
+        cylinder "replica"
+        arrow from first cylinder.s down 1cm
+        file "backup"
+        "#,
+    );
+}
+
+#[test]
+fn state_machine_traffic_light() {
+    assert_renders(
+        r#"
+        R: circle "red"
+        G: circle "green" at 3cm right of R
+        Y: circle "yellow" at 1.5cm right of R + (0, -2.5cm)
+        arrow from R.e to G.w "go" above
+        arrow from G.s to Y.e "slow" rjust
+        arrow from Y.w to R.s "stop" ljust
+        "#,
+    );
+}
+
+#[test]
+fn state_machine_connection() {
+    assert_renders(
+        r#"
+        circle "closed" fit
+        arrow "open" above
+        circle "listening" fit
+        arrow "accept" above
+        circle "established" fit
+        arrow "close" above
+        circle "closed" fit
+        "#,
+    );
+}
+
+#[test]
+fn state_machine_initial_final() {
+    assert_renders(
+        r#"
+        dot
+        arrow
+        circle "idle" fit
+        arrow "job" above
+        circle "busy" fit
+        arrow "done" above
+        circle radius 0.1
+        circle radius 0.15 at last circle
+        "#,
+    );
+}
+
+#[test]
+fn state_machine_self_transition() {
+    assert_renders(
+        r#"
+        S: circle "polling" fit
+        spline from S.ne up 1cm right 0.5cm then down 1cm right 0.5cm then to S.e ->
+        text "tick" at 1cm above S.ne
+        "#,
+    );
+}
+
+#[test]
+fn er_two_entities() {
+    assert_renders(
+        r#"
+        U: box "User" "id" "name" ht 1.5cm
+        P: box "Post" "id" "user_id" "title" ht 1.5cm at 4cm right of U
+        line from U.e to P.w "1..*" above
+        "#,
+    );
+}
+
+#[test]
+fn er_attribute_lines() {
+    assert_renders(
+        r#"
+        E: box "Customer" fit
+        line from E.s down 0.5cm
+        line left 0.5cm
+        text "email" ljust at 0.5cm right of last line.end
+        line from E.n up 0.5cm
+        ellipse "id" fit
+        "#,
+    );
+}
+
+#[test]
+fn er_join_table() {
+    assert_renders(
+        r#"
+        S: box "Student" fit
+        J: box "Enrolment" fit at 3cm right of S
+        C: box "Course" fit at 3cm right of J
+        line from S.e to J.w "1" above "*" below
+        line from J.e to C.w "*" above "1" below
+        "#,
+    );
+}
+
+#[test]
+fn macro_definitions() {
+    assert_renders(
+        r#"
+        define node { circle $1 fit }
+        define edge { arrow $1 above }
+        node("a")
+        edge("x")
+        node("b")
+        edge("y")
+        node("c")
+        "#,
+    );
+}
+
+#[test]
+fn macro_nested() {
+    assert_renders(
+        r#"
+        define cell { box $1 width 1cm height 1cm }
+        define row { cell("1"); cell("2"); cell("3") }
+        row
+        move to first box.s then down 1cm
+        row
+        "#,
+    );
+}
+
+#[test]
+fn move_layout() {
+    assert_renders(
+        r#"
+        box "a"
+        move
+        box "b"
+        move right 2cm
+        box "c"
+        move down
+        box "d"
+        "#,
+    );
+}
+
+#[test]
+fn splines_and_lines() {
+    assert_renders(
+        r#"
+        A: box "source"
+        B: box "sink" at 4cm right of A
+        spline from A.n up 1cm then right 4cm then to B.n ->
+        line from A.s down 1cm then right 4cm then to B.s dotted ->
+        "#,
+    );
+}
+
+#[test]
+fn files_and_cylinders() {
+    assert_renders(
+        r#"
+        file "config.toml"
+        arrow
+        box "loader" fit
+        arrow
+        cylinder "store"
+        "#,
+    );
+}
+
+#[test]
+fn styled_shapes() {
+    assert_renders(
+        r#"
+        box "info" fill lightblue color blue thick
+        arrow dashed
+        circle "warn" fill yellow
+        arrow dotted
+        oval "error" fill pink color red thin
+        "#,
+    );
+}
+
+#[test]
+fn variables_and_expressions() {
+    assert_renders(
+        r#"
+        boxwid = 2cm
+        gap = 0.5cm
+        box "wide"
+        move right gap
+        box "wide" width boxwid * 1.5
+        circle radius boxwid / 4 at 1cm below first box
+        "#,
+    );
+}
+
+#[test]
+fn labels_and_text() {
+    assert_renders(
+        r#"
+        box "centered"
+        text "above" at 0.5cm above last box
+        text "left" ljust at last box.e
+        arrow from first box.s down 1cm "label" rjust italic
+        "#,
+    );
+}
+
+#[test]
+fn arcs() {
+    assert_renders(
+        r#"
+        arc -> "cw" ljust cw
+        arc -> "ccw" rjust
+        circle at last arc.end radius 0.05
+        "#,
+    );
+}
@@ -0,0 +1,103 @@
+//! Renders every diagram in `tests/regression/` and compares the result with the expected file.
+//!
+//! - `valid/<name>.pikchr` must render to `valid/<name>.svg`.
+//! - `invalid/<name>.pikchr` must fail with the error in `invalid/<name>.err`.
+//!
+//! Run with `PIKCHR_UPDATE_SNAPSHOTS=1` to write the expected files instead of comparing them.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn regression_dir(kind: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("regression")
+        .join(kind)
+}
+
+fn sources(kind: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(regression_dir(kind))
+        .expect("regression directory must exist")
+        .map(|entry| entry.expect("regression entry must be readable").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pikchr"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn update_mode() -> bool {
+    std::env::var("PIKCHR_UPDATE_SNAPSHOTS").is_ok_and(|value| value == "1")
+}
+
+/// Compares the actual output with the expected file, returning a description of the mismatch.
+fn check(source: &Path, extension: &str, actual: &str) -> Option<String> {
+    let expected_path = source.with_extension(extension);
+
+    if update_mode() {
+        fs::write(&expected_path, actual).expect("expected file must be writable");
+        return None;
+    }
+
+    match fs::read_to_string(&expected_path) {
+        Ok(expected) if expected == actual => None,
+        Ok(expected) => Some(format!(
+            "{}: output differs\n--- expected\n{}\n--- actual\n{}",
+            source.display(),
+            expected,
+            actual
+        )),
+        Err(_) => Some(format!(
+            "{}: missing expected file",
+            expected_path.display()
+        )),
+    }
+}
+
+fn assert_no_failures(failures: Vec<String>) {
+    assert!(
+        failures.is_empty(),
+        "{} regression(s) failed:\n\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn valid_diagrams() {
+    let sources = sources("valid");
+    assert!(sources.len() >= 10, "expected at least 10 valid diagrams");
+
+    let failures = sources
+        .iter()
+        .filter_map(|source| {
+            let input = fs::read_to_string(source).expect("source must be readable");
+
+            match pikt::render(&input) {
+                Ok(output) => check(source, "svg", &output.svg),
+                Err(err) => Some(format!("{}: unexpected error: {}", source.display(), err)),
+            }
+        })
+        .collect();
+
+    assert_no_failures(failures);
+}
+
+#[test]
+fn invalid_diagrams() {
+    let sources = sources("invalid");
+    assert!(sources.len() >= 5, "expected at least 5 invalid diagrams");
+
+    let failures = sources
+        .iter()
+        .filter_map(|source| {
+            let input = fs::read_to_string(source).expect("source must be readable");
+
+            match pikt::render(&input) {
+                Ok(_) => Some(format!("{}: unexpected success", source.display())),
+                Err(err) => check(source, "err", &format!("{}\n", err)),
+            }
+        })
+        .collect();
+
+    assert_no_failures(failures);
+}
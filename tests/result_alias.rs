@@ -0,0 +1,24 @@
+//! Checks that the `Error` and `Result` aliases can be used in downstream signatures.
+
+use pikt::{PikchrOutput, PiktError};
+use static_assertions::assert_type_eq_all;
+
+assert_type_eq_all!(pikt::Error, PiktError);
+assert_type_eq_all!(pikt::Result<PikchrOutput>, Result<PikchrOutput, PiktError>);
+assert_type_eq_all!(pikt::Result<(), std::fmt::Error>, Result<(), std::fmt::Error>);
+
+fn render_box() -> pikt::Result<PikchrOutput> {
+    let output = pikt::render("box")?;
+
+    Ok(output)
+}
+
+fn first_error(inputs: &[&str]) -> Option<pikt::Error> {
+    inputs.iter().find_map(|input| pikt::validate(input).err())
+}
+
+#[test]
+fn alias_in_signatures() {
+    assert!(render_box().is_ok());
+    assert!(first_error(&["box", "box 'x'"]).is_some());
+}
@@ -0,0 +1,20 @@
+//! Compile-time checks that the public types can be shared across threads.
+//!
+//! `Send` also rules out non-`Send` fields such as `Rc` being added to these types.
+
+use pikt::{
+    Diagram, DiagramBuilder, Flags, Options, OptionsBuilder, OutputFormat, PikchrOutput, PiktError,
+    PiktErrorKind, PiktErrorReason,
+};
+use static_assertions::assert_impl_all;
+
+assert_impl_all!(PikchrOutput: Send, Sync);
+assert_impl_all!(PiktError: Send, Sync);
+assert_impl_all!(PiktErrorReason: Send, Sync);
+assert_impl_all!(PiktErrorKind: Send, Sync);
+assert_impl_all!(Options: Send, Sync);
+assert_impl_all!(OptionsBuilder: Send, Sync);
+assert_impl_all!(Flags: Send, Sync);
+assert_impl_all!(OutputFormat: Send, Sync);
+assert_impl_all!(Diagram: Send, Sync);
+assert_impl_all!(DiagramBuilder: Send, Sync);